@@ -0,0 +1,213 @@
+pub mod mod_anomalie {
+    use crate::minmax::mod_min_max::{create_min_max, MinMax};
+    use crate::rapport::mod_rapport::{Anomalie, TypeAnomalie};
+    use serde_json::{Map, Value};
+    use std::collections::HashMap;
+
+    /// Suit les pages recues pendant un traitement pour reperer les
+    /// decalages dus aux modifications faites sur le serveur pendant le
+    /// parcourt (`sort: oldest` + `offset`).
+    #[derive(Debug, Default)]
+    pub struct Detecteur {
+        positions: HashMap<String, u64>,
+        derniere_date: Option<i64>,
+        dernier_total: Option<u64>,
+    }
+
+    impl Detecteur {
+        /// `elements` : identifiant et date de tri de chaque element de la
+        /// page, dans l'ordre de la reponse. Les anomalies qui peuvent faire
+        /// sauter des elements (ordre entre deux pages, total en baisse) ont
+        /// `rechargement` a vrai.
+        pub fn analyser_page(
+            &mut self,
+            offset: u64,
+            elements: &[(String, i64)],
+            total: Option<u64>,
+            since: Option<u64>,
+        ) -> Vec<Anomalie> {
+            let mut anomalies = Vec::new();
+            let mut anomalie = |type_anomalie, detail: String, rechargement| {
+                anomalies.push(Anomalie {
+                    type_anomalie,
+                    offset,
+                    since,
+                    detail,
+                    rechargement,
+                })
+            };
+
+            let mut dates: MinMax<i64> = create_min_max();
+            for (_, date) in elements.iter().filter(|e| e.1 > 0) {
+                dates.add(*date);
+            }
+            if dates.nb_inversions > 0 {
+                anomalie(
+                    TypeAnomalie::Ordre,
+                    format!("{} inversions dans la page {}", dates.nb_inversions, dates),
+                    false,
+                );
+            }
+            if let (Some(precedente), Some(premiere)) = (
+                self.derniere_date,
+                elements.iter().map(|e| e.1).find(|d| *d > 0),
+            ) {
+                if precedente > premiere {
+                    anomalie(
+                        TypeAnomalie::Ordre,
+                        format!(
+                            "debut de page {} avant la fin de la page precedente {}",
+                            premiere, precedente
+                        ),
+                        true,
+                    );
+                }
+            }
+
+            let mut doublons = Vec::new();
+            for (i, (id, _)) in elements.iter().enumerate() {
+                let position = offset + i as u64;
+                match self.positions.insert(id.clone(), position) {
+                    Some(ancienne) if ancienne != position => {
+                        doublons.push(format!("{} ({} -> {})", id, ancienne, position))
+                    }
+                    _ => {}
+                }
+            }
+            if !doublons.is_empty() {
+                anomalie(TypeAnomalie::Doublon, doublons.join(", "), false);
+            }
+
+            if let (Some(precedent), Some(total)) = (self.dernier_total, total) {
+                if total < precedent {
+                    anomalie(
+                        TypeAnomalie::TotalEnBaisse,
+                        format!("total {} -> {}", precedent, total),
+                        true,
+                    );
+                }
+            }
+
+            if dates.last.is_some() {
+                self.derniere_date = dates.last;
+            }
+            if total.is_some() {
+                self.dernier_total = total;
+            }
+            anomalies
+        }
+
+        /// Apres un retour en arriere, la page suivante recommence avant la
+        /// fin de la precedente et le total de reference change.
+        pub fn reprise(&mut self) {
+            self.derniere_date = None;
+            self.dernier_total = None;
+        }
+    }
+
+    /// Le champ `total` (demande avec `total: 1`) est une chaine.
+    pub fn total_reponse(obj: &Map<String, Value>) -> Option<u64> {
+        match obj.get("total") {
+            Some(Value::String(s)) => s.parse::<u64>().ok(),
+            Some(Value::Number(n)) => n.as_u64(),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::rapport::mod_rapport::TypeAnomalie;
+
+        fn page(elements: &[(&str, i64)]) -> Vec<(String, i64)> {
+            elements
+                .iter()
+                .map(|(id, d)| (id.to_string(), *d))
+                .collect()
+        }
+
+        fn types(anomalies: &[Anomalie]) -> Vec<(TypeAnomalie, bool)> {
+            anomalies
+                .iter()
+                .map(|a| (a.type_anomalie, a.rechargement))
+                .collect()
+        }
+
+        #[test]
+        fn pages_en_ordre_sans_anomalie() {
+            let mut detecteur = Detecteur::default();
+            let p1 = page(&[("1", 10), ("2", 20)]);
+            let p2 = page(&[("3", 20), ("4", 30)]);
+            assert!(detecteur.analyser_page(0, &p1, Some(4), None).is_empty());
+            assert!(detecteur.analyser_page(2, &p2, Some(4), None).is_empty());
+        }
+
+        #[test]
+        fn inversion_dans_la_page() {
+            let mut detecteur = Detecteur::default();
+            let p1 = page(&[("1", 30), ("2", 0), ("3", 20)]);
+            let anomalies = detecteur.analyser_page(0, &p1, None, Some(5));
+            assert_eq!(types(&anomalies), vec![(TypeAnomalie::Ordre, false)]);
+            assert_eq!(anomalies[0].offset, 0);
+            assert_eq!(anomalies[0].since, Some(5));
+        }
+
+        #[test]
+        fn page_qui_commence_avant_la_fin_de_la_precedente() {
+            let mut detecteur = Detecteur::default();
+            detecteur.analyser_page(0, &page(&[("1", 10), ("2", 50)]), None, None);
+            let anomalies = detecteur.analyser_page(2, &page(&[("3", 0), ("4", 40)]), None, None);
+            assert_eq!(types(&anomalies), vec![(TypeAnomalie::Ordre, true)]);
+            assert_eq!(anomalies[0].offset, 2);
+        }
+
+        #[test]
+        fn doublon_entre_deux_pages() {
+            let mut detecteur = Detecteur::default();
+            detecteur.analyser_page(0, &page(&[("1", 10), ("2", 20)]), None, None);
+            let anomalies = detecteur.analyser_page(2, &page(&[("2", 20), ("3", 30)]), None, None);
+            assert_eq!(types(&anomalies), vec![(TypeAnomalie::Doublon, false)]);
+            assert_eq!(anomalies[0].detail, "2 (1 -> 2)");
+        }
+
+        #[test]
+        fn total_en_baisse() {
+            let mut detecteur = Detecteur::default();
+            detecteur.analyser_page(0, &page(&[("1", 10)]), Some(10), None);
+            // une page sans total garde le total de reference
+            detecteur.analyser_page(1, &page(&[("2", 20)]), None, None);
+            let anomalies = detecteur.analyser_page(2, &page(&[("3", 30)]), Some(9), None);
+            assert_eq!(types(&anomalies), vec![(TypeAnomalie::TotalEnBaisse, true)]);
+            assert_eq!(anomalies[0].detail, "total 10 -> 9");
+            assert!(detecteur
+                .analyser_page(3, &page(&[("4", 40)]), Some(12), None)
+                .is_empty());
+        }
+
+        #[test]
+        fn reprise_oublie_date_et_total_mais_pas_les_positions() {
+            let mut detecteur = Detecteur::default();
+            detecteur.analyser_page(0, &page(&[("1", 10), ("2", 50)]), Some(10), None);
+            detecteur.reprise();
+            // la page de recouvrement recommence plus tot avec un total plus bas
+            let anomalies =
+                detecteur.analyser_page(1, &page(&[("1", 10), ("3", 60)]), Some(8), None);
+            assert_eq!(types(&anomalies), vec![(TypeAnomalie::Doublon, false)]);
+        }
+
+        #[test]
+        fn total_de_la_reponse() {
+            let obj = |v: Value| v.as_object().unwrap().clone();
+            assert_eq!(
+                total_reponse(&obj(serde_json::json!({"total": "12"}))),
+                Some(12)
+            );
+            assert_eq!(
+                total_reponse(&obj(serde_json::json!({"total": 7}))),
+                Some(7)
+            );
+            assert_eq!(total_reponse(&obj(serde_json::json!({"total": "x"}))), None);
+            assert_eq!(total_reponse(&obj(serde_json::json!({}))), None);
+        }
+    }
+}
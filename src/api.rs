@@ -0,0 +1,206 @@
+pub mod mod_api {
+    use reqwest::header::HeaderMap;
+    use reqwest::{Client, RequestBuilder, StatusCode};
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use std::fmt;
+
+    /// Corps de la requete `/v3/get`.
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct Parameters {
+        pub consumer_key: String,
+        pub access_token: String,
+        #[serde(rename = "detail_type")]
+        pub detail_type: String,
+        pub count: u64,
+        pub offset: u64,
+        pub total: u8,
+        pub sort: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub since: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub state: Option<String>,
+    }
+
+    impl fmt::Display for Parameters {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            // Customize so only `x` and `y` are denoted.
+            write!(
+                f,
+                "detail_type: {}, count: {}, offset: {}, total: {}, sort: {}, since: {:?}, state: {:?}",
+                self.detail_type, self.count, self.offset, self.total, self.sort, self.since, self.state
+            )
+        }
+    }
+
+    #[derive(Debug)]
+    pub enum ErreurApi {
+        Requete(String),
+        Statut(StatusCode),
+        /// 429 d'une API JSON, avec le delai de `Retry-After` en secondes
+        Limite(Option<u64>),
+        Lecture(String),
+        Json(String),
+    }
+
+    impl ErreurApi {
+        /// Libelle utilise pour compter les erreurs (code HTTP ou type).
+        pub fn code(&self) -> String {
+            match self {
+                ErreurApi::Requete(_) => "requete".to_string(),
+                ErreurApi::Statut(statut) => statut.as_u16().to_string(),
+                ErreurApi::Limite(_) => StatusCode::TOO_MANY_REQUESTS.as_u16().to_string(),
+                ErreurApi::Lecture(_) => "lecture".to_string(),
+                ErreurApi::Json(_) => "json".to_string(),
+            }
+        }
+
+        /// Erreur qui peut disparaitre en recommencant l'appel : reseau,
+        /// 429 ou erreur du serveur.
+        pub fn temporaire(&self) -> bool {
+            match self {
+                ErreurApi::Requete(_) | ErreurApi::Limite(_) => true,
+                ErreurApi::Statut(code) => {
+                    *code == StatusCode::TOO_MANY_REQUESTS || code.is_server_error()
+                }
+                _ => false,
+            }
+        }
+    }
+
+    /// Reponse de `/v3/get` et limites d'appel donnees dans les entetes.
+    #[derive(Debug)]
+    pub struct ReponseApi {
+        pub json: Value,
+        /// `X-Limit-User-Remaining`
+        pub limite_utilisateur: Option<i64>,
+        /// `X-Limit-Key-Remaining`
+        pub limite_cle: Option<i64>,
+    }
+
+    fn entete_nombre(headers: &HeaderMap, nom: &str) -> Option<i64> {
+        headers
+            .get(nom)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<i64>().ok())
+    }
+
+    impl fmt::Display for ErreurApi {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ErreurApi::Requete(err) => write!(f, "Erreur lors de la requête : {}", err),
+                ErreurApi::Statut(statut) => write!(f, "Réponse en erreur : {}", statut),
+                ErreurApi::Limite(attente) => write!(
+                    f,
+                    "Réponse en erreur : {} (Retry-After : {:?})",
+                    StatusCode::TOO_MANY_REQUESTS,
+                    attente
+                ),
+                ErreurApi::Lecture(err) => write!(f, "Erreur en lisant la réponse : {}", err),
+                ErreurApi::Json(err) => write!(f, "Erreur de parsing : {}", err),
+            }
+        }
+    }
+
+    /// Appel de `/v3/get` ; retourne l'objet JSON de la reponse.
+    pub async fn appel_get(
+        client: &Client,
+        url: &str,
+        param: &Parameters,
+    ) -> Result<ReponseApi, ErreurApi> {
+        let json_output = serde_json::to_string(param).expect("Erreur de sérialisation");
+
+        let response = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("X-Accept", "application/json")
+            .body(json_output.to_owned())
+            .send()
+            .await;
+
+        let (body_ok, limite_utilisateur, limite_cle) = match response {
+            Ok(resp) => match resp.status() {
+                StatusCode::OK => {
+                    log::info!("OK");
+                    log::info!("headers: {:?}", resp.headers());
+                    let limite_utilisateur =
+                        entete_nombre(resp.headers(), "X-Limit-User-Remaining");
+                    let limite_cle = entete_nombre(resp.headers(), "X-Limit-Key-Remaining");
+                    match resp.text().await {
+                        Ok(body) => (body, limite_utilisateur, limite_cle),
+                        Err(err) => {
+                            log::error!("Erreur en lisant la réponse : {}", err);
+                            return Err(ErreurApi::Lecture(err.to_string()));
+                        }
+                    }
+                }
+                StatusCode::NOT_FOUND => {
+                    log::error!("Erreur 404 : Ressource non trouvée.");
+                    log::error!("headers: {:?}", resp.headers());
+                    log::error!("body: {:?}", resp.text().await);
+                    return Err(ErreurApi::Statut(StatusCode::NOT_FOUND));
+                }
+                StatusCode::BAD_REQUEST => {
+                    log::error!("Erreur 400 : Bad request.");
+                    log::error!("headers: {:?}", resp.headers());
+                    log::error!("body: {:?}", resp.text().await);
+                    return Err(ErreurApi::Statut(StatusCode::BAD_REQUEST));
+                }
+                other => {
+                    log::error!("Réponse inattendue : {:?}", other);
+                    log::error!("headers: {:?}", resp.headers());
+                    log::error!("body: {:?}", resp.text().await);
+                    return Err(ErreurApi::Statut(other));
+                }
+            },
+            Err(err) => {
+                log::error!("Erreur lors de la requête : {}", err);
+                return Err(ErreurApi::Requete(err.to_string()));
+            }
+        };
+
+        let json_value: Value =
+            serde_json::from_str(&body_ok).map_err(|err| ErreurApi::Json(err.to_string()))?;
+        if !json_value.is_object() {
+            return Err(ErreurApi::Json("la réponse n'est pas un objet".to_string()));
+        }
+        Ok(ReponseApi {
+            json: json_value,
+            limite_utilisateur,
+            limite_cle,
+        })
+    }
+
+    /// Envoi d'une requete a une API JSON autre que Pocket (Wallabag,
+    /// Linkding) ; tout statut hors 2xx est une erreur, 429 donne le delai
+    /// demande par `Retry-After` (en secondes ; une date n'est pas lue).
+    pub async fn appel_json(requete: RequestBuilder) -> Result<Value, ErreurApi> {
+        let resp = requete
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .map_err(|err| {
+                log::error!("Erreur lors de la requête : {}", err);
+                ErreurApi::Requete(err.to_string())
+            })?;
+        let statut = resp.status();
+        if statut == StatusCode::TOO_MANY_REQUESTS {
+            let attente = entete_nombre(resp.headers(), "Retry-After").map(|s| s.max(0) as u64);
+            log::warn!("Réponse 429, Retry-After : {:?}", attente);
+            return Err(ErreurApi::Limite(attente));
+        }
+        if !statut.is_success() {
+            log::error!("Réponse inattendue : {:?}", statut);
+            log::error!("body: {:?}", resp.text().await);
+            return Err(ErreurApi::Statut(statut));
+        }
+        let body = resp
+            .text()
+            .await
+            .map_err(|err| ErreurApi::Lecture(err.to_string()))?;
+        if body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&body).map_err(|err| ErreurApi::Json(err.to_string()))
+    }
+}
@@ -0,0 +1,753 @@
+pub mod mod_archive {
+    use crate::api::mod_api::ErreurApi;
+    use crate::config::mod_config::ConfigArchive;
+    use crate::donnees::mod_donnees::{champ_nombre, liste, statut, url, STATUT_SUPPRIME};
+    use crate::export::mod_export::elements_tries;
+    use crate::extraction::mod_extraction::{ecrire_article, extraire, Extraction};
+    use crate::import::mod_import::attributs;
+    use crate::warc::mod_warc::EcrivainWarc;
+    use chrono::{DateTime, Utc};
+    use reqwest::header::HeaderMap;
+    use reqwest::redirect::Policy;
+    use reqwest::{Client, StatusCode, Url};
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use sha2::{Digest, Sha256};
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt;
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::task::JoinSet;
+
+    pub const REPERTOIRE_ARCHIVE: &str = "archive";
+
+    pub const ARCHIVE_OK: &str = "ok";
+    /// erreur reseau, 429 ou 5xx : la page est redemandee aux executions
+    /// suivantes, jusqu'a `essais_max`
+    pub const ARCHIVE_ERREUR: &str = "erreur";
+    /// 404, 410 ou autre 4xx : la page n'est plus redemandee
+    pub const ARCHIVE_ABSENT: &str = "absent";
+
+    /// Une reponse HTTP telechargee.
+    #[derive(Debug, Clone)]
+    pub struct Telechargement {
+        /// URL qui a donne cette reponse, apres les redirections pour la
+        /// page
+        pub url_finale: String,
+        pub date: DateTime<Utc>,
+        /// `HTTP/1.1`
+        pub version_requete: String,
+        pub version: String,
+        pub entetes_requete: Vec<(String, String)>,
+        pub code: u16,
+        pub entetes: Vec<(String, String)>,
+        pub corps: Vec<u8>,
+    }
+
+    impl Telechargement {
+        pub fn type_contenu(&self) -> String {
+            self.entetes
+                .iter()
+                .find(|(nom, _)| nom.eq_ignore_ascii_case("content-type"))
+                .map(|(_, valeur)| valeur.clone())
+                .unwrap_or_default()
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Ressource {
+        pub url: String,
+        pub hash: String,
+        pub type_contenu: String,
+        pub taille: u64,
+    }
+
+    /// Resultat du dernier telechargement de la page d'un element.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EtatArchive {
+        pub url: String,
+        pub statut: String,
+        pub code_http: Option<u16>,
+        /// hash SHA-256 de la page dans le magasin
+        pub hash: Option<String>,
+        pub url_finale: String,
+        pub type_contenu: String,
+        pub taille: u64,
+        #[serde(default)]
+        pub ressources: Vec<Ressource>,
+        pub essais: u32,
+        pub date: i64,
+        pub erreur: Option<String>,
+        /// article extrait de la page, dans `archive/articles`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub extraction: Option<Extraction>,
+    }
+
+    pub fn repertoire_archive(repertoire: &str) -> PathBuf {
+        PathBuf::from(repertoire).join(REPERTOIRE_ARCHIVE)
+    }
+
+    fn fichier_etat(repertoire: &str) -> PathBuf {
+        repertoire_archive(repertoire).join("etat.json")
+    }
+
+    /// Objets ranges par les deux premiers caracteres du hash :
+    /// `archive/objets/ab/abcdef...`.
+    pub fn chemin_objet(repertoire: &str, hash: &str) -> PathBuf {
+        repertoire_archive(repertoire)
+            .join("objets")
+            .join(hash.get(..2).unwrap_or("_"))
+            .join(hash)
+    }
+
+    const MAX_REDIRECTIONS: usize = 10;
+
+    static NUMERO_TEMPORAIRE: AtomicU64 = AtomicU64::new(0);
+
+    /// Ecrit le contenu s'il n'est pas deja dans le magasin et retourne son
+    /// hash.
+    pub fn stocker(repertoire: &str, contenu: &[u8]) -> io::Result<String> {
+        let hash = format!("{:x}", Sha256::digest(contenu));
+        let chemin = chemin_objet(repertoire, hash.as_str());
+        if !chemin.exists() {
+            if let Some(parent) = chemin.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // nom propre a cet appel : deux taches peuvent stocker le meme
+            // objet en meme temps
+            let tmp = chemin.with_extension(format!(
+                "{}.{}.tmp",
+                std::process::id(),
+                NUMERO_TEMPORAIRE.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::write(&tmp, contenu)?;
+            if let Err(err) = fs::rename(&tmp, &chemin) {
+                let _ = fs::remove_file(&tmp);
+                // meme hash, meme contenu : l'objet deja la convient
+                if !chemin.exists() {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(hash)
+    }
+
+    pub fn charger_etat(repertoire: &str) -> BTreeMap<String, EtatArchive> {
+        let fichier = fichier_etat(repertoire);
+        match fs::read_to_string(&fichier) {
+            Ok(contenu) => serde_json::from_str(&contenu).unwrap_or_else(|err| {
+                log::error!("fichier {} invalide : {}", fichier.display(), err);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        }
+    }
+
+    pub fn lire_objet(repertoire: &str, hash: &str) -> io::Result<Vec<u8>> {
+        if hash.len() < 2 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("hash invalide : '{}'", hash),
+            ));
+        }
+        fs::read(chemin_objet(repertoire, hash))
+    }
+
+    fn sauver_etat(repertoire: &str, etat: &BTreeMap<String, EtatArchive>) {
+        let fichier = fichier_etat(repertoire);
+        let tmp = fichier.with_extension("tmp");
+        let contenu = serde_json::to_string(etat).expect("Erreur de sérialisation");
+        let res = fs::create_dir_all(repertoire_archive(repertoire))
+            .and_then(|_| fs::write(&tmp, contenu))
+            .and_then(|_| fs::rename(&tmp, &fichier));
+        if let Err(err) = res {
+            log::error!("Erreur d'ecriture de {} : {}", fichier.display(), err);
+        }
+    }
+
+    /// Feuilles de style, icones et images referencees par la page.
+    pub fn ressources_page(html: &str, base: &Url) -> Vec<Url> {
+        let mut res: Vec<Url> = Vec::new();
+        let minuscules = html.to_ascii_lowercase();
+        let mut pos = 0;
+        while let Some(debut) = minuscules[pos..].find('<').map(|p| p + pos) {
+            let Some(fin) = minuscules[debut..].find('>').map(|p| p + debut) else {
+                break;
+            };
+            pos = fin + 1;
+            let balise = &minuscules[debut + 1..fin];
+            let lien = if balise.starts_with("img ") {
+                attributs(&html[debut + 4..fin]).remove("src")
+            } else if balise.starts_with("link ") {
+                let mut attributs = attributs(&html[debut + 5..fin]);
+                let rel = attributs.remove("rel").unwrap_or_default().to_lowercase();
+                if rel.contains("stylesheet") || rel.contains("icon") {
+                    attributs.remove("href")
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+            let Some(url) = lien.and_then(|l| base.join(l.trim()).ok()) else {
+                continue;
+            };
+            if (url.scheme() == "http" || url.scheme() == "https") && !res.contains(&url) {
+                res.push(url);
+            }
+        }
+        res
+    }
+
+    /// Client HTTP partage par les taches, et date de la prochaine requete
+    /// autorisee pour chaque domaine.
+    struct Contexte {
+        client: Client,
+        config: ConfigArchive,
+        repertoire: String,
+        creneaux: Mutex<HashMap<String, Instant>>,
+        warc: Option<Mutex<EcrivainWarc>>,
+        /// ressources deja telechargees pendant l'execution, partagees par
+        /// plusieurs pages
+        ressources: Mutex<HashMap<String, Ressource>>,
+    }
+
+    impl Contexte {
+        /// Attend le tour du domaine : deux requetes vers un meme domaine
+        /// sont espacees d'au moins `delai_domaine_ms`.
+        async fn attendre_tour(&self, url: &Url) {
+            let domaine = url.host_str().unwrap_or("").to_string();
+            let delai = Duration::from_millis(self.config.delai_domaine_ms);
+            let attente = {
+                let mut creneaux = self.creneaux.lock().expect("verrou des domaines");
+                let maintenant = Instant::now();
+                let creneau = creneaux
+                    .get(&domaine)
+                    .map(|c| (*c).max(maintenant))
+                    .unwrap_or(maintenant);
+                creneaux.insert(domaine, creneau + delai);
+                creneau - maintenant
+            };
+            if !attente.is_zero() {
+                tokio::time::sleep(attente).await;
+            }
+        }
+
+        /// Une requete, sans suivre de redirection : chaque etape est
+        /// enregistree dans le WARC avec sa propre requete.
+        async fn telecharger_etape(&self, url: &Url) -> Result<Telechargement, ErreurApi> {
+            self.attendre_tour(url).await;
+            let date = Utc::now();
+            let requete = self
+                .client
+                .get(url.clone())
+                .header("User-Agent", self.config.agent.as_str())
+                .timeout(Duration::from_secs(self.config.timeout_s))
+                .build()
+                .map_err(|err| ErreurApi::Requete(err.to_string()))?;
+            let entetes_requete = liste_entetes(requete.headers());
+            let version_requete = format!("{:?}", requete.version());
+            let mut reponse = self
+                .client
+                .execute(requete)
+                .await
+                .map_err(|err| ErreurApi::Requete(err.to_string()))?;
+            let statut = reponse.status();
+            if !statut.is_success() && !statut.is_redirection() {
+                return Err(ErreurApi::Statut(statut));
+            }
+            let taille_max = self.config.taille_max_ko * 1024;
+            let mut corps = Vec::new();
+            while let Some(morceau) = reponse
+                .chunk()
+                .await
+                // coupure pendant le corps : erreur reseau, a retenter
+                .map_err(|err| ErreurApi::Requete(err.to_string()))?
+            {
+                corps.extend_from_slice(&morceau);
+                if corps.len() as u64 > taille_max {
+                    return Err(ErreurApi::Lecture(format!(
+                        "taille superieure a {} ko",
+                        self.config.taille_max_ko
+                    )));
+                }
+            }
+            let telechargement = Telechargement {
+                url_finale: url.to_string(),
+                date,
+                version_requete,
+                version: format!("{:?}", reponse.version()),
+                entetes_requete,
+                code: statut.as_u16(),
+                entetes: liste_entetes(reponse.headers()),
+                corps,
+            };
+            if let Some(warc) = &self.warc {
+                let mut warc = warc.lock().expect("verrou WARC");
+                if let Err(err) = warc.ecrire(&telechargement) {
+                    log::error!("Erreur d'ecriture WARC : {}", err);
+                }
+            }
+            Ok(telechargement)
+        }
+
+        /// Suit les redirections (au plus `MAX_REDIRECTIONS`) jusqu'a la page.
+        async fn telecharger(&self, url: &Url) -> Result<Telechargement, ErreurApi> {
+            let mut url = url.clone();
+            for _ in 0..=MAX_REDIRECTIONS {
+                let etape = self.telecharger_etape(&url).await?;
+                if !(300..400).contains(&etape.code) {
+                    return Ok(etape);
+                }
+                let Some(suivante) = etape
+                    .entetes
+                    .iter()
+                    .find(|(nom, _)| nom.eq_ignore_ascii_case("location"))
+                    .and_then(|(_, valeur)| url.join(valeur).ok())
+                else {
+                    return Err(ErreurApi::Statut(
+                        StatusCode::from_u16(etape.code).unwrap_or(StatusCode::BAD_GATEWAY),
+                    ));
+                };
+                log::info!("{} redirige vers {}", url, suivante);
+                url = suivante;
+            }
+            Err(ErreurApi::Lecture(format!(
+                "plus de {} redirections",
+                MAX_REDIRECTIONS
+            )))
+        }
+
+        /// Nouvel essai, avec une attente doublee a chaque fois, sur une
+        /// erreur reseau, un 429 ou un 5xx.
+        async fn telecharger_avec_essais(&self, url: &Url) -> Result<Telechargement, ErreurApi> {
+            let mut attente = self.config.delai_domaine_ms.max(1000);
+            let mut tentative = 1;
+            loop {
+                match self.telecharger(url).await {
+                    Err(err) if tentative < self.config.tentatives && err.temporaire() => {
+                        log::warn!("{} : {}, nouvel essai dans {} ms", url, err, attente);
+                        tokio::time::sleep(Duration::from_millis(attente)).await;
+                        attente *= 2;
+                        tentative += 1;
+                    }
+                    res => return res,
+                }
+            }
+        }
+
+        async fn archiver_element(&self, id: &str, adresse: String, essais: u32) -> EtatArchive {
+            let mut etat = EtatArchive {
+                url: adresse.clone(),
+                essais: essais + 1,
+                date: Utc::now().timestamp(),
+                ..Default::default()
+            };
+            let url = match Url::parse(adresse.as_str()) {
+                Ok(url) => url,
+                Err(err) => {
+                    etat.statut = ARCHIVE_ABSENT.to_string();
+                    etat.erreur = Some(format!("URL invalide : {}", err));
+                    return etat;
+                }
+            };
+            let page = match self.telecharger_avec_essais(&url).await {
+                Ok(page) => page,
+                Err(err) => {
+                    etat.statut = if err.temporaire() {
+                        ARCHIVE_ERREUR
+                    } else {
+                        ARCHIVE_ABSENT
+                    }
+                    .to_string();
+                    if let ErreurApi::Statut(code) = err {
+                        etat.code_http = Some(code.as_u16());
+                    }
+                    etat.erreur = Some(err.to_string());
+                    return etat;
+                }
+            };
+            etat.code_http = Some(page.code);
+            etat.date = page.date.timestamp();
+            etat.url_finale = page.url_finale.clone();
+            etat.type_contenu = page.type_contenu();
+            etat.taille = page.corps.len() as u64;
+            match stocker(self.repertoire.as_str(), &page.corps) {
+                Ok(hash) => etat.hash = Some(hash),
+                Err(err) => {
+                    etat.statut = ARCHIVE_ERREUR.to_string();
+                    etat.erreur = Some(err.to_string());
+                    return etat;
+                }
+            }
+            etat.statut = ARCHIVE_OK.to_string();
+            let base = Url::parse(page.url_finale.as_str()).unwrap_or(url);
+            let html = String::from_utf8_lossy(&page.corps);
+            if self.config.extraction && etat.type_contenu.contains("html") {
+                etat.extraction = extraire_page(self.repertoire.as_str(), id, &html, &base);
+            }
+            if self.config.ressources && etat.type_contenu.contains("html") {
+                for ressource in ressources_page(&html, &base)
+                    .iter()
+                    .take(self.config.nb_ressources_max)
+                {
+                    let deja = self
+                        .ressources
+                        .lock()
+                        .expect("verrou des ressources")
+                        .get(ressource.as_str())
+                        .cloned();
+                    if let Some(deja) = deja {
+                        etat.ressources.push(deja);
+                        continue;
+                    }
+                    match self.telecharger_avec_essais(ressource).await {
+                        Ok(t) => match stocker(self.repertoire.as_str(), &t.corps) {
+                            Ok(hash) => {
+                                let nouvelle = Ressource {
+                                    url: ressource.to_string(),
+                                    hash,
+                                    type_contenu: t.type_contenu(),
+                                    taille: t.corps.len() as u64,
+                                };
+                                self.ressources
+                                    .lock()
+                                    .expect("verrou des ressources")
+                                    .insert(nouvelle.url.clone(), nouvelle.clone());
+                                etat.ressources.push(nouvelle);
+                            }
+                            Err(err) => log::error!("{} : {}", ressource, err),
+                        },
+                        Err(err) => log::info!("ressource {} : {}", ressource, err),
+                    }
+                }
+            }
+            etat
+        }
+    }
+
+    /// Extrait l'article et l'ecrit a cote des autres articles.
+    fn extraire_page(repertoire: &str, id: &str, html: &str, base: &Url) -> Option<Extraction> {
+        let article = extraire(html, base);
+        match ecrire_article(&repertoire_archive(repertoire), id, &article) {
+            Ok(_) => Some(Extraction::from(&article)),
+            Err(err) => {
+                log::error!("Erreur d'ecriture de l'article {} : {}", id, err);
+                None
+            }
+        }
+    }
+
+    fn liste_entetes(entetes: &HeaderMap) -> Vec<(String, String)> {
+        entetes
+            .iter()
+            .map(|(nom, valeur)| {
+                (
+                    nom.to_string(),
+                    String::from_utf8_lossy(valeur.as_bytes()).to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanArchive {
+        pub nb_archives: u64,
+        pub nb_erreurs: u64,
+        pub nb_absents: u64,
+        pub nb_ressources: u64,
+        pub octets: u64,
+        /// elements deja archives, ou en erreur apres `essais_max` essais
+        pub nb_ignores: u64,
+        pub nb_enregistrements_warc: u64,
+    }
+
+    impl fmt::Display for BilanArchive {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(f, "pages archivees : {}", self.nb_archives)?;
+            writeln!(f, "ressources : {}", self.nb_ressources)?;
+            writeln!(f, "octets telecharges : {}", self.octets)?;
+            writeln!(f, "erreurs (a reessayer) : {}", self.nb_erreurs)?;
+            writeln!(f, "pages absentes : {}", self.nb_absents)?;
+            writeln!(f, "ignores : {}", self.nb_ignores)?;
+            writeln!(f, "enregistrements WARC : {}", self.nb_enregistrements_warc)
+        }
+    }
+
+    /// Telecharge la page (`resolved_url`, sinon `given_url`) des elements
+    /// non encore archives, `concurrence` a la fois. L'etat de chaque
+    /// element est garde dans `archive/etat.json`, sauve regulierement.
+    /// `reessayer` redemande aussi les pages absentes ou abandonnees.
+    pub async fn archiver(
+        config: &ConfigArchive,
+        repertoire: &str,
+        data: &Value,
+        limite: Option<usize>,
+        reessayer: bool,
+    ) -> BilanArchive {
+        let mut etat = charger_etat(repertoire);
+        let mut bilan = BilanArchive::default();
+        let mut a_archiver: Vec<(String, String, u32)> = Vec::new();
+        for (id, element) in elements_tries(data) {
+            let adresse = url(element);
+            if statut(element) == STATUT_SUPPRIME || adresse.is_empty() {
+                continue;
+            }
+            let essais = match etat.get(id) {
+                None => 0,
+                Some(e) if e.statut == ARCHIVE_OK => {
+                    bilan.nb_ignores += 1;
+                    continue;
+                }
+                Some(e) if reessayer => e.essais,
+                Some(e) if e.statut == ARCHIVE_ERREUR && e.essais < config.essais_max => e.essais,
+                Some(_) => {
+                    bilan.nb_ignores += 1;
+                    continue;
+                }
+            };
+            a_archiver.push((id.clone(), adresse.to_string(), essais));
+        }
+        if let Some(limite) = limite {
+            a_archiver.truncate(limite);
+        }
+        log::info!("archivage : {} pages a telecharger", a_archiver.len());
+        let mut warc = None;
+        if config.warc && !a_archiver.is_empty() {
+            let dossier = repertoire_archive(repertoire).join("warc");
+            match EcrivainWarc::new(&dossier, config.warc_taille_max_mo * 1024 * 1024) {
+                Ok(ecrivain) => warc = Some(Mutex::new(ecrivain)),
+                Err(err) => log::error!("{} : {}", dossier.display(), err),
+            }
+        }
+        let contexte = Arc::new(Contexte {
+            client: Client::builder()
+                .redirect(Policy::none())
+                .build()
+                .unwrap_or_default(),
+            config: config.clone(),
+            repertoire: repertoire.to_string(),
+            creneaux: Mutex::new(HashMap::new()),
+            warc,
+            ressources: Mutex::new(HashMap::new()),
+        });
+        let mut taches = JoinSet::new();
+        let mut restants = a_archiver.into_iter();
+        let mut nb_termines = 0;
+        loop {
+            while taches.len() < config.concurrence.max(1) {
+                let Some((id, adresse, essais)) = restants.next() else {
+                    break;
+                };
+                let contexte = contexte.clone();
+                taches.spawn(async move {
+                    let resultat = contexte
+                        .archiver_element(id.as_str(), adresse, essais)
+                        .await;
+                    (id, resultat)
+                });
+            }
+            let Some(res) = taches.join_next().await else {
+                break;
+            };
+            let (id, resultat) = match res {
+                Ok(res) => res,
+                Err(err) => {
+                    log::error!("tache d'archivage : {}", err);
+                    continue;
+                }
+            };
+            match resultat.statut.as_str() {
+                ARCHIVE_OK => {
+                    bilan.nb_archives += 1;
+                    bilan.nb_ressources += resultat.ressources.len() as u64;
+                    bilan.octets +=
+                        resultat.taille + resultat.ressources.iter().map(|r| r.taille).sum::<u64>();
+                }
+                ARCHIVE_ERREUR => bilan.nb_erreurs += 1,
+                _ => bilan.nb_absents += 1,
+            }
+            log::info!(
+                "archive {} {} : {}",
+                id,
+                resultat.url,
+                resultat
+                    .erreur
+                    .as_deref()
+                    .unwrap_or(resultat.statut.as_str())
+            );
+            etat.insert(id, resultat);
+            nb_termines += 1;
+            if nb_termines % 20 == 0 {
+                sauver_etat(repertoire, &etat);
+            }
+        }
+        sauver_etat(repertoire, &etat);
+        let warc = Arc::into_inner(contexte).and_then(|c| c.warc);
+        if let Some(warc) = warc {
+            match warc.into_inner().expect("verrou WARC").terminer() {
+                Ok(nb) => bilan.nb_enregistrements_warc = nb,
+                Err(err) => log::error!("Erreur d'ecriture de l'index CDX : {}", err),
+            }
+        }
+        bilan
+    }
+
+    /// Ecart entre le nombre de mots extraits et le `word_count` de Pocket.
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EcartMots {
+        pub item_id: String,
+        pub url: String,
+        pub nb_mots: u64,
+        pub word_count: i64,
+        /// en pourcentage du `word_count`
+        pub ecart: f64,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanExtraction {
+        pub nb_extraits: u64,
+        /// pages sans contenu reconnu
+        pub nb_vides: u64,
+        /// elements ayant un `word_count` Pocket
+        pub nb_compares: u64,
+        pub ecart_median: f64,
+        /// ecarts superieurs au seuil, du plus grand au plus petit
+        pub ecarts: Vec<EcartMots>,
+    }
+
+    impl fmt::Display for BilanExtraction {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(f, "articles extraits : {}", self.nb_extraits)?;
+            writeln!(f, "sans contenu : {}", self.nb_vides)?;
+            writeln!(
+                f,
+                "compares a word_count : {}, ecart median {:.1} %",
+                self.nb_compares, self.ecart_median
+            )?;
+            for ecart in &self.ecarts {
+                writeln!(
+                    f,
+                    "  {} {} : {} mots, word_count {} ({:+.1} %)",
+                    ecart.item_id, ecart.url, ecart.nb_mots, ecart.word_count, ecart.ecart
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Extrait a nouveau les pages deja archivees (toutes, ou `item`) et
+    /// compare le nombre de mots au `word_count` de Pocket ; les ecarts
+    /// au dela de `seuil` % sont listes.
+    pub fn extraire_archives(
+        repertoire: &str,
+        data: &Value,
+        item: Option<&str>,
+        seuil: f64,
+    ) -> BilanExtraction {
+        let mut etat = charger_etat(repertoire);
+        let mut bilan = BilanExtraction::default();
+        let mut ecarts = Vec::new();
+        let elements = liste(data);
+        for (id, archive) in etat.iter_mut() {
+            if item.is_some_and(|i| i != id) || archive.statut != ARCHIVE_OK {
+                continue;
+            }
+            let Some(hash) = &archive.hash else {
+                continue;
+            };
+            if !archive.type_contenu.contains("html") {
+                continue;
+            }
+            let contenu = match lire_objet(repertoire, hash) {
+                Ok(contenu) => contenu,
+                Err(err) => {
+                    log::error!("objet {} de {} : {}", hash, id, err);
+                    continue;
+                }
+            };
+            let Ok(base) = Url::parse(archive.url_finale.as_str())
+                .or_else(|_| Url::parse(archive.url.as_str()))
+            else {
+                continue;
+            };
+            let html = String::from_utf8_lossy(&contenu);
+            archive.extraction = extraire_page(repertoire, id, &html, &base);
+            let Some(extraction) = &archive.extraction else {
+                continue;
+            };
+            bilan.nb_extraits += 1;
+            if extraction.nb_mots == 0 {
+                bilan.nb_vides += 1;
+            }
+            let word_count = elements
+                .and_then(|l| l.get(id))
+                .map(|e| champ_nombre(e, "word_count"))
+                .unwrap_or(0);
+            if word_count > 0 {
+                let ecart =
+                    (extraction.nb_mots as f64 - word_count as f64) * 100.0 / word_count as f64;
+                ecarts.push(EcartMots {
+                    item_id: id.clone(),
+                    url: archive.url.clone(),
+                    nb_mots: extraction.nb_mots,
+                    word_count,
+                    ecart,
+                });
+            }
+        }
+        sauver_etat(repertoire, &etat);
+        bilan.nb_compares = ecarts.len() as u64;
+        let mut absolus: Vec<f64> = ecarts.iter().map(|e| e.ecart.abs()).collect();
+        absolus.sort_by(|a, b| a.total_cmp(b));
+        if !absolus.is_empty() {
+            bilan.ecart_median = absolus[absolus.len() / 2];
+        }
+        ecarts.retain(|e| e.ecart.abs() > seuil);
+        ecarts.sort_by(|a, b| b.ecart.abs().total_cmp(&a.ecart.abs()));
+        bilan.ecarts = ecarts;
+        bilan
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::thread;
+
+        #[test]
+        fn stockage_concurrent_du_meme_objet() {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_objets_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            let repertoire = dossier.to_string_lossy().to_string();
+            let taches: Vec<_> = (0..8)
+                .map(|_| {
+                    let repertoire = repertoire.clone();
+                    thread::spawn(move || stocker(&repertoire, b"<html>page</html>"))
+                })
+                .collect();
+            let hashes: Vec<String> = taches
+                .into_iter()
+                .map(|t| t.join().unwrap().unwrap())
+                .collect();
+            assert!(hashes.iter().all(|h| *h == hashes[0]));
+            assert_eq!(
+                lire_objet(&repertoire, &hashes[0]).unwrap(),
+                b"<html>page</html>"
+            );
+            let parent = chemin_objet(&repertoire, &hashes[0]);
+            let fichiers = fs::read_dir(parent.parent().unwrap()).unwrap().count();
+            assert_eq!(fichiers, 1);
+            let _ = fs::remove_dir_all(&dossier);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+pub mod mod_bouchon {
+    use reqwest::Url;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use tiny_http::{Header, Response, Server};
+
+    /// Requete recue par le bouchon.
+    #[derive(Debug, Clone)]
+    pub struct Requete {
+        pub methode: String,
+        /// chemin et parametres (`/api/entries?page=2`)
+        pub url: String,
+        /// entete `Authorization`, vide s'il est absent
+        pub autorisation: String,
+        pub corps: String,
+    }
+
+    impl Requete {
+        pub fn chemin(&self) -> String {
+            self.url.split('?').next().unwrap_or("").to_string()
+        }
+
+        /// Valeur d'un parametre de la chaine de requete.
+        pub fn parametre(&self, nom: &str) -> Option<String> {
+            Url::parse(format!("http://localhost{}", self.url).as_str())
+                .ok()?
+                .query_pairs()
+                .find(|(k, _)| k == nom)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    /// Serveur HTTP local qui remplace un service dans les tests des
+    /// sources : chaque requete est enregistree puis recoit la reponse
+    /// `(code, corps JSON)` calculee par `repondre`.
+    ///
+    /// `demarrer_avec_entetes` ajoute des entetes a la reponse
+    /// (`Retry-After`...).
+    pub struct Bouchon {
+        pub adresse: String,
+        requetes: Arc<Mutex<Vec<Requete>>>,
+    }
+
+    impl Bouchon {
+        pub fn demarrer<F>(repondre: F) -> Bouchon
+        where
+            F: Fn(&Requete) -> (u16, String) + Send + 'static,
+        {
+            Bouchon::demarrer_avec_entetes(move |requete| {
+                let (code, corps) = repondre(requete);
+                (code, corps, Vec::new())
+            })
+        }
+
+        pub fn demarrer_avec_entetes<F>(repondre: F) -> Bouchon
+        where
+            F: Fn(&Requete) -> (u16, String, Vec<(&'static str, String)>) + Send + 'static,
+        {
+            let serveur = Server::http("127.0.0.1:0").expect("port local libre");
+            let adresse = format!(
+                "http://{}",
+                serveur.server_addr().to_ip().expect("adresse IP")
+            );
+            let requetes: Arc<Mutex<Vec<Requete>>> = Arc::new(Mutex::new(Vec::new()));
+            let enregistrees = requetes.clone();
+            thread::spawn(move || {
+                for mut requete in serveur.incoming_requests() {
+                    let mut corps = String::new();
+                    let _ = requete.as_reader().read_to_string(&mut corps);
+                    let recue = Requete {
+                        methode: requete.method().to_string(),
+                        url: requete.url().to_string(),
+                        autorisation: requete
+                            .headers()
+                            .iter()
+                            .find(|h| h.field.equiv("Authorization"))
+                            .map(|h| h.value.to_string())
+                            .unwrap_or_default(),
+                        corps,
+                    };
+                    let (code, reponse, entetes) = repondre(&recue);
+                    enregistrees.lock().unwrap().push(recue);
+                    let entete =
+                        Header::from_bytes("Content-Type", "application/json").expect("entete");
+                    let mut reponse = Response::from_string(reponse)
+                        .with_status_code(code)
+                        .with_header(entete);
+                    for (nom, valeur) in entetes {
+                        reponse.add_header(
+                            Header::from_bytes(nom.as_bytes(), valeur.as_bytes()).expect("entete"),
+                        );
+                    }
+                    let _ = requete.respond(reponse);
+                }
+            });
+            Bouchon { adresse, requetes }
+        }
+
+        pub fn requetes(&self) -> Vec<Requete> {
+            self.requetes.lock().unwrap().clone()
+        }
+    }
+}
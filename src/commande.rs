@@ -0,0 +1,955 @@
+pub mod mod_commande {
+    use crate::archive::mod_archive::{archiver, extraire_archives};
+    use crate::config::mod_config::Config2;
+    use crate::donnees::mod_donnees::{
+        charger_donnees, donnees_initiales, fichier_data, fichier_param, param_initial,
+        sauver_donnees, sauver_param,
+    };
+    use crate::export::mod_export::{
+        export_bookmarks_html, export_tableau, FormatDate, OptionsTableau, COLONNES_DEFAUT,
+        FORMAT_BOOKMARKS_HTML, FORMAT_CSV, FORMAT_TSV,
+    };
+    use crate::filtre::mod_filtre::{
+        ecrire_liste, filtrer, Filtres, FormatListe, Tri, NOMS_FILTRES,
+    };
+    use crate::historique::mod_historique::{charger_historique, derniere_execution_reussie};
+    use crate::import::mod_import::{fusionner, lire_export};
+    use crate::instantane::mod_instantane::{
+        chemin_instantane, comparer_instantanes, dernier_instantane, lire_instantane, restaurer,
+        verifier, verifier_tous, BilanVerification,
+    };
+    use crate::notes::mod_notes::{export_notes, FormatNote, Regroupement};
+    use crate::push::mod_push::pousser;
+    use crate::recherche::mod_recherche::{index_a_jour, rechercher, Classement};
+    use crate::reconciliation::mod_reconciliation::{comparer, lister_distant, recuperer};
+    use crate::retention::mod_retention::{elaguer, lire_nom};
+    use crate::stats::mod_stats::{calcul_stats, ecrire_csv, formater, FormatSortie};
+    use crate::statut::mod_statut::{calcul_statut, CODE_CRITIQUE, CODE_OK};
+    use crate::trous::mod_trous::{
+        ajouter_a_la_file, detecter_trous, trous_executions_en_echec, Trou,
+    };
+    use crate::verrou::mod_verrou::Verrou;
+    use crate::web::mod_web::servir;
+    use chrono::Local;
+    use serde_json::Value;
+    use std::fs;
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+    use std::path::{Path, PathBuf};
+
+    pub const COMMANDE_STATS: &str = "stats";
+    pub const COMMANDE_GAPS: &str = "gaps";
+    pub const COMMANDE_RECONCILE: &str = "reconcile";
+    pub const COMMANDE_HISTORY: &str = "history";
+    pub const COMMANDE_STATUS: &str = "status";
+    pub const COMMANDE_EXPORT: &str = "export";
+    pub const COMMANDE_IMPORT: &str = "import";
+    pub const COMMANDE_PUSH: &str = "push";
+    pub const COMMANDE_ARCHIVE: &str = "archive";
+    pub const COMMANDE_EXTRACT: &str = "extract";
+    pub const COMMANDE_SEARCH: &str = "search";
+    pub const COMMANDE_LIST: &str = "list";
+    pub const COMMANDE_SERVE: &str = "serve";
+    pub const COMMANDE_PRUNE: &str = "prune";
+    pub const COMMANDE_VERIFY: &str = "verify";
+    pub const COMMANDE_DIFF: &str = "diff";
+    pub const COMMANDE_RESTORE: &str = "restore";
+
+    /// Les commandes sont passees apres le fichier de configuration :
+    /// `pbackup <config_file> <commande> [options]`.
+    pub fn est_commande(args: &[String]) -> bool {
+        matches!(
+            args.get(2).map(|s| s.as_str()),
+            Some(COMMANDE_STATS)
+                | Some(COMMANDE_GAPS)
+                | Some(COMMANDE_RECONCILE)
+                | Some(COMMANDE_HISTORY)
+                | Some(COMMANDE_STATUS)
+                | Some(COMMANDE_EXPORT)
+                | Some(COMMANDE_IMPORT)
+                | Some(COMMANDE_PUSH)
+                | Some(COMMANDE_ARCHIVE)
+                | Some(COMMANDE_EXTRACT)
+                | Some(COMMANDE_SEARCH)
+                | Some(COMMANDE_LIST)
+                | Some(COMMANDE_SERVE)
+                | Some(COMMANDE_PRUNE)
+                | Some(COMMANDE_VERIFY)
+                | Some(COMMANDE_DIFF)
+                | Some(COMMANDE_RESTORE)
+        )
+    }
+
+    /// Valeur d'une option `--nom valeur`.
+    pub fn option(args: &[String], nom: &str) -> Option<String> {
+        let pos = args.iter().position(|a| a == nom)?;
+        args.get(pos + 1).cloned()
+    }
+
+    /// Presence d'une option sans valeur (`--dry-run`).
+    pub fn drapeau(args: &[String], nom: &str) -> bool {
+        args.iter().any(|a| a == nom)
+    }
+
+    /// Retourne le code de sortie du programme.
+    pub async fn executer_commande(config: &Config2, args: &[String]) -> i32 {
+        match args[2].as_str() {
+            COMMANDE_STATS => commande_stats(config, args),
+            COMMANDE_GAPS => commande_gaps(config, args),
+            COMMANDE_RECONCILE => commande_reconcile(config, args).await,
+            COMMANDE_HISTORY => commande_history(config, args),
+            COMMANDE_STATUS => commande_status(config, args),
+            COMMANDE_EXPORT => commande_export(config, args),
+            COMMANDE_IMPORT => commande_import(config, args),
+            COMMANDE_PUSH => commande_push(config, args).await,
+            COMMANDE_ARCHIVE => commande_archive(config, args).await,
+            COMMANDE_EXTRACT => commande_extract(config, args),
+            COMMANDE_SEARCH => commande_search(config, args),
+            COMMANDE_LIST => commande_list(config, args),
+            COMMANDE_SERVE => commande_serve(config, args),
+            COMMANDE_PRUNE => commande_prune(config, args),
+            COMMANDE_VERIFY => commande_verify(config, args),
+            COMMANDE_DIFF => commande_diff(config, args),
+            COMMANDE_RESTORE => commande_restore(config, args),
+            _ => 1,
+        }
+    }
+
+    /// Verrou des commandes qui reecrivent data.json ou param.json, pour ne
+    /// pas croiser une execution de la sauvegarde.
+    fn verrouiller(config: &Config2) -> Option<Verrou> {
+        match Verrou::prendre(config.repertoire.as_str()) {
+            Ok(verrou) => Some(verrou),
+            Err(err) => {
+                log::error!("{}", err);
+                None
+            }
+        }
+    }
+
+    fn format_sortie(args: &[String]) -> Option<FormatSortie> {
+        let format = option(args, "--format").unwrap_or_default();
+        let res = FormatSortie::parse(format.as_str());
+        if res.is_none() {
+            log::error!("format inconnu : {}", format);
+        }
+        res
+    }
+
+    fn commande_stats(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let nb_domaines = option(args, "--top")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(20);
+        let fichier = fichier_data(config.repertoire.as_str());
+        let Some(data) = charger_donnees(fichier.as_str()) else {
+            log::error!("fichier {} absent", fichier);
+            return 1;
+        };
+        let rapport = calcul_stats(&data, nb_domaines);
+        ecrire_sortie(args, formater(&rapport, format).as_str())
+    }
+
+    fn commande_gaps(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let fichier = fichier_data(config.repertoire.as_str());
+        let Some(data) = charger_donnees(fichier.as_str()) else {
+            log::error!("fichier {} absent", fichier);
+            return 1;
+        };
+        let mut trous = detecter_trous(&data, &config.trous);
+        trous.append(&mut trous_executions_en_echec(&charger_historique(
+            config.repertoire.as_str(),
+        )));
+        log::info!("nb trous : {}", trous.len());
+        if drapeau(args, "--recharger") {
+            match ajouter_a_la_file(config.repertoire.as_str(), &trous) {
+                Ok(dates) => log::info!("dates a recharger : {:?}", dates),
+                Err(err) => {
+                    log::error!("Erreur d'ecriture de la file de rechargement : {}", err);
+                    return 1;
+                }
+            }
+        }
+        ecrire_sortie(args, formater_trous(&trous, format).as_str())
+    }
+
+    fn formater_trous(trous: &[Trou], format: FormatSortie) -> String {
+        match format {
+            FormatSortie::Texte => trous.iter().map(|t| format!("{}\n", t)).collect(),
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(trous).expect("Erreur de sérialisation")
+            }
+            FormatSortie::Csv => {
+                let mut lignes = vec![[
+                    "debut",
+                    "fin",
+                    "nb_jours",
+                    "densite_avant",
+                    "densite_apres",
+                    "raison",
+                ]
+                .map(String::from)
+                .to_vec()];
+                for t in trous {
+                    lignes.push(vec![
+                        t.debut.to_string(),
+                        t.fin.to_string(),
+                        t.nb_jours.to_string(),
+                        format!("{:.2}", t.densite_avant),
+                        format!("{:.2}", t.densite_apres),
+                        t.raison.clone(),
+                    ]);
+                }
+                ecrire_csv(&lignes)
+            }
+        }
+    }
+
+    async fn commande_reconcile(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let recuperation = drapeau(args, "--recuperer");
+        let _verrou = if recuperation {
+            let Some(verrou) = verrouiller(config) else {
+                return 1;
+            };
+            Some(verrou)
+        } else {
+            None
+        };
+        let fichier = fichier_data(config.repertoire.as_str());
+        let Some(mut data) = charger_donnees(fichier.as_str()) else {
+            log::error!("fichier {} absent", fichier);
+            return 1;
+        };
+        let distant = match lister_distant(config, recuperation).await {
+            Ok(distant) => distant,
+            Err(err) => {
+                log::error!("{}", err);
+                return 1;
+            }
+        };
+        let mut reconciliation = comparer(&data, &distant);
+        if recuperation
+            && (!reconciliation.manquants_local.is_empty() || !reconciliation.obsoletes.is_empty())
+        {
+            if let Err(err) = crate::backup_data(config.clone(), &fichier, &"data".to_string()) {
+                log::error!("Erreur de copie de {} : {}", fichier, err);
+                return 1;
+            }
+            recuperer(&mut data, &distant, &mut reconciliation);
+            sauver_donnees(&data, fichier.as_str());
+        }
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&reconciliation).expect("Erreur de sérialisation")
+            }
+            _ => reconciliation.to_string(),
+        };
+        ecrire_sortie(args, contenu.as_str())
+    }
+
+    fn commande_history(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let nb = option(args, "--nb")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(20);
+        let historique = charger_historique(config.repertoire.as_str());
+        let derniere = derniere_execution_reussie(&historique);
+        let affichees = &historique[historique.len().saturating_sub(nb)..];
+        let contenu = match format {
+            FormatSortie::Json => serde_json::to_string_pretty(&serde_json::json!({
+                "executions": affichees,
+                "derniereReussie": derniere,
+            }))
+            .expect("Erreur de sérialisation"),
+            _ => {
+                let mut s: String = affichees.iter().map(|r| format!("{}\n", r)).collect();
+                match derniere {
+                    Some(r) => s.push_str(
+                        format!(
+                            "derniere execution reussie : {} (il y a {} h)\n",
+                            r.fin.format("%Y-%m-%d %H:%M:%S"),
+                            (Local::now().fixed_offset() - r.fin).num_hours()
+                        )
+                        .as_str(),
+                    ),
+                    None => s.push_str("aucune execution reussie\n"),
+                }
+                s
+            }
+        };
+        ecrire_sortie(args, contenu.as_str())
+    }
+
+    /// Code de sortie au format Nagios : 0 si a jour, 2 sinon.
+    fn commande_status(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let age_max = option(args, "--age-max")
+            .and_then(|s| s.parse::<i64>().ok())
+            .unwrap_or(config.statut.age_max_heures);
+        let statut = calcul_statut(config, age_max);
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&statut).expect("Erreur de sérialisation")
+            }
+            _ => statut.to_string(),
+        };
+        let code = ecrire_sortie(args, contenu.as_str());
+        if code != 0 {
+            code
+        } else if statut.a_jour {
+            CODE_OK
+        } else {
+            CODE_CRITIQUE
+        }
+    }
+
+    fn commande_export(config: &Config2, args: &[String]) -> i32 {
+        let format = option(args, "--format").unwrap_or_default();
+        let fichier = fichier_data(config.repertoire.as_str());
+        let Some(data) = charger_donnees(fichier.as_str()) else {
+            log::error!("fichier {} absent", fichier);
+            return 1;
+        };
+        if let Some(format_note) = FormatNote::parse(format.as_str()) {
+            return commande_export_notes(&data, format_note, args);
+        }
+        let mut sortie = match ouvrir_sortie(args) {
+            Ok(sortie) => sortie,
+            Err(err) => {
+                log::error!("Erreur d'ouverture de la sortie : {}", err);
+                return 1;
+            }
+        };
+        let res = match format.as_str() {
+            FORMAT_BOOKMARKS_HTML => export_bookmarks_html(&data, &mut sortie),
+            FORMAT_CSV | FORMAT_TSV => {
+                let Some(options) = options_tableau(args, format.as_str()) else {
+                    return 1;
+                };
+                export_tableau(&data, &options, &mut sortie)
+            }
+            _ => {
+                log::error!("format d'export inconnu : {}", format);
+                return 1;
+            }
+        };
+        match res {
+            Ok(_) => 0,
+            Err(err) => {
+                log::error!("Erreur d'export : {}", err);
+                1
+            }
+        }
+    }
+
+    /// `--fichier <export>` : export Pocket (ril_export.html, CSV, ZIP),
+    /// Instapaper, Raindrop.io ou Wallabag ; `--source` force le format. Avec
+    /// `--dry-run`, le bilan est affiche sans modifier `data.json`.
+    fn commande_import(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let Some(fichier_import) = option(args, "--fichier") else {
+            log::error!("option --fichier obligatoire");
+            return 1;
+        };
+        let (source, elements) =
+            match lire_export(fichier_import.as_str(), option(args, "--source").as_deref()) {
+                Ok(res) => res,
+                Err(err) => {
+                    log::error!("{} : {}", fichier_import, err);
+                    return 1;
+                }
+            };
+        let dry_run = drapeau(args, "--dry-run");
+        let _verrou = if dry_run {
+            None
+        } else {
+            let Some(verrou) = verrouiller(config) else {
+                return 1;
+            };
+            Some(verrou)
+        };
+        let fichier = fichier_data(config.repertoire.as_str());
+        let existe = Path::new(&fichier).exists();
+        let mut data = charger_donnees(fichier.as_str()).unwrap_or_else(donnees_initiales);
+        let bilan = fusionner(&mut data, &elements, source);
+        if !dry_run && (!bilan.ajoutes.is_empty() || bilan.nb_completes > 0) {
+            if existe {
+                if let Err(err) = crate::backup_data(config.clone(), &fichier, &"data".to_string())
+                {
+                    log::error!("Erreur de copie de {} : {}", fichier, err);
+                    return 1;
+                }
+            }
+            sauver_donnees(&data, fichier.as_str());
+            if !existe {
+                // la premiere execution doit faire un parcourt complet
+                let fichier_param = fichier_param(config.repertoire.as_str());
+                sauver_param(&param_initial(), fichier_param.as_str());
+            }
+        }
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&bilan).expect("Erreur de sérialisation")
+            }
+            _ => bilan.to_string(),
+        };
+        ecrire_sortie(args, contenu.as_str())
+    }
+
+    /// Envoie les elements vers la cible de `[push]`. `--limite N` borne le
+    /// nombre d'envois, `--dry-run` affiche ce qui serait envoye.
+    async fn commande_push(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let fichier = fichier_data(config.repertoire.as_str());
+        let Some(data) = charger_donnees(fichier.as_str()) else {
+            log::error!("impossible de lire {}", fichier);
+            return 1;
+        };
+        let limite = option(args, "--limite").and_then(|s| s.parse::<usize>().ok());
+        let Some(bilan) = pousser(
+            &config.push,
+            config.repertoire.as_str(),
+            &data,
+            drapeau(args, "--dry-run"),
+            limite,
+        )
+        .await
+        else {
+            return 1;
+        };
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&bilan).expect("Erreur de sérialisation")
+            }
+            _ => bilan.to_string(),
+        };
+        let code = ecrire_sortie(args, contenu.as_str());
+        if code == 0 && !bilan.erreurs.is_empty() {
+            2
+        } else {
+            code
+        }
+    }
+
+    /// Telecharge les pages non archivees. `--limite N` borne le nombre de
+    /// pages, `--reessayer` redemande les pages absentes ou abandonnees.
+    async fn commande_archive(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let fichier = fichier_data(config.repertoire.as_str());
+        let Some(data) = charger_donnees(fichier.as_str()) else {
+            log::error!("impossible de lire {}", fichier);
+            return 1;
+        };
+        let limite = option(args, "--limite").and_then(|s| s.parse::<usize>().ok());
+        let bilan = archiver(
+            &config.archive,
+            config.repertoire.as_str(),
+            &data,
+            limite,
+            drapeau(args, "--reessayer"),
+        )
+        .await;
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&bilan).expect("Erreur de sérialisation")
+            }
+            _ => bilan.to_string(),
+        };
+        ecrire_sortie(args, contenu.as_str())
+    }
+
+    /// Extrait a nouveau les articles des pages archivees (`--item <id>`
+    /// pour un seul) ; les ecarts de plus de `--ecart` % (30 par defaut)
+    /// avec le `word_count` de Pocket sont listes.
+    fn commande_extract(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let fichier = fichier_data(config.repertoire.as_str());
+        let data = charger_donnees(fichier.as_str()).unwrap_or(serde_json::json!({}));
+        let seuil = option(args, "--ecart")
+            .and_then(|s| s.parse::<f64>().ok())
+            .unwrap_or(30.0);
+        let bilan = extraire_archives(
+            config.repertoire.as_str(),
+            &data,
+            option(args, "--item").as_deref(),
+            seuil,
+        );
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&bilan).expect("Erreur de sérialisation")
+            }
+            _ => bilan.to_string(),
+        };
+        ecrire_sortie(args, contenu.as_str())
+    }
+
+    /// `search <requete> [--limite N] [--tri pertinence|date] [--reconstruire]` ;
+    /// les arguments qui ne sont pas des options forment la requete.
+    fn commande_search(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let mut mots: Vec<&str> = Vec::new();
+        let mut reste = args[3..].iter();
+        while let Some(arg) = reste.next() {
+            match arg.as_str() {
+                "--limite" | "--tri" | "--format" | "--sortie" => {
+                    reste.next();
+                }
+                "--reconstruire" => {}
+                _ => mots.push(arg.as_str()),
+            }
+        }
+        let classement = match option(args, "--tri").unwrap_or_default().as_str() {
+            "" | "pertinence" => Classement::Pertinence,
+            "date" => Classement::Date,
+            autre => {
+                log::error!("tri inconnu : {}", autre);
+                return 1;
+            }
+        };
+        let limite = option(args, "--limite")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(20);
+        let index = match index_a_jour(
+            &config.recherche,
+            config.repertoire.as_str(),
+            drapeau(args, "--reconstruire"),
+        ) {
+            Ok(index) => index,
+            Err(err) => {
+                log::error!("Erreur de mise a jour de l'index de recherche : {}", err);
+                return 1;
+            }
+        };
+        let bilan = match rechercher(&index, mots.join(" ").as_str(), classement, limite) {
+            Ok(bilan) => bilan,
+            Err(err) => {
+                log::error!("requete invalide : {}", err);
+                return 1;
+            }
+        };
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&bilan).expect("Erreur de sérialisation")
+            }
+            _ => bilan.to_string(),
+        };
+        ecrire_sortie(args, contenu.as_str())
+    }
+
+    /// Filtres `--statut`, `--favori`, `--tag`, `--sans-tag`, `--domaine`,
+    /// `--lang`, `--ajoute`, `--maj`, `--mots`, `--sans`, `--avec` (chacun
+    /// peut etre repete), `--tri`, `--limite`, `--format table|jsonl|urls`.
+    fn commande_list(config: &Config2, args: &[String]) -> i32 {
+        let format = option(args, "--format").unwrap_or_default();
+        let Some(format) = FormatListe::parse(format.as_str()) else {
+            log::error!("format inconnu : {}", format);
+            return 1;
+        };
+        let mut filtres = Filtres::default();
+        for paire in args[3..].windows(2) {
+            let Some(nom) = paire[0].strip_prefix("--") else {
+                continue;
+            };
+            if !NOMS_FILTRES.contains(&nom) {
+                continue;
+            }
+            if let Err(err) = filtres.ajouter(nom, paire[1].as_str()) {
+                log::error!("--{} : {}", nom, err);
+                return 1;
+            }
+        }
+        let tri = match option(args, "--tri") {
+            Some(tri) => match Tri::parse(tri.as_str()) {
+                Ok(tri) => tri,
+                Err(err) => {
+                    log::error!("{}", err);
+                    return 1;
+                }
+            },
+            None => Tri::default(),
+        };
+        let fichier = fichier_data(config.repertoire.as_str());
+        let Some(data) = charger_donnees(fichier.as_str()) else {
+            log::error!("fichier {} absent", fichier);
+            return 1;
+        };
+        let mut elements = filtrer(&data, &filtres, tri);
+        log::info!("{} elements", elements.len());
+        if let Some(limite) = option(args, "--limite").and_then(|s| s.parse::<usize>().ok()) {
+            elements.truncate(limite);
+        }
+        let mut sortie = match ouvrir_sortie(args) {
+            Ok(sortie) => sortie,
+            Err(err) => {
+                log::error!("Erreur d'ouverture de la sortie : {}", err);
+                return 1;
+            }
+        };
+        match ecrire_liste(&mut sortie, &elements, format).and_then(|_| sortie.flush()) {
+            Ok(_) => 0,
+            Err(err) => {
+                log::error!("Erreur d'ecriture : {}", err);
+                1
+            }
+        }
+    }
+
+    /// `--adresse` et `--port` remplacent ceux de `[serveur]`.
+    fn commande_serve(config: &Config2, args: &[String]) -> i32 {
+        let adresse = option(args, "--adresse").unwrap_or(config.serveur.adresse.clone());
+        let port = option(args, "--port")
+            .and_then(|s| s.parse::<u16>().ok())
+            .unwrap_or(config.serveur.port);
+        match servir(config, adresse.as_str(), port) {
+            Ok(_) => 0,
+            Err(err) => {
+                log::error!("Erreur du serveur sur {}:{} : {}", adresse, port, err);
+                1
+            }
+        }
+    }
+
+    /// Applique la retention de `[retention]` a `backup/` ; `--dry-run`
+    /// affiche les copies qui seraient supprimees.
+    fn commande_prune(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        if !config.retention.est_active() {
+            log::warn!("aucune regle dans [retention] : toutes les copies sont gardees");
+        }
+        let bilan = match elaguer(
+            &config.retention,
+            config.repertoire.as_str(),
+            drapeau(args, "--dry-run"),
+        ) {
+            Ok(bilan) => bilan,
+            Err(err) => {
+                log::error!("Erreur de lecture de backup/ : {}", err);
+                return 1;
+            }
+        };
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&bilan).expect("Erreur de sérialisation")
+            }
+            _ => bilan.to_string(),
+        };
+        let code = ecrire_sortie(args, contenu.as_str());
+        if bilan.erreurs.is_empty() {
+            code
+        } else {
+            1
+        }
+    }
+
+    /// Serie (`data` ou `param`) d'apres le nom de l'instantane.
+    fn serie_instantane(chemin: &Path) -> Option<String> {
+        let nom = chemin.file_name()?.to_string_lossy().to_string();
+        lire_nom(nom.as_str()).map(|(serie, _)| serie)
+    }
+
+    /// Relit les instantanes de `backup/`, compresses ou non ; `--fichier`
+    /// n'en verifie qu'un. Code de sortie 1 si l'un d'eux est illisible.
+    fn commande_verify(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let repertoire = config.repertoire.as_str();
+        let bilan = match option(args, "--fichier") {
+            Some(nom) => {
+                let chemin = chemin_instantane(repertoire, nom.as_str());
+                let serie = serie_instantane(&chemin).unwrap_or_default();
+                let verification = verifier(&chemin, serie.as_str());
+                BilanVerification {
+                    nb_erreurs: usize::from(verification.erreur.is_some()),
+                    verifications: vec![verification],
+                }
+            }
+            None => match verifier_tous(repertoire) {
+                Ok(bilan) => bilan,
+                Err(err) => {
+                    log::error!("Erreur de lecture de backup/ : {}", err);
+                    return 1;
+                }
+            },
+        };
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&bilan).expect("Erreur de sérialisation")
+            }
+            _ => bilan.to_string(),
+        };
+        let code = ecrire_sortie(args, contenu.as_str());
+        if bilan.nb_erreurs > 0 {
+            1
+        } else {
+            code
+        }
+    }
+
+    /// Compare l'instantane `--fichier` (par defaut le plus recent de
+    /// `data`) a `--avec` (par defaut `data.json`).
+    fn commande_diff(config: &Config2, args: &[String]) -> i32 {
+        let Some(format) = format_sortie(args) else {
+            return 1;
+        };
+        let repertoire = config.repertoire.as_str();
+        let avant = match option(args, "--fichier") {
+            Some(nom) => chemin_instantane(repertoire, nom.as_str()),
+            None => match dernier_instantane(repertoire, "data") {
+                Ok(Some(chemin)) => chemin,
+                Ok(None) => {
+                    log::error!("aucun instantane de data.json dans backup/");
+                    return 1;
+                }
+                Err(err) => {
+                    log::error!("Erreur de lecture de backup/ : {}", err);
+                    return 1;
+                }
+            },
+        };
+        let apres = match option(args, "--avec") {
+            Some(nom) => chemin_instantane(repertoire, nom.as_str()),
+            None => PathBuf::from(fichier_data(repertoire)),
+        };
+        let mut valeurs = Vec::new();
+        for chemin in [&avant, &apres] {
+            match lire_instantane(chemin) {
+                Ok(valeur) => valeurs.push(valeur),
+                Err(err) => {
+                    log::error!("{} : {}", chemin.display(), err);
+                    return 1;
+                }
+            }
+        }
+        let mut difference = comparer_instantanes(&valeurs[0], &valeurs[1]);
+        difference.avant = avant.display().to_string();
+        difference.apres = apres.display().to_string();
+        let contenu = match format {
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(&difference).expect("Erreur de sérialisation")
+            }
+            _ => difference.to_string(),
+        };
+        ecrire_sortie(args, contenu.as_str())
+    }
+
+    /// Remet l'instantane `--fichier` a la place de `data.json` ou
+    /// `param.json` selon sa serie, apres une copie du fichier remplace.
+    /// `--dry-run` verifie l'instantane sans rien modifier.
+    fn commande_restore(config: &Config2, args: &[String]) -> i32 {
+        let Some(nom) = option(args, "--fichier") else {
+            log::error!("option --fichier obligatoire");
+            return 1;
+        };
+        let repertoire = config.repertoire.as_str();
+        let chemin = chemin_instantane(repertoire, nom.as_str());
+        let serie = serie_instantane(&chemin).unwrap_or_default();
+        let destination = match serie.as_str() {
+            "data" => fichier_data(repertoire),
+            "param" => fichier_param(repertoire),
+            _ => {
+                log::error!("{} : serie inconnue, data_ ou param_ attendu", chemin.display());
+                return 1;
+            }
+        };
+        let verification = verifier(&chemin, serie.as_str());
+        if let Some(err) = verification.erreur {
+            log::error!("{} : {}", chemin.display(), err);
+            return 1;
+        }
+        if drapeau(args, "--dry-run") {
+            println!(
+                "{} remplacerait {} (dry-run)",
+                chemin.display(),
+                destination
+            );
+            return 0;
+        }
+        let Some(_verrou) = verrouiller(config) else {
+            return 1;
+        };
+        if let Err(err) = crate::backup_data(config.clone(), &destination, &serie) {
+            log::error!("Erreur de copie de {} : {}", destination, err);
+            return 1;
+        }
+        if let Err(err) = restaurer(&chemin, destination.as_str()) {
+            log::error!("Erreur de restauration de {} : {}", chemin.display(), err);
+            return 1;
+        }
+        println!("{} restaure dans {}", chemin.display(), destination);
+        0
+    }
+
+    /// `--dossier <rep>` obligatoire, `--par element|mois`.
+    fn commande_export_notes(data: &Value, format: FormatNote, args: &[String]) -> i32 {
+        let Some(dossier) = option(args, "--dossier") else {
+            log::error!("option --dossier obligatoire pour l'export en notes");
+            return 1;
+        };
+        let regroupement = match option(args, "--par").unwrap_or_default().as_str() {
+            "" | "element" => Regroupement::Element,
+            "mois" => Regroupement::Mois,
+            autre => {
+                log::error!("regroupement inconnu : {}", autre);
+                return 1;
+            }
+        };
+        match export_notes(data, dossier.as_str(), format, regroupement) {
+            Ok(bilan) => ecrire_sortie(
+                args,
+                format!(
+                    "{} notes creees, {} mises a jour, {} inchangees, {} renommees\n",
+                    bilan.nb_crees, bilan.nb_mis_a_jour, bilan.nb_inchanges, bilan.nb_renommes
+                )
+                .as_str(),
+            ),
+            Err(err) => {
+                log::error!("Erreur d'export dans {} : {}", dossier, err);
+                1
+            }
+        }
+    }
+
+    /// `--colonnes a,b,c`, `--dates rfc3339|epoch`, `--separateur-liste ;`
+    fn options_tableau(args: &[String], format: &str) -> Option<OptionsTableau> {
+        let format_date = match option(args, "--dates").unwrap_or_default().as_str() {
+            "" | "rfc3339" => FormatDate::Rfc3339,
+            "epoch" => FormatDate::Epoch,
+            autre => {
+                log::error!("format de date inconnu : {}", autre);
+                return None;
+            }
+        };
+        let colonnes = option(args, "--colonnes").unwrap_or(COLONNES_DEFAUT.to_string());
+        Some(OptionsTableau {
+            separateur: if format == FORMAT_TSV { '\t' } else { ',' },
+            colonnes: colonnes
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect(),
+            format_date,
+            separateur_liste: option(args, "--separateur-liste").unwrap_or("|".to_string()),
+        })
+    }
+
+    /// Sortie standard, ou le fichier donne par `--sortie`.
+    pub fn ouvrir_sortie(args: &[String]) -> std::io::Result<Box<dyn Write>> {
+        match option(args, "--sortie") {
+            Some(fichier) => Ok(Box::new(BufWriter::new(File::create(fichier)?))),
+            None => Ok(Box::new(BufWriter::new(std::io::stdout()))),
+        }
+    }
+
+    /// Ecrit le resultat sur la sortie standard, ou dans le fichier
+    /// donne par `--sortie`.
+    pub fn ecrire_sortie(args: &[String], contenu: &str) -> i32 {
+        match option(args, "--sortie") {
+            Some(fichier) => match fs::write(&fichier, contenu) {
+                Ok(_) => {
+                    log::info!("Fichier {} sauve", fichier);
+                    0
+                }
+                Err(err) => {
+                    log::error!("Erreur d'ecriture de {} : {}", fichier, err);
+                    1
+                }
+            },
+            None => {
+                print!("{}", contenu);
+                0
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::config::mod_config::{init_config_param, DATA_ETAT_INITIALISATION};
+        use crate::donnees::mod_donnees::{liste, DATA_ETAT};
+        use crate::verrou::mod_verrou::FICHIER_VERROU;
+
+        fn repertoire(nom: &str) -> String {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            dossier.to_string_lossy().to_string()
+        }
+
+        fn arguments(ligne: &str) -> Vec<String> {
+            ligne.split_whitespace().map(|a| a.to_string()).collect()
+        }
+
+        #[test]
+        fn import_dans_un_repertoire_vide() {
+            let repertoire = repertoire("import_vide");
+            let config = Config2::pour_test(&repertoire);
+            let export = format!("{}/export.csv", repertoire);
+            fs::write(
+                &export,
+                "title,url,time_added,cursor,tags,status\nA,https://a.example/,1700000000,c,,unread\n",
+            )
+            .unwrap();
+            let sortie = format!("{}/bilan.json", repertoire);
+            let args = arguments(&format!(
+                "import --fichier {} --format json --sortie {}",
+                export, sortie
+            ));
+            assert_eq!(commande_import(&config, &args), 0);
+
+            let data = charger_donnees(fichier_data(&repertoire).as_str()).unwrap();
+            assert_eq!(data[DATA_ETAT], DATA_ETAT_INITIALISATION);
+            assert_eq!(liste(&data).unwrap().len(), 1);
+            let param = init_config_param(fichier_param(&repertoire));
+            assert_eq!(param.etat, DATA_ETAT_INITIALISATION);
+            assert_eq!(param.date_dernier_traiment, 0);
+            let bilan: Value = serde_json::from_str(&fs::read_to_string(&sortie).unwrap()).unwrap();
+            assert_eq!(bilan["ajoutes"].as_array().unwrap().len(), 1);
+            // le verrou est rendu a la fin de la commande
+            assert!(!Path::new(&(repertoire.clone() + FICHIER_VERROU)).exists());
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+
+        #[test]
+        fn import_dry_run_n_ecrit_rien() {
+            let repertoire = repertoire("import_dry_run");
+            let config = Config2::pour_test(&repertoire);
+            let export = format!("{}/export.csv", repertoire);
+            fs::write(&export, "title,url\nA,https://a.example/\n").unwrap();
+            let sortie = format!("{}/bilan.txt", repertoire);
+            let args = arguments(&format!(
+                "import --fichier {} --dry-run --sortie {}",
+                export, sortie
+            ));
+            assert_eq!(commande_import(&config, &args), 0);
+            assert!(!Path::new(&fichier_data(&repertoire)).exists());
+            assert!(!Path::new(&fichier_param(&repertoire)).exists());
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+    }
+}
@@ -21,6 +21,78 @@ use reqwest::Error;
         pub sauvegarde: u64,
         pub rechargement: ConfigRechargement,
         pub mise_a_jour_jusqua_fin :MajFin,
+        #[serde(default)]
+        pub anomalies: ConfigAnomalies,
+        #[serde(default)]
+        pub trous: ConfigTrous,
+        #[serde(default)]
+        pub metriques: ConfigMetriques,
+        #[serde(default)]
+        pub hooks: ConfigHooks,
+        #[serde(default)]
+        pub statut: ConfigStatut,
+        #[serde(default)]
+        pub source: ConfigSource,
+        #[serde(default)]
+        pub push: ConfigPush,
+        #[serde(default)]
+        pub archive: ConfigArchive,
+        #[serde(default)]
+        pub recherche: ConfigRecherche,
+        #[serde(default)]
+        pub serveur: ConfigServeur,
+        #[serde(default)]
+        pub api: ConfigApi,
+        #[serde(default)]
+        pub retention: ConfigRetention,
+        #[serde(default)]
+        pub backup: ConfigBackup,
+    }
+
+    fn masquer(secret: &mut String) {
+        if !secret.is_empty() {
+            *secret = "***".to_string();
+        }
+    }
+
+    impl Config2 {
+        /// Copie sans les cles, mots de passe et jetons, pour les logs.
+        pub fn masquee(&self) -> Config2 {
+            let mut config = self.clone();
+            masquer(&mut config.consumer_key);
+            masquer(&mut config.access_token);
+            for source in [&mut config.source, &mut config.push.cible] {
+                masquer(&mut source.client_secret);
+                masquer(&mut source.mot_de_passe);
+                masquer(&mut source.jeton);
+            }
+            masquer(&mut config.api.jeton);
+            config
+        }
+
+        /// Configuration minimale sur `repertoire`, pour les tests.
+        #[cfg(test)]
+        pub fn pour_test(repertoire: &str) -> Config2 {
+            let contenu = format!(
+                "url = 'http://127.0.0.1:1/v3/get'
+consumer_key = ''
+access_token = ''
+repertoire = '{}'
+temporisation = 0
+config_log = ''
+sauvegarde = 10
+[rechargement]
+date_debut = ''
+dates = []
+nb_jours = 0
+nb_parcourt = 0
+[mise_a_jour_jusqua_fin]
+nb_pages = 3
+",
+                repertoire
+            );
+            toml::from_str(&contenu).expect("configuration de test")
+        }
     }
 
     #[derive(Debug, Deserialize, Clone)]
@@ -36,6 +108,335 @@ use reqwest::Error;
         pub nb_pages: u32,
     }
     
+    /// Detection des decalages de pages pendant un parcourt, et reprise
+    /// des appels en erreur temporaire.
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigAnomalies {
+        /// nombre d'elements deja recus qui sont recharges
+        pub chevauchement: u64,
+        /// nombre maximum de rechargements par traitement
+        pub nb_rechargements_max: u64,
+        /// essais d'une page en erreur reseau, 429 ou 5xx
+        pub tentatives: u32,
+        pub delai_reessai_ms: u64,
+    }
+
+    impl Default for ConfigAnomalies {
+        fn default() -> Self {
+            ConfigAnomalies {
+                chevauchement: 30,
+                nb_rechargements_max: 3,
+                tentatives: 3,
+                delai_reessai_ms: 5000,
+            }
+        }
+    }
+
+    /// Recherche des periodes suspectes (commande `gaps`).
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigTrous {
+        /// nombre minimum de jours consecutifs sans activite
+        pub jours_vides_min: i64,
+        /// nombre de jours pris en compte avant et apres la periode vide
+        pub jours_voisins: i64,
+        /// nombre moyen d'elements par jour pour qu'une periode soit active
+        pub densite_min: f64,
+    }
+
+    impl Default for ConfigTrous {
+        fn default() -> Self {
+            ConfigTrous {
+                jours_vides_min: 7,
+                jours_voisins: 14,
+                densite_min: 0.5,
+            }
+        }
+    }
+
+    /// Fichier `.prom` pour le collecteur textfile de node_exporter.
+    #[derive(Debug, Deserialize, Clone, Default)]
+    #[serde(default)]
+    pub struct ConfigMetriques {
+        /// vide : pas de metriques
+        pub fichier: String,
+    }
+
+    /// Actions lancees a la fin d'une execution.
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigHooks {
+        pub succes: Vec<Hook>,
+        pub echec: Vec<Hook>,
+        pub anomalie: Vec<Hook>,
+        /// nombre d'essais pour un webhook
+        pub tentatives: u32,
+        /// attente entre deux essais
+        pub delai_ms: u64,
+        pub timeout_ms: u64,
+    }
+
+    impl Default for ConfigHooks {
+        fn default() -> Self {
+            ConfigHooks {
+                succes: Vec::new(),
+                echec: Vec::new(),
+                anomalie: Vec::new(),
+                tentatives: 3,
+                delai_ms: 5000,
+                timeout_ms: 10000,
+            }
+        }
+    }
+
+    /// Commande externe (programme et arguments) et/ou URL appelee en POST.
+    #[derive(Debug, Deserialize, Clone, Default)]
+    #[serde(default)]
+    pub struct Hook {
+        pub commande: Vec<String>,
+        pub url: String,
+    }
+
+    /// Commande `status`.
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigStatut {
+        /// au dela, la sauvegarde n'est plus consideree a jour
+        pub age_max_heures: i64,
+    }
+
+    impl Default for ConfigStatut {
+        fn default() -> Self {
+            ConfigStatut { age_max_heures: 48 }
+        }
+    }
+
+    /// Service sauvegarde. Pour Pocket, `url`, `consumer_key` et
+    /// `access_token` de la configuration principale sont utilises.
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigSource {
+        /// `pocket`, `wallabag` ou `linkding`
+        #[serde(rename = "type")]
+        pub type_source: String,
+        /// adresse de l'instance (`https://wallabag.exemple.fr`)
+        pub url: String,
+        /// Wallabag : client OAuth et compte
+        pub client_id: String,
+        pub client_secret: String,
+        pub utilisateur: String,
+        pub mot_de_passe: String,
+        /// Linkding : jeton d'API
+        pub jeton: String,
+        pub taille_page: u64,
+    }
+
+    impl Default for ConfigSource {
+        fn default() -> Self {
+            ConfigSource {
+                type_source: "pocket".to_string(),
+                url: "".to_string(),
+                client_id: "".to_string(),
+                client_secret: "".to_string(),
+                utilisateur: "".to_string(),
+                mot_de_passe: "".to_string(),
+                jeton: "".to_string(),
+                taille_page: 30,
+            }
+        }
+    }
+
+    /// Commande `push` : instance Wallabag ou Linkding qui recoit la
+    /// bibliotheque.
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigPush {
+        pub cible: ConfigSource,
+        /// la correspondance des elements envoyes est sauvee a chaque lot
+        pub taille_lot: usize,
+        /// pause entre deux requetes
+        pub delai_ms: u64,
+        /// nombre d'essais sur une reponse 429
+        pub tentatives: u32,
+        /// attente apres un 429 sans `Retry-After`, doublee a chaque essai
+        pub attente_429_ms: u64,
+    }
+
+    impl Default for ConfigPush {
+        fn default() -> Self {
+            ConfigPush {
+                cible: ConfigSource {
+                    type_source: "".to_string(),
+                    ..Default::default()
+                },
+                taille_lot: 20,
+                delai_ms: 500,
+                tentatives: 3,
+                attente_429_ms: 60000,
+            }
+        }
+    }
+
+    /// Archivage des pages des elements (commande `archive`, ou a la fin de
+    /// chaque execution si `actif`).
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigArchive {
+        pub actif: bool,
+        /// nombre de pages telechargees en parallele
+        pub concurrence: usize,
+        /// pause minimale entre deux requetes vers un meme domaine
+        pub delai_domaine_ms: u64,
+        /// essais d'un telechargement (erreur reseau, 429 ou 5xx)
+        pub tentatives: u32,
+        pub timeout_s: u64,
+        /// au dela, la page ou la ressource est abandonnee
+        pub taille_max_ko: u64,
+        /// feuilles de style et images de la page
+        pub ressources: bool,
+        pub nb_ressources_max: usize,
+        pub agent: String,
+        /// nombre d'executions avant d'abandonner une page en erreur
+        pub essais_max: u32,
+        /// ecriture des telechargements dans `archive/warc`, avec un index
+        /// CDX
+        pub warc: bool,
+        /// taille a partir de laquelle un nouveau fichier WARC est commence
+        pub warc_taille_max_mo: u64,
+        /// texte et Markdown de l'article dans `archive/articles`
+        pub extraction: bool,
+    }
+
+    impl Default for ConfigArchive {
+        fn default() -> Self {
+            ConfigArchive {
+                actif: false,
+                concurrence: 4,
+                delai_domaine_ms: 2000,
+                tentatives: 3,
+                timeout_s: 30,
+                taille_max_ko: 10240,
+                ressources: true,
+                nb_ressources_max: 40,
+                agent: "pbackup".to_string(),
+                essais_max: 3,
+                warc: false,
+                warc_taille_max_mo: 1024,
+                extraction: true,
+            }
+        }
+    }
+
+    /// Index plein texte de `repertoire/recherche` (commande `search`).
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigRecherche {
+        /// mise a jour de l'index a la fin de chaque traitement
+        pub actif: bool,
+        /// indexation du texte des articles extraits de l'archive
+        pub texte_article: bool,
+        /// au dela, la fin du texte de l'article n'est pas indexee
+        pub taille_texte_max_ko: usize,
+    }
+
+    impl Default for ConfigRecherche {
+        fn default() -> Self {
+            ConfigRecherche {
+                actif: true,
+                texte_article: true,
+                taille_texte_max_ko: 256,
+            }
+        }
+    }
+
+    /// Interface web en lecture seule (commande `serve`).
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigServeur {
+        /// adresse d'ecoute ; une autre adresse que la boucle locale expose
+        /// la sauvegarde sur le reseau
+        pub adresse: String,
+        pub port: u16,
+        /// nombre d'elements par page
+        pub taille_page: usize,
+    }
+
+    impl Default for ConfigServeur {
+        fn default() -> Self {
+            ConfigServeur {
+                adresse: "127.0.0.1".to_string(),
+                port: 8484,
+                taille_page: 50,
+            }
+        }
+    }
+
+    /// API JSON en lecture seule, servie sous `/api/v1` par la commande `serve`.
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigApi {
+        pub actif: bool,
+        /// jeton attendu dans `Authorization: Bearer ...` par l'API et
+        /// l'interface web ; vide, elles sont ouvertes
+        pub jeton: String,
+        /// nombre d'elements par page quand `limite` est absent
+        pub limite: usize,
+        pub limite_max: usize,
+    }
+
+    impl Default for ConfigApi {
+        fn default() -> Self {
+            ConfigApi {
+                actif: true,
+                jeton: String::new(),
+                limite: 50,
+                limite_max: 500,
+            }
+        }
+    }
+
+    /// Rotation des copies de `backup/` (commande `prune`). Sans aucune
+    /// regle, toutes les copies sont gardees.
+    #[derive(Debug, Deserialize, Clone, Default)]
+    #[serde(default)]
+    pub struct ConfigRetention {
+        /// nombre de copies les plus recentes gardees
+        pub derniers: u32,
+        /// la plus recente copie de chacun des `jours` derniers jours
+        pub jours: u32,
+        pub semaines: u32,
+        pub mois: u32,
+        /// elague apres les copies de chaque traitement
+        pub automatique: bool,
+    }
+
+    /// Copies de `data.json` et `param.json` dans `backup/` a chaque
+    /// traitement.
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(default)]
+    pub struct ConfigBackup {
+        /// `aucune`, `gzip` ou `zstd`
+        pub compression: String,
+        /// 1 a 9 pour gzip, 1 a 22 pour zstd ; 0 pour le niveau par defaut
+        pub niveau: i32,
+    }
+
+    impl Default for ConfigBackup {
+        fn default() -> Self {
+            ConfigBackup {
+                compression: "aucune".to_string(),
+                niveau: 0,
+            }
+        }
+    }
+
+    impl ConfigRetention {
+        pub fn est_active(&self) -> bool {
+            self.derniers > 0 || self.jours > 0 || self.semaines > 0 || self.mois > 0
+        }
+    }
+
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ConfigParam {
@@ -58,21 +459,18 @@ use reqwest::Error;
 
 
     pub fn init_config_param(fichier_param: String) -> ConfigParam {
-        let data_param: ConfigParam;
         //let fichier_param = fichier_param; //fichier.clone()+"/../param.json";
         let is_present = Path::new(&fichier_param.clone()).exists();
         if is_present {
             let file = File::open(fichier_param.clone()).expect("file should open read only");
-            data_param = serde_json::from_reader(file).expect("file should be proper JSON");
+            serde_json::from_reader(file).expect("file should be proper JSON")
         } else {
-            let p = ConfigParam {
+            ConfigParam {
                 date_dernier_traiment: 0,
                 offset: 0,
                 etat: "".to_string(),
-            };
-            data_param = p;
+            }
         }
-        data_param
     }
 
 
@@ -90,16 +488,13 @@ use reqwest::Error;
         // Parser le fichier TOML
         let config: Config2 = toml::from_str(&config_content)?;
 
-        // Afficher la config chargée
-        println!("Configuration chargée : {:?}", config);
-        log::info!("Configuration chargée : {:?}", config);
-
         log::info!("Reconfiguration des logs ...");
         let chemin_config_log = config.config_log.as_str();
         let configuration_log =
             log4rs::config::load_config_file(chemin_config_log, Default::default())?;
         handle.set_config(configuration_log);
         log::info!("Reconfiguration des logs ok");
+        log::debug!("Configuration chargée : {:?}", config.masquee());
 
         Ok(config)
     }
@@ -108,19 +503,13 @@ use reqwest::Error;
     pub fn init_config(handle: Handle) -> Result<Config2, Error> {
         let config_or_err = get_config(handle);
 
-        let config: Config2;
-        match config_or_err {
-            Ok(valeur) => {
-                log::info!("Résultat : {:?}", valeur);
-                config = valeur
-            }
+        let config: Config2 = match config_or_err {
+            Ok(valeur) => valeur,
             Err(erreur) => {
                 log::error!("Erreur : {}", erreur);
                 std::process::exit(1);
             }
-        }
-
-        log::info!("Configuration chargée : {:?}", config);
+        };
 
         Ok(config)
     }
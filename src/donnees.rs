@@ -0,0 +1,176 @@
+pub mod mod_donnees {
+    use crate::config::mod_config::{ConfigParam, DATA_ETAT_INITIALISATION};
+    use chrono::{DateTime, Utc};
+    use reqwest::Url;
+    use serde_json::{Map, Value};
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+
+    pub const DATA_ETAT: &str = "etat";
+    pub const DATA_OFFSET: &str = "offset";
+    pub const DATA_DATE: &str = "date";
+    pub const DATA_LISTE: &str = "liste";
+
+    pub const STATUT_NON_LU: &str = "0";
+    pub const STATUT_ARCHIVE: &str = "1";
+    pub const STATUT_SUPPRIME: &str = "2";
+
+    pub fn fichier_data(repertoire: &str) -> String {
+        repertoire.to_string() + "/data.json"
+    }
+
+    pub fn fichier_param(repertoire: &str) -> String {
+        repertoire.to_string() + "/param.json"
+    }
+
+    /// Lit `data.json` ; retourne `None` si le fichier n'existe pas encore.
+    pub fn charger_donnees(fichier: &str) -> Option<Value> {
+        if !Path::new(fichier).exists() {
+            return None;
+        }
+        let file = File::open(fichier).expect("file should open read only");
+        let json: Value = serde_json::from_reader(file).expect("file should be proper JSON");
+        Some(json)
+    }
+
+    pub fn sauver_donnees(data: &Value, fichier: &str) {
+        log::info!("Sauvegarde de {} ...", fichier);
+        let list_as_json = serde_json::to_string(data).unwrap();
+
+        let mut file = File::create(fichier).expect("Could not create file!");
+
+        file.write_all(list_as_json.as_bytes())
+            .expect("Cannot write to the file!");
+        log::info!("Fichier {} sauve", fichier);
+    }
+
+    pub fn sauver_param(param: &ConfigParam, fichier_param: &str) {
+        log::info!("Sauvegarde de {} ...", fichier_param);
+        let param_as_json = serde_json::to_string(param).unwrap();
+
+        let mut file = File::create(fichier_param).expect("Could not create file!");
+
+        file.write_all(param_as_json.as_bytes())
+            .expect("Cannot write to the file!");
+        log::info!("Fichier {} sauve", fichier_param);
+    }
+
+    /// `data.json` d'une bibliotheque vide, avant la premiere execution.
+    pub fn donnees_initiales() -> Value {
+        serde_json::json!({
+            DATA_ETAT: DATA_ETAT_INITIALISATION,
+            DATA_OFFSET: 0,
+            DATA_DATE: 0,
+            DATA_LISTE: {}
+        })
+    }
+
+    /// `param.json` qui fait demarrer la prochaine execution par une
+    /// initialisation (parcourt complet, sans since).
+    pub fn param_initial() -> ConfigParam {
+        ConfigParam {
+            date_dernier_traiment: 0,
+            offset: 0,
+            etat: DATA_ETAT_INITIALISATION.to_string(),
+        }
+    }
+
+    pub fn liste(data: &Value) -> Option<&Map<String, Value>> {
+        data.get(DATA_LISTE).and_then(|l| l.as_object())
+    }
+
+    pub fn champ_str<'a>(element: &'a Value, champ: &str) -> &'a str {
+        element.get(champ).and_then(|v| v.as_str()).unwrap_or("")
+    }
+
+    /// Pocket renvoie les nombres sous forme de chaines ("1612345678"),
+    /// mais certains champs (time_to_read) sont de vrais nombres.
+    pub fn champ_nombre(element: &Value, champ: &str) -> i64 {
+        match element.get(champ) {
+            Some(Value::String(s)) => s.parse::<i64>().unwrap_or(0),
+            Some(Value::Number(n)) => n.as_i64().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    pub fn date(element: &Value, champ: &str) -> Option<DateTime<Utc>> {
+        let timestamp = champ_nombre(element, champ);
+        if timestamp > 0 {
+            DateTime::from_timestamp(timestamp, 0)
+        } else {
+            None
+        }
+    }
+
+    pub fn url(element: &Value) -> &str {
+        let resolved = champ_str(element, "resolved_url");
+        if !resolved.is_empty() {
+            resolved
+        } else {
+            champ_str(element, "given_url")
+        }
+    }
+
+    pub fn titre(element: &Value) -> &str {
+        let resolved = champ_str(element, "resolved_title");
+        if !resolved.is_empty() {
+            resolved
+        } else {
+            champ_str(element, "given_title")
+        }
+    }
+
+    pub fn domaine(element: &Value) -> String {
+        domaine_url(url(element))
+    }
+
+    pub fn domaine_url(url: &str) -> String {
+        match Url::parse(url) {
+            Ok(u) => {
+                let host = u.host_str().unwrap_or("");
+                host.strip_prefix("www.").unwrap_or(host).to_string()
+            }
+            Err(_) => "".to_string(),
+        }
+    }
+
+    pub fn statut(element: &Value) -> &str {
+        champ_str(element, "status")
+    }
+
+    pub fn est_favori(element: &Value) -> bool {
+        champ_str(element, "favorite") == "1"
+    }
+
+    /// Les tags ne sont presents qu'avec `detail_type = complete` :
+    /// `{"tags": {"rust": {"item_id": "...", "tag": "rust"}}}`.
+    pub fn tags(element: &Value) -> Vec<String> {
+        match element.get("tags").and_then(|t| t.as_object()) {
+            Some(obj) => obj.keys().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Objet `tags` au format Pocket.
+    pub fn objet_tags(item_id: &str, tags: &[String]) -> Value {
+        let mut res = Map::new();
+        for tag in tags {
+            res.insert(
+                tag.clone(),
+                serde_json::json!({"item_id": item_id, "tag": tag}),
+            );
+        }
+        Value::Object(res)
+    }
+
+    /// Date ISO 8601, avec ou sans `:` dans le decalage
+    /// (`2021-03-04T10:20:30.000Z`, `2021-03-04T10:20:30+0100`) ; 0 si
+    /// la date est invalide.
+    pub fn timestamp_iso(date: &str) -> i64 {
+        DateTime::parse_from_rfc3339(date)
+            .or_else(|_| DateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%z"))
+            .map(|d| d.timestamp())
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,321 @@
+pub mod mod_export {
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, domaine, est_favori, liste, statut, tags, titre, url,
+        STATUT_ARCHIVE, STATUT_NON_LU,
+    };
+    use chrono::DateTime;
+    use csv::QuoteStyle;
+    use serde_json::Value;
+    use std::io::{self, Write};
+
+    pub const FORMAT_BOOKMARKS_HTML: &str = "bookmarks-html";
+    pub const FORMAT_CSV: &str = "csv";
+    pub const FORMAT_TSV: &str = "tsv";
+
+    pub const COLONNES_DEFAUT: &str =
+        "item_id,url,title,time_added,time_updated,status,favorite,tags,word_count,lang";
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum FormatDate {
+        Rfc3339,
+        Epoch,
+    }
+
+    /// Options de l'export CSV / TSV.
+    #[derive(Debug, Clone)]
+    pub struct OptionsTableau {
+        pub separateur: char,
+        pub colonnes: Vec<String>,
+        pub format_date: FormatDate,
+        /// separateur des valeurs multiples (tags, auteurs)
+        pub separateur_liste: String,
+    }
+
+    pub fn echapper_html(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    /// Elements tries par date d'ajout.
+    pub fn elements_tries(data: &Value) -> Vec<(&String, &Value)> {
+        let mut res: Vec<(&String, &Value)> = match liste(data) {
+            Some(obj) => obj.iter().collect(),
+            None => Vec::new(),
+        };
+        res.sort_by_key(|(id, element)| (champ_nombre(element, "time_added"), id.to_string()));
+        res
+    }
+
+    fn ecrire_dossier(sortie: &mut dyn Write, nom: &str, elements: &[&Value]) -> io::Result<()> {
+        let date = elements
+            .iter()
+            .map(|e| champ_nombre(e, "time_added"))
+            .min()
+            .unwrap_or(0);
+        writeln!(
+            sortie,
+            "    <DT><H3 ADD_DATE=\"{}\">{}</H3>",
+            date,
+            echapper_html(nom)
+        )?;
+        writeln!(sortie, "    <DL><p>")?;
+        for element in elements {
+            let mut attributs = format!(
+                "HREF=\"{}\" ADD_DATE=\"{}\"",
+                echapper_html(url(element)),
+                champ_nombre(element, "time_added")
+            );
+            let time_updated = champ_nombre(element, "time_updated");
+            if time_updated > 0 {
+                attributs.push_str(format!(" LAST_MODIFIED=\"{}\"", time_updated).as_str());
+            }
+            let tags = tags(element);
+            if !tags.is_empty() {
+                attributs
+                    .push_str(format!(" TAGS=\"{}\"", echapper_html(&tags.join(","))).as_str());
+            }
+            let titre = match titre(element) {
+                "" => url(element),
+                t => t,
+            };
+            writeln!(
+                sortie,
+                "        <DT><A {}>{}</A>",
+                attributs,
+                echapper_html(titre)
+            )?;
+            let extrait = champ_str(element, "excerpt");
+            if !extrait.is_empty() {
+                writeln!(sortie, "        <DD>{}", echapper_html(extrait))?;
+            }
+        }
+        writeln!(sortie, "    </DL><p>")
+    }
+
+    /// Format Netscape (import des navigateurs et gestionnaires de favoris) :
+    /// un dossier pour les non lus, les archives et les favoris. Les
+    /// elements supprimes ne sont pas exportes.
+    pub fn export_bookmarks_html(data: &Value, sortie: &mut dyn Write) -> io::Result<()> {
+        let elements = elements_tries(data);
+        let filtre = |f: &dyn Fn(&Value) -> bool| -> Vec<&Value> {
+            elements.iter().map(|e| e.1).filter(|e| f(e)).collect()
+        };
+        writeln!(sortie, "<!DOCTYPE NETSCAPE-Bookmark-file-1>")?;
+        writeln!(sortie, "<!-- This is an automatically generated file.")?;
+        writeln!(sortie, "     It will be read and overwritten.")?;
+        writeln!(sortie, "     DO NOT EDIT! -->")?;
+        writeln!(
+            sortie,
+            "<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">"
+        )?;
+        writeln!(sortie, "<TITLE>Bookmarks</TITLE>")?;
+        writeln!(sortie, "<H1>Bookmarks</H1>")?;
+        writeln!(sortie, "<DL><p>")?;
+        ecrire_dossier(sortie, "Non lus", &filtre(&|e| statut(e) == STATUT_NON_LU))?;
+        ecrire_dossier(
+            sortie,
+            "Archives",
+            &filtre(&|e| statut(e) == STATUT_ARCHIVE),
+        )?;
+        ecrire_dossier(
+            sortie,
+            "Favoris",
+            &filtre(&|e| {
+                est_favori(e) && (statut(e) == STATUT_NON_LU || statut(e) == STATUT_ARCHIVE)
+            }),
+        )?;
+        writeln!(sortie, "</DL><p>")?;
+        sortie.flush()
+    }
+
+    /// TSV : pas de guillemets, les tabulations et retours a la ligne sont
+    /// remplaces par des espaces. Le CSV est echappe par `csv::Writer`.
+    fn nettoyer_champ(s: &str, separateur: char) -> String {
+        if separateur == '\t' {
+            s.replace(['\t', '\r', '\n'], " ")
+        } else {
+            s.to_string()
+        }
+    }
+
+    fn valeur_date(timestamp: i64, format: FormatDate) -> String {
+        if timestamp <= 0 {
+            return "".to_string();
+        }
+        match format {
+            FormatDate::Epoch => timestamp.to_string(),
+            FormatDate::Rfc3339 => DateTime::from_timestamp(timestamp, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Valeur d'une colonne : `url`, `title`, `domain` et `tags` sont
+    /// calculees, les autres sont les champs de l'element. Les champs
+    /// `time_*` sont des dates ; les objets imbriques sont joints avec
+    /// `separateur_liste` (tags, auteurs) ou ecrits en JSON.
+    pub fn valeur_colonne(element: &Value, colonne: &str, options: &OptionsTableau) -> String {
+        match colonne {
+            "url" => url(element).to_string(),
+            "title" => titre(element).to_string(),
+            "domain" => domaine(element),
+            "tags" => {
+                let mut tags = tags(element);
+                tags.sort();
+                tags.join(options.separateur_liste.as_str())
+            }
+            "authors" => match element.get("authors").and_then(|a| a.as_object()) {
+                Some(auteurs) => auteurs
+                    .values()
+                    .map(|a| champ_str(a, "name"))
+                    .collect::<Vec<&str>>()
+                    .join(options.separateur_liste.as_str()),
+                None => "".to_string(),
+            },
+            c if c.starts_with("time_") && c != "time_to_read" => {
+                valeur_date(champ_nombre(element, c), options.format_date)
+            }
+            c => match element.get(c) {
+                None | Some(Value::Null) => "".to_string(),
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Number(n)) => n.to_string(),
+                Some(Value::Bool(b)) => b.to_string(),
+                Some(v) => v.to_string(),
+            },
+        }
+    }
+
+    /// Ecrit une ligne par element, sans construire le fichier en memoire.
+    pub fn export_tableau(
+        data: &Value,
+        options: &OptionsTableau,
+        sortie: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut csv = csv::WriterBuilder::new()
+            .delimiter(options.separateur as u8)
+            .quote_style(if options.separateur == '\t' {
+                QuoteStyle::Never
+            } else {
+                QuoteStyle::Necessary
+            })
+            .from_writer(&mut *sortie);
+        csv.write_record(
+            options
+                .colonnes
+                .iter()
+                .map(|c| nettoyer_champ(c, options.separateur)),
+        )?;
+        let mut nb = 0;
+        for (_, element) in elements_tries(data) {
+            csv.write_record(options.colonnes.iter().map(|c| {
+                nettoyer_champ(&valeur_colonne(element, c, options), options.separateur)
+            }))?;
+            nb += 1;
+        }
+        csv.flush()?;
+        drop(csv);
+        log::info!("{} lignes exportees", nb);
+        sortie.flush()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        fn donnees() -> Value {
+            json!({"liste": {
+                "1": {"item_id": "1", "resolved_url": "https://a.example/?x=1&y=2",
+                      "resolved_title": "A <b> & \"c\"", "time_added": "100",
+                      "time_updated": "150", "status": "0", "favorite": "1",
+                      "excerpt": "court", "tags": {"rust": {}, "web": {}}},
+                "2": {"item_id": "2", "given_url": "https://b.example/", "time_added": "200",
+                      "status": "1", "favorite": "0"},
+                "3": {"item_id": "3", "given_url": "https://c.example/", "time_added": "50",
+                      "status": "2", "favorite": "1"}
+            }})
+        }
+
+        #[test]
+        fn bookmarks_html_dossiers_attributs_et_echappement() {
+            let mut sortie = Vec::new();
+            export_bookmarks_html(&donnees(), &mut sortie).unwrap();
+            let html = String::from_utf8(sortie).unwrap();
+            assert!(html.starts_with("<!DOCTYPE NETSCAPE-Bookmark-file-1>\n"));
+            let non_lus = html.find("<H3 ADD_DATE=\"100\">Non lus</H3>").unwrap();
+            let archives = html.find("<H3 ADD_DATE=\"200\">Archives</H3>").unwrap();
+            let favoris = html.find("<H3 ADD_DATE=\"100\">Favoris</H3>").unwrap();
+            assert!(non_lus < archives && archives < favoris);
+            let lien = "<DT><A HREF=\"https://a.example/?x=1&amp;y=2\" ADD_DATE=\"100\" \
+                        LAST_MODIFIED=\"150\" TAGS=\"rust,web\">\
+                        A &lt;b&gt; &amp; &quot;c&quot;</A>";
+            assert_eq!(html.matches(lien).count(), 2);
+            assert!(html.contains("<DD>court\n"));
+            // sans titre, le lien porte l'url ; sans mise a jour, pas de LAST_MODIFIED
+            assert!(html.contains(
+                "<DT><A HREF=\"https://b.example/\" ADD_DATE=\"200\">https://b.example/</A>"
+            ));
+            // supprime, meme favori
+            assert!(!html.contains("c.example"));
+            assert!(html.trim_end().ends_with("</DL><p>"));
+        }
+
+        fn options(separateur: char, colonnes: &str, format_date: FormatDate) -> OptionsTableau {
+            OptionsTableau {
+                separateur,
+                colonnes: colonnes.split(',').map(|c| c.to_string()).collect(),
+                format_date,
+                separateur_liste: "|".to_string(),
+            }
+        }
+
+        fn tableau(data: &Value, options: &OptionsTableau) -> String {
+            let mut sortie = Vec::new();
+            export_tableau(data, options, &mut sortie).unwrap();
+            String::from_utf8(sortie).unwrap()
+        }
+
+        #[test]
+        fn colonnes_choisies_dans_l_ordre_demande() {
+            let data = json!({"liste": {
+                "1": {"item_id": "1", "given_url": "https://a.example/", "time_added": "86400",
+                      "word_count": 12, "favorite": "1",
+                      "authors": {"1": {"name": "Ada"}, "2": {"name": "Bob"}},
+                      "tags": {"web": {}, "rust": {}}},
+                "2": {"item_id": "2", "given_url": "https://b.example/", "time_added": "0"}
+            }});
+            let csv = tableau(
+                &data,
+                &options(
+                    ',',
+                    "tags,item_id,time_added,authors,word_count,inconnue",
+                    FormatDate::Rfc3339,
+                ),
+            );
+            assert_eq!(
+                csv,
+                "tags,item_id,time_added,authors,word_count,inconnue\n\
+                 ,2,,,,\n\
+                 rust|web,1,1970-01-02T00:00:00+00:00,Ada|Bob,12,\n"
+            );
+            let csv = tableau(
+                &data,
+                &options(',', "item_id,time_added", FormatDate::Epoch),
+            );
+            assert_eq!(csv, "item_id,time_added\n2,\n1,86400\n");
+        }
+
+        #[test]
+        fn csv_echappe_et_tsv_nettoye() {
+            let data = json!({"liste": {
+                "1": {"item_id": "1", "resolved_title": "a,\"b\"\tc\nd"}
+            }});
+            let csv = tableau(&data, &options(',', "item_id,title", FormatDate::Epoch));
+            assert_eq!(csv, "item_id,title\n1,\"a,\"\"b\"\"\tc\nd\"\n");
+            let tsv = tableau(&data, &options('\t', "item_id,title", FormatDate::Epoch));
+            assert_eq!(tsv, "item_id\ttitle\n1\ta,\"b\" c d\n");
+        }
+    }
+}
@@ -0,0 +1,1081 @@
+pub mod mod_extraction {
+    use crate::import::mod_import::{attributs, decoder_entites};
+    use reqwest::Url;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    /// Balises dont le contenu n'est pas du HTML : il est garde tel quel.
+    const BALISES_BRUTES: [&str; 8] = [
+        "script", "style", "noscript", "template", "svg", "iframe", "textarea", "title",
+    ];
+    /// Balises sans contenu ni balise fermante.
+    const BALISES_VIDES: [&str; 14] = [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+        "source", "track", "wbr",
+    ];
+    /// Navigation, formulaires... jamais repris dans l'article.
+    const BALISES_EXCLUES: [&str; 7] = [
+        "nav", "aside", "footer", "form", "button", "select", "input",
+    ];
+    const BALISES_BLOC: [&str; 36] = [
+        "address",
+        "article",
+        "aside",
+        "blockquote",
+        "body",
+        "dd",
+        "div",
+        "dl",
+        "dt",
+        "figcaption",
+        "figure",
+        "footer",
+        "form",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "header",
+        "hr",
+        "li",
+        "main",
+        "nav",
+        "ol",
+        "p",
+        "pre",
+        "section",
+        "table",
+        "tbody",
+        "td",
+        "tfoot",
+        "th",
+        "thead",
+        "tr",
+        "ul",
+    ];
+    /// Une balise ouvrante de ce type ferme le `<p>` en cours.
+    const FERMENT_P: [&str; 20] = [
+        "address",
+        "article",
+        "aside",
+        "blockquote",
+        "div",
+        "dl",
+        "figure",
+        "footer",
+        "form",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "header",
+        "hr",
+        "ol",
+        "pre",
+        "ul",
+    ];
+    const CLASSES_POSITIVES: [&str; 11] = [
+        "article", "body", "content", "entry", "hentry", "main", "page", "post", "text", "blog",
+        "story",
+    ];
+    const CLASSES_NEGATIVES: [&str; 27] = [
+        "comment",
+        "com-",
+        "contact",
+        "footer",
+        "footnote",
+        "masthead",
+        "outbrain",
+        "promo",
+        "related",
+        "scroll",
+        "share",
+        "shoutbox",
+        "sidebar",
+        "skyscraper",
+        "sponsor",
+        "shopping",
+        "widget",
+        "nav",
+        "menu",
+        "social",
+        "banner",
+        "advert",
+        "cookie",
+        "newsletter",
+        "subscribe",
+        "popup",
+        "modal",
+    ];
+    const SEPARATEURS_TITRE: [&str; 6] = [" | ", " - ", " – ", " — ", " :: ", " » "];
+
+    const TEXTE: &str = "#texte";
+
+    struct Noeud {
+        balise: String,
+        attributs: HashMap<String, String>,
+        /// texte des noeuds texte et des balises brutes
+        texte: String,
+        parent: usize,
+        enfants: Vec<usize>,
+    }
+
+    impl Noeud {
+        fn attribut(&self, nom: &str) -> &str {
+            self.attributs.get(nom).map(|a| a.as_str()).unwrap_or("")
+        }
+
+        /// +25 si la classe ou l'id evoque le contenu, -25 s'ils evoquent
+        /// la navigation, les commentaires, la publicite...
+        fn poids_classe(&self) -> f64 {
+            let noms = format!("{} {}", self.attribut("class"), self.attribut("id")).to_lowercase();
+            let mut poids = 0.0;
+            if CLASSES_NEGATIVES.iter().any(|c| noms.contains(c)) {
+                poids -= 25.0;
+            }
+            if CLASSES_POSITIVES.iter().any(|c| noms.contains(c)) {
+                poids += 25.0;
+            }
+            poids
+        }
+    }
+
+    /// Arbre tolerant : les balises non fermees le sont a la fin de leur
+    /// parent, `<p>`, `<li>`, `<td>`... sont fermes implicitement.
+    fn analyser(html: &str) -> Vec<Noeud> {
+        let mut noeuds = vec![Noeud {
+            balise: "#document".to_string(),
+            attributs: HashMap::new(),
+            texte: String::new(),
+            parent: 0,
+            enfants: Vec::new(),
+        }];
+        let minuscules = html.to_ascii_lowercase();
+        let mut pile = vec![0];
+        let mut pos = 0;
+        while pos < html.len() {
+            let Some(debut) = minuscules[pos..].find('<').map(|p| p + pos) else {
+                ajouter_texte(&mut noeuds, &pile, &html[pos..]);
+                break;
+            };
+            ajouter_texte(&mut noeuds, &pile, &html[pos..debut]);
+            let reste = &minuscules[debut..];
+            if reste.starts_with("<!--") {
+                pos = reste
+                    .find("-->")
+                    .map(|p| debut + p + 3)
+                    .unwrap_or(html.len());
+                continue;
+            }
+            if reste.starts_with("<!") || reste.starts_with("<?") {
+                pos = reste.find('>').map(|p| debut + p + 1).unwrap_or(html.len());
+                continue;
+            }
+            let fermante = reste.starts_with("</");
+            let debut_nom = debut + if fermante { 2 } else { 1 };
+            let fin_nom = minuscules[debut_nom..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+                .map(|p| p + debut_nom)
+                .unwrap_or(html.len());
+            let nom = &minuscules[debut_nom..fin_nom];
+            if nom.is_empty() {
+                ajouter_texte(&mut noeuds, &pile, "<");
+                pos = debut + 1;
+                continue;
+            }
+            let Some(fin) = minuscules[fin_nom..].find('>').map(|p| p + fin_nom) else {
+                break;
+            };
+            pos = fin + 1;
+            if fermante {
+                if let Some(i) = pile.iter().rposition(|&n| noeuds[n].balise == nom) {
+                    if i > 0 {
+                        pile.truncate(i);
+                    }
+                }
+                continue;
+            }
+            fermer_implicitement(&noeuds, &mut pile, nom);
+            let parent = *pile.last().unwrap_or(&0);
+            let id = noeuds.len();
+            noeuds.push(Noeud {
+                balise: nom.to_string(),
+                attributs: attributs(&html[fin_nom..fin]),
+                texte: String::new(),
+                parent,
+                enfants: Vec::new(),
+            });
+            noeuds[parent].enfants.push(id);
+            if BALISES_BRUTES.contains(&nom) {
+                let fermeture = format!("</{}", nom);
+                let fin_contenu = minuscules[pos..]
+                    .find(fermeture.as_str())
+                    .map(|p| p + pos)
+                    .unwrap_or(html.len());
+                noeuds[id].texte = decoder_entites(&html[pos..fin_contenu]);
+                pos = minuscules[fin_contenu..]
+                    .find('>')
+                    .map(|p| p + fin_contenu + 1)
+                    .unwrap_or(html.len());
+            } else if !BALISES_VIDES.contains(&nom) && !html[..fin].ends_with('/') {
+                pile.push(id);
+            }
+        }
+        noeuds
+    }
+
+    fn ajouter_texte(noeuds: &mut Vec<Noeud>, pile: &[usize], texte: &str) {
+        if texte.is_empty() {
+            return;
+        }
+        let parent = *pile.last().unwrap_or(&0);
+        let id = noeuds.len();
+        noeuds.push(Noeud {
+            balise: TEXTE.to_string(),
+            attributs: HashMap::new(),
+            texte: decoder_entites(texte),
+            parent,
+            enfants: Vec::new(),
+        });
+        noeuds[parent].enfants.push(id);
+    }
+
+    fn fermer_implicitement(noeuds: &[Noeud], pile: &mut Vec<usize>, nom: &str) {
+        let (cibles, limites): (&[&str], &[&str]) = match nom {
+            "li" => (&["li"], &["ul", "ol"]),
+            "dt" | "dd" => (&["dt", "dd"], &["dl"]),
+            "td" | "th" => (&["td", "th"], &["tr", "table"]),
+            "tr" => (&["tr", "td", "th"], &["table", "tbody", "thead", "tfoot"]),
+            "p" => (
+                &["p"],
+                &["div", "td", "li", "blockquote", "section", "article"],
+            ),
+            _ if FERMENT_P.contains(&nom) => (&["p"], &["div", "td", "li", "blockquote"]),
+            _ => return,
+        };
+        for i in (1..pile.len()).rev() {
+            let balise = noeuds[pile[i]].balise.as_str();
+            if limites.contains(&balise) {
+                return;
+            }
+            if cibles.contains(&balise) {
+                pile.truncate(i);
+                return;
+            }
+        }
+    }
+
+    /// Acces a l'arbre : noeuds retenus (`visibles`) et texte de chaque
+    /// noeud, calcule une seule fois.
+    struct Arbre {
+        noeuds: Vec<Noeud>,
+        visibles: Vec<bool>,
+        textes: Vec<String>,
+        longueurs_liens: Vec<usize>,
+    }
+
+    impl Arbre {
+        /// `filtrer` ecarte aussi les blocs dont la classe evoque la
+        /// navigation, les commentaires, la publicite...
+        fn new(noeuds: Vec<Noeud>, filtrer: bool) -> Arbre {
+            let mut visibles = vec![true; noeuds.len()];
+            // les parents sont toujours crees avant leurs enfants
+            for id in 1..noeuds.len() {
+                let noeud = &noeuds[id];
+                let balise = noeud.balise.as_str();
+                let improbable = filtrer
+                    && balise != TEXTE
+                    && !matches!(balise, "body" | "html" | "article" | "main")
+                    && noeud.poids_classe() < 0.0;
+                visibles[id] = visibles[noeud.parent]
+                    && !BALISES_EXCLUES.contains(&balise)
+                    && (balise == "title" || !BALISES_BRUTES.contains(&balise))
+                    && noeud.attribut("hidden").is_empty()
+                    && !noeud.attributs.contains_key("hidden")
+                    && noeud.attribut("aria-hidden") != "true"
+                    && !improbable;
+            }
+            let mut textes = vec![String::new(); noeuds.len()];
+            let mut longueurs_liens = vec![0; noeuds.len()];
+            for id in (0..noeuds.len()).rev() {
+                if !visibles[id] {
+                    continue;
+                }
+                let noeud = &noeuds[id];
+                let mut texte = String::new();
+                let mut liens = 0;
+                if noeud.balise == TEXTE {
+                    texte = normaliser_espaces(noeud.texte.as_str());
+                } else if noeud.balise != "title" {
+                    for &enfant in &noeud.enfants {
+                        if !visibles[enfant] || textes[enfant].is_empty() {
+                            continue;
+                        }
+                        if !texte.is_empty() {
+                            texte.push(' ');
+                        }
+                        texte.push_str(textes[enfant].as_str());
+                        liens += longueurs_liens[enfant];
+                    }
+                }
+                if noeud.balise == "a" {
+                    liens = texte.chars().count();
+                }
+                textes[id] = texte;
+                longueurs_liens[id] = liens;
+            }
+            Arbre {
+                noeuds,
+                visibles,
+                textes,
+                longueurs_liens,
+            }
+        }
+
+        fn densite_liens(&self, id: usize) -> f64 {
+            let longueur = self.textes[id].chars().count();
+            if longueur == 0 {
+                0.0
+            } else {
+                self.longueurs_liens[id] as f64 / longueur as f64
+            }
+        }
+
+        fn premier(&self, balise: &str) -> Option<usize> {
+            (0..self.noeuds.len()).find(|&id| self.noeuds[id].balise == balise)
+        }
+
+        fn meta(&self, noms: &[&str]) -> Option<String> {
+            self.noeuds
+                .iter()
+                .filter(|n| n.balise == "meta")
+                .find(|n| {
+                    let nom = n.attributs.get("property").or(n.attributs.get("name"));
+                    nom.is_some_and(|nom| noms.contains(&nom.to_lowercase().as_str()))
+                })
+                .map(|n| normaliser_espaces(n.attribut("content")))
+                .filter(|c| !c.is_empty())
+        }
+
+        fn score_initial(&self, id: usize) -> f64 {
+            let noeud = &self.noeuds[id];
+            let poids = match noeud.balise.as_str() {
+                "div" | "article" | "section" | "main" => 5.0,
+                "pre" | "td" | "blockquote" => 3.0,
+                "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" => -3.0,
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+                _ => 0.0,
+            };
+            poids + noeud.poids_classe()
+        }
+
+        /// Blocs de l'article : le meilleur candidat et ses freres assez
+        /// proches.
+        fn contenu(&self) -> Vec<usize> {
+            let mut scores: HashMap<usize, f64> = HashMap::new();
+            for id in 0..self.noeuds.len() {
+                let noeud = &self.noeuds[id];
+                let paragraphe = match noeud.balise.as_str() {
+                    "p" | "pre" | "td" => true,
+                    "div" => !noeud
+                        .enfants
+                        .iter()
+                        .any(|&e| BALISES_BLOC.contains(&self.noeuds[e].balise.as_str())),
+                    _ => false,
+                };
+                if !self.visibles[id] || !paragraphe {
+                    continue;
+                }
+                let texte = &self.textes[id];
+                let longueur = texte.chars().count();
+                if longueur < 25 {
+                    continue;
+                }
+                let score =
+                    1.0 + texte.matches(',').count() as f64 + ((longueur / 100) as f64).min(3.0);
+                let parent = noeud.parent;
+                let grand_parent = self.noeuds[parent].parent;
+                for (ancetre, diviseur) in [(parent, 1.0), (grand_parent, 2.0)] {
+                    if ancetre == 0 {
+                        continue;
+                    }
+                    *scores
+                        .entry(ancetre)
+                        .or_insert_with(|| self.score_initial(ancetre)) += score / diviseur;
+                }
+            }
+            let scores: HashMap<usize, f64> = scores
+                .into_iter()
+                .map(|(id, score)| (id, score * (1.0 - self.densite_liens(id))))
+                .collect();
+            let meilleur = scores
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(id, score)| (*id, *score));
+            let Some((meilleur, score)) = meilleur else {
+                return self.premier("body").into_iter().collect();
+            };
+            let parent = self.noeuds[meilleur].parent;
+            if parent == 0 {
+                return vec![meilleur];
+            }
+            let seuil = (score * 0.2).max(10.0);
+            let classe = self.noeuds[meilleur].attribut("class");
+            self.noeuds[parent]
+                .enfants
+                .iter()
+                .copied()
+                .filter(|&frere| {
+                    if frere == meilleur {
+                        return true;
+                    }
+                    if !self.visibles[frere] {
+                        return false;
+                    }
+                    let noeud = &self.noeuds[frere];
+                    let mut score_frere = scores.get(&frere).copied().unwrap_or(0.0);
+                    if !classe.is_empty() && noeud.attribut("class") == classe {
+                        score_frere += score * 0.2;
+                    }
+                    if score_frere >= seuil {
+                        return true;
+                    }
+                    if noeud.balise != "p" {
+                        return false;
+                    }
+                    let longueur = self.textes[frere].chars().count();
+                    let densite = self.densite_liens(frere);
+                    (longueur > 80 && densite < 0.25)
+                        || (longueur > 0 && densite == 0.0 && self.textes[frere].contains(". "))
+                })
+                .collect()
+        }
+
+        fn titre(&self) -> String {
+            if let Some(titre) = self.meta(&["og:title", "twitter:title"]) {
+                return titre;
+            }
+            let titre = self
+                .premier("title")
+                .map(|id| normaliser_espaces(self.noeuds[id].texte.as_str()))
+                .unwrap_or_default();
+            if titre.is_empty() {
+                return self
+                    .premier("h1")
+                    .map(|id| self.textes[id].clone())
+                    .unwrap_or_default();
+            }
+            // "Titre de l'article | Nom du site"
+            let coupe = SEPARATEURS_TITRE
+                .iter()
+                .filter_map(|s| titre.rfind(s))
+                .max()
+                .map(|pos| titre[..pos].trim().to_string());
+            match coupe {
+                Some(coupe) if coupe.split_whitespace().count() >= 3 => coupe,
+                _ => titre,
+            }
+        }
+
+        fn auteur(&self) -> Option<String> {
+            let meta = self
+                .meta(&[
+                    "author",
+                    "article:author",
+                    "byl",
+                    "dc.creator",
+                    "parsely-author",
+                ])
+                .filter(|a| !a.starts_with("http"));
+            if meta.is_some() {
+                return meta;
+            }
+            (0..self.noeuds.len())
+                .filter(|&id| self.visibles[id] && self.noeuds[id].balise != TEXTE)
+                .find(|&id| {
+                    let noeud = &self.noeuds[id];
+                    let noms = format!(
+                        "{} {} {} {}",
+                        noeud.attribut("class"),
+                        noeud.attribut("id"),
+                        noeud.attribut("rel"),
+                        noeud.attribut("itemprop")
+                    )
+                    .to_lowercase();
+                    let longueur = self.textes[id].chars().count();
+                    (noms.contains("byline") || noms.contains("author"))
+                        && longueur > 0
+                        && longueur < 100
+                })
+                .map(|id| self.textes[id].clone())
+        }
+    }
+
+    fn normaliser_espaces(texte: &str) -> String {
+        texte.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    /// Rendu en Markdown, ou en texte brut (`markdown` a faux).
+    struct Rendu<'a> {
+        arbre: &'a Arbre,
+        base: &'a Url,
+        titre: &'a str,
+        markdown: bool,
+    }
+
+    impl Rendu<'_> {
+        fn enfants(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+            self.arbre.noeuds[id]
+                .enfants
+                .iter()
+                .copied()
+                .filter(|&e| self.arbre.visibles[e])
+        }
+
+        fn lien(&self, adresse: &str) -> Option<String> {
+            let adresse = adresse.trim();
+            if adresse.is_empty() || adresse.starts_with('#') || adresse.starts_with("javascript:")
+            {
+                return None;
+            }
+            self.base.join(adresse).ok().map(|u| u.to_string())
+        }
+
+        /// Listes de liens (articles lies, partage...) au milieu du contenu.
+        fn a_ecarter(&self, id: usize) -> bool {
+            let balise = self.arbre.noeuds[id].balise.as_str();
+            matches!(
+                balise,
+                "ul" | "ol" | "div" | "section" | "table" | "td" | "th"
+            ) && self.arbre.densite_liens(id) > 0.5
+                && self.arbre.textes[id].chars().count() < 1000
+        }
+
+        fn blocs(&self, id: usize, res: &mut Vec<String>) {
+            let mut ligne = String::new();
+            for enfant in self.enfants(id) {
+                let balise = self.arbre.noeuds[enfant].balise.as_str();
+                if balise == TEXTE || !BALISES_BLOC.contains(&balise) {
+                    self.en_ligne(enfant, &mut ligne);
+                    continue;
+                }
+                vider(&mut ligne, res);
+                if !self.a_ecarter(enfant) {
+                    self.bloc(enfant, res);
+                }
+            }
+            vider(&mut ligne, res);
+        }
+
+        fn bloc(&self, id: usize, res: &mut Vec<String>) {
+            let balise = self.arbre.noeuds[id].balise.as_str();
+            match balise {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    let texte = self.texte_en_ligne(id);
+                    // le titre est deja en tete de l'article
+                    if texte.is_empty() || texte == self.titre {
+                        return;
+                    }
+                    if self.markdown {
+                        let niveau = balise[1..].parse::<usize>().unwrap_or(1);
+                        res.push(format!("{} {}", "#".repeat(niveau), texte));
+                    } else {
+                        res.push(texte);
+                    }
+                }
+                "ul" | "ol" => {
+                    let mut lignes = Vec::new();
+                    for (numero, element) in self.enfants(id).enumerate() {
+                        let mut sous_blocs = Vec::new();
+                        self.blocs(element, &mut sous_blocs);
+                        if sous_blocs.is_empty() {
+                            continue;
+                        }
+                        let puce = if balise == "ol" && self.markdown {
+                            format!("{}. ", numero + 1)
+                        } else {
+                            "- ".to_string()
+                        };
+                        let retrait = format!("\n{}", " ".repeat(puce.len()));
+                        lignes.push(format!(
+                            "{}{}",
+                            puce,
+                            sous_blocs.join("\n").replace('\n', retrait.as_str())
+                        ));
+                    }
+                    if !lignes.is_empty() {
+                        res.push(lignes.join("\n"));
+                    }
+                }
+                "blockquote" => {
+                    let mut sous_blocs = Vec::new();
+                    self.blocs(id, &mut sous_blocs);
+                    if !self.markdown {
+                        res.extend(sous_blocs);
+                    } else if !sous_blocs.is_empty() {
+                        res.push(
+                            sous_blocs
+                                .iter()
+                                .map(|b| format!("> {}", b.replace('\n', "\n> ")))
+                                .collect::<Vec<String>>()
+                                .join("\n>\n"),
+                        );
+                    }
+                }
+                "pre" => {
+                    let mut texte = String::new();
+                    texte_brut(self.arbre, id, &mut texte);
+                    let texte = texte.trim_matches('\n');
+                    if texte.trim().is_empty() {
+                        return;
+                    }
+                    if self.markdown {
+                        res.push(format!("```\n{}\n```", texte));
+                    } else {
+                        res.push(texte.to_string());
+                    }
+                }
+                "hr" => {
+                    if self.markdown {
+                        res.push("---".to_string());
+                    }
+                }
+                "table" if !self.tableau_de_mise_en_page(id) => self.tableau(id, res),
+                _ => self.blocs(id, res),
+            }
+        }
+
+        /// Tableau contenant des paragraphes ou des blocs : simple mise en
+        /// page, rendu comme une suite de blocs.
+        fn tableau_de_mise_en_page(&self, id: usize) -> bool {
+            let mut pile = vec![id];
+            while let Some(n) = pile.pop() {
+                for enfant in self.enfants(n) {
+                    let balise = self.arbre.noeuds[enfant].balise.as_str();
+                    if matches!(balise, "p" | "div" | "table" | "ul" | "ol" | "pre") && enfant != id
+                    {
+                        return true;
+                    }
+                    pile.push(enfant);
+                }
+            }
+            false
+        }
+
+        fn tableau(&self, id: usize, res: &mut Vec<String>) {
+            let mut lignes: Vec<Vec<String>> = Vec::new();
+            let mut pile = vec![id];
+            let mut ordre = Vec::new();
+            while let Some(n) = pile.pop() {
+                if self.arbre.noeuds[n].balise == "tr" {
+                    ordre.push(n);
+                    continue;
+                }
+                let enfants: Vec<usize> = self.enfants(n).collect();
+                pile.extend(enfants.into_iter().rev());
+            }
+            for tr in ordre {
+                let cellules: Vec<String> = self
+                    .enfants(tr)
+                    .filter(|&c| matches!(self.arbre.noeuds[c].balise.as_str(), "td" | "th"))
+                    .map(|c| {
+                        let texte = self.texte_en_ligne(c).replace('\n', " ");
+                        if self.markdown {
+                            texte.replace('|', "\\|")
+                        } else {
+                            texte
+                        }
+                    })
+                    .collect();
+                if !cellules.is_empty() {
+                    lignes.push(cellules);
+                }
+            }
+            if lignes.is_empty() {
+                return;
+            }
+            let mut texte = Vec::new();
+            for (i, cellules) in lignes.iter().enumerate() {
+                if self.markdown {
+                    texte.push(format!("| {} |", cellules.join(" | ")));
+                    if i == 0 {
+                        texte.push(format!("|{}", " --- |".repeat(cellules.len())));
+                    }
+                } else {
+                    texte.push(cellules.join(" | "));
+                }
+            }
+            res.push(texte.join("\n"));
+        }
+
+        fn texte_en_ligne(&self, id: usize) -> String {
+            let mut res = String::new();
+            for enfant in self.enfants(id) {
+                self.en_ligne(enfant, &mut res);
+            }
+            res.trim().to_string()
+        }
+
+        fn en_ligne(&self, id: usize, res: &mut String) {
+            let noeud = &self.arbre.noeuds[id];
+            match noeud.balise.as_str() {
+                TEXTE => {
+                    for c in noeud.texte.chars() {
+                        if c.is_whitespace() {
+                            if !res.is_empty() && !res.ends_with(' ') && !res.ends_with('\n') {
+                                res.push(' ');
+                            }
+                        } else {
+                            res.push(c);
+                        }
+                    }
+                }
+                "br" => {
+                    let fin = res.trim_end_matches(' ').len();
+                    res.truncate(fin);
+                    res.push('\n');
+                }
+                "img" => {
+                    if self.markdown {
+                        if let Some(src) = self.lien(noeud.attribut("src")) {
+                            res.push_str(
+                                format!("![{}]({})", noeud.attribut("alt").trim(), src).as_str(),
+                            );
+                        }
+                    }
+                }
+                balise => {
+                    let texte = self.texte_en_ligne(id);
+                    if texte.is_empty() {
+                        return;
+                    }
+                    let texte = match balise {
+                        "a" if self.markdown => match self.lien(noeud.attribut("href")) {
+                            Some(href) => format!("[{}]({})", texte, href),
+                            None => texte,
+                        },
+                        "strong" | "b" if self.markdown => format!("**{}**", texte),
+                        "em" | "i" if self.markdown => format!("*{}*", texte),
+                        "code" if self.markdown => format!("`{}`", texte),
+                        _ => texte,
+                    };
+                    res.push_str(texte.as_str());
+                }
+            }
+        }
+    }
+
+    fn vider(ligne: &mut String, res: &mut Vec<String>) {
+        let texte = ligne
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        if !texte.is_empty() {
+            res.push(texte);
+        }
+        ligne.clear();
+    }
+
+    fn texte_brut(arbre: &Arbre, id: usize, res: &mut String) {
+        let noeud = &arbre.noeuds[id];
+        if noeud.balise == TEXTE {
+            res.push_str(noeud.texte.as_str());
+        } else if noeud.balise == "br" {
+            res.push('\n');
+        }
+        for &enfant in &noeud.enfants {
+            if arbre.visibles[enfant] {
+                texte_brut(arbre, enfant, res);
+            }
+        }
+    }
+
+    pub fn compter_mots(texte: &str) -> u64 {
+        texte
+            .split_whitespace()
+            .filter(|m| m.chars().any(|c| c.is_alphanumeric()))
+            .count() as u64
+    }
+
+    /// Article extrait d'une page.
+    #[derive(Debug, Clone)]
+    pub struct Article {
+        pub titre: String,
+        pub auteur: Option<String>,
+        pub markdown: String,
+        pub texte: String,
+        /// mots du contenu, sans le titre
+        pub nb_mots: u64,
+    }
+
+    /// Resume garde dans l'etat de l'archive.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Extraction {
+        pub titre: String,
+        pub auteur: Option<String>,
+        pub nb_mots: u64,
+    }
+
+    impl From<&Article> for Extraction {
+        fn from(article: &Article) -> Self {
+            Extraction {
+                titre: article.titre.clone(),
+                auteur: article.auteur.clone(),
+                nb_mots: article.nb_mots,
+            }
+        }
+    }
+
+    fn rendre(
+        arbre: &Arbre,
+        contenu: &[usize],
+        titre: &str,
+        base: &Url,
+        markdown: bool,
+    ) -> Vec<String> {
+        let rendu = Rendu {
+            arbre,
+            base,
+            titre,
+            markdown,
+        };
+        let mut blocs = Vec::new();
+        for &id in contenu {
+            let balise = arbre.noeuds[id].balise.as_str();
+            if balise == TEXTE || !BALISES_BLOC.contains(&balise) {
+                let mut ligne = String::new();
+                rendu.en_ligne(id, &mut ligne);
+                vider(&mut ligne, &mut blocs);
+            } else {
+                rendu.bloc(id, &mut blocs);
+            }
+        }
+        blocs
+    }
+
+    /// Titre, auteur et contenu principal de la page, facon Readability :
+    /// les paragraphes donnent un score a leurs parents, le bloc le mieux
+    /// note (corrige de la densite de liens) est retenu avec ses freres
+    /// proches. Sans resultat suffisant, l'analyse est refaite sans ecarter
+    /// les blocs dont la classe evoque la navigation.
+    pub fn extraire(html: &str, base: &Url) -> Article {
+        let mut arbre = Arbre::new(analyser(html), true);
+        let mut contenu = arbre.contenu();
+        let longueur: usize = contenu.iter().map(|&id| arbre.textes[id].len()).sum();
+        if longueur < 250 {
+            let sans_filtre = Arbre::new(analyser(html), false);
+            let contenu_sans_filtre = sans_filtre.contenu();
+            let longueur_sans_filtre: usize = contenu_sans_filtre
+                .iter()
+                .map(|&id| sans_filtre.textes[id].len())
+                .sum();
+            if longueur_sans_filtre > longueur {
+                arbre = sans_filtre;
+                contenu = contenu_sans_filtre;
+            }
+        }
+        let titre = arbre.titre();
+        let auteur = arbre.auteur();
+        let blocs_markdown = rendre(&arbre, &contenu, titre.as_str(), base, true);
+        let blocs_texte = rendre(&arbre, &contenu, titre.as_str(), base, false);
+        let corps = blocs_texte.join("\n\n");
+        let nb_mots = compter_mots(corps.as_str());
+        let mut markdown = format!("# {}\n\n", titre);
+        let mut texte = format!("{}\n", titre);
+        if let Some(auteur) = &auteur {
+            markdown.push_str(format!("*{}*\n\n", auteur).as_str());
+            texte.push_str(format!("{}\n", auteur).as_str());
+        }
+        markdown.push_str(blocs_markdown.join("\n\n").as_str());
+        markdown.push('\n');
+        texte.push('\n');
+        texte.push_str(corps.as_str());
+        texte.push('\n');
+        Article {
+            titre,
+            auteur,
+            markdown,
+            texte,
+            nb_mots,
+        }
+    }
+
+    pub fn repertoire_articles(repertoire_archive: &Path) -> PathBuf {
+        repertoire_archive.join("articles")
+    }
+
+    /// `articles/<item_id>.md` et `articles/<item_id>.txt`.
+    pub fn ecrire_article(
+        repertoire_archive: &Path,
+        item_id: &str,
+        article: &Article,
+    ) -> io::Result<()> {
+        let dossier = repertoire_articles(repertoire_archive);
+        fs::create_dir_all(&dossier)?;
+        fs::write(
+            dossier.join(format!("{}.md", item_id)),
+            article.markdown.as_str(),
+        )?;
+        fs::write(
+            dossier.join(format!("{}.txt", item_id)),
+            article.texte.as_str(),
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const PARAGRAPHE: &str = "Un paragraphe assez long pour compter, avec quelques virgules, \
+            des mots et encore des mots, afin que le bloc qui le contient soit retenu comme \
+            contenu principal de la page, ce qui suppose un peu plus de deux cents caracteres.";
+
+        fn base() -> Url {
+            Url::parse("https://exemple.fr/dossier/page.html").unwrap()
+        }
+
+        fn page(titre: &str, corps: &str) -> String {
+            format!(
+                "<html><head><title>{}</title></head><body>{}</body></html>",
+                titre, corps
+            )
+        }
+
+        #[test]
+        fn paragraphes_et_elements_non_fermes() {
+            let html = page(
+                "Titre de test",
+                format!(
+                    "<article><p>{0}<p>Second {0}<ul><li>un<li>deux</ul></article>",
+                    PARAGRAPHE
+                )
+                .as_str(),
+            );
+            let article = extraire(html.as_str(), &base());
+            assert!(article
+                .markdown
+                .contains(format!("{0}\n\nSecond {0}", PARAGRAPHE).as_str()));
+            assert!(article.markdown.contains("- un\n- deux"));
+        }
+
+        #[test]
+        fn scripts_et_styles_ignores() {
+            let html = page(
+                "Titre de test",
+                format!(
+                    "<style>.cache {{ display: none }}</style><article><p>{}\
+                     <script>var cache = \"<p>faux</p>\";</script></p></article>",
+                    PARAGRAPHE
+                )
+                .as_str(),
+            );
+            let article = extraire(html.as_str(), &base());
+            assert!(article.texte.contains(PARAGRAPHE));
+            assert!(!article.texte.contains("cache"));
+            assert!(!article.texte.contains("faux"));
+        }
+
+        #[test]
+        fn navigation_et_commentaires_exclus() {
+            let html = page(
+                "Titre de test",
+                format!(
+                    "<nav><a href=\"/\">Accueil</a> <a href=\"/rubriques\">Rubriques</a></nav>\
+                     <article><p>{0}</p><p>Encore {0}</p></article>\
+                     <div class=\"comments\"><p>Premier commentaire, assez long pour avoir \
+                     un score, mais sans interet pour l'article.</p></div>",
+                    PARAGRAPHE
+                )
+                .as_str(),
+            );
+            let article = extraire(html.as_str(), &base());
+            assert!(article.texte.contains(PARAGRAPHE));
+            assert!(!article.texte.contains("Accueil"));
+            assert!(!article.texte.contains("commentaire"));
+        }
+
+        #[test]
+        fn titre_sans_nom_du_site() {
+            let corps = format!("<p>{}</p>", PARAGRAPHE);
+            let article = extraire(
+                page("Un titre assez long | Mon Site", corps.as_str()).as_str(),
+                &base(),
+            );
+            assert_eq!(article.titre, "Un titre assez long");
+            assert!(article.markdown.starts_with("# Un titre assez long\n\n"));
+            // trop court une fois coupe : le titre est garde entier
+            let article = extraire(page("Accueil | Mon Site", corps.as_str()).as_str(), &base());
+            assert_eq!(article.titre, "Accueil | Mon Site");
+        }
+
+        #[test]
+        fn tableau_liste_et_citation_en_markdown() {
+            let html = page(
+                "Titre de test",
+                format!(
+                    "<article><p>{}</p>\
+                     <table><tr><th>Nom</th><th>Valeur</th></tr>\
+                     <tr><td>a|b</td><td>1</td></tr></table>\
+                     <ol><li>premier</li><li>second</li></ol>\
+                     <blockquote><p>Une citation</p><p>sur deux paragraphes</p></blockquote>\
+                     </article>",
+                    PARAGRAPHE
+                )
+                .as_str(),
+            );
+            let article = extraire(html.as_str(), &base());
+            assert!(article
+                .markdown
+                .contains("| Nom | Valeur |\n| --- | --- |\n| a\\|b | 1 |"));
+            assert!(article.markdown.contains("1. premier\n2. second"));
+            assert!(article
+                .markdown
+                .contains("> Une citation\n>\n> sur deux paragraphes"));
+            assert!(article.texte.contains("Nom | Valeur\na|b | 1"));
+            assert!(article.texte.contains("- premier\n- second"));
+        }
+
+        #[test]
+        fn liens_relatifs_resolus() {
+            let html = page(
+                "Titre de test",
+                format!(
+                    "<article><p>{} Voir <a href=\"../autre.html\">cette page</a>, \
+                     <a href=\"#note\">la note</a> et <img src=\"img/schema.png\" alt=\"schema\">.\
+                     </p></article>",
+                    PARAGRAPHE
+                )
+                .as_str(),
+            );
+            let article = extraire(html.as_str(), &base());
+            assert!(article
+                .markdown
+                .contains("[cette page](https://exemple.fr/autre.html)"));
+            assert!(article.markdown.contains(" la note "));
+            assert!(article
+                .markdown
+                .contains("![schema](https://exemple.fr/dossier/img/schema.png)"));
+        }
+
+        #[test]
+        fn mots_comptes_sans_la_ponctuation() {
+            assert_eq!(compter_mots("Bonjour , le monde - 42 !"), 4);
+            assert_eq!(compter_mots("  \n "), 0);
+            let article = extraire(
+                page("Titre de test", "<p>un deux trois</p>").as_str(),
+                &base(),
+            );
+            assert_eq!(article.nb_mots, 3);
+        }
+    }
+}
@@ -0,0 +1,604 @@
+pub mod mod_filtre {
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, domaine, est_favori, liste, statut, tags, titre, url,
+        STATUT_ARCHIVE, STATUT_NON_LU, STATUT_SUPPRIME,
+    };
+    use chrono::{DateTime, Datelike, NaiveDate};
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use std::cmp::Ordering;
+    use std::io::{self, Write};
+
+    /// Code Pocket d'un statut : `non_lu`, `archive`, `supprime` (ou
+    /// `unread`, `archived`, `deleted`, ou le code lui meme).
+    pub fn code_statut(valeur: &str) -> Result<&'static str, String> {
+        match valeur.to_lowercase().as_str() {
+            "0" | "non_lu" | "unread" => Ok(STATUT_NON_LU),
+            "1" | "archive" | "archived" => Ok(STATUT_ARCHIVE),
+            "2" | "supprime" | "deleted" => Ok(STATUT_SUPPRIME),
+            _ => Err(format!("statut inconnu : '{}'", valeur)),
+        }
+    }
+
+    pub fn nom_statut(code: &str) -> &'static str {
+        match code {
+            STATUT_NON_LU => "non_lu",
+            STATUT_ARCHIVE => "archive",
+            STATUT_SUPPRIME => "supprime",
+            _ => "?",
+        }
+    }
+
+    pub fn oui_non(valeur: &str) -> Result<bool, String> {
+        match valeur.to_lowercase().as_str() {
+            "1" | "oui" | "yes" | "true" => Ok(true),
+            "0" | "non" | "no" | "false" => Ok(false),
+            _ => Err(format!(
+                "valeur inconnue : '{}' (oui ou non attendu)",
+                valeur
+            )),
+        }
+    }
+
+    /// Debut (inclus) et fin (exclue) d'une date `AAAA`, `AAAA-MM` ou
+    /// `AAAA-MM-JJ`, en UTC.
+    fn bornes_date(valeur: &str) -> Result<(i64, i64), String> {
+        let parties: Vec<&str> = valeur.split('-').collect();
+        let nombres: Vec<u32> = parties
+            .iter()
+            .map(|p| p.parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("date invalide : '{}'", valeur))?;
+        let invalide = || format!("date invalide : '{}'", valeur);
+        // annee sur 4 chiffres au plus : pas de debordement de `annee + 1`
+        if nombres.first().is_some_and(|annee| *annee > 9999) {
+            return Err(invalide());
+        }
+        let (debut, fin) = match nombres.as_slice() {
+            [annee] => (
+                NaiveDate::from_ymd_opt(*annee as i32, 1, 1),
+                NaiveDate::from_ymd_opt(*annee as i32 + 1, 1, 1),
+            ),
+            [annee, mois] => {
+                let debut = NaiveDate::from_ymd_opt(*annee as i32, *mois, 1);
+                let fin = debut.and_then(|d| {
+                    if d.month() == 12 {
+                        NaiveDate::from_ymd_opt(d.year() + 1, 1, 1)
+                    } else {
+                        NaiveDate::from_ymd_opt(d.year(), d.month() + 1, 1)
+                    }
+                });
+                (debut, fin)
+            }
+            [annee, mois, jour] => {
+                let debut = NaiveDate::from_ymd_opt(*annee as i32, *mois, *jour);
+                (debut, debut.and_then(|d| d.succ_opt()))
+            }
+            _ => (None, None),
+        };
+        let horodatage = |d: NaiveDate| {
+            d.and_hms_opt(0, 0, 0)
+                .map(|d| d.and_utc().timestamp())
+                .ok_or_else(invalide)
+        };
+        match (debut, fin) {
+            (Some(debut), Some(fin)) => Ok((horodatage(debut)?, horodatage(fin)?)),
+            _ => Err(invalide()),
+        }
+    }
+
+    /// `2023`, `2023-01..2023-06`, `2023-01-15..`, `..2022`, `>=2024-03`,
+    /// `<2020`.
+    pub fn periode(valeur: &str) -> Result<(Option<i64>, Option<i64>), String> {
+        if let Some((debut, fin)) = valeur.split_once("..") {
+            let debut = if debut.is_empty() {
+                None
+            } else {
+                Some(bornes_date(debut)?.0)
+            };
+            let fin = if fin.is_empty() {
+                None
+            } else {
+                Some(bornes_date(fin)?.1)
+            };
+            return Ok((debut, fin));
+        }
+        if let Some(v) = valeur.strip_prefix(">=") {
+            Ok((Some(bornes_date(v)?.0), None))
+        } else if let Some(v) = valeur.strip_prefix('>') {
+            Ok((Some(bornes_date(v)?.1), None))
+        } else if let Some(v) = valeur.strip_prefix("<=") {
+            Ok((None, Some(bornes_date(v)?.1)))
+        } else if let Some(v) = valeur.strip_prefix('<') {
+            Ok((None, Some(bornes_date(v)?.0)))
+        } else {
+            let (debut, fin) = bornes_date(valeur)?;
+            Ok((Some(debut), Some(fin)))
+        }
+    }
+
+    /// `1000`, `500..2000`, `..300`, `>=1000`, `<500`.
+    pub fn intervalle(valeur: &str) -> Result<(Option<i64>, Option<i64>), String> {
+        let nombre = |v: &str| {
+            v.trim()
+                .parse::<i64>()
+                .map_err(|_| format!("nombre invalide : '{}'", v))
+        };
+        if let Some((min, max)) = valeur.split_once("..") {
+            let min = if min.is_empty() {
+                None
+            } else {
+                Some(nombre(min)?)
+            };
+            let max = if max.is_empty() {
+                None
+            } else {
+                Some(nombre(max)?)
+            };
+            return Ok((min, max));
+        }
+        if let Some(v) = valeur.strip_prefix(">=") {
+            Ok((Some(nombre(v)?), None))
+        } else if let Some(v) = valeur.strip_prefix('>') {
+            let min = nombre(v)?
+                .checked_add(1)
+                .ok_or_else(|| format!("nombre hors limites : '{}'", v))?;
+            Ok((Some(min), None))
+        } else if let Some(v) = valeur.strip_prefix("<=") {
+            Ok((None, Some(nombre(v)?)))
+        } else if let Some(v) = valeur.strip_prefix('<') {
+            let max = nombre(v)?
+                .checked_sub(1)
+                .ok_or_else(|| format!("nombre hors limites : '{}'", v))?;
+            Ok((None, Some(max)))
+        } else {
+            let n = nombre(valeur)?;
+            Ok((Some(n), Some(n)))
+        }
+    }
+
+    /// Champ Pocket designe par son nom francais ou son nom d'origine.
+    fn nom_champ(champ: &str) -> &str {
+        match champ {
+            "extrait" => "excerpt",
+            "mots" => "word_count",
+            "langue" => "lang",
+            "image" => "top_image_url",
+            "auteurs" => "authors",
+            "duree" => "time_to_read",
+            autre => autre,
+        }
+    }
+
+    /// Champ absent, vide ou nul (`""`, `"0"`, `{}`, `[]`).
+    fn champ_vide(element: &Value, champ: &str) -> bool {
+        if champ == "titre" {
+            return titre(element).is_empty();
+        }
+        match element.get(nom_champ(champ)) {
+            None | Some(Value::Null) => true,
+            Some(Value::String(s)) => s.is_empty() || s == "0",
+            Some(Value::Number(n)) => n.as_f64() == Some(0.0),
+            Some(Value::Object(o)) => o.is_empty(),
+            Some(Value::Array(a)) => a.is_empty(),
+            Some(Value::Bool(b)) => !b,
+        }
+    }
+
+    /// Filtres combines (tous doivent etre satisfaits) ; les valeurs d'une
+    /// meme liste sont des alternatives, sauf pour les tags qui sont tous
+    /// requis.
+    #[derive(Debug, Clone, Default)]
+    pub struct Filtres {
+        pub statuts: Vec<&'static str>,
+        pub favori: Option<bool>,
+        pub tags: Vec<String>,
+        pub sans_tags: Vec<String>,
+        pub domaines: Vec<String>,
+        pub langues: Vec<String>,
+        pub ajoute: (Option<i64>, Option<i64>),
+        pub maj: (Option<i64>, Option<i64>),
+        pub mots: (Option<i64>, Option<i64>),
+        /// champs qui doivent etre vides
+        pub sans: Vec<String>,
+        /// champs qui doivent etre renseignes
+        pub avec: Vec<String>,
+    }
+
+    pub const NOMS_FILTRES: [&str; 11] = [
+        "statut", "favori", "tag", "sans-tag", "domaine", "lang", "ajoute", "maj", "mots", "sans",
+        "avec",
+    ];
+
+    fn valeurs(valeur: &str) -> impl Iterator<Item = String> + '_ {
+        valeur
+            .split(',')
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+    }
+
+    impl Filtres {
+        /// Ajoute le filtre `nom` (voir `NOMS_FILTRES`) ; une valeur peut
+        /// contenir plusieurs elements separes par des virgules.
+        pub fn ajouter(&mut self, nom: &str, valeur: &str) -> Result<(), String> {
+            match nom {
+                "statut" => {
+                    for v in valeurs(valeur) {
+                        self.statuts.push(code_statut(v.as_str())?);
+                    }
+                }
+                "favori" => self.favori = Some(oui_non(valeur)?),
+                "tag" => self.tags.extend(valeurs(valeur)),
+                "sans-tag" => self.sans_tags.extend(valeurs(valeur)),
+                "domaine" => self.domaines.extend(
+                    valeurs(valeur).map(|d| d.strip_prefix("www.").unwrap_or(&d).to_string()),
+                ),
+                "lang" => self.langues.extend(valeurs(valeur)),
+                "ajoute" => self.ajoute = periode(valeur)?,
+                "maj" => self.maj = periode(valeur)?,
+                "mots" => self.mots = intervalle(valeur)?,
+                "sans" => self.sans.extend(valeurs(valeur)),
+                "avec" => self.avec.extend(valeurs(valeur)),
+                _ => return Err(format!("filtre inconnu : '{}'", nom)),
+            }
+            Ok(())
+        }
+
+        pub fn accepte(&self, element: &Value) -> bool {
+            let dans = |(min, max): (Option<i64>, Option<i64>), n: i64, exclu: bool| {
+                min.is_none_or(|min| n >= min)
+                    && max.is_none_or(|max| if exclu { n < max } else { n <= max })
+            };
+            let periode = |bornes: (Option<i64>, Option<i64>), champ: &str| {
+                bornes == (None, None) || {
+                    let date = champ_nombre(element, champ);
+                    date > 0 && dans(bornes, date, true)
+                }
+            };
+            if !self.statuts.is_empty() && !self.statuts.contains(&statut(element)) {
+                return false;
+            }
+            if self.favori.is_some_and(|f| f != est_favori(element)) {
+                return false;
+            }
+            if !self.tags.is_empty() || !self.sans_tags.is_empty() {
+                let tags: Vec<String> = tags(element).iter().map(|t| t.to_lowercase()).collect();
+                if !self.tags.iter().all(|t| tags.contains(t))
+                    || self.sans_tags.iter().any(|t| tags.contains(t))
+                {
+                    return false;
+                }
+            }
+            if !self.domaines.is_empty() {
+                let domaine = domaine(element);
+                if !self
+                    .domaines
+                    .iter()
+                    .any(|d| domaine == *d || domaine.ends_with(format!(".{}", d).as_str()))
+                {
+                    return false;
+                }
+            }
+            if !self.langues.is_empty()
+                && !self
+                    .langues
+                    .contains(&champ_str(element, "lang").to_lowercase())
+            {
+                return false;
+            }
+            periode(self.ajoute, "time_added")
+                && periode(self.maj, "time_updated")
+                && (self.mots == (None, None)
+                    || dans(self.mots, champ_nombre(element, "word_count"), false))
+                && self.sans.iter().all(|c| champ_vide(element, c))
+                && self.avec.iter().all(|c| !champ_vide(element, c))
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum CleTri {
+        Ajoute,
+        Maj,
+        Mots,
+        Titre,
+        Domaine,
+        Id,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    pub enum Cle {
+        Nombre(i64),
+        Texte(String),
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Tri {
+        pub cle: CleTri,
+        pub decroissant: bool,
+    }
+
+    impl Default for Tri {
+        fn default() -> Self {
+            Tri {
+                cle: CleTri::Ajoute,
+                decroissant: true,
+            }
+        }
+    }
+
+    impl Tri {
+        /// `ajoute`, `maj`, `mots`, `titre`, `domaine` ou `id` ; `-` devant
+        /// pour l'ordre decroissant (`-ajoute`).
+        pub fn parse(s: &str) -> Result<Tri, String> {
+            let (nom, decroissant) = match s.strip_prefix('-') {
+                Some(nom) => (nom, true),
+                None => (s, false),
+            };
+            let cle = match nom {
+                "ajoute" => CleTri::Ajoute,
+                "maj" => CleTri::Maj,
+                "mots" => CleTri::Mots,
+                "titre" => CleTri::Titre,
+                "domaine" => CleTri::Domaine,
+                "id" => CleTri::Id,
+                _ => return Err(format!("tri inconnu : '{}'", s)),
+            };
+            Ok(Tri { cle, decroissant })
+        }
+
+        /// Valeur triee d'un element, gardee dans les curseurs de l'API.
+        pub fn cle(&self, element: &Value) -> Cle {
+            match self.cle {
+                CleTri::Ajoute => Cle::Nombre(champ_nombre(element, "time_added")),
+                CleTri::Maj => Cle::Nombre(champ_nombre(element, "time_updated")),
+                CleTri::Mots => Cle::Nombre(champ_nombre(element, "word_count")),
+                CleTri::Titre => Cle::Texte(titre(element).to_lowercase()),
+                CleTri::Domaine => Cle::Texte(domaine(element)),
+                CleTri::Id => Cle::Nombre(0),
+            }
+        }
+
+        /// Ordre de deux elements donnes par leur id et leur cle ; a cle
+        /// egale, les ids sont compares comme des nombres.
+        pub fn comparer_cles(&self, a: (&str, &Cle), b: (&str, &Cle)) -> Ordering {
+            let ordre = a.1.cmp(b.1).then_with(|| {
+                a.0.parse::<u64>()
+                    .unwrap_or(0)
+                    .cmp(&b.0.parse::<u64>().unwrap_or(0))
+                    .then(a.0.cmp(b.0))
+            });
+            if self.decroissant {
+                ordre.reverse()
+            } else {
+                ordre
+            }
+        }
+
+        pub fn comparer(&self, a: (&String, &Value), b: (&String, &Value)) -> Ordering {
+            self.comparer_cles((a.0, &self.cle(a.1)), (b.0, &self.cle(b.1)))
+        }
+    }
+
+    /// Elements qui passent les filtres, tries.
+    pub fn filtrer<'a>(
+        data: &'a Value,
+        filtres: &Filtres,
+        tri: Tri,
+    ) -> Vec<(&'a String, &'a Value)> {
+        let mut res: Vec<(&String, &Value)> = match liste(data) {
+            Some(obj) => obj.iter().filter(|(_, e)| filtres.accepte(e)).collect(),
+            None => Vec::new(),
+        };
+        res.sort_by(|a, b| tri.comparer(*a, *b));
+        res
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum FormatListe {
+        Tableau,
+        JsonLignes,
+        Urls,
+    }
+
+    impl FormatListe {
+        pub fn parse(s: &str) -> Option<FormatListe> {
+            match s {
+                "" | "table" | "tableau" | "texte" => Some(FormatListe::Tableau),
+                "jsonl" | "json" => Some(FormatListe::JsonLignes),
+                "urls" | "url" => Some(FormatListe::Urls),
+                _ => None,
+            }
+        }
+    }
+
+    fn tronquer(s: &str, taille: usize) -> String {
+        if s.chars().count() <= taille {
+            s.to_string()
+        } else {
+            let mut res: String = s.chars().take(taille - 1).collect();
+            res.push('…');
+            res
+        }
+    }
+
+    /// Tableau aligne (id, date d'ajout, statut, favori, mots, domaine,
+    /// titre), un element JSON par ligne, ou les URLs seules.
+    pub fn ecrire_liste(
+        sortie: &mut dyn Write,
+        elements: &[(&String, &Value)],
+        format: FormatListe,
+    ) -> io::Result<()> {
+        match format {
+            FormatListe::JsonLignes => {
+                for (_, element) in elements {
+                    writeln!(sortie, "{}", element)?;
+                }
+            }
+            FormatListe::Urls => {
+                for (_, element) in elements {
+                    writeln!(sortie, "{}", url(element))?;
+                }
+            }
+            FormatListe::Tableau => {
+                let largeur_id = elements
+                    .iter()
+                    .map(|(id, _)| id.len())
+                    .max()
+                    .unwrap_or(2)
+                    .max(2);
+                let domaines: Vec<String> = elements
+                    .iter()
+                    .map(|(_, e)| tronquer(domaine(e).as_str(), 30))
+                    .collect();
+                let largeur_domaine = domaines
+                    .iter()
+                    .map(|d| d.chars().count())
+                    .max()
+                    .unwrap_or(7)
+                    .max(7);
+                writeln!(
+                    sortie,
+                    "{:<largeur_id$}  {:<10}  {:<8}  {:<3}  {:>6}  {:<largeur_domaine$}  titre",
+                    "id", "ajoute", "statut", "fav", "mots", "domaine"
+                )?;
+                for ((id, element), domaine) in elements.iter().zip(domaines) {
+                    let date = DateTime::from_timestamp(champ_nombre(element, "time_added"), 0)
+                        .filter(|d| d.timestamp() > 0)
+                        .map(|d| d.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default();
+                    let titre = match titre(element) {
+                        "" => url(element),
+                        t => t,
+                    };
+                    writeln!(
+                        sortie,
+                        "{:<largeur_id$}  {:<10}  {:<8}  {:<3}  {:>6}  {:<largeur_domaine$}  {}",
+                        id,
+                        date,
+                        nom_statut(statut(element)),
+                        if est_favori(element) { "*" } else { "" },
+                        champ_nombre(element, "word_count"),
+                        domaine,
+                        tronquer(titre, 80)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn bornes_des_dates() {
+            assert_eq!(bornes_date("2024"), Ok((1704067200, 1735689600)));
+            assert_eq!(bornes_date("2024-12"), Ok((1733011200, 1735689600)));
+            assert_eq!(bornes_date("2024-02-29"), Ok((1709164800, 1709251200)));
+            assert!(bornes_date("2023-02-29").is_err());
+        }
+
+        #[test]
+        fn annees_hors_limites_refusees() {
+            assert!(bornes_date("2147483647").is_err());
+            assert!(bornes_date("4294967295-01").is_err());
+            assert!(bornes_date("10000").is_err());
+            assert!(bornes_date("9999").is_ok());
+        }
+
+        #[test]
+        fn intervalles_sans_debordement() {
+            assert_eq!(intervalle("1000"), Ok((Some(1000), Some(1000))));
+            assert_eq!(intervalle("500..2000"), Ok((Some(500), Some(2000))));
+            assert_eq!(intervalle("..300"), Ok((None, Some(300))));
+            assert_eq!(intervalle(">1000"), Ok((Some(1001), None)));
+            assert_eq!(intervalle("<500"), Ok((None, Some(499))));
+            assert!(intervalle(">9223372036854775807").is_err());
+            assert!(intervalle("<-9223372036854775808").is_err());
+            assert!(intervalle("beaucoup").is_err());
+        }
+
+        fn element(id: &str, url: &str, tags: &[&str], ajoute: i64, mots: i64) -> Value {
+            let tags: serde_json::Map<String, Value> = tags
+                .iter()
+                .map(|t| (t.to_string(), serde_json::json!({"item_id": id, "tag": t})))
+                .collect();
+            serde_json::json!({
+                "item_id": id,
+                "given_url": url,
+                "resolved_title": format!("Titre {}", id),
+                "status": "0",
+                "tags": tags,
+                "time_added": ajoute.to_string(),
+                "word_count": mots.to_string(),
+            })
+        }
+
+        fn filtres(noms: &[(&str, &str)]) -> Filtres {
+            let mut filtres = Filtres::default();
+            for (nom, valeur) in noms {
+                filtres.ajouter(nom, valeur).unwrap();
+            }
+            filtres
+        }
+
+        #[test]
+        fn tous_les_tags_sont_requis() {
+            let e = element("1", "https://exemple.org/a", &["rust", "Web"], 0, 0);
+            assert!(filtres(&[("tag", "rust")]).accepte(&e));
+            assert!(filtres(&[("tag", "rust,web")]).accepte(&e));
+            assert!(filtres(&[("tag", "rust"), ("tag", "web")]).accepte(&e));
+            assert!(!filtres(&[("tag", "rust,python")]).accepte(&e));
+            assert!(!filtres(&[("sans-tag", "web")]).accepte(&e));
+            assert!(filtres(&[("sans-tag", "python")]).accepte(&e));
+        }
+
+        #[test]
+        fn domaine_et_sous_domaines() {
+            let e = element("1", "https://blog.exemple.org/a", &[], 0, 0);
+            assert!(filtres(&[("domaine", "exemple.org")]).accepte(&e));
+            assert!(filtres(&[("domaine", "www.blog.exemple.org")]).accepte(&e));
+            assert!(filtres(&[("domaine", "autre.fr,exemple.org")]).accepte(&e));
+            assert!(!filtres(&[("domaine", "ple.org")]).accepte(&e));
+            assert!(!filtres(&[("domaine", "autre.fr")]).accepte(&e));
+        }
+
+        #[test]
+        fn champs_vides_ou_renseignes() {
+            let mut e = element("1", "https://exemple.org/a", &[], 1704067200, 1200);
+            e["excerpt"] = Value::String(String::new());
+            assert!(filtres(&[("sans", "extrait,image")]).accepte(&e));
+            assert!(!filtres(&[("avec", "extrait")]).accepte(&e));
+            assert!(filtres(&[("avec", "titre,mots")]).accepte(&e));
+            assert!(!filtres(&[("sans", "titre")]).accepte(&e));
+            assert!(filtres(&[("mots", ">1000"), ("ajoute", "2024")]).accepte(&e));
+            assert!(!filtres(&[("mots", "<1200")]).accepte(&e));
+            assert!(!filtres(&[("ajoute", "2023")]).accepte(&e));
+            assert!(Filtres::default().ajouter("inconnu", "1").is_err());
+        }
+
+        #[test]
+        fn tri_par_cle_puis_par_id() {
+            let data = serde_json::json!({"liste": {
+                "10": element("10", "https://b.org/", &[], 300, 50),
+                "9": element("9", "https://a.org/", &[], 300, 100),
+                "2": element("2", "https://c.org/", &[], 100, 100),
+            }});
+            let ordre = |tri: &str| -> Vec<String> {
+                filtrer(&data, &Filtres::default(), Tri::parse(tri).unwrap())
+                    .into_iter()
+                    .map(|(id, _)| id.clone())
+                    .collect()
+            };
+            // ids compares comme des nombres a cle egale
+            assert_eq!(ordre("ajoute"), ["2", "9", "10"]);
+            assert_eq!(ordre("-ajoute"), ["10", "9", "2"]);
+            assert_eq!(ordre("-mots"), ["9", "2", "10"]);
+            assert_eq!(ordre("domaine"), ["9", "10", "2"]);
+            assert_eq!(ordre("id"), ["2", "9", "10"]);
+            assert_eq!(Tri::default(), Tri::parse("-ajoute").unwrap());
+            assert!(Tri::parse("taille").is_err());
+        }
+    }
+}
@@ -0,0 +1,51 @@
+pub mod mod_historique {
+    use crate::rapport::mod_rapport::RapportExecution;
+    use std::fs::{File, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::path::Path;
+
+    pub const FICHIER_HISTORIQUE: &str = "/runs.jsonl";
+
+    /// Ajoute une ligne JSON a `runs.jsonl`.
+    pub fn ajouter_execution(repertoire: &str, rapport: &RapportExecution) -> std::io::Result<()> {
+        let fichier = repertoire.to_string() + FICHIER_HISTORIQUE;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&fichier)?;
+        let ligne = serde_json::to_string(rapport).expect("Erreur de sérialisation");
+        writeln!(file, "{}", ligne)?;
+        log::info!("execution ajoutee a {}", fichier);
+        Ok(())
+    }
+
+    /// Executions dans l'ordre du fichier ; les lignes illisibles sont
+    /// ignorees.
+    pub fn charger_historique(repertoire: &str) -> Vec<RapportExecution> {
+        let fichier = repertoire.to_string() + FICHIER_HISTORIQUE;
+        if !Path::new(&fichier).exists() {
+            return Vec::new();
+        }
+        let file = File::open(&fichier).expect("file should open read only");
+        let mut res = Vec::new();
+        for (i, ligne) in BufReader::new(file).lines().enumerate() {
+            let Ok(ligne) = ligne else {
+                break;
+            };
+            if ligne.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<RapportExecution>(&ligne) {
+                Ok(rapport) => res.push(rapport),
+                Err(err) => log::warn!("{} ligne {} ignoree : {}", fichier, i + 1, err),
+            }
+        }
+        res
+    }
+
+    pub fn derniere_execution_reussie(
+        historique: &[RapportExecution],
+    ) -> Option<&RapportExecution> {
+        historique.iter().rev().find(|r| r.succes)
+    }
+}
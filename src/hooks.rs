@@ -0,0 +1,221 @@
+pub mod mod_hooks {
+    use crate::config::mod_config::{ConfigHooks, Hook};
+    use crate::historique::mod_historique::ajouter_execution;
+    use crate::rapport::mod_rapport::RapportExecution;
+    use chrono::{DateTime, FixedOffset, Local};
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    use std::thread;
+    use std::time::Duration;
+
+    pub const EVENEMENT_SUCCES: &str = "succes";
+    pub const EVENEMENT_ECHEC: &str = "echec";
+    pub const EVENEMENT_ANOMALIE: &str = "anomalie";
+
+    /// Lance les hooks correspondant au resultat de l'execution : succes ou
+    /// echec, puis anomalie s'il y en a eu.
+    pub async fn declencher_hooks(config: &ConfigHooks, execution: &RapportExecution) {
+        let json = serde_json::to_string(execution).expect("Erreur de sérialisation");
+        if execution.succes {
+            declencher(config, &config.succes, EVENEMENT_SUCCES, &json).await;
+        } else {
+            declencher(config, &config.echec, EVENEMENT_ECHEC, &json).await;
+        }
+        if execution.nb_anomalies > 0 {
+            declencher(config, &config.anomalie, EVENEMENT_ANOMALIE, &json).await;
+        }
+    }
+
+    /// Garde posee au debut d'une execution : si elle est liberee sans
+    /// avoir ete desarmee (panic, retour anticipe), un rapport marque
+    /// `panic` ou `interrompu` est ajoute a l'historique et les hooks
+    /// d'echec sont lances avec ce rapport.
+    pub struct GardeEchec {
+        config: ConfigHooks,
+        repertoire: String,
+        mode: String,
+        debut: DateTime<FixedOffset>,
+        since: u64,
+        armee: bool,
+    }
+
+    impl GardeEchec {
+        pub fn new(
+            config: &ConfigHooks,
+            repertoire: &str,
+            mode: &str,
+            debut: DateTime<FixedOffset>,
+            since: u64,
+        ) -> GardeEchec {
+            GardeEchec {
+                config: config.clone(),
+                repertoire: repertoire.to_string(),
+                mode: mode.to_string(),
+                debut,
+                since,
+                armee: true,
+            }
+        }
+
+        /// L'execution est arrivee a son terme : les hooks sont lances
+        /// normalement par `declencher_hooks`.
+        pub fn desarmer(&mut self) {
+            self.armee = false;
+        }
+    }
+
+    impl Drop for GardeEchec {
+        fn drop(&mut self) {
+            if !self.armee {
+                return;
+            }
+            let cause = if thread::panicking() {
+                "panic"
+            } else {
+                "interrompu"
+            };
+            let mut execution = RapportExecution::creer(
+                self.mode.as_str(),
+                self.debut,
+                Local::now().fixed_offset(),
+                self.since,
+                self.since,
+                Vec::new(),
+            );
+            execution.succes = false;
+            execution.erreurs.insert(cause.to_string(), 1);
+            log::error!("execution {} : {}", cause, execution);
+            if let Err(err) = ajouter_execution(self.repertoire.as_str(), &execution) {
+                log::error!("Erreur d'ecriture de l'historique : {}", err);
+            }
+            if self.config.echec.is_empty() {
+                return;
+            }
+            let json = serde_json::to_string(&execution).expect("Erreur de sérialisation");
+            let config = self.config.clone();
+            // le runtime de main peut etre en train de s'arreter : les hooks
+            // tournent dans leur propre runtime, sur un autre thread
+            let lancement =
+                thread::spawn(move || {
+                    match tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                    {
+                        Ok(runtime) => runtime.block_on(declencher(
+                            &config,
+                            &config.echec,
+                            EVENEMENT_ECHEC,
+                            &json,
+                        )),
+                        Err(err) => log::error!("hooks d'echec non lances : {}", err),
+                    }
+                });
+            let _ = lancement.join();
+        }
+    }
+
+    async fn declencher(config: &ConfigHooks, hooks: &[Hook], evenement: &str, json: &str) {
+        for hook in hooks {
+            if !hook.commande.is_empty() {
+                if let Err(err) = lancer_commande(&hook.commande, evenement, json) {
+                    log::error!("hook {} {:?} en erreur : {}", evenement, hook.commande, err);
+                }
+            }
+            if !hook.url.is_empty() {
+                appeler_webhook(config, hook.url.as_str(), evenement, json).await;
+            }
+        }
+    }
+
+    /// Le rapport est envoye sur l'entree standard, l'evenement dans la
+    /// variable `PBACKUP_EVENEMENT`.
+    fn lancer_commande(commande: &[String], evenement: &str, json: &str) -> std::io::Result<()> {
+        log::info!("hook {} : {:?}", evenement, commande);
+        let mut child = Command::new(&commande[0])
+            .args(&commande[1..])
+            .env("PBACKUP_EVENEMENT", evenement)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        // le processus est attendu meme si l'ecriture echoue, pour ne pas
+        // laisser de zombie ; stdin est ferme a la fin du bloc
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Err(err) = stdin.write_all(json.as_bytes()) {
+                log::warn!("hook {} : rapport non transmis : {}", evenement, err);
+            }
+        }
+        let statut = child.wait()?;
+        if statut.success() {
+            log::info!("hook {} termine", evenement);
+        } else {
+            log::error!("hook {} {:?} : {}", evenement, commande, statut);
+        }
+        Ok(())
+    }
+
+    async fn appeler_webhook(config: &ConfigHooks, url: &str, evenement: &str, json: &str) {
+        let client = reqwest::Client::new();
+        let tentatives = config.tentatives.max(1);
+        for tentative in 1..=tentatives {
+            log::info!("webhook {} : {} (tentative {})", evenement, url, tentative);
+            let response = client
+                .post(url)
+                .header("Content-Type", "application/json")
+                .header("X-Pbackup-Evenement", evenement)
+                .timeout(Duration::from_millis(config.timeout_ms))
+                .body(json.to_owned())
+                .send()
+                .await;
+            match response {
+                Ok(resp) if resp.status().is_success() => {
+                    log::info!("webhook {} : {}", url, resp.status());
+                    return;
+                }
+                Ok(resp) => log::error!("webhook {} : {}", url, resp.status()),
+                Err(err) => log::error!("webhook {} : {}", url, err),
+            }
+            if tentative < tentatives {
+                tokio::time::sleep(Duration::from_millis(config.delai_ms)).await;
+            }
+        }
+        log::error!("webhook {} abandonne apres {} tentatives", url, tentatives);
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::historique::mod_historique::charger_historique;
+        use std::fs;
+
+        fn repertoire(nom: &str) -> String {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            dossier.to_string_lossy().to_string()
+        }
+
+        #[test]
+        fn garde_non_desarmee_ajoute_l_echec_a_l_historique() {
+            let repertoire = repertoire("garde_echec");
+            let debut = Local::now().fixed_offset();
+            drop(GardeEchec::new(
+                &ConfigHooks::default(),
+                &repertoire,
+                "miseAJour",
+                debut,
+                42,
+            ));
+            let mut garde =
+                GardeEchec::new(&ConfigHooks::default(), &repertoire, "miseAJour", debut, 43);
+            garde.desarmer();
+            drop(garde);
+
+            let historique = charger_historique(&repertoire);
+            assert_eq!(historique.len(), 1);
+            assert!(!historique[0].succes);
+            assert_eq!(historique[0].since_apres, 42);
+            assert_eq!(historique[0].erreurs.get("interrompu"), Some(&1));
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+    }
+}
@@ -0,0 +1,1201 @@
+pub mod mod_import {
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, est_favori, liste, objet_tags, statut, tags, timestamp_iso, url,
+        DATA_LISTE, STATUT_ARCHIVE, STATUT_NON_LU,
+    };
+    use reqwest::Url;
+    use serde::Serialize;
+    use serde_json::{Map, Value};
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::path::Path;
+
+    pub const SOURCE_POCKET_HTML: &str = "pocket-html";
+    pub const SOURCE_POCKET_CSV: &str = "pocket-csv";
+    pub const SOURCE_INSTAPAPER: &str = "instapaper";
+    pub const SOURCE_RAINDROP: &str = "raindrop";
+    pub const SOURCE_WALLABAG: &str = "wallabag";
+
+    /// Les elements venant d'un autre service recoivent le tag
+    /// `source:<service>`.
+    const PREFIXE_TAG_SOURCE: &str = "source:";
+
+    /// Prefixe des cles des elements importes.
+    const PREFIXE_CLE_IMPORT: &str = "imp";
+
+    #[derive(Debug)]
+    pub enum ErreurImport {
+        Lecture(io::Error),
+        Format(String),
+    }
+
+    impl fmt::Display for ErreurImport {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ErreurImport::Lecture(err) => write!(f, "Erreur de lecture : {}", err),
+                ErreurImport::Format(err) => write!(f, "Format invalide : {}", err),
+            }
+        }
+    }
+
+    impl From<io::Error> for ErreurImport {
+        fn from(err: io::Error) -> Self {
+            ErreurImport::Lecture(err)
+        }
+    }
+
+    impl From<csv::Error> for ErreurImport {
+        fn from(err: csv::Error) -> Self {
+            ErreurImport::Format(err.to_string())
+        }
+    }
+
+    impl From<zip::result::ZipError> for ErreurImport {
+        fn from(err: zip::result::ZipError) -> Self {
+            ErreurImport::Format(err.to_string())
+        }
+    }
+
+    /// Element lu dans un fichier d'export, avant fusion. Les champs
+    /// absents du format sont a `None` / vides.
+    #[derive(Debug, Clone, Default)]
+    pub struct ElementImporte {
+        pub url: String,
+        pub titre: String,
+        pub time_added: i64,
+        pub tags: Vec<String>,
+        pub statut: Option<String>,
+        pub favori: Option<bool>,
+        pub extrait: String,
+        pub surlignages: Vec<String>,
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Conflit {
+        pub item_id: String,
+        pub url: String,
+        pub detail: String,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanImport {
+        pub source: String,
+        pub nb_lus: usize,
+        pub nb_existants: usize,
+        /// elements existants auxquels des tags ou une date ont ete ajoutes
+        pub nb_completes: usize,
+        pub ajoutes: Vec<String>,
+        pub conflits: Vec<Conflit>,
+    }
+
+    impl fmt::Display for BilanImport {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(f, "source : {}", self.source)?;
+            writeln!(f, "lus : {}", self.nb_lus)?;
+            writeln!(f, "ajoutes : {}", self.ajoutes.len())?;
+            writeln!(f, "deja presents : {}", self.nb_existants)?;
+            writeln!(f, "completes : {}", self.nb_completes)?;
+            writeln!(f, "conflits : {}", self.conflits.len())?;
+            for conflit in &self.conflits {
+                writeln!(
+                    f,
+                    "  {} {} : {}",
+                    conflit.item_id, conflit.url, conflit.detail
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Cle de comparaison des URL : schema et hote en minuscules, sans
+    /// `www.`, sans fragment, sans parametres `utm_*`, sans `/` final.
+    pub fn normaliser_url(url: &str) -> String {
+        let url = url.trim();
+        let Ok(mut u) = Url::parse(url) else {
+            return url.to_lowercase();
+        };
+        u.set_fragment(None);
+        let parametres: Vec<(String, String)> = u
+            .query_pairs()
+            .filter(|(k, _)| !k.starts_with("utm_"))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        if parametres.is_empty() {
+            u.set_query(None);
+        } else {
+            u.query_pairs_mut().clear().extend_pairs(parametres);
+        }
+        let hote = u.host_str().unwrap_or("").to_lowercase();
+        let hote = hote.strip_prefix("www.").unwrap_or(hote.as_str());
+        let mut res = format!("{}{}", hote, u.path().trim_end_matches('/'));
+        if let Some(query) = u.query() {
+            res.push('?');
+            res.push_str(query);
+        }
+        res
+    }
+
+    /// FNV-1a : cle stable d'un element importe.
+    fn cle_import(url_normalisee: &str) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for octet in url_normalisee.as_bytes() {
+            hash ^= *octet as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{}{:016x}", PREFIXE_CLE_IMPORT, hash)
+    }
+
+    /// Entites nommees du jeu Latin-1 (`&eacute;`, `&copy;`...).
+    const ENTITES_LATIN1: [(&str, char); 96] = [
+        ("nbsp", '\u{a0}'),
+        ("iexcl", '\u{a1}'),
+        ("cent", '\u{a2}'),
+        ("pound", '\u{a3}'),
+        ("curren", '\u{a4}'),
+        ("yen", '\u{a5}'),
+        ("brvbar", '\u{a6}'),
+        ("sect", '\u{a7}'),
+        ("uml", '\u{a8}'),
+        ("copy", '\u{a9}'),
+        ("ordf", '\u{aa}'),
+        ("laquo", '\u{ab}'),
+        ("not", '\u{ac}'),
+        ("shy", '\u{ad}'),
+        ("reg", '\u{ae}'),
+        ("macr", '\u{af}'),
+        ("deg", '\u{b0}'),
+        ("plusmn", '\u{b1}'),
+        ("sup2", '\u{b2}'),
+        ("sup3", '\u{b3}'),
+        ("acute", '\u{b4}'),
+        ("micro", '\u{b5}'),
+        ("para", '\u{b6}'),
+        ("middot", '\u{b7}'),
+        ("cedil", '\u{b8}'),
+        ("sup1", '\u{b9}'),
+        ("ordm", '\u{ba}'),
+        ("raquo", '\u{bb}'),
+        ("frac14", '\u{bc}'),
+        ("frac12", '\u{bd}'),
+        ("frac34", '\u{be}'),
+        ("iquest", '\u{bf}'),
+        ("Agrave", '\u{c0}'),
+        ("Aacute", '\u{c1}'),
+        ("Acirc", '\u{c2}'),
+        ("Atilde", '\u{c3}'),
+        ("Auml", '\u{c4}'),
+        ("Aring", '\u{c5}'),
+        ("AElig", '\u{c6}'),
+        ("Ccedil", '\u{c7}'),
+        ("Egrave", '\u{c8}'),
+        ("Eacute", '\u{c9}'),
+        ("Ecirc", '\u{ca}'),
+        ("Euml", '\u{cb}'),
+        ("Igrave", '\u{cc}'),
+        ("Iacute", '\u{cd}'),
+        ("Icirc", '\u{ce}'),
+        ("Iuml", '\u{cf}'),
+        ("ETH", '\u{d0}'),
+        ("Ntilde", '\u{d1}'),
+        ("Ograve", '\u{d2}'),
+        ("Oacute", '\u{d3}'),
+        ("Ocirc", '\u{d4}'),
+        ("Otilde", '\u{d5}'),
+        ("Ouml", '\u{d6}'),
+        ("times", '\u{d7}'),
+        ("Oslash", '\u{d8}'),
+        ("Ugrave", '\u{d9}'),
+        ("Uacute", '\u{da}'),
+        ("Ucirc", '\u{db}'),
+        ("Uuml", '\u{dc}'),
+        ("Yacute", '\u{dd}'),
+        ("THORN", '\u{de}'),
+        ("szlig", '\u{df}'),
+        ("agrave", '\u{e0}'),
+        ("aacute", '\u{e1}'),
+        ("acirc", '\u{e2}'),
+        ("atilde", '\u{e3}'),
+        ("auml", '\u{e4}'),
+        ("aring", '\u{e5}'),
+        ("aelig", '\u{e6}'),
+        ("ccedil", '\u{e7}'),
+        ("egrave", '\u{e8}'),
+        ("eacute", '\u{e9}'),
+        ("ecirc", '\u{ea}'),
+        ("euml", '\u{eb}'),
+        ("igrave", '\u{ec}'),
+        ("iacute", '\u{ed}'),
+        ("icirc", '\u{ee}'),
+        ("iuml", '\u{ef}'),
+        ("eth", '\u{f0}'),
+        ("ntilde", '\u{f1}'),
+        ("ograve", '\u{f2}'),
+        ("oacute", '\u{f3}'),
+        ("ocirc", '\u{f4}'),
+        ("otilde", '\u{f5}'),
+        ("ouml", '\u{f6}'),
+        ("divide", '\u{f7}'),
+        ("oslash", '\u{f8}'),
+        ("ugrave", '\u{f9}'),
+        ("uacute", '\u{fa}'),
+        ("ucirc", '\u{fb}'),
+        ("uuml", '\u{fc}'),
+        ("yacute", '\u{fd}'),
+        ("thorn", '\u{fe}'),
+        ("yuml", '\u{ff}'),
+    ];
+
+    /// Entites nommees courantes et numeriques (`&#233;`, `&#xE9;`) ; les
+    /// autres sont laissees telles quelles.
+    pub fn decoder_entites(s: &str) -> String {
+        let mut res = String::with_capacity(s.len());
+        let mut reste = s;
+        while let Some(pos) = reste.find('&') {
+            res.push_str(&reste[..pos]);
+            reste = &reste[pos..];
+            let fin = reste[1..]
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+                .map(|p| p + 1)
+                .unwrap_or(reste.len());
+            let nom = &reste[1..fin];
+            let car = match nom {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                "hellip" => Some('…'),
+                "mdash" => Some('—'),
+                "ndash" => Some('–'),
+                "rsquo" => Some('’'),
+                "lsquo" => Some('‘'),
+                "rdquo" => Some('”'),
+                "ldquo" => Some('“'),
+                "laquo" => Some('«'),
+                "raquo" => Some('»'),
+                _ => ENTITES_LATIN1
+                    .iter()
+                    .find(|(n, _)| *n == nom)
+                    .map(|(_, c)| *c)
+                    .or_else(|| {
+                        nom.strip_prefix("#x")
+                            .or_else(|| nom.strip_prefix("#X"))
+                            .and_then(|h| u32::from_str_radix(h, 16).ok())
+                            .and_then(char::from_u32)
+                    })
+                    .or_else(|| {
+                        nom.strip_prefix('#')
+                            .and_then(|d| d.parse().ok())
+                            .and_then(char::from_u32)
+                    }),
+            };
+            match car {
+                Some(car) => {
+                    res.push(car);
+                    reste = reste[fin..].strip_prefix(';').unwrap_or(&reste[fin..]);
+                }
+                None => {
+                    res.push('&');
+                    reste = &reste[1..];
+                }
+            }
+        }
+        res.push_str(reste);
+        res
+    }
+
+    /// Attributs d'une balise ouvrante (`href="..." time_added="..."`).
+    pub fn attributs(balise: &str) -> HashMap<String, String> {
+        let mut res = HashMap::new();
+        let mut reste = balise;
+        while let Some(pos) = reste.find('=') {
+            let nom = reste[..pos]
+                .rsplit(|c: char| c.is_whitespace())
+                .next()
+                .unwrap_or("")
+                .to_lowercase();
+            let apres = reste[pos + 1..].trim_start();
+            let (valeur, suite) = match apres.chars().next() {
+                Some(q) if q == '"' || q == '\'' => match apres[1..].find(q) {
+                    Some(fin) => (&apres[1..fin + 1], &apres[fin + 2..]),
+                    None => (&apres[1..], ""),
+                },
+                _ => {
+                    let fin = apres.find(char::is_whitespace).unwrap_or(apres.len());
+                    (&apres[..fin], &apres[fin..])
+                }
+            };
+            res.insert(nom, decoder_entites(valeur));
+            reste = suite;
+        }
+        res
+    }
+
+    /// `ril_export.html` : une liste de liens sous `<h1>Unread</h1>` puis
+    /// sous `<h1>Read Archive</h1>`, avec `time_added` et `tags`.
+    pub fn lire_export_html(contenu: &str) -> Vec<ElementImporte> {
+        let mut res = Vec::new();
+        let mut statut_courant = STATUT_NON_LU;
+        let minuscules = contenu.to_ascii_lowercase();
+        let mut pos = 0;
+        while let Some(debut) = minuscules[pos..].find('<').map(|p| p + pos) {
+            let Some(fin_balise) = minuscules[debut..].find('>').map(|p| p + debut) else {
+                break;
+            };
+            let balise = &minuscules[debut + 1..fin_balise];
+            pos = fin_balise + 1;
+            if balise.starts_with("h1") {
+                let titre = match minuscules[pos..].find("</h1>") {
+                    Some(fin) => &minuscules[pos..pos + fin],
+                    None => "",
+                };
+                statut_courant = if titre.contains("unread") {
+                    STATUT_NON_LU
+                } else {
+                    STATUT_ARCHIVE
+                };
+            } else if balise.starts_with("a ") {
+                let attributs = attributs(&contenu[debut + 2..fin_balise]);
+                let fin_lien = minuscules[pos..]
+                    .find("</a>")
+                    .map(|p| p + pos)
+                    .unwrap_or(pos);
+                let Some(href) = attributs.get("href").filter(|h| !h.is_empty()) else {
+                    continue;
+                };
+                res.push(ElementImporte {
+                    url: href.clone(),
+                    titre: decoder_entites(contenu[pos..fin_lien].trim()),
+                    time_added: attributs
+                        .get("time_added")
+                        .and_then(|t| t.parse().ok())
+                        .unwrap_or(0),
+                    tags: attributs
+                        .get("tags")
+                        .map(|t| {
+                            t.split(',')
+                                .map(|t| t.trim().to_string())
+                                .filter(|t| !t.is_empty())
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    statut: Some(statut_courant.to_string()),
+                    ..Default::default()
+                });
+                pos = fin_lien;
+            }
+        }
+        res
+    }
+
+    /// CSV de l'export Pocket : `title,url,time_added,cursor,tags,status`,
+    /// tags separes par `|`, status `unread` ou `archive`. Les colonnes
+    /// sont lues par leur nom.
+    pub fn lire_export_csv<R: Read>(lecteur: R) -> Result<Vec<ElementImporte>, ErreurImport> {
+        let mut csv = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(lecteur);
+        let entetes = entetes_csv(&mut csv)?;
+        let colonne = |nom: &str| entetes.iter().position(|h| h == nom);
+        let Some(col_url) = colonne("url") else {
+            return Err(ErreurImport::Format("colonne url absente".to_string()));
+        };
+        let (col_titre, col_date, col_tags, col_statut, col_favori) = (
+            colonne("title"),
+            colonne("time_added"),
+            colonne("tags"),
+            colonne("status"),
+            colonne("favorite"),
+        );
+        let mut res = Vec::new();
+        for ligne in csv.records() {
+            let ligne = ligne?;
+            let champ = |col: Option<usize>| col.and_then(|c| ligne.get(c)).unwrap_or("").trim();
+            if champ(Some(col_url)).is_empty() {
+                continue;
+            }
+            res.push(ElementImporte {
+                url: champ(Some(col_url)).to_string(),
+                titre: champ(col_titre).to_string(),
+                time_added: champ(col_date).parse().unwrap_or(0),
+                tags: champ(col_tags)
+                    .split('|')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                statut: match champ(col_statut) {
+                    "unread" => Some(STATUT_NON_LU.to_string()),
+                    "archive" | "archived" | "read" => Some(STATUT_ARCHIVE.to_string()),
+                    _ => None,
+                },
+                favori: match champ(col_favori) {
+                    "" => None,
+                    f => Some(f == "1" || f == "true"),
+                },
+                ..Default::default()
+            });
+        }
+        Ok(res)
+    }
+
+    /// Surlignages de l'export ZIP (`annotations/part_*.json`) :
+    /// `[{"url": ..., "highlights": [{"quote": ...}]}]`.
+    fn lire_annotations(contenu: &str) -> HashMap<String, Vec<String>> {
+        let mut res = HashMap::new();
+        let Ok(Value::Array(liste)) = serde_json::from_str::<Value>(contenu) else {
+            return res;
+        };
+        for element in liste {
+            let quotes: Vec<String> = element
+                .get("highlights")
+                .and_then(|h| h.as_array())
+                .map(|h| {
+                    h.iter()
+                        .map(|q| champ_str(q, "quote").to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            if !quotes.is_empty() {
+                res.insert(normaliser_url(champ_str(&element, "url")), quotes);
+            }
+        }
+        res
+    }
+
+    /// ZIP de l'export Pocket : tous les CSV, plus les surlignages.
+    pub fn lire_export_zip(fichier: File) -> Result<Vec<ElementImporte>, ErreurImport> {
+        let mut archive = zip::ZipArchive::new(fichier)?;
+        let mut res = Vec::new();
+        let mut annotations = HashMap::new();
+        for i in 0..archive.len() {
+            let mut entree = archive.by_index(i)?;
+            let nom = entree.name().to_lowercase();
+            if nom.ends_with(".csv") {
+                log::info!("lecture de {}", entree.name());
+                res.extend(lire_export_csv(&mut entree)?);
+            } else if nom.starts_with("annotations") && nom.ends_with(".json") {
+                let mut contenu = String::new();
+                entree.read_to_string(&mut contenu)?;
+                annotations.extend(lire_annotations(contenu.as_str()));
+            }
+        }
+        for element in res.iter_mut() {
+            if let Some(quotes) = annotations.get(&normaliser_url(element.url.as_str())) {
+                element.surlignages = quotes.clone();
+            }
+        }
+        Ok(res)
+    }
+
+    fn tag_source(source: &str) -> String {
+        format!("{}{}", PREFIXE_TAG_SOURCE, source)
+    }
+
+    fn entetes_csv<R: Read>(csv: &mut csv::Reader<R>) -> Result<Vec<String>, ErreurImport> {
+        Ok(csv
+            .headers()?
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect())
+    }
+
+    /// Instapaper : `URL,Title,Selection,Folder,Timestamp`. Le dossier
+    /// `Archive` donne le statut archive, `Starred` le favori, les autres
+    /// dossiers deviennent des tags.
+    pub fn lire_export_instapaper<R: Read>(
+        lecteur: R,
+    ) -> Result<Vec<ElementImporte>, ErreurImport> {
+        let mut csv = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(lecteur);
+        let entetes = entetes_csv(&mut csv)?;
+        let colonne = |nom: &str| entetes.iter().position(|h| h == nom);
+        let Some(col_url) = colonne("url") else {
+            return Err(ErreurImport::Format("colonne URL absente".to_string()));
+        };
+        let (col_titre, col_selection, col_dossier, col_date) = (
+            colonne("title"),
+            colonne("selection"),
+            colonne("folder"),
+            colonne("timestamp"),
+        );
+        let mut res = Vec::new();
+        for ligne in csv.records() {
+            let ligne = ligne?;
+            let champ = |col: Option<usize>| col.and_then(|c| ligne.get(c)).unwrap_or("").trim();
+            if champ(Some(col_url)).is_empty() {
+                continue;
+            }
+            let dossier = champ(col_dossier);
+            let mut tags = vec![tag_source(SOURCE_INSTAPAPER)];
+            if !dossier.is_empty()
+                && !["unread", "archive", "starred"].contains(&dossier.to_lowercase().as_str())
+            {
+                tags.push(dossier.to_string());
+            }
+            res.push(ElementImporte {
+                url: champ(Some(col_url)).to_string(),
+                titre: champ(col_titre).to_string(),
+                time_added: champ(col_date).parse().unwrap_or(0),
+                tags,
+                statut: match dossier.to_lowercase().as_str() {
+                    "archive" => Some(STATUT_ARCHIVE.to_string()),
+                    "unread" => Some(STATUT_NON_LU.to_string()),
+                    _ => None,
+                },
+                favori: if dossier.eq_ignore_ascii_case("starred") {
+                    Some(true)
+                } else {
+                    None
+                },
+                extrait: champ(col_selection).to_string(),
+                ..Default::default()
+            });
+        }
+        Ok(res)
+    }
+
+    /// Raindrop.io : `id,title,note,excerpt,url,folder,tags,created,cover,
+    /// highlights,favorite`. Pas de notion de lu / non lu ; le dossier
+    /// (sauf `Unsorted`) devient un tag.
+    pub fn lire_export_raindrop<R: Read>(lecteur: R) -> Result<Vec<ElementImporte>, ErreurImport> {
+        let mut csv = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(lecteur);
+        let entetes = entetes_csv(&mut csv)?;
+        let colonne = |nom: &str| entetes.iter().position(|h| h == nom);
+        let Some(col_url) = colonne("url") else {
+            return Err(ErreurImport::Format("colonne url absente".to_string()));
+        };
+        let (col_titre, col_extrait, col_dossier, col_tags, col_date, col_surlignages, col_favori) = (
+            colonne("title"),
+            colonne("excerpt"),
+            colonne("folder"),
+            colonne("tags"),
+            colonne("created"),
+            colonne("highlights"),
+            colonne("favorite"),
+        );
+        let mut res = Vec::new();
+        for ligne in csv.records() {
+            let ligne = ligne?;
+            let champ = |col: Option<usize>| col.and_then(|c| ligne.get(c)).unwrap_or("").trim();
+            if champ(Some(col_url)).is_empty() {
+                continue;
+            }
+            let mut tags = vec![tag_source(SOURCE_RAINDROP)];
+            tags.extend(
+                champ(col_tags)
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty()),
+            );
+            let dossier = champ(col_dossier);
+            if !dossier.is_empty() && !dossier.eq_ignore_ascii_case("unsorted") {
+                tags.push(dossier.to_string());
+            }
+            res.push(ElementImporte {
+                url: champ(Some(col_url)).to_string(),
+                titre: champ(col_titre).to_string(),
+                time_added: timestamp_iso(champ(col_date)),
+                tags,
+                favori: match champ(col_favori) {
+                    "" => None,
+                    f => Some(f == "true" || f == "1"),
+                },
+                extrait: champ(col_extrait).to_string(),
+                surlignages: champ(col_surlignages)
+                    .lines()
+                    .map(|l| l.trim().trim_start_matches("Highlight:").trim().to_string())
+                    .filter(|l| !l.is_empty())
+                    .collect(),
+                ..Default::default()
+            });
+        }
+        Ok(res)
+    }
+
+    /// Booleen Wallabag : `0`/`1` selon les versions, ou `true`/`false`.
+    fn booleen(valeur: Option<&Value>) -> Option<bool> {
+        match valeur {
+            Some(Value::Bool(b)) => Some(*b),
+            Some(Value::Number(n)) => Some(n.as_i64() == Some(1)),
+            Some(Value::String(s)) => Some(s == "1" || s == "true"),
+            _ => None,
+        }
+    }
+
+    /// Export JSON de Wallabag : tableau d'entrees avec `is_archived`,
+    /// `is_starred`, `tags` (liste de noms), `created_at` et `annotations`.
+    pub fn lire_export_wallabag(contenu: &str) -> Result<Vec<ElementImporte>, ErreurImport> {
+        let json: Value =
+            serde_json::from_str(contenu).map_err(|err| ErreurImport::Format(err.to_string()))?;
+        let Some(entrees) = json.as_array() else {
+            return Err(ErreurImport::Format(
+                "tableau d'entrees attendu".to_string(),
+            ));
+        };
+        let mut res = Vec::new();
+        for entree in entrees {
+            let url = champ_str(entree, "url");
+            if url.is_empty() {
+                continue;
+            }
+            let mut tags = vec![tag_source(SOURCE_WALLABAG)];
+            if let Some(liste) = entree.get("tags").and_then(|t| t.as_array()) {
+                tags.extend(
+                    liste
+                        .iter()
+                        .filter_map(|t| {
+                            t.as_str()
+                                .or_else(|| t.get("label").and_then(|l| l.as_str()))
+                        })
+                        .map(|t| t.to_string()),
+                );
+            }
+            res.push(ElementImporte {
+                url: url.to_string(),
+                titre: champ_str(entree, "title").to_string(),
+                time_added: timestamp_iso(champ_str(entree, "created_at")),
+                tags,
+                statut: booleen(entree.get("is_archived"))
+                    .map(|a| if a { STATUT_ARCHIVE } else { STATUT_NON_LU }.to_string()),
+                favori: booleen(entree.get("is_starred")),
+                surlignages: entree
+                    .get("annotations")
+                    .and_then(|a| a.as_array())
+                    .map(|a| {
+                        a.iter()
+                            .map(|q| champ_str(q, "quote").to_string())
+                            .filter(|q| !q.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                ..Default::default()
+            });
+        }
+        Ok(res)
+    }
+
+    /// Service d'origine d'un CSV d'apres ses colonnes.
+    fn source_csv(contenu: &str) -> &'static str {
+        let entete = contenu.lines().next().unwrap_or("").to_lowercase();
+        if entete.contains("selection") && entete.contains("folder") {
+            SOURCE_INSTAPAPER
+        } else if entete.contains("created") && entete.contains("excerpt") {
+            SOURCE_RAINDROP
+        } else {
+            SOURCE_POCKET_CSV
+        }
+    }
+
+    /// Lit un fichier d'export. Sans `source`, elle est deduite de
+    /// l'extension (`.html`, `.zip` : Pocket, `.json` : Wallabag) et, pour
+    /// un CSV, des colonnes.
+    pub fn lire_export(
+        fichier: &str,
+        source: Option<&str>,
+    ) -> Result<(&'static str, Vec<ElementImporte>), ErreurImport> {
+        let extension = Path::new(fichier)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let source = match (source, extension.as_str()) {
+            (Some("pocket"), "html" | "htm") | (None, "html" | "htm") => SOURCE_POCKET_HTML,
+            (Some("pocket"), _) | (None, "zip") => SOURCE_POCKET_CSV,
+            (Some(SOURCE_INSTAPAPER), _) => SOURCE_INSTAPAPER,
+            (Some(SOURCE_RAINDROP), _) => SOURCE_RAINDROP,
+            (Some(SOURCE_WALLABAG), _) | (None, "json") => SOURCE_WALLABAG,
+            (None, "csv") => source_csv(std::fs::read_to_string(fichier)?.as_str()),
+            (Some(autre), _) => {
+                return Err(ErreurImport::Format(format!("source inconnue : {}", autre)))
+            }
+            (None, _) => {
+                return Err(ErreurImport::Format(format!(
+                    "extension inconnue : {}",
+                    fichier
+                )))
+            }
+        };
+        let elements = match source {
+            SOURCE_POCKET_HTML => lire_export_html(std::fs::read_to_string(fichier)?.as_str()),
+            SOURCE_POCKET_CSV if extension == "zip" => lire_export_zip(File::open(fichier)?)?,
+            SOURCE_POCKET_CSV => lire_export_csv(File::open(fichier)?)?,
+            SOURCE_INSTAPAPER => lire_export_instapaper(File::open(fichier)?)?,
+            SOURCE_RAINDROP => lire_export_raindrop(File::open(fichier)?)?,
+            _ => lire_export_wallabag(std::fs::read_to_string(fichier)?.as_str())?,
+        };
+        Ok((source, elements))
+    }
+
+    /// Element au format `/v3/get` (valeurs numeriques en chaines).
+    fn nouvel_element(cle: &str, element: &ElementImporte, source: &str) -> Value {
+        let mut res = serde_json::json!({
+            "item_id": cle,
+            "given_url": element.url,
+            "given_title": element.titre,
+            "time_added": element.time_added.to_string(),
+            "time_updated": element.time_added.to_string(),
+            "status": element.statut.clone().unwrap_or(STATUT_NON_LU.to_string()),
+            "favorite": if element.favori == Some(true) { "1" } else { "0" },
+            "source_import": source,
+        });
+        if !element.tags.is_empty() {
+            res["tags"] = objet_tags(cle, &element.tags);
+        }
+        if !element.extrait.is_empty() {
+            res["excerpt"] = Value::String(element.extrait.clone());
+        }
+        if !element.surlignages.is_empty() {
+            res["annotations"] = annotations(&element.surlignages);
+        }
+        res
+    }
+
+    fn annotations(surlignages: &[String]) -> Value {
+        Value::Array(
+            surlignages
+                .iter()
+                .map(|q| serde_json::json!({"quote": q}))
+                .collect(),
+        )
+    }
+
+    /// Complete un element existant sans ecraser ce qui vient de l'API :
+    /// tags manquants, date d'ajout, extrait et surlignages absents. Un
+    /// statut ou un favori different est signale comme conflit.
+    fn completer(
+        cle: &str,
+        existant: &mut Value,
+        element: &ElementImporte,
+        bilan: &mut BilanImport,
+    ) {
+        let mut complete = false;
+        let tags_existants = tags(existant);
+        let manquants: Vec<String> = element
+            .tags
+            .iter()
+            .filter(|t| !tags_existants.contains(t))
+            .cloned()
+            .collect();
+        if !manquants.is_empty() {
+            if !existant.get("tags").is_some_and(|t| t.is_object()) {
+                existant["tags"] = Value::Object(Map::new());
+            }
+            if let Value::Object(nouveaux) = objet_tags(cle, &manquants) {
+                existant["tags"].as_object_mut().unwrap().extend(nouveaux);
+            }
+            complete = true;
+        }
+        if champ_nombre(existant, "time_added") == 0 && element.time_added > 0 {
+            existant["time_added"] = Value::String(element.time_added.to_string());
+            complete = true;
+        }
+        if champ_str(existant, "excerpt").is_empty() && !element.extrait.is_empty() {
+            existant["excerpt"] = Value::String(element.extrait.clone());
+            complete = true;
+        }
+        if existant.get("annotations").is_none() && !element.surlignages.is_empty() {
+            existant["annotations"] = annotations(&element.surlignages);
+            complete = true;
+        }
+        if complete {
+            bilan.nb_completes += 1;
+        }
+        let mut differences = Vec::new();
+        if let Some(s) = &element.statut {
+            if statut(existant) != s.as_str() {
+                differences.push(format!("status {} / importe {}", statut(existant), s));
+            }
+        }
+        if let Some(f) = element.favori {
+            if est_favori(existant) != f {
+                differences.push(format!("favorite {} / importe {}", est_favori(existant), f));
+            }
+        }
+        if !differences.is_empty() {
+            bilan.conflits.push(Conflit {
+                item_id: cle.to_string(),
+                url: element.url.clone(),
+                detail: differences.join(", "),
+            });
+        }
+    }
+
+    /// Reporte sur la nouvelle version d'un element, recue de l'API, ce
+    /// qu'un import y avait ajoute : les surlignages et, pour un element
+    /// importe, `source_import`, l'extrait et les tags absents de la
+    /// reponse.
+    pub fn conserver_import(cle: &str, ancien: &Value, nouveau: &mut Value) {
+        if !nouveau.is_object() {
+            return;
+        }
+        if nouveau.get("annotations").is_none() {
+            if let Some(a) = ancien.get("annotations") {
+                nouveau["annotations"] = a.clone();
+            }
+        }
+        let Some(source) = ancien.get("source_import") else {
+            return;
+        };
+        if nouveau.get("source_import").is_none() {
+            nouveau["source_import"] = source.clone();
+        }
+        if champ_str(nouveau, "excerpt").is_empty() && !champ_str(ancien, "excerpt").is_empty() {
+            nouveau["excerpt"] = ancien["excerpt"].clone();
+        }
+        let tags_nouveaux = tags(nouveau);
+        let manquants: Vec<String> = tags(ancien)
+            .into_iter()
+            .filter(|t| !tags_nouveaux.contains(t))
+            .collect();
+        if !manquants.is_empty() {
+            if !nouveau.get("tags").is_some_and(|t| t.is_object()) {
+                nouveau["tags"] = Value::Object(Map::new());
+            }
+            if let Value::Object(ajoutes) = objet_tags(cle, &manquants) {
+                nouveau["tags"].as_object_mut().unwrap().extend(ajoutes);
+            }
+        }
+    }
+
+    /// Fusionne les elements importes dans `data` par URL normalisee
+    /// (resolved_url et given_url) : aucun format d'export ne donne
+    /// l'item_id Pocket.
+    pub fn fusionner(data: &mut Value, elements: &[ElementImporte], source: &str) -> BilanImport {
+        let mut bilan = BilanImport {
+            source: source.to_string(),
+            nb_lus: elements.len(),
+            ..Default::default()
+        };
+        if liste(data).is_none() {
+            data[DATA_LISTE] = Value::Object(Map::new());
+        }
+        let mut par_url: HashMap<String, String> = HashMap::new();
+        for (id, element) in liste(data).unwrap() {
+            par_url.insert(normaliser_url(url(element)), id.clone());
+            let given = champ_str(element, "given_url");
+            if !given.is_empty() {
+                par_url.entry(normaliser_url(given)).or_insert(id.clone());
+            }
+        }
+        let liste = data[DATA_LISTE].as_object_mut().unwrap();
+        for element in elements {
+            let url_normalisee = normaliser_url(element.url.as_str());
+            match par_url.get(&url_normalisee).cloned() {
+                Some(cle) => {
+                    bilan.nb_existants += 1;
+                    completer(&cle, liste.get_mut(&cle).unwrap(), element, &mut bilan);
+                }
+                None => {
+                    let cle = cle_import(url_normalisee.as_str());
+                    liste.insert(cle.clone(), nouvel_element(&cle, element, source));
+                    par_url.insert(url_normalisee, cle);
+                    bilan.ajoutes.push(element.url.clone());
+                }
+            }
+        }
+        log::info!(
+            "import {} : {} lus, {} ajoutes, {} deja presents, {} conflits",
+            source,
+            bilan.nb_lus,
+            bilan.ajoutes.len(),
+            bilan.nb_existants,
+            bilan.conflits.len()
+        );
+        bilan
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        #[test]
+        fn mise_a_jour_garde_les_champs_de_l_import() {
+            let mut ancien = json!({
+                "item_id": "1",
+                "resolved_title": "ancien",
+                "source_import": "instapaper",
+                "excerpt": "extrait importe",
+                "annotations": [{"quote": "passage"}],
+            });
+            ancien["tags"] = objet_tags("1", &["lu".to_string(), "rust".to_string()]);
+            let mut nouveau = json!({"item_id": "1", "resolved_title": "nouveau"});
+            nouveau["tags"] = objet_tags("1", &["rust".to_string()]);
+            conserver_import("1", &ancien, &mut nouveau);
+            assert_eq!(nouveau["resolved_title"], "nouveau");
+            assert_eq!(nouveau["source_import"], "instapaper");
+            assert_eq!(nouveau["excerpt"], "extrait importe");
+            assert_eq!(nouveau["annotations"], ancien["annotations"]);
+            let mut tags_nouveaux = tags(&nouveau);
+            tags_nouveaux.sort();
+            assert_eq!(tags_nouveaux, ["lu", "rust"]);
+        }
+
+        #[test]
+        fn tags_retires_sur_l_api_non_restaures_hors_import() {
+            let mut ancien = json!({"item_id": "1"});
+            ancien["tags"] = objet_tags("1", &["lu".to_string()]);
+            let mut nouveau = json!({"item_id": "1", "excerpt": "api"});
+            conserver_import("1", &ancien, &mut nouveau);
+            assert!(tags(&nouveau).is_empty());
+            assert!(nouveau.get("source_import").is_none());
+            assert_eq!(nouveau["excerpt"], "api");
+        }
+
+        #[test]
+        fn url_normalisee() {
+            assert_eq!(
+                normaliser_url(" HTTPS://www.Example.com/a/?utm_source=x&b=2#frag "),
+                "example.com/a?b=2"
+            );
+            assert_eq!(
+                normaliser_url("http://example.com/?utm_medium=y"),
+                "example.com"
+            );
+            assert_eq!(normaliser_url("pas une URL"), "pas une url");
+        }
+
+        #[test]
+        fn export_html_par_section() {
+            let html = r#"<!DOCTYPE html><html><body>
+<h1>Unread</h1>
+<ul>
+<li><a href="https://a.example/" time_added="1700000000" tags="rust,web">Caf&eacute; &amp; co</a></li>
+<li><a name="sans-lien">ignore</a></li>
+</ul>
+<H1>Read Archive</H1>
+<ul>
+<li><A HREF="https://b.example/" TIME_ADDED="1600000000" TAGS="">B</A></li>
+</ul>
+</body></html>"#;
+            let elements = lire_export_html(html);
+            assert_eq!(elements.len(), 2);
+            assert_eq!(elements[0].url, "https://a.example/");
+            assert_eq!(elements[0].titre, "Café & co");
+            assert_eq!(elements[0].time_added, 1700000000);
+            assert_eq!(elements[0].tags, ["rust", "web"]);
+            assert_eq!(elements[0].statut.as_deref(), Some(STATUT_NON_LU));
+            assert_eq!(elements[1].titre, "B");
+            assert!(elements[1].tags.is_empty());
+            assert_eq!(elements[1].statut.as_deref(), Some(STATUT_ARCHIVE));
+        }
+
+        #[test]
+        fn export_csv_colonnes_par_nom() {
+            let csv = "status,url,title,time_added,cursor,tags\n\
+                       archive,https://a.example/,\"A, b\",1700000000,x,rust|web\n\
+                       unread,https://b.example/,B,,y,\n\
+                       unread,,vide,1,z,\n";
+            let elements = lire_export_csv(csv.as_bytes()).unwrap();
+            assert_eq!(elements.len(), 2);
+            assert_eq!(elements[0].titre, "A, b");
+            assert_eq!(elements[0].tags, ["rust", "web"]);
+            assert_eq!(elements[0].statut.as_deref(), Some(STATUT_ARCHIVE));
+            assert_eq!(elements[0].favori, None);
+            assert_eq!(elements[1].time_added, 0);
+            assert_eq!(elements[1].statut.as_deref(), Some(STATUT_NON_LU));
+            assert!(lire_export_csv("title,tags\nA,b\n".as_bytes()).is_err());
+        }
+
+        #[test]
+        fn export_zip_avec_surlignages() {
+            use std::io::Write;
+            let fichier =
+                std::env::temp_dir().join(format!("pbackup_import_{}.zip", std::process::id()));
+            let mut zip = zip::ZipWriter::new(File::create(&fichier).unwrap());
+            let options = zip::write::SimpleFileOptions::default();
+            zip.start_file("part_000000.csv", options).unwrap();
+            zip.write_all(
+                b"title,url,time_added,cursor,tags,status\nA,https://a.example/x,1,c,,unread\n",
+            )
+            .unwrap();
+            zip.start_file("annotations/part_000000.json", options)
+                .unwrap();
+            zip.write_all(
+                br#"[{"url": "https://www.a.example/x#top", "highlights": [{"quote": "q1"}, {"quote": "q2"}]}]"#,
+            )
+            .unwrap();
+            zip.finish().unwrap();
+
+            let elements = lire_export_zip(File::open(&fichier).unwrap()).unwrap();
+            assert_eq!(elements.len(), 1);
+            assert_eq!(elements[0].surlignages, ["q1", "q2"]);
+            let _ = std::fs::remove_file(&fichier);
+        }
+
+        #[test]
+        fn fusion_par_url_normalisee() {
+            let mut data = json!({"liste": {"42": {
+                "item_id": "42", "resolved_url": "https://example.com/a",
+                "given_url": "http://example.com/b?utm_source=x", "status": "1", "favorite": "0",
+                "time_added": "0"
+            }}});
+            let existant = ElementImporte {
+                url: "https://www.example.com/a/#haut".to_string(),
+                time_added: 1700000000,
+                tags: vec!["rust".to_string()],
+                statut: Some(STATUT_NON_LU.to_string()),
+                ..Default::default()
+            };
+            let par_given_url = ElementImporte {
+                url: "http://example.com/b".to_string(),
+                ..Default::default()
+            };
+            let nouveau = ElementImporte {
+                url: "https://autre.example/".to_string(),
+                titre: "Autre".to_string(),
+                time_added: 1600000000,
+                tags: vec!["web".to_string()],
+                favori: Some(true),
+                ..Default::default()
+            };
+            let doublon = ElementImporte {
+                url: "https://AUTRE.example".to_string(),
+                ..Default::default()
+            };
+            let bilan = fusionner(
+                &mut data,
+                &[existant, par_given_url, nouveau, doublon],
+                SOURCE_POCKET_CSV,
+            );
+            assert_eq!(bilan.nb_lus, 4);
+            assert_eq!(bilan.nb_existants, 3);
+            assert_eq!(bilan.ajoutes, ["https://autre.example/"]);
+            assert_eq!(bilan.nb_completes, 1);
+            assert_eq!(bilan.conflits.len(), 1);
+            assert_eq!(bilan.conflits[0].item_id, "42");
+
+            let liste = liste(&data).unwrap();
+            assert_eq!(liste.len(), 2);
+            assert_eq!(tags(&liste["42"]), ["rust"]);
+            assert_eq!(liste["42"]["time_added"], "1700000000");
+            assert_eq!(liste["42"]["status"], "1");
+            let cle = cle_import("autre.example");
+            assert!(cle.starts_with(PREFIXE_CLE_IMPORT));
+            let ajoute = &liste[&cle];
+            assert_eq!(ajoute["item_id"], cle.as_str());
+            assert_eq!(ajoute["given_title"], "Autre");
+            assert_eq!(ajoute["status"], STATUT_NON_LU);
+            assert_eq!(ajoute["favorite"], "1");
+            assert_eq!(ajoute["source_import"], SOURCE_POCKET_CSV);
+            assert_eq!(tags(ajoute), ["web"]);
+        }
+
+        #[test]
+        fn export_instapaper() {
+            let csv = "URL,Title,Selection,Folder,Timestamp\n\
+                       https://a.example/,A,passage,Unread,1700000000\n\
+                       https://b.example/,B,,Archive,1600000000\n\
+                       https://c.example/,C,,Starred,1500000000\n\
+                       https://d.example/,D,,Rust,1400000000\n";
+            let elements = lire_export_instapaper(csv.as_bytes()).unwrap();
+            assert_eq!(elements.len(), 4);
+            assert_eq!(elements[0].titre, "A");
+            assert_eq!(elements[0].extrait, "passage");
+            assert_eq!(elements[0].time_added, 1700000000);
+            assert_eq!(elements[0].statut.as_deref(), Some(STATUT_NON_LU));
+            assert_eq!(elements[0].tags, ["source:instapaper"]);
+            assert_eq!(elements[1].statut.as_deref(), Some(STATUT_ARCHIVE));
+            assert_eq!(
+                (elements[2].statut.as_deref(), elements[2].favori),
+                (None, Some(true))
+            );
+            assert_eq!(elements[3].tags, ["source:instapaper", "Rust"]);
+            assert_eq!(
+                (elements[3].statut.as_deref(), elements[3].favori),
+                (None, None)
+            );
+        }
+
+        #[test]
+        fn export_raindrop() {
+            let csv = "id,title,note,excerpt,url,folder,tags,created,cover,highlights,favorite\n\
+                       1,A,,resume,https://a.example/,Lectures,\"rust, web\",2024-03-02T12:00:00.000Z,,\"Highlight: q1\nHighlight: q2\",true\n\
+                       2,B,,,https://b.example/,Unsorted,,,,,false\n";
+            let elements = lire_export_raindrop(csv.as_bytes()).unwrap();
+            assert_eq!(elements.len(), 2);
+            assert_eq!(elements[0].titre, "A");
+            assert_eq!(elements[0].extrait, "resume");
+            assert_eq!(
+                elements[0].tags,
+                ["source:raindrop", "rust", "web", "Lectures"]
+            );
+            assert_eq!(elements[0].time_added, 1709380800);
+            assert_eq!(elements[0].surlignages, ["q1", "q2"]);
+            assert_eq!(elements[0].favori, Some(true));
+            assert_eq!(elements[0].statut, None);
+            assert_eq!(elements[1].tags, ["source:raindrop"]);
+            assert_eq!(elements[1].favori, Some(false));
+        }
+
+        #[test]
+        fn export_wallabag() {
+            let json = r#"[
+                {"url": "https://a.example/", "title": "A", "is_archived": 1, "is_starred": 0,
+                 "created_at": "2024-03-02T12:00:00+0000", "tags": ["rust", {"label": "web"}],
+                 "annotations": [{"quote": "q1"}, {"quote": ""}]},
+                {"url": "https://b.example/", "title": "B", "is_archived": false,
+                 "is_starred": "1"},
+                {"title": "sans url"}
+            ]"#;
+            let elements = lire_export_wallabag(json).unwrap();
+            assert_eq!(elements.len(), 2);
+            assert_eq!(elements[0].tags, ["source:wallabag", "rust", "web"]);
+            assert_eq!(elements[0].time_added, 1709380800);
+            assert_eq!(elements[0].statut.as_deref(), Some(STATUT_ARCHIVE));
+            assert_eq!(elements[0].favori, Some(false));
+            assert_eq!(elements[0].surlignages, ["q1"]);
+            assert_eq!(elements[1].statut.as_deref(), Some(STATUT_NON_LU));
+            assert_eq!(elements[1].favori, Some(true));
+            assert!(lire_export_wallabag("{}").is_err());
+        }
+
+        #[test]
+        fn source_deduite_des_colonnes() {
+            assert_eq!(
+                source_csv("URL,Title,Selection,Folder,Timestamp\n"),
+                SOURCE_INSTAPAPER
+            );
+            assert_eq!(
+                source_csv("id,title,note,excerpt,url,folder,tags,created\n"),
+                SOURCE_RAINDROP
+            );
+            assert_eq!(source_csv("title,url,time_added\n"), SOURCE_POCKET_CSV);
+        }
+
+        #[test]
+        fn dedoublonnage_entre_formats() {
+            let mut data = json!({});
+            let instapaper = lire_export_instapaper(
+                "URL,Title,Selection,Folder,Timestamp\nhttps://www.a.example/x/,A,,Unread,1\n"
+                    .as_bytes(),
+            )
+            .unwrap();
+            let bilan = fusionner(&mut data, &instapaper, SOURCE_INSTAPAPER);
+            assert_eq!(bilan.ajoutes.len(), 1);
+            let wallabag = lire_export_wallabag(
+                r#"[{"url": "https://a.example/x?utm_source=rss", "tags": ["rust"]}]"#,
+            )
+            .unwrap();
+            let bilan = fusionner(&mut data, &wallabag, SOURCE_WALLABAG);
+            assert!(bilan.ajoutes.is_empty());
+            assert_eq!((bilan.nb_existants, bilan.nb_completes), (1, 1));
+
+            let liste = liste(&data).unwrap();
+            assert_eq!(liste.len(), 1);
+            let element = liste.values().next().unwrap();
+            assert_eq!(element["source_import"], SOURCE_INSTAPAPER);
+            let mut tags = tags(element);
+            tags.sort();
+            assert_eq!(tags, ["rust", "source:instapaper", "source:wallabag"]);
+        }
+    }
+}
@@ -0,0 +1,418 @@
+pub mod mod_instantane {
+    use crate::donnees::mod_donnees::liste;
+    use crate::retention::mod_retention::{lister_instantanes, repertoire_backup, Instantane};
+    use flate2::read::GzDecoder;
+    use flate2::write::GzEncoder;
+    use serde::de::IgnoredAny;
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::fmt;
+    use std::fs::{self, File};
+    use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+    use std::path::{Path, PathBuf};
+
+    const MAGIQUE_GZIP: [u8; 2] = [0x1f, 0x8b];
+    const MAGIQUE_ZSTD: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum Compression {
+        Aucune,
+        Gzip,
+        Zstd,
+    }
+
+    impl Compression {
+        pub fn parse(s: &str) -> Option<Compression> {
+            match s {
+                "" | "aucune" => Some(Compression::Aucune),
+                "gzip" => Some(Compression::Gzip),
+                "zstd" => Some(Compression::Zstd),
+                _ => None,
+            }
+        }
+
+        /// Extension ajoutee apres `.json`.
+        pub fn extension(&self) -> &'static str {
+            match self {
+                Compression::Aucune => "",
+                Compression::Gzip => ".gz",
+                Compression::Zstd => ".zst",
+            }
+        }
+
+        /// Compression d'apres la fin du nom (`.json`, `.json.gz`, `.json.zst`).
+        pub fn depuis_nom(nom: &str) -> Option<Compression> {
+            [Compression::Aucune, Compression::Gzip, Compression::Zstd]
+                .into_iter()
+                .find(|c| nom.ends_with(format!(".json{}", c.extension()).as_str()))
+        }
+
+        /// Niveau borne a ce qu'accepte l'algorithme, 0 pour son niveau par
+        /// defaut.
+        fn niveau(&self, niveau: i32) -> i32 {
+            match self {
+                Compression::Aucune => 0,
+                Compression::Gzip if niveau == 0 => 6,
+                Compression::Gzip => niveau.clamp(1, 9),
+                Compression::Zstd if niveau == 0 => zstd::DEFAULT_COMPRESSION_LEVEL,
+                Compression::Zstd => niveau.clamp(1, 22),
+            }
+        }
+    }
+
+    /// Copie `source` vers `destination` (suivi de l'extension de la
+    /// compression) en compressant au fil de la lecture. Le fichier est ecrit
+    /// sous un nom temporaire puis renomme : une copie interrompue n'est pas
+    /// prise pour un instantane.
+    pub fn ecrire_instantane(
+        source: &str,
+        destination: &str,
+        compression: Compression,
+        niveau: i32,
+    ) -> io::Result<String> {
+        let fichier = format!("{}{}", destination, compression.extension());
+        let temporaire = format!("{}.tmp", fichier);
+        let mut entree = BufReader::new(File::open(source)?);
+        let sortie = BufWriter::new(File::create(&temporaire)?);
+        let niveau = compression.niveau(niveau);
+        let res = match compression {
+            Compression::Aucune => {
+                let mut sortie = sortie;
+                io::copy(&mut entree, &mut sortie).and_then(|_| sortie.flush())
+            }
+            Compression::Gzip => {
+                let mut encodeur = GzEncoder::new(sortie, flate2::Compression::new(niveau as u32));
+                io::copy(&mut entree, &mut encodeur)
+                    .and_then(|_| encodeur.finish())
+                    .and_then(|mut s| s.flush())
+            }
+            Compression::Zstd => zstd::stream::Encoder::new(sortie, niveau).and_then(|mut e| {
+                io::copy(&mut entree, &mut e)
+                    .and_then(|_| e.finish())
+                    .and_then(|mut s| s.flush())
+            }),
+        };
+        if let Err(err) = res {
+            let _ = fs::remove_file(&temporaire);
+            return Err(err);
+        }
+        fs::rename(&temporaire, &fichier)?;
+        Ok(fichier)
+    }
+
+    /// Lecteur decompresse ; la compression est reconnue a son en-tete, quel
+    /// que soit le nom du fichier.
+    pub fn ouvrir_instantane(chemin: &Path) -> io::Result<Box<dyn Read>> {
+        let mut lecteur = BufReader::new(File::open(chemin)?);
+        let debut = lecteur.fill_buf()?;
+        if debut.starts_with(&MAGIQUE_GZIP) {
+            Ok(Box::new(GzDecoder::new(lecteur)))
+        } else if debut.starts_with(&MAGIQUE_ZSTD) {
+            Ok(Box::new(zstd::stream::Decoder::with_buffer(lecteur)?))
+        } else {
+            Ok(Box::new(lecteur))
+        }
+    }
+
+    pub fn lire_instantane(chemin: &Path) -> io::Result<Value> {
+        let lecteur = BufReader::new(ouvrir_instantane(chemin)?);
+        Ok(serde_json::from_reader(lecteur)?)
+    }
+
+    /// `nom` est un chemin, ou un nom de fichier de `backup/`.
+    pub fn chemin_instantane(repertoire: &str, nom: &str) -> PathBuf {
+        let chemin = PathBuf::from(nom);
+        if chemin.exists() {
+            chemin
+        } else {
+            Path::new(&repertoire_backup(repertoire)).join(nom)
+        }
+    }
+
+    /// Instantane le plus recent d'une serie (`data` ou `param`).
+    pub fn dernier_instantane(repertoire: &str, serie: &str) -> io::Result<Option<PathBuf>> {
+        Ok(lister_instantanes(repertoire)?
+            .into_iter()
+            .filter(|i| i.serie == serie)
+            .max_by_key(|i| i.date)
+            .map(|i| Path::new(&repertoire_backup(repertoire)).join(i.fichier)))
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Verification {
+        pub fichier: String,
+        pub taille: u64,
+        pub nb_elements: Option<usize>,
+        pub erreur: Option<String>,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanVerification {
+        pub verifications: Vec<Verification>,
+        pub nb_erreurs: usize,
+    }
+
+    impl fmt::Display for BilanVerification {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for v in &self.verifications {
+                match (&v.erreur, v.nb_elements) {
+                    (Some(err), _) => writeln!(f, "ERREUR {} : {}", v.fichier, err)?,
+                    (None, Some(nb)) => {
+                        writeln!(f, "OK     {} ({} o, {} elements)", v.fichier, v.taille, nb)?
+                    }
+                    (None, None) => writeln!(f, "OK     {} ({} o)", v.fichier, v.taille)?,
+                }
+            }
+            writeln!(
+                f,
+                "{} instantanes verifies, {} en erreur",
+                self.verifications.len(),
+                self.nb_erreurs
+            )
+        }
+    }
+
+    /// Decompresse et relit un instantane ; ceux de la serie `data` doivent
+    /// contenir la liste des elements.
+    pub fn verifier(chemin: &Path, serie: &str) -> Verification {
+        let mut res = Verification {
+            fichier: chemin.display().to_string(),
+            taille: fs::metadata(chemin).map(|m| m.len()).unwrap_or(0),
+            nb_elements: None,
+            erreur: None,
+        };
+        match lire_instantane(chemin) {
+            Ok(valeur) if serie == "data" => match liste(&valeur) {
+                Some(l) => res.nb_elements = Some(l.len()),
+                None => res.erreur = Some("liste des elements absente".to_string()),
+            },
+            Ok(valeur) if !valeur.is_object() => {
+                res.erreur = Some("objet JSON attendu".to_string())
+            }
+            Ok(_) => {}
+            Err(err) => res.erreur = Some(err.to_string()),
+        }
+        res
+    }
+
+    /// Verifie tous les instantanes de `backup/`, du plus ancien au plus
+    /// recent.
+    pub fn verifier_tous(repertoire: &str) -> io::Result<BilanVerification> {
+        let mut instantanes: Vec<Instantane> = lister_instantanes(repertoire)?;
+        instantanes.sort_by(|a, b| a.date.cmp(&b.date).then(a.serie.cmp(&b.serie)));
+        let dossier = repertoire_backup(repertoire);
+        let verifications: Vec<Verification> = instantanes
+            .iter()
+            .map(|i| verifier(&Path::new(&dossier).join(&i.fichier), &i.serie))
+            .collect();
+        Ok(BilanVerification {
+            nb_erreurs: verifications.iter().filter(|v| v.erreur.is_some()).count(),
+            verifications,
+        })
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Modification {
+        pub item_id: String,
+        pub champs: Vec<String>,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Difference {
+        pub avant: String,
+        pub apres: String,
+        pub nb_avant: usize,
+        pub nb_apres: usize,
+        pub ajoutes: Vec<String>,
+        pub supprimes: Vec<String>,
+        pub modifies: Vec<Modification>,
+    }
+
+    impl fmt::Display for Difference {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(f, "{} ({} elements)", self.avant, self.nb_avant)?;
+            writeln!(f, "-> {} ({} elements)", self.apres, self.nb_apres)?;
+            writeln!(
+                f,
+                "{} ajoutes, {} supprimes, {} modifies",
+                self.ajoutes.len(),
+                self.supprimes.len(),
+                self.modifies.len()
+            )?;
+            for id in &self.ajoutes {
+                writeln!(f, "  + {}", id)?;
+            }
+            for id in &self.supprimes {
+                writeln!(f, "  - {}", id)?;
+            }
+            for m in &self.modifies {
+                writeln!(f, "  ~ {} : {}", m.item_id, m.champs.join(", "))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Elements ajoutes, disparus et modifies (avec les champs qui changent)
+    /// entre deux sauvegardes.
+    pub fn comparer_instantanes(avant: &Value, apres: &Value) -> Difference {
+        let vide = serde_json::Map::new();
+        let avant = liste(avant).unwrap_or(&vide);
+        let apres = liste(apres).unwrap_or(&vide);
+        let mut res = Difference {
+            nb_avant: avant.len(),
+            nb_apres: apres.len(),
+            ..Default::default()
+        };
+        for (id, element) in apres {
+            match avant.get(id) {
+                None => res.ajoutes.push(id.clone()),
+                Some(ancien) if ancien != element => {
+                    let a = ancien.as_object().unwrap_or(&vide);
+                    let b = element.as_object().unwrap_or(&vide);
+                    let mut champs: Vec<String> = a
+                        .keys()
+                        .chain(b.keys())
+                        .filter(|c| a.get(*c) != b.get(*c))
+                        .cloned()
+                        .collect();
+                    champs.sort();
+                    champs.dedup();
+                    res.modifies.push(Modification {
+                        item_id: id.clone(),
+                        champs,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        res.supprimes = avant
+            .keys()
+            .filter(|id| !apres.contains_key(*id))
+            .cloned()
+            .collect();
+        res
+    }
+
+    /// Remplace `destination` par le contenu decompresse de l'instantane. Le
+    /// contenu est decompresse dans un fichier temporaire et relu avant de
+    /// prendre la place de `destination`.
+    pub fn restaurer(chemin: &Path, destination: &str) -> io::Result<()> {
+        let temporaire = format!("{}.tmp", destination);
+        let res = (|| {
+            let mut entree = ouvrir_instantane(chemin)?;
+            let mut sortie = BufWriter::new(File::create(&temporaire)?);
+            io::copy(&mut entree, &mut sortie)?;
+            sortie.flush()?;
+            drop(sortie);
+            let lecteur = BufReader::new(File::open(&temporaire)?);
+            serde_json::from_reader::<_, IgnoredAny>(lecteur)?;
+            fs::rename(&temporaire, destination)
+        })();
+        if res.is_err() {
+            let _ = fs::remove_file(&temporaire);
+        }
+        res
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        fn dossier(nom: &str) -> PathBuf {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            dossier
+        }
+
+        fn donnees() -> Value {
+            json!({"liste": {
+                "1": {"item_id": "1", "resolved_title": "un", "status": "0"},
+                "2": {"item_id": "2", "resolved_title": "deux", "status": "1"},
+            }})
+        }
+
+        #[test]
+        fn ecriture_puis_relecture_et_restauration() {
+            let dossier = dossier("instantanes");
+            let source = dossier.join("data.json");
+            fs::write(&source, serde_json::to_vec_pretty(&donnees()).unwrap()).unwrap();
+            let original = fs::read(&source).unwrap();
+            for (compression, magique) in [
+                (Compression::Aucune, &b"{"[..]),
+                (Compression::Gzip, &MAGIQUE_GZIP[..]),
+                (Compression::Zstd, &MAGIQUE_ZSTD[..]),
+            ] {
+                let destination = dossier.join("data_1700000000.json");
+                let fichier = ecrire_instantane(
+                    source.to_str().unwrap(),
+                    destination.to_str().unwrap(),
+                    compression,
+                    0,
+                )
+                .unwrap();
+                assert!(fichier.ends_with(format!(".json{}", compression.extension()).as_str()));
+                assert_eq!(Compression::depuis_nom(&fichier), Some(compression));
+                assert!(fs::read(&fichier).unwrap().starts_with(magique));
+                assert!(!Path::new(&format!("{}.tmp", fichier)).exists());
+
+                let chemin = Path::new(&fichier);
+                assert_eq!(lire_instantane(chemin).unwrap(), donnees());
+                assert_eq!(verifier(chemin, "data").nb_elements, Some(2));
+
+                let restaure = dossier.join("restaure.json");
+                restaurer(chemin, restaure.to_str().unwrap()).unwrap();
+                assert_eq!(fs::read(&restaure).unwrap(), original);
+                fs::remove_file(&fichier).unwrap();
+            }
+            let _ = fs::remove_dir_all(&dossier);
+        }
+
+        #[test]
+        fn instantane_corrompu_non_restaure() {
+            let dossier = dossier("instantane_corrompu");
+            let source = dossier.join("data.json");
+            fs::write(&source, serde_json::to_vec(&donnees()).unwrap()).unwrap();
+            let fichier = ecrire_instantane(
+                source.to_str().unwrap(),
+                dossier.join("data_1.json").to_str().unwrap(),
+                Compression::Gzip,
+                9,
+            )
+            .unwrap();
+            let mut octets = fs::read(&fichier).unwrap();
+            octets.truncate(octets.len() / 2);
+            fs::write(&fichier, octets).unwrap();
+
+            let chemin = Path::new(&fichier);
+            assert!(verifier(chemin, "data").erreur.is_some());
+            let destination = dossier.join("actuel.json");
+            fs::write(&destination, b"{}").unwrap();
+            assert!(restaurer(chemin, destination.to_str().unwrap()).is_err());
+            assert_eq!(fs::read(&destination).unwrap(), b"{}");
+            assert!(!dossier.join("actuel.json.tmp").exists());
+            let _ = fs::remove_dir_all(&dossier);
+        }
+
+        #[test]
+        fn differences_entre_instantanes() {
+            let avant = donnees();
+            let mut apres = donnees();
+            apres["liste"]["2"]["status"] = json!("0");
+            apres["liste"].as_object_mut().unwrap().remove("1");
+            apres["liste"]["3"] = json!({"item_id": "3"});
+            let difference = comparer_instantanes(&avant, &apres);
+            assert_eq!(difference.ajoutes, ["3"]);
+            assert_eq!(difference.supprimes, ["1"]);
+            assert_eq!(difference.modifies.len(), 1);
+            assert_eq!(difference.modifies[0].champs, ["status"]);
+        }
+    }
+}
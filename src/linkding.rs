@@ -0,0 +1,321 @@
+pub mod mod_linkding {
+    use crate::api::mod_api::{appel_json, ErreurApi};
+    use crate::config::mod_config::ConfigSource;
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, objet_tags, statut, tags, timestamp_iso, titre, url,
+        STATUT_ARCHIVE, STATUT_NON_LU,
+    };
+    use crate::source::mod_source::{ChampTri, Page, SourceProvider, SOURCE_LINKDING};
+    use chrono::{DateTime, Local};
+    use reqwest::Client;
+    use serde_json::Value;
+
+    /// API Linkding : jeton dans l'entete `Authorization: Token`. Les
+    /// favoris actifs (`/api/bookmarks/`) et archives
+    /// (`/api/bookmarks/archived/`) sont deux listes : l'offset parcourt
+    /// la premiere puis la seconde.
+    pub struct SourceLinkding {
+        url: String,
+        jeton: String,
+        taille_page: u64,
+        /// nombre de favoris actifs, connu apres le premier appel
+        nb_actifs: Option<u64>,
+    }
+
+    impl SourceLinkding {
+        pub fn new(config: &ConfigSource, taille_page: u64) -> SourceLinkding {
+            SourceLinkding {
+                url: config.url.trim_end_matches('/').to_string(),
+                jeton: config.jeton.clone(),
+                taille_page,
+                nb_actifs: None,
+            }
+        }
+
+        async fn appel_bookmarks(
+            &self,
+            client: &Client,
+            chemin: &str,
+            offset: u64,
+            since: Option<u64>,
+        ) -> Result<Value, ErreurApi> {
+            let mut requete = client
+                .get(format!("{}/api/bookmarks/{}", self.url, chemin))
+                .header("Authorization", format!("Token {}", self.jeton))
+                .query(&[("limit", self.taille_page), ("offset", offset)]);
+            if let Some(date) = since.and_then(|s| DateTime::from_timestamp(s as i64, 0)) {
+                requete = requete.query(&[("modified_since", date.to_rfc3339())]);
+            }
+            appel_json(requete).await
+        }
+
+        /// Identifiant du favori deja present pour cette URL.
+        pub async fn rechercher(
+            &mut self,
+            client: &Client,
+            url: &str,
+        ) -> Result<Option<String>, ErreurApi> {
+            let requete = client
+                .get(format!("{}/api/bookmarks/check/", self.url))
+                .header("Authorization", format!("Token {}", self.jeton))
+                .query(&[("url", url)]);
+            let json = appel_json(requete).await?;
+            Ok(json
+                .pointer("/bookmark/id")
+                .and_then(|id| id.as_i64())
+                .map(|id| id.to_string()))
+        }
+
+        /// Cree le favori ; l'API ne permet pas de fixer la date d'ajout.
+        pub async fn ajouter(
+            &mut self,
+            client: &Client,
+            element: &Value,
+        ) -> Result<String, ErreurApi> {
+            let mut corps = corps_favori(element);
+            corps["url"] = Value::String(url(element).to_string());
+            let requete = client
+                .post(format!("{}/api/bookmarks/", self.url))
+                .header("Authorization", format!("Token {}", self.jeton))
+                .header("Content-Type", "application/json")
+                .body(corps.to_string());
+            let json = appel_json(requete).await?;
+            match json.get("id").and_then(|id| id.as_i64()) {
+                Some(id) => Ok(id.to_string()),
+                None => Err(ErreurApi::Json("id absent".to_string())),
+            }
+        }
+
+        pub async fn modifier(
+            &mut self,
+            client: &Client,
+            id: &str,
+            element: &Value,
+        ) -> Result<(), ErreurApi> {
+            let requete = client
+                .patch(format!("{}/api/bookmarks/{}/", self.url, id))
+                .header("Authorization", format!("Token {}", self.jeton))
+                .header("Content-Type", "application/json")
+                .body(corps_favori(element).to_string());
+            appel_json(requete).await.map(|_| ())
+        }
+    }
+
+    /// Titre, description, tags et etat d'un element Pocket.
+    fn corps_favori(element: &Value) -> Value {
+        serde_json::json!({
+            "title": titre(element),
+            "description": champ_str(element, "excerpt"),
+            "tag_names": tags(element),
+            "is_archived": statut(element) == STATUT_ARCHIVE,
+            "unread": statut(element) == STATUT_NON_LU,
+        })
+    }
+
+    /// Favori Linkding converti au format des elements Pocket.
+    pub fn vers_element(favori: &Value) -> (String, Value) {
+        let id = champ_nombre(favori, "id").to_string();
+        let titre = match champ_str(favori, "title") {
+            "" => champ_str(favori, "website_title"),
+            t => t,
+        };
+        let extrait = match champ_str(favori, "description") {
+            "" => champ_str(favori, "website_description"),
+            d => d,
+        };
+        let archive = favori.get("is_archived").is_some_and(|a| a == true);
+        let tags: Vec<String> = favori
+            .get("tag_names")
+            .and_then(|t| t.as_array())
+            .map(|t| {
+                t.iter()
+                    .filter_map(|t| t.as_str())
+                    .map(|t| t.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut element = serde_json::json!({
+            "item_id": id,
+            "given_url": champ_str(favori, "url"),
+            "given_title": titre,
+            "resolved_title": titre,
+            "excerpt": extrait,
+            "time_added": timestamp_iso(champ_str(favori, "date_added")).to_string(),
+            "time_updated": timestamp_iso(champ_str(favori, "date_modified")).to_string(),
+            "status": if archive { STATUT_ARCHIVE } else { STATUT_NON_LU },
+            "favorite": "0",
+        });
+        if !tags.is_empty() {
+            element["tags"] = objet_tags(id.as_str(), &tags);
+        }
+        (id, element)
+    }
+
+    impl SourceProvider for SourceLinkding {
+        fn nom(&self) -> &'static str {
+            SOURCE_LINKDING
+        }
+
+        /// Le jeton est envoye dans chaque requete.
+        async fn authentifier(&mut self, _client: &Client) -> Result<(), ErreurApi> {
+            Ok(())
+        }
+
+        async fn page(
+            &mut self,
+            client: &Client,
+            offset: u64,
+            since: Option<u64>,
+        ) -> Result<Page, ErreurApi> {
+            let debut = Local::now().timestamp();
+            let mut json = Value::Null;
+            let mut nb_actifs = self.nb_actifs.unwrap_or(u64::MAX);
+            if offset < nb_actifs {
+                json = self.appel_bookmarks(client, "", offset, since).await?;
+                nb_actifs = json.get("count").and_then(|c| c.as_u64()).unwrap_or(0);
+                self.nb_actifs = Some(nb_actifs);
+            }
+            let total_archives = if offset >= nb_actifs {
+                json = self
+                    .appel_bookmarks(client, "archived/", offset - nb_actifs, since)
+                    .await?;
+                json.get("count").and_then(|c| c.as_u64())
+            } else {
+                None
+            };
+            let elements = json
+                .get("results")
+                .and_then(|r| r.as_array())
+                .map(|r| r.iter().map(vers_element).collect())
+                .unwrap_or_default();
+            Ok(Page {
+                elements,
+                since: Some(debut),
+                total: total_archives.map(|t| t + nb_actifs),
+                ..Default::default()
+            })
+        }
+
+        /// L'API trie par date d'ajout decroissante, sans option de tri.
+        fn champ_tri(&self, _since: Option<u64>) -> Option<ChampTri> {
+            None
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::bouchon::mod_bouchon::Bouchon;
+        use serde_json::json;
+
+        fn favori(id: i64, archive: bool) -> Value {
+            json!({
+                "id": id,
+                "url": format!("https://exemple.org/{}", id),
+                "title": "",
+                "website_title": format!("Site {}", id),
+                "description": "",
+                "website_description": "Resume",
+                "date_added": "2024-01-02T03:04:05Z",
+                "date_modified": "2024-01-03T03:04:05Z",
+                "is_archived": archive,
+                "tag_names": ["rust"]
+            })
+        }
+
+        /// 3 favoris actifs (1 a 3) puis 2 archives (4 et 5), 2 par page.
+        fn bouchon_linkding() -> Bouchon {
+            Bouchon::demarrer(|requete| {
+                let offset: usize = requete
+                    .parametre("offset")
+                    .and_then(|o| o.parse().ok())
+                    .unwrap_or(0);
+                let (favoris, archive) = match requete.chemin().as_str() {
+                    "/api/bookmarks/" => (vec![1, 2, 3], false),
+                    "/api/bookmarks/archived/" => (vec![4, 5], true),
+                    _ => return (404, "{}".to_string()),
+                };
+                let results: Vec<Value> = favoris
+                    .iter()
+                    .skip(offset)
+                    .take(2)
+                    .map(|id| favori(*id, archive))
+                    .collect();
+                let corps = json!({"count": favoris.len(), "results": results});
+                (200, corps.to_string())
+            })
+        }
+
+        #[tokio::test]
+        async fn linkding_actifs_puis_archives() {
+            let bouchon = bouchon_linkding();
+            let config = ConfigSource {
+                url: format!("{}/", bouchon.adresse),
+                jeton: "jeton".to_string(),
+                ..Default::default()
+            };
+            let mut source = SourceLinkding::new(&config, 2);
+            let client = Client::new();
+            let mut ids = Vec::new();
+            let mut offset = 0;
+            let mut total = None;
+            loop {
+                let page = source
+                    .page(&client, offset, Some(1700000000))
+                    .await
+                    .unwrap();
+                if page.elements.is_empty() {
+                    break;
+                }
+                offset += page.elements.len() as u64;
+                ids.extend(page.elements.into_iter().map(|(id, _)| id));
+                total = page.total.or(total);
+            }
+            assert_eq!(ids, ["1", "2", "3", "4", "5"]);
+            assert_eq!(total, Some(5));
+
+            let requetes = bouchon.requetes();
+            let appels: Vec<(String, Option<String>)> = requetes
+                .iter()
+                .map(|r| (r.chemin(), r.parametre("offset")))
+                .collect();
+            let attendus = [
+                ("/api/bookmarks/", "0"),
+                ("/api/bookmarks/", "2"),
+                ("/api/bookmarks/archived/", "0"),
+                ("/api/bookmarks/archived/", "2"),
+            ];
+            assert_eq!(
+                appels,
+                attendus.map(|(c, o)| (c.to_string(), Some(o.to_string())))
+            );
+            assert!(requetes.iter().all(|r| r.autorisation == "Token jeton"));
+            assert!(requetes
+                .iter()
+                .all(|r| r.parametre("limit").as_deref() == Some("2")));
+            let depuis = DateTime::from_timestamp(1700000000, 0)
+                .unwrap()
+                .to_rfc3339();
+            assert_eq!(requetes[0].parametre("modified_since"), Some(depuis));
+        }
+
+        #[test]
+        fn linkding_conversion_en_element() {
+            let (id, element) = vers_element(&favori(4, true));
+            assert_eq!(id, "4");
+            assert_eq!(element["given_url"], "https://exemple.org/4");
+            assert_eq!(element["resolved_title"], "Site 4");
+            assert_eq!(element["excerpt"], "Resume");
+            assert_eq!(element["time_added"], "1704164645");
+            assert_eq!(element["time_updated"], "1704251045");
+            assert_eq!(element["status"], STATUT_ARCHIVE);
+            assert_eq!(tags(&element), ["rust"]);
+
+            let mut renomme = favori(1, false);
+            renomme["title"] = json!("Mon titre");
+            let (_, element) = vers_element(&renomme);
+            assert_eq!(element["resolved_title"], "Mon titre");
+            assert_eq!(element["status"], STATUT_NON_LU);
+        }
+    }
+}
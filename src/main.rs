@@ -1,5 +1,37 @@
+mod anomalie;
+mod api;
+mod archive;
+#[cfg(test)]
+mod bouchon;
+mod commande;
 mod config;
+mod donnees;
+mod export;
+mod extraction;
+mod filtre;
+mod historique;
+mod hooks;
+mod import;
+mod instantane;
+mod linkding;
+mod metriques;
 mod minmax;
+mod notes;
+mod push;
+mod rapport;
+mod recherche;
+mod reconciliation;
+mod rest;
+mod retention;
+mod source;
+mod stats;
+mod statut;
+mod trous;
+mod verrou;
+mod versions;
+mod wallabag;
+mod warc;
+mod web;
 
 use crate::config::mod_config::{
     Config2, ConfigParam, ConfigParamForce, DATA_ETAT_INITIALISATION, DATA_ETAT_MISE_A_JOUR,
@@ -7,64 +39,62 @@ use crate::config::mod_config::{
 };
 use chrono::{DateTime, FixedOffset, Local, NaiveTime};
 use log::LevelFilter;
-use log4rs::append::console::ConsoleAppender;
+use log4rs::append::console::{ConsoleAppender, Target};
 use log4rs::config::{Appender, Root};
 use log4rs::{Handle};
-use reqwest;
 use reqwest::Error;
-use reqwest::StatusCode;
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs::File;
-use std::io::Write;
 use std::path::Path;
 use std::time::Duration;
-use std::{env, fmt, fs, thread};
+use std::{env, fs, thread};
 
+use crate::anomalie::mod_anomalie::Detecteur;
+use crate::archive::mod_archive::archiver;
+use crate::commande::mod_commande::{est_commande, executer_commande};
 use crate::config::mod_config::{init_config, init_config_param};
+use crate::donnees::mod_donnees::{
+    charger_donnees, donnees_initiales, fichier_data, fichier_param, sauver_donnees, sauver_param, DATA_ETAT, DATA_LISTE,
+};
+use crate::metriques::mod_metriques::{ecrire_metriques, generer_metriques};
 use crate::minmax::mod_min_max::create_min_max;
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Parameters {
-    consumer_key: String,
-    access_token: String,
-    #[serde(rename = "detail_type")]
-    detail_type: String,
-    count: u64,
-    offset: u64,
-    total: u8,
-    sort: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    since: Option<u64>,
-}
+use crate::historique::mod_historique::{
+    ajouter_execution, charger_historique, derniere_execution_reussie,
+};
+use crate::instantane::mod_instantane::{ecrire_instantane, Compression};
+use crate::hooks::mod_hooks::{declencher_hooks, GardeEchec};
+use crate::import::mod_import::conserver_import;
+use crate::rapport::mod_rapport::{RapportExecution, RapportTraitement};
+use crate::recherche::mod_recherche::mettre_a_jour_index;
+use crate::retention::mod_retention::{elaguer, repertoire_backup};
+use crate::source::mod_source::{creer_source, ChampTri, SourceProvider};
+use crate::trous::mod_trous::{charger_file, sauver_file};
+use crate::verrou::mod_verrou::Verrou;
+use crate::versions::mod_versions::{ajouter_versions, version_remplacee, Version};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum ListeDates {
     DatesContinues(DateTime<FixedOffset>, u32, i32),
     ListeDates(Vec<DateTime<FixedOffset>>, i32),
+    FileRechargement(Vec<DateTime<FixedOffset>>, i32),
     DateJusquaFin(i32),
 }
 
-const DATA_ETAT: &str = "etat";
-const DATA_OFFSET: &str = "offset";
-const DATA_DATE: &str = "date";
-const DATA_LISTE: &str = "liste";
-
-impl fmt::Display for Parameters {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // Customize so only `x` and `y` are denoted.
-        write!(
-            f,
-            "detail_type: {}, count: {}, offset: {}, total: {}, sort: {}, since: {:?}",
-            self.detail_type, self.count, self.offset, self.total, self.sort, self.since
-        )
+impl ListeDates {
+    fn mode(&self) -> &'static str {
+        match self {
+            ListeDates::DatesContinues(..) => "datesContinues",
+            ListeDates::ListeDates(..) => "listeDates",
+            ListeDates::FileRechargement(..) => "fileRechargement",
+            ListeDates::DateJusquaFin(..) => DATA_ETAT_MISE_A_JOUR,
+        }
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let start = Local::now();
-    println!("debut : {}", start.format("%Y-%m-%d %H:%M:%S"));
 
     let handle = init_logs();
 
@@ -73,20 +103,45 @@ async fn main() -> Result<(), Error> {
     let config: Config2 = init_config(handle)?;
 
     let args: Vec<String> = env::args().collect();
-    dbg!(&args);
 
     let arg0: Vec<String> = args.clone();
-    if arg0.len() >= 1 {
+    if !arg0.is_empty() {
         log::info!("param1 : {}", arg0[0]);
     }
     if arg0.len() >= 2 {
         log::info!("param2 : {}", arg0[1]);
     }
+    if est_commande(&arg0) {
+        let code = executer_commande(&config, &arg0).await;
+        if code != 0 {
+            std::process::exit(code);
+        }
+        return Ok(());
+    }
     let dates: ListeDates = initialise_parametrage(arg0, config.clone());
     log::info!("date : {:?}", dates);
 
-    let fichier = config.repertoire.clone() + "/data.json";
-    let fichier_param = config.repertoire.clone() + "/param.json";
+    let fichier = fichier_data(config.repertoire.as_str());
+    let fichier_param = fichier_param(config.repertoire.as_str());
+
+    let _verrou = match Verrou::prendre(config.repertoire.as_str()) {
+        Ok(verrou) => verrou,
+        Err(err) => {
+            log::error!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let config_param = init_config_param(fichier_param.clone());
+
+    let mode = dates.mode();
+    let mut garde_echec = GardeEchec::new(
+        &config.hooks,
+        config.repertoire.as_str(),
+        mode,
+        start.fixed_offset(),
+        config_param.date_dernier_traiment,
+    );
 
     let config2 = config.clone();
     backup_data(config2, &fichier.clone(), &"data".to_string()).unwrap();
@@ -97,16 +152,25 @@ async fn main() -> Result<(), Error> {
         &"param".to_string(),
     )
     .unwrap();
+    if config.retention.automatique && config.retention.est_active() {
+        match elaguer(&config.retention, config.repertoire.as_str(), false) {
+            Ok(bilan) => log::info!(
+                "retention : {} copies gardees, {} supprimees",
+                bilan.gardes.len(),
+                bilan.supprimes.len()
+            ),
+            Err(err) => log::error!("Erreur de rotation de backup/ : {}", err),
+        }
+    }
 
-    let config_param = init_config_param(fichier_param.clone());
-
+    let mut rapports: Vec<RapportTraitement> = Vec::new();
     match dates {
         ListeDates::DatesContinues(date, max_jours, nb_count_max) => {
             log::info!("parcourt de dates consecutives");
             for i in 0..max_jours {
                 let date2 = date + chrono::Duration::days(i as i64);
                 log::info!("traitement de : {}", date2);
-                traitement_specifique(
+                let rapport = traitement_specifique(
                     config.clone(),
                     nb_count_max,
                     fichier.clone(),
@@ -115,13 +179,14 @@ async fn main() -> Result<(), Error> {
                     &date2,
                 )
                 .await;
+                rapports.push(rapport);
             }
         }
         ListeDates::ListeDates(liste_dates, nb_count_max) => {
             log::info!("parcourt de dates");
             for date in liste_dates.iter() {
                 log::info!("traitement de : {}", date);
-                traitement_specifique(
+                let rapport = traitement_specifique(
                     config.clone(),
                     nb_count_max,
                     fichier.clone(),
@@ -130,6 +195,35 @@ async fn main() -> Result<(), Error> {
                     date,
                 )
                 .await;
+                rapports.push(rapport);
+            }
+        }
+        ListeDates::FileRechargement(liste_dates, nb_count_max) => {
+            log::info!("parcourt de la file de rechargement");
+            for date in liste_dates.iter() {
+                log::info!("traitement de : {}", date);
+                let rapport = traitement_specifique(
+                    config.clone(),
+                    nb_count_max,
+                    fichier.clone(),
+                    fichier_param.clone(),
+                    config_param.clone(),
+                    date,
+                )
+                .await;
+                // une date en erreur reste dans la file pour le prochain passage
+                let en_erreur = !rapport.erreurs.is_empty();
+                rapports.push(rapport);
+                if en_erreur {
+                    log::warn!("{} reste dans la file de rechargement", date);
+                    continue;
+                }
+                let traite = date.format("%Y-%m-%d").to_string();
+                let reste: Vec<String> = charger_file(config.repertoire.as_str())
+                    .into_iter()
+                    .filter(|d| *d != traite)
+                    .collect();
+                sauver_file(config.repertoire.as_str(), &reste).unwrap();
             }
         }
         ListeDates::DateJusquaFin(nb_count_max) => {
@@ -140,14 +234,15 @@ async fn main() -> Result<(), Error> {
                     nb_count_max,
                     force: false,
                 };
-                traitement(
-                    config,
+                let rapport = traitement(
+                    config.clone(),
                     config_force,
                     fichier.clone(),
                     fichier_param.clone(),
                     config_param.clone(),
                 )
                 .await;
+                rapports.push(rapport);
             } else {
                 let mut i = 0;
                 let timestamp = config_param.date_dernier_traiment;
@@ -162,7 +257,7 @@ async fn main() -> Result<(), Error> {
                         break;
                     }
                     log::info!("traitement de : {}", date2);
-                    traitement_specifique(
+                    let rapport = traitement_specifique(
                         config.clone(),
                         nb_count_max,
                         fichier.clone(),
@@ -171,6 +266,7 @@ async fn main() -> Result<(), Error> {
                         &date2,
                     )
                     .await;
+                    rapports.push(rapport);
                     i += 1;
                 }
             }
@@ -184,6 +280,60 @@ async fn main() -> Result<(), Error> {
     log::info!("fin : {}", end.format("%Y-%m-%d %H:%M:%S"));
 
     log::info!("duree totale : {}", diff);
+
+    for rapport in &rapports {
+        for anomalie in &rapport.anomalies {
+            log::warn!("anomalie : {}", anomalie);
+        }
+    }
+    log::info!(
+        "anomalies : {}, rechargements : {}",
+        rapports.iter().map(|r| r.anomalies.len()).sum::<usize>(),
+        rapports.iter().map(|r| r.nb_rechargements).sum::<u64>()
+    );
+
+    let execution = RapportExecution::creer(
+        mode,
+        start.fixed_offset(),
+        end.fixed_offset(),
+        config_param.date_dernier_traiment,
+        init_config_param(fichier_param.clone()).date_dernier_traiment,
+        rapports,
+    );
+    log::info!("execution : {}", execution);
+    if let Err(err) = ajouter_execution(config.repertoire.as_str(), &execution) {
+        log::error!("Erreur d'ecriture de l'historique : {}", err);
+    }
+
+    garde_echec.desarmer();
+    declencher_hooks(&config.hooks, &execution).await;
+
+    if !config.metriques.fichier.is_empty() {
+        let since_reussi = derniere_execution_reussie(&charger_historique(
+            config.repertoire.as_str(),
+        ))
+        .map_or(execution.since_avant, |r| r.since_apres);
+        let contenu = generer_metriques(&execution, since_reussi);
+        if let Err(err) = ecrire_metriques(config.metriques.fichier.as_str(), contenu.as_str()) {
+            log::error!("Erreur d'ecriture des metriques : {}", err);
+        }
+    }
+
+    if config.archive.actif {
+        if let Some(data) = charger_donnees(fichier.as_str()) {
+            let bilan = archiver(&config.archive, config.repertoire.as_str(), &data, None, false).await;
+            log::info!(
+                "archivage : {} pages, {} ressources, {} erreurs, {} absentes",
+                bilan.nb_archives,
+                bilan.nb_ressources,
+                bilan.nb_erreurs,
+                bilan.nb_absents
+            );
+            if config.recherche.actif {
+                mettre_a_jour_recherche(&config, &data);
+            }
+        }
+    }
     Ok(())
 }
 
@@ -221,23 +371,25 @@ fn initialise_parametrage(arg0: Vec<String>, config: Config2) -> ListeDates {
         } else if !config2.rechargement.dates.is_empty() {
             log::info!("config dates : {:?}", config2.rechargement.dates);
             let mut nb_count_max = 2;
-            let mut liste_dates: Vec<DateTime<FixedOffset>> = Vec::new();
-            for date_str in &config2.rechargement.dates {
-                let d = date_str.clone();
-                let s2 = d + "T00:00:00+00:00";
-                let datetime = DateTime::parse_from_rfc3339(s2.as_str()).unwrap();
-                liste_dates.push(datetime);
+            let liste_dates = parse_liste_dates(&config2.rechargement.dates);
+            if config2.rechargement.nb_parcourt > 0 {
+                nb_count_max = config2.rechargement.nb_parcourt;
             }
+            ListeDates::ListeDates(liste_dates, nb_count_max)
+        } else if !charger_file(config2.repertoire.as_str()).is_empty() {
+            let file = charger_file(config2.repertoire.as_str());
+            log::info!("file de rechargement : {:?}", file);
+            let mut nb_count_max = 2;
             if config2.rechargement.nb_parcourt > 0 {
                 nb_count_max = config2.rechargement.nb_parcourt;
             }
-            return ListeDates::ListeDates(liste_dates, nb_count_max);
+            ListeDates::FileRechargement(parse_liste_dates(&file), nb_count_max)
         } else {
-            return if config2.mise_a_jour_jusqua_fin.nb_pages > 0 {
+            if config2.mise_a_jour_jusqua_fin.nb_pages > 0 {
                 ListeDates::DateJusquaFin(config2.mise_a_jour_jusqua_fin.nb_pages as i32)
             } else {
                 ListeDates::DateJusquaFin(0)
-            };
+            }
         }
     }
 }
@@ -249,7 +401,7 @@ async fn traitement_specifique(
     fichier_param: String,
     mut config_param: ConfigParam,
     date: &DateTime<FixedOffset>,
-) {
+) -> RapportTraitement {
     config_param.etat = DATA_ETAT_SPECIFIQUE.to_string();
     config_param.offset = 0;
     config_param.date_dernier_traiment = date.timestamp() as u64;
@@ -265,29 +417,39 @@ async fn traitement_specifique(
         fichier_param.clone(),
         config_param.clone(),
     )
-    .await;
+    .await
 }
 
 fn init_logs() -> Handle {
-    let stdout = ConsoleAppender::builder().build();
+    // la sortie standard est reservee au resultat des commandes
+    let stdout = ConsoleAppender::builder().target(Target::Stderr).build();
 
     let config = log4rs::config::Config::builder()
         .appender(Appender::builder().build("stdout", Box::new(stdout)))
         .build(Root::builder().appender("stdout").build(LevelFilter::Info))
         .unwrap();
 
-    let handle = log4rs::init_config(config).unwrap();
-    handle
+    log4rs::init_config(config).unwrap()
+}
+
+fn parse_liste_dates(dates: &[String]) -> Vec<DateTime<FixedOffset>> {
+    let mut liste_dates: Vec<DateTime<FixedOffset>> = Vec::new();
+    for date_str in dates {
+        let d = date_str.clone();
+        let s2 = d + "T00:00:00+00:00";
+        let datetime = DateTime::parse_from_rfc3339(s2.as_str()).unwrap();
+        liste_dates.push(datetime);
+    }
+    liste_dates
 }
 
 fn parse_date(s: String) -> DateTime<FixedOffset> {
     let test = s.parse::<u64>();
     match test {
         Ok(ok) => {
-            let datetime = DateTime::from_timestamp_millis(ok as i64 * 1000)
+            DateTime::from_timestamp_millis(ok as i64 * 1000)
                 .unwrap()
-                .fixed_offset();
-            datetime
+                .fixed_offset()
         }
         Err(_e) => {
             let s2 = s + "T00:00:00+00:00";
@@ -303,7 +465,8 @@ async fn traitement(
     fichier: String,
     fichier_param: String,
     mut data_param: ConfigParam,
-) {
+) -> RapportTraitement {
+    let debut = Local::now();
     let nb_appel_max: u64;
 
     if config_force.force && config_force.nb_count_max > 0 {
@@ -332,13 +495,11 @@ async fn traitement(
 
     let initialisation: bool;
 
-    let nb_sauvegarde: u64;
-
-    if config.sauvegarde > 0 {
-        nb_sauvegarde = config.sauvegarde;
+    let nb_sauvegarde: u64 = if config.sauvegarde > 0 {
+        config.sauvegarde
     } else {
-        nb_sauvegarde = 10;
-    }
+        10
+    };
 
     let is_present = Path::new(&fichier.clone()).exists();
     if is_present {
@@ -373,218 +534,200 @@ async fn traitement(
             log::info!("taille au debut: {}", obj.len());
         }
     } else {
-        data = serde_json::json!({
-            DATA_ETAT:DATA_ETAT_INITIALISATION,
-            DATA_OFFSET: 0,
-            DATA_DATE:0,
-            DATA_LISTE: {}
-        });
+        data = donnees_initiales();
         initialisation = true;
     }
 
     let mut total_ajout = 0;
     let mut total_modifie = 0;
+    let mut dates_ajout = create_min_max();
+    let mut dates_maj = create_min_max();
 
-    let consumer_key = config.consumer_key.clone();
-    let access_token = config.access_token.clone();
-
-    let param: Parameters;
-
-    if initialisation {
-        param = Parameters {
-            consumer_key,
-            access_token,
-            //detail_type: "complete".parse().unwrap(),
-            detail_type: "simple".parse().unwrap(),
-            count: 30,
-            offset,
-            total: 1,
-            sort: "oldest".parse().unwrap(),
-            since: Option::None,
-        };
+    let since_requete: Option<u64> = if initialisation {
+        None
     } else {
         check_timestamp(&since);
-        param = Parameters {
-            consumer_key,
-            access_token,
-            //detail_type: "complete".parse().unwrap(),
-            detail_type: "simple".parse().unwrap(),
-            count: 30,
-            offset,
-            total: 1,
-            sort: "oldest".parse().unwrap(),
-            since: Option::Some(since),
-        };
-    }
+        Some(since)
+    };
 
-    log::info!("parametre de démarrage : {}", param);
     log::info!("parametre config force : {:?}", config_force);
     log::info!("parametre data_param : {:?}", data_param);
 
-    loop {
-        let client = reqwest::Client::new();
-
-        let json_output = serde_json::to_string(&param).expect("Erreur de sérialisation");
+    let mut rapport = RapportTraitement {
+        since: since_requete,
+        ..Default::default()
+    };
+    let mut detecteur = Detecteur::default();
 
-        let request_url = config.url.clone();
+    let Some(mut source) = creer_source(&config, false) else {
+        *rapport.erreurs.entry("source".to_string()).or_insert(0) += 1;
+        return rapport;
+    };
+    log::info!("source : {}", source.nom());
+    let client = reqwest::Client::new();
+    if let Err(err) = source.authentifier(&client).await {
+        log::error!("{}", err);
+        *rapport.erreurs.entry(err.code()).or_insert(0) += 1;
+        return rapport;
+    }
 
+    loop {
         log::info!(
             "appel serveur offset: {}, since: {:?} ({:?})",
             offset,
-            param.since,
-            DateTime::from_timestamp(param.since.unwrap_or(0) as i64, 0).unwrap()
+            since_requete,
+            DateTime::from_timestamp(since_requete.unwrap_or(0) as i64, 0).unwrap()
         );
 
-        let response = client
-            .post(request_url)
-            .header("Content-Type", "application/json")
-            .header("X-Accept", "application/json")
-            .body(json_output.to_owned())
-            .send()
-            .await;
-
-        let body_ok: String;
-
-        match response {
-            Ok(resp) => match resp.status() {
-                StatusCode::OK => {
-                    log::info!("OK");
-                    log::info!("headers: {:?}", resp.headers());
-                    match resp.text().await {
-                        Ok(body) => {
-                            // println!("Réponse reçue : {}", body)
-
-                            body_ok = body;
-                        }
-                        Err(err) => {
-                            log::error!("Erreur en lisant la réponse : {}", err);
-                            break;
-                        }
-                    }
-                }
-                StatusCode::NOT_FOUND => {
-                    log::error!("Erreur 404 : Ressource non trouvée.");
-                    log::error!("headers: {:?}", resp.headers());
-                    log::error!("body: {:?}", resp.text().await);
-                    break;
+        let mut tentative = 1;
+        let resultat = loop {
+            match source.page(&client, offset, since_requete).await {
+                Err(err) if err.temporaire() && tentative < config.anomalies.tentatives => {
+                    log::warn!(
+                        "{} : nouvel essai dans {} ms",
+                        err,
+                        config.anomalies.delai_reessai_ms
+                    );
+                    rapport.nb_reessais += 1;
+                    tentative += 1;
+                    tokio::time::sleep(Duration::from_millis(config.anomalies.delai_reessai_ms))
+                        .await;
                 }
-                StatusCode::BAD_REQUEST => {
-                    log::error!("Erreur 400 : Bad request.");
-                    log::error!("headers: {:?}", resp.headers());
-                    log::error!("body: {:?}", resp.text().await);
-                    break;
+                res => break res,
+            }
+        };
+        let page = match resultat {
+            Ok(page) => {
+                if page.limite_utilisateur.is_some() {
+                    rapport.limite_utilisateur = page.limite_utilisateur;
                 }
-                other => {
-                    log::error!("Réponse inattendue : {:?}", other);
-                    log::error!("headers: {:?}", resp.headers());
-                    log::error!("body: {:?}", resp.text().await);
-                    break;
+                if page.limite_cle.is_some() {
+                    rapport.limite_cle = page.limite_cle;
                 }
-            },
+                page
+            }
             Err(err) => {
-                log::error!("Erreur lors de la requête : {}", err);
+                log::error!("{}", err);
+                *rapport.erreurs.entry(err.code()).or_insert(0) += 1;
                 break;
             }
-        }
-
-        let json_value: Value = serde_json::from_str(&*body_ok).expect("Erreur de parsing");
-
-        let obj = json_value.as_object().unwrap();
-
-        if obj.contains_key("maxActions") {
-            log::info!("maxActions: {}", obj["maxActions"].as_i64().unwrap_or(-1));
-        }
-        if obj.contains_key("cachetype") {
-            log::info!(
-                "cachetype: {}",
-                obj["cachetype"].as_str().unwrap_or("Inconnu")
-            );
-        }
-        if obj.contains_key("since") {
-            log::info!("since: {}", obj["since"].as_i64().unwrap_or(-1));
-        }
+        };
 
         let mut fin = false;
-        if obj.contains_key("list") && obj["list"].is_object() {
-            let obj2 = obj["list"].as_object().unwrap();
+        log::info!("nb: {}", page.elements.len());
 
-            log::info!("nb: {}", obj2.len());
-
-            if obj2.len() == 0 {
-                fin = true;
-            } else {
-                offset = offset + obj2.len() as u64;
-
-                let liste = &mut data[DATA_LISTE];
-                let mut nb_ajout = 0;
-                let mut nb_remplace = 0;
-                let mut s = "".to_string();
-                let mut ajout = create_min_max();
-                let mut remplace = create_min_max();
-                for tmp in obj2.iter() {
-                    let mut ajout_element=false;
-                    if !liste.as_object().unwrap().contains_key(tmp.0) {
-                        nb_ajout += 1;
-                        ajout_element=true;
-                    } else {
-                        nb_remplace += 1;
-                    }
-                    liste[tmp.0] = tmp.1.clone();
-                    let res = tmp.1.clone();
-                    let mut time_added = 0;
-                    let mut time_updated = 0;
-                    if res.is_object() {
-                        let res2 = res.as_object().unwrap();
-                        if res2.contains_key("time_added") {
-                            time_added = res2["time_added"]
-                                .as_str()
-                                .unwrap_or("")
-                                .parse::<i32>()
-                                .unwrap_or(0);
-                        }
-                        if res2.contains_key("time_updated") {
-                            time_updated = res2["time_updated"]
-                                .as_str()
-                                .unwrap_or("")
-                                .parse::<i32>()
-                                .unwrap_or(0);
-                        }
-                    }
-                    if time_added > 0 {
-                        ajout.add(time_added);
+        if page.elements.is_empty() {
+            fin = true;
+        } else {
+            let offset_page = offset;
+            offset += page.elements.len() as u64;
+            rapport.nb_pages += 1;
+            let mut dates_page: Vec<(String, i64)> = Vec::new();
+
+            let liste = &mut data[DATA_LISTE];
+            let mut nb_ajout = 0;
+            let mut nb_remplace = 0;
+            let mut s = "".to_string();
+            let mut ajout = create_min_max();
+            let mut remplace = create_min_max();
+            let mut versions: Vec<Version> = Vec::new();
+            for tmp in page.elements.iter() {
+                let mut ajout_element=false;
+                let mut element = tmp.1.clone();
+                if !liste.as_object().unwrap().contains_key(&tmp.0) {
+                    nb_ajout += 1;
+                    ajout_element=true;
+                } else {
+                    nb_remplace += 1;
+                    conserver_import(&tmp.0, &liste[&tmp.0], &mut element);
+                    if let Some(version) = version_remplacee(&tmp.0, &liste[&tmp.0], &element) {
+                        versions.push(version);
                     }
-                    if time_updated > 0 {
-                        remplace.add(time_updated);
+                }
+                let res = element.clone();
+                liste[&tmp.0] = element;
+                let mut time_added = 0;
+                let mut time_updated = 0;
+                if res.is_object() {
+                    let res2 = res.as_object().unwrap();
+                    if res2.contains_key("time_added") {
+                        time_added = res2["time_added"]
+                            .as_str()
+                            .unwrap_or("")
+                            .parse::<i32>()
+                            .unwrap_or(0);
                     }
-                    let s0 = format!(
-                        "({},{},{},{},{})",
-                        tmp.0,
-                        time_added,
-                        time_updated,
-                        time_added == time_updated,
-                        ajout_element
-                    );
-                    if s.len() > 0 {
-                        s.push_str(",")
+                    if res2.contains_key("time_updated") {
+                        time_updated = res2["time_updated"]
+                            .as_str()
+                            .unwrap_or("")
+                            .parse::<i32>()
+                            .unwrap_or(0);
                     }
-                    s.push_str(s0.as_str());
                 }
-                log::info!("nb_ajout: {}, nb_remplace: {}", nb_ajout, nb_remplace);
-                log::info!("elements: {}", s);
-                log::info!("added: {}, updated: {}", ajout, remplace);
-                total_ajout += nb_ajout;
-                total_modifie += nb_remplace;
-                if data_param.etat == DATA_ETAT_SPECIFIQUE {
-                    data_param.offset = offset as i64;
+                if time_added > 0 {
+                    ajout.add(time_added);
+                }
+                if time_updated > 0 {
+                    remplace.add(time_updated);
                 }
-                let date = obj["since"].as_i64().unwrap_or(-1);
-                if date > 0 {
-                    dernier_since = date as u64;
-                    log::info!("dernier: {}", dernier_since);
+                let date_tri = match source.champ_tri(since_requete) {
+                    Some(ChampTri::Ajout) => time_added,
+                    Some(ChampTri::MiseAJour) => time_updated,
+                    None => 0,
+                };
+                dates_page.push((tmp.0.clone(), date_tri as i64));
+                let s0 = format!(
+                    "({},{},{},{},{})",
+                    tmp.0,
+                    time_added,
+                    time_updated,
+                    time_added == time_updated,
+                    ajout_element
+                );
+                if !s.is_empty() {
+                    s.push(',')
                 }
+                s.push_str(s0.as_str());
+            }
+            if let Err(err) = ajouter_versions(config.repertoire.as_str(), &versions) {
+                log::error!("Erreur d'ecriture des versions : {}", err);
+            }
+            log::info!("nb_ajout: {}, nb_remplace: {}", nb_ajout, nb_remplace);
+            log::info!("elements: {}", s);
+            log::info!("added: {}, updated: {}", ajout, remplace);
+            dates_ajout.fusionner(&ajout);
+            dates_maj.fusionner(&remplace);
+            total_ajout += nb_ajout;
+            total_modifie += nb_remplace;
+
+            let mut anomalies = detecteur.analyser_page(
+                offset_page,
+                &dates_page,
+                page.total,
+                since_requete,
+            );
+            let recharger = anomalies.iter().any(|a| a.rechargement)
+                && rapport.nb_rechargements < config.anomalies.nb_rechargements_max;
+            for anomalie in anomalies.iter_mut() {
+                anomalie.rechargement = anomalie.rechargement && recharger;
+                log::warn!("anomalie : {}", anomalie);
+            }
+            rapport.anomalies.append(&mut anomalies);
+            if recharger {
+                offset = offset_page.saturating_sub(config.anomalies.chevauchement);
+                rapport.nb_rechargements += 1;
+                detecteur.reprise();
+                log::warn!("rechargement à partir de l'offset : {}", offset);
+            }
+            if data_param.etat == DATA_ETAT_SPECIFIQUE {
+                data_param.offset = offset as i64;
+            }
+            let date = page.since.unwrap_or(-1);
+            if date > 0 {
+                dernier_since = date as u64;
+                log::info!("dernier: {}", dernier_since);
             }
-        } else {
-            fin = true;
         }
         if fin {
             log::info!("Pas de liste");
@@ -630,7 +773,8 @@ async fn traitement(
                     );
                 }
             }
-            if initialisation && false {
+            let fin_initialisation = false;
+            if initialisation && fin_initialisation {
                 log::info!("fin d'initialisation");
                 //data[DATA_ETAT] = Value::String(DATA_ETAT_MISE_A_JOUR.to_string());
                 data_param.etat = DATA_ETAT_MISE_A_JOUR.to_string();
@@ -648,12 +792,9 @@ async fn traitement(
             total_modifie
         );
 
-        let taille_totale: usize;
-        match data[DATA_LISTE].as_object() {
-            Some(obj) => {
-                taille_totale = obj.len();
-            }
-            _ => taille_totale = 0,
+        let taille_totale: usize = match data[DATA_LISTE].as_object() {
+            Some(obj) => obj.len(),
+            _ => 0,
         };
         log::info!("taille_totale: {}", taille_totale);
 
@@ -662,7 +803,7 @@ async fn traitement(
             break;
         }
 
-        if count % nb_sauvegarde == 0 {
+        if count.is_multiple_of(nb_sauvegarde) {
             save_as_json_list(&data, &fichier, &data_param, &fichier_param);
         }
 
@@ -674,14 +815,46 @@ async fn traitement(
     }
 
     log::info!("nb total: {}", data.as_object().unwrap().len());
+    log::info!(
+        "added total: {}, updated total: {}",
+        dates_ajout,
+        dates_maj
+    );
+    if dates_maj.nb_inversions > 0 {
+        log::warn!(
+            "time_updated non trie : {} inversions sur {} elements",
+            dates_maj.nb_inversions,
+            dates_maj.nb
+        );
+    }
 
     log::info!("termine : {}", count);
 
     save_as_json_list(&data, &fichier, &data_param, &fichier_param);
+
+    if config.recherche.actif {
+        mettre_a_jour_recherche(&config, &data);
+    }
+
+    rapport.nb_ajout = total_ajout;
+    rapport.nb_modifie = total_modifie;
+    rapport.nb_total = data[DATA_LISTE].as_object().map_or(0, |l| l.len() as u64);
+    rapport.duree_ms = (Local::now() - debut).num_milliseconds();
+    log::info!("rapport : {}", rapport);
+    rapport
+}
+
+fn mettre_a_jour_recherche(config: &Config2, data: &Value) {
+    match mettre_a_jour_index(&config.recherche, config.repertoire.as_str(), data) {
+        Ok(bilan) => log::info!("index de recherche : {}", bilan),
+        Err(err) => log::error!("Erreur de mise a jour de l'index de recherche : {}", err),
+    }
 }
 
 fn check_timestamp(since: &u64) {
-    if *since > 1764601526 {
+    // un since dans le futur (plus d'un jour) est une erreur
+    let limite = Local::now().timestamp() as u64 + 86400;
+    if *since > limite {
         panic!("{}", format!("since invalid: {since}"));
     }
     if *since <= 100 {
@@ -698,36 +871,29 @@ fn backup_data(
 
     let is_present = Path::new(fichier).exists();
     if is_present {
-        let mut s2: String = "".to_owned();
         let s = date.timestamp().to_string();
-        let rep = config.repertoire.as_str();
-        s2.push_str(format!("/backup/{debut_nom_fichier}_").as_str());
-        s2.push_str(&s);
-        s2.push_str(".json");
-        let file_resultat = format!("{rep}/backup/{debut_nom_fichier}_{s}.json");
-        fs::copy(fichier, &file_resultat)?;
+        let rep = repertoire_backup(config.repertoire.as_str());
+        fs::create_dir_all(&rep)?;
+        let compression = Compression::parse(config.backup.compression.as_str())
+            .unwrap_or_else(|| {
+                log::error!(
+                    "compression inconnue : '{}', copie non compressee",
+                    config.backup.compression
+                );
+                Compression::Aucune
+            });
+        let file_resultat = ecrire_instantane(
+            fichier,
+            format!("{rep}/{debut_nom_fichier}_{s}.json").as_str(),
+            compression,
+            config.backup.niveau,
+        )?;
         log::info!("copie vers : {}", file_resultat);
     }
     Ok(())
 }
 
-fn save_as_json_list(list: &Value, fname: &str, param: &ConfigParam, fname_param: &String) {
-    log::info!("Sauvegarde de {} ...", fname);
-    let list_as_json = serde_json::to_string(list).unwrap();
-
-    let mut file = File::create(fname).expect("Could not create file!");
-
-    file.write_all(list_as_json.as_bytes())
-        .expect("Cannot write to the file!");
-    log::info!("Fichier {} sauve", fname);
-
-    log::info!("Sauvegarde de {} ...", fname_param);
-
-    let list_as_json = serde_json::to_string(param).unwrap();
-
-    let mut file = File::create(fname_param).expect("Could not create file!");
-
-    file.write_all(list_as_json.as_bytes())
-        .expect("Cannot write to the file!");
-    log::info!("Fichier {} sauve", fname_param);
+fn save_as_json_list(list: &Value, fname: &str, param: &ConfigParam, fname_param: &str) {
+    sauver_donnees(list, fname);
+    sauver_param(param, fname_param);
 }
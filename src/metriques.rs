@@ -0,0 +1,185 @@
+pub mod mod_metriques {
+    use crate::rapport::mod_rapport::{RapportExecution, RapportTraitement};
+    use std::fmt::Write as _;
+    use std::fs;
+
+    fn metrique(s: &mut String, nom: &str, aide: &str, valeurs: &[(String, f64)]) {
+        let _ = writeln!(s, "# HELP {} {}", nom, aide);
+        let _ = writeln!(s, "# TYPE {} gauge", nom);
+        for (labels, valeur) in valeurs {
+            let _ = writeln!(s, "{}{} {}", nom, labels, valeur);
+        }
+    }
+
+    fn simple(s: &mut String, nom: &str, aide: &str, valeur: f64) {
+        metrique(s, nom, aide, &[("".to_string(), valeur)]);
+    }
+
+    /// Contenu au format texte de Prometheus (collecteur textfile de
+    /// node_exporter). `since_reussi` : since de la derniere execution
+    /// reussie de l'historique, ecrit meme apres un echec.
+    pub fn generer_metriques(execution: &RapportExecution, since_reussi: u64) -> String {
+        let mut s = String::new();
+        let rapports = &execution.traitements;
+        let somme = |f: &dyn Fn(&RapportTraitement) -> u64| -> f64 {
+            rapports.iter().map(f).sum::<u64>() as f64
+        };
+
+        simple(
+            &mut s,
+            "pbackup_last_run_timestamp_seconds",
+            "Date de fin de la derniere execution.",
+            execution.fin.timestamp() as f64,
+        );
+        simple(
+            &mut s,
+            "pbackup_last_run_duration_seconds",
+            "Duree de la derniere execution.",
+            execution.duree_ms as f64 / 1000.0,
+        );
+        simple(
+            &mut s,
+            "pbackup_last_run_success",
+            "1 si la derniere execution n'a eu aucune erreur.",
+            if execution.succes { 1.0 } else { 0.0 },
+        );
+        simple(
+            &mut s,
+            "pbackup_items_added",
+            "Elements ajoutes pendant la derniere execution.",
+            execution.nb_ajout as f64,
+        );
+        simple(
+            &mut s,
+            "pbackup_items_modified",
+            "Elements modifies pendant la derniere execution.",
+            execution.nb_modifie as f64,
+        );
+        simple(
+            &mut s,
+            "pbackup_items_total",
+            "Nombre d'elements dans la sauvegarde.",
+            execution.nb_total as f64,
+        );
+        simple(
+            &mut s,
+            "pbackup_pages_fetched",
+            "Pages recues pendant la derniere execution.",
+            somme(&|r| r.nb_pages),
+        );
+        simple(
+            &mut s,
+            "pbackup_refetches",
+            "Rechargements de pages apres une anomalie.",
+            somme(&|r| r.nb_rechargements),
+        );
+        simple(
+            &mut s,
+            "pbackup_anomalies",
+            "Anomalies detectees pendant la derniere execution.",
+            execution.nb_anomalies as f64,
+        );
+        simple(
+            &mut s,
+            "pbackup_retries",
+            "Appels recommences apres une erreur temporaire.",
+            somme(&|r| r.nb_reessais),
+        );
+        // les codes HTTP et les autres erreurs (requete, lecture, json...)
+        // sont deux metriques distinctes
+        let (http, autres): (Vec<_>, Vec<_>) = execution
+            .erreurs
+            .iter()
+            .partition(|(code, _)| code.parse::<u16>().is_ok());
+        metrique(
+            &mut s,
+            "pbackup_http_errors",
+            "Erreurs d'appel par code HTTP.",
+            &http
+                .iter()
+                .map(|(code, nb)| (format!("{{status=\"{}\"}}", code), **nb as f64))
+                .collect::<Vec<_>>(),
+        );
+        metrique(
+            &mut s,
+            "pbackup_errors",
+            "Erreurs hors code HTTP, par type.",
+            &autres
+                .iter()
+                .map(|(code, nb)| (format!("{{erreur=\"{}\"}}", code), **nb as f64))
+                .collect::<Vec<_>>(),
+        );
+        simple(
+            &mut s,
+            "pbackup_since_timestamp_seconds",
+            "Since enregistre par la derniere execution reussie.",
+            since_reussi as f64,
+        );
+        let limite_utilisateur = rapports.iter().rev().find_map(|r| r.limite_utilisateur);
+        if let Some(limite) = limite_utilisateur {
+            simple(
+                &mut s,
+                "pbackup_ratelimit_user_remaining",
+                "Valeur de X-Limit-User-Remaining du dernier appel.",
+                limite as f64,
+            );
+        }
+        let limite_cle = rapports.iter().rev().find_map(|r| r.limite_cle);
+        if let Some(limite) = limite_cle {
+            simple(
+                &mut s,
+                "pbackup_ratelimit_key_remaining",
+                "Valeur de X-Limit-Key-Remaining du dernier appel.",
+                limite as f64,
+            );
+        }
+        s
+    }
+
+    /// Ecrit dans un fichier temporaire puis renomme, pour que le
+    /// collecteur ne lise jamais un fichier incomplet.
+    pub fn ecrire_metriques(fichier: &str, contenu: &str) -> std::io::Result<()> {
+        let temporaire = format!("{}.{}", fichier, std::process::id());
+        fs::write(&temporaire, contenu)?;
+        fs::rename(&temporaire, fichier)?;
+        log::info!("metriques ecrites dans {}", fichier);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::Local;
+
+        fn execution(erreurs: &[(&str, u64)]) -> RapportExecution {
+            let mut rapport = RapportTraitement {
+                nb_reessais: 2,
+                ..Default::default()
+            };
+            for (code, nb) in erreurs {
+                rapport.erreurs.insert(code.to_string(), *nb);
+            }
+            let maintenant = Local::now().fixed_offset();
+            RapportExecution::creer("miseAJour", maintenant, maintenant, 10, 20, vec![rapport])
+        }
+
+        #[test]
+        fn erreurs_http_et_autres_separees() {
+            let contenu = generer_metriques(&execution(&[("503", 1), ("requete", 3)]), 5);
+            assert!(contenu.contains("pbackup_retries 2\n"));
+            assert!(contenu.contains("pbackup_http_errors{status=\"503\"} 1\n"));
+            assert!(contenu.contains("pbackup_errors{erreur=\"requete\"} 3\n"));
+            assert!(!contenu.contains("status=\"requete\""));
+            // le since de la derniere execution reussie reste publie
+            assert!(contenu.contains("pbackup_since_timestamp_seconds 5\n"));
+            assert!(contenu.contains("pbackup_last_run_success 0\n"));
+        }
+
+        #[test]
+        fn since_de_l_execution_reussie() {
+            let contenu = generer_metriques(&execution(&[]), 20);
+            assert!(contenu.contains("pbackup_since_timestamp_seconds 20\n"));
+            assert!(contenu.contains("pbackup_last_run_success 1\n"));
+        }
+    }
+}
@@ -1,53 +1,211 @@
 pub mod mod_min_max {
     use std::fmt;
 
-    #[derive(Default, Debug, Clone, PartialEq)]
-    pub struct MinMax {
-        pub nb: i32,
-        pub min: i32,
-        pub max: i32,
-        pub last: i32,
-        pub ordre: bool,
+    /// Conversion pour les calculs de moyenne.
+    pub trait Numerique: Copy {
+        fn en_f64(self) -> f64;
     }
 
-    impl MinMax {
-        pub(crate) fn add(&mut self, valeur: i32) {
-            if self.nb == 0 {
-                self.min = valeur;
-                self.max = valeur;
-            } else {
-                if self.min > valeur {
-                    self.min = valeur;
-                }
-                if self.max < valeur {
-                    self.max = valeur;
+    macro_rules! impl_numerique {
+        ($($t:ty),*) => {
+            $(impl Numerique for $t {
+                fn en_f64(self) -> f64 {
+                    self as f64
                 }
+            })*
+        };
+    }
+
+    impl_numerique!(i32, i64, u32, u64, usize);
+
+    /// Accumulateur de statistiques : min, max, derniere valeur, nombre
+    /// d'inversions d'ordre (valeur plus petite que la precedente). Les
+    /// valeurs elles-memes ne sont gardees que par
+    /// `create_min_max_avec_valeurs`, pour la moyenne, les percentiles et
+    /// l'histogramme.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct MinMax<T: Ord + Copy> {
+        pub nb: u64,
+        pub min: Option<T>,
+        pub max: Option<T>,
+        pub last: Option<T>,
+        pub ordre: bool,
+        pub nb_inversions: u64,
+        premier: Option<T>,
+        valeurs: Option<Vec<T>>,
+    }
+
+    impl<T: Ord + Copy> Default for MinMax<T> {
+        fn default() -> Self {
+            MinMax {
+                nb: 0,
+                min: None,
+                max: None,
+                last: None,
+                ordre: true,
+                nb_inversions: 0,
+                premier: None,
+                valeurs: None,
+            }
+        }
+    }
+
+    impl<T: Ord + Copy> MinMax<T> {
+        pub(crate) fn add(&mut self, valeur: T) {
+            match self.min {
+                Some(min) if min <= valeur => {}
+                _ => self.min = Some(valeur),
+            }
+            match self.max {
+                Some(max) if max >= valeur => {}
+                _ => self.max = Some(valeur),
             }
             self.nb += 1;
-            if self.ordre {
-                if self.last > valeur {
+            if let Some(last) = self.last {
+                if last > valeur {
+                    self.nb_inversions += 1;
                     self.ordre = false;
                 }
             }
-            self.last = valeur;
+            self.premier.get_or_insert(valeur);
+            self.last = Some(valeur);
+            if let Some(valeurs) = self.valeurs.as_mut() {
+                valeurs.push(valeur);
+            }
+        }
+
+        /// Ajoute les valeurs de `autre` comme si elles avaient ete
+        /// ajoutees apres celles de `self`. Les valeurs ne restent gardees
+        /// que si les deux accumulateurs les gardent.
+        pub fn fusionner(&mut self, autre: &MinMax<T>) {
+            if autre.nb == 0 {
+                return;
+            }
+            if let (Some(last), Some(premier)) = (self.last, autre.premier) {
+                if last > premier {
+                    self.nb_inversions += 1;
+                }
+            }
+            self.nb_inversions += autre.nb_inversions;
+            self.ordre = self.nb_inversions == 0;
+            self.min = match (self.min, autre.min) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            self.max = match (self.max, autre.max) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+            self.nb += autre.nb;
+            self.premier = self.premier.or(autre.premier);
+            self.last = autre.last;
+            self.valeurs = match (self.valeurs.take(), &autre.valeurs) {
+                (Some(mut valeurs), Some(autres)) => {
+                    valeurs.extend_from_slice(autres);
+                    Some(valeurs)
+                }
+                _ => None,
+            };
+        }
+
+        /// Percentile par rang le plus proche, `p` entre 0 et 100 ; `None`
+        /// si les valeurs ne sont pas gardees.
+        pub fn percentile(&self, p: f64) -> Option<T> {
+            let valeurs = self.valeurs.as_ref().filter(|v| !v.is_empty())?;
+            let mut tri = valeurs.clone();
+            tri.sort();
+            let rang = ((p.clamp(0.0, 100.0) / 100.0) * tri.len() as f64).ceil() as usize;
+            Some(tri[rang.saturating_sub(1).min(tri.len() - 1)])
+        }
+
+        /// Histogramme a tranches fixes : `bornes` sont les bornes basses
+        /// des tranches, triees. Les valeurs inferieures a la premiere borne
+        /// sont comptees dans la premiere tranche.
+        pub fn histogramme(&self, bornes: &[T]) -> Vec<u64> {
+            let mut res = vec![0u64; bornes.len()];
+            if bornes.is_empty() {
+                return res;
+            }
+            for valeur in self.valeurs.iter().flatten() {
+                let i = bornes.partition_point(|b| b <= valeur).saturating_sub(1);
+                res[i] += 1;
+            }
+            res
         }
     }
 
+    impl<T: Ord + Numerique> MinMax<T> {
+        pub fn moyenne(&self) -> Option<f64> {
+            let valeurs = self.valeurs.as_ref().filter(|v| !v.is_empty())?;
+            let somme: f64 = valeurs.iter().map(|v| v.en_f64()).sum();
+            Some(somme / valeurs.len() as f64)
+        }
+    }
 
-    impl fmt::Display for MinMax {
+    impl<T: Ord + Copy + fmt::Display> fmt::Display for MinMax<T> {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "({},{},{})", self.min, self.max, self.ordre)
+            match (self.min, self.max) {
+                (Some(min), Some(max)) => {
+                    write!(f, "({},{},{},{})", min, max, self.ordre, self.nb_inversions)
+                }
+                _ => write!(f, "(-,-,{},{})", self.ordre, self.nb_inversions),
+            }
         }
     }
 
-    pub fn create_min_max() -> MinMax {
+    pub fn create_min_max<T: Ord + Copy>() -> MinMax<T> {
+        MinMax::default()
+    }
+
+    /// Accumulateur qui garde aussi les valeurs (statistiques de la
+    /// bibliotheque) ; le suivi des pages n'en a pas besoin.
+    pub fn create_min_max_avec_valeurs<T: Ord + Copy>() -> MinMax<T> {
         MinMax {
-            nb: 0,
-            min: 0,
-            max: 0,
-            last: 0,
-            ordre: true,
+            valeurs: Some(Vec::new()),
+            ..Default::default()
         }
     }
 
-}
\ No newline at end of file
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn defaut_identique_a_create_min_max() {
+            let defaut: MinMax<i64> = MinMax::default();
+            assert!(defaut.ordre);
+            assert_eq!(defaut, create_min_max());
+        }
+
+        #[test]
+        fn fusion_sans_valeurs_gardees() {
+            let mut a: MinMax<i64> = create_min_max();
+            a.add(1);
+            a.add(5);
+            let mut b: MinMax<i64> = create_min_max();
+            b.add(3);
+            b.add(4);
+            a.fusionner(&b);
+            assert_eq!((a.nb, a.min, a.max, a.last), (4, Some(1), Some(5), Some(4)));
+            assert_eq!(a.nb_inversions, 1);
+            assert!(!a.ordre);
+            assert_eq!(a.percentile(50.0), None);
+            assert_eq!(a.moyenne(), None);
+        }
+
+        #[test]
+        fn percentiles_et_histogramme_avec_valeurs() {
+            let mut a: MinMax<i64> = create_min_max_avec_valeurs();
+            for v in [10, 1, 7, 3] {
+                a.add(v);
+            }
+            let mut b: MinMax<i64> = create_min_max_avec_valeurs();
+            b.add(20);
+            a.fusionner(&b);
+            assert_eq!(a.percentile(50.0), Some(7));
+            assert_eq!(a.percentile(100.0), Some(20));
+            assert_eq!(a.histogramme(&[0, 5, 15]), vec![2, 2, 1]);
+            assert_eq!(a.moyenne(), Some(8.2));
+        }
+    }
+}
@@ -0,0 +1,535 @@
+pub mod mod_notes {
+    use crate::donnees::mod_donnees::{
+        champ_str, date, est_favori, statut, tags, titre, url, STATUT_ARCHIVE, STATUT_NON_LU,
+        STATUT_SUPPRIME,
+    };
+    use crate::export::mod_export::elements_tries;
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::collections::{BTreeMap, HashMap};
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    pub const FORMAT_MARKDOWN: &str = "markdown";
+    pub const FORMAT_ORG: &str = "org";
+
+    /// Tout ce qui suit cette ligne dans une note existante appartient a
+    /// l'utilisateur et est conserve lors d'un nouvel export.
+    const MARQUEUR_MARKDOWN: &str = "<!-- pbackup: notes personnelles ci-dessous -->";
+    const MARQUEUR_ORG: &str = "# pbackup: notes personnelles ci-dessous";
+
+    const LONGUEUR_SLUG_MAX: usize = 60;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum FormatNote {
+        Markdown,
+        Org,
+    }
+
+    impl FormatNote {
+        pub fn parse(format: &str) -> Option<FormatNote> {
+            match format {
+                FORMAT_MARKDOWN => Some(FormatNote::Markdown),
+                FORMAT_ORG => Some(FormatNote::Org),
+                _ => None,
+            }
+        }
+
+        fn extension(&self) -> &str {
+            match self {
+                FormatNote::Markdown => "md",
+                FormatNote::Org => "org",
+            }
+        }
+
+        fn marqueur(&self) -> &str {
+            match self {
+                FormatNote::Markdown => MARQUEUR_MARKDOWN,
+                FormatNote::Org => MARQUEUR_ORG,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Regroupement {
+        Element,
+        Mois,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanNotes {
+        pub nb_crees: u64,
+        pub nb_mis_a_jour: u64,
+        pub nb_inchanges: u64,
+        pub nb_renommes: u64,
+    }
+
+    /// Nom de fichier : minuscules ascii, le reste remplace par `-`.
+    pub fn slug(titre: &str) -> String {
+        let mut res = String::new();
+        for c in titre.chars() {
+            if c.is_ascii_alphanumeric() {
+                res.push(c.to_ascii_lowercase());
+            } else if !res.is_empty() && !res.ends_with('-') {
+                res.push('-');
+            }
+            if res.len() >= LONGUEUR_SLUG_MAX {
+                break;
+            }
+        }
+        res.trim_end_matches('-').to_string()
+    }
+
+    fn libelle_statut(element: &Value) -> &str {
+        match statut(element) {
+            STATUT_NON_LU => "unread",
+            STATUT_ARCHIVE => "archived",
+            STATUT_SUPPRIME => "deleted",
+            _ => "",
+        }
+    }
+
+    fn tags_tries(element: &Value) -> Vec<String> {
+        let mut tags = tags(element);
+        tags.sort();
+        tags
+    }
+
+    /// Passages surlignes (`annotations` avec `annotations = 1`).
+    fn surlignages(element: &Value) -> Vec<&str> {
+        match element.get("annotations").and_then(|a| a.as_array()) {
+            Some(annotations) => annotations
+                .iter()
+                .map(|a| champ_str(a, "quote"))
+                .filter(|q| !q.is_empty())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn date_jour(element: &Value, champ: &str) -> String {
+        date(element, champ)
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    }
+
+    fn date_org(element: &Value, champ: &str) -> String {
+        date(element, champ)
+            .map(|d| d.format("[%Y-%m-%d %a %H:%M]").to_string())
+            .unwrap_or_default()
+    }
+
+    fn chaine_yaml(s: &str) -> String {
+        format!(
+            "\"{}\"",
+            s.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace(['\n', '\r'], " ")
+        )
+    }
+
+    fn titre_ou_url(element: &Value) -> &str {
+        match titre(element) {
+            "" => url(element),
+            t => t,
+        }
+    }
+
+    fn front_matter_yaml(element: &Value) -> String {
+        let tags: Vec<String> = tags_tries(element).iter().map(|t| chaine_yaml(t)).collect();
+        let mut res = String::from("---\n");
+        res.push_str(format!("title: {}\n", chaine_yaml(titre_ou_url(element))).as_str());
+        res.push_str(format!("url: {}\n", chaine_yaml(url(element))).as_str());
+        res.push_str(format!("item_id: {}\n", chaine_yaml(champ_str(element, "item_id"))).as_str());
+        res.push_str(format!("tags: [{}]\n", tags.join(", ")).as_str());
+        res.push_str(format!("added: {}\n", date_jour(element, "time_added")).as_str());
+        res.push_str(format!("status: {}\n", libelle_statut(element)).as_str());
+        res.push_str(format!("favorite: {}\n", est_favori(element)).as_str());
+        res.push_str("---\n");
+        res
+    }
+
+    fn corps_markdown(element: &Value, niveau: &str) -> String {
+        let mut res = String::new();
+        let extrait = champ_str(element, "excerpt");
+        if !extrait.is_empty() {
+            res.push_str(format!("\n> {}\n", extrait.replace('\n', "\n> ")).as_str());
+        }
+        let surlignages = surlignages(element);
+        if !surlignages.is_empty() {
+            res.push_str(format!("\n{} Surlignages\n\n", niveau).as_str());
+            for quote in surlignages {
+                res.push_str(format!("- {}\n", quote.replace('\n', " ")).as_str());
+            }
+        }
+        res
+    }
+
+    fn note_markdown(element: &Value) -> String {
+        let mut res = front_matter_yaml(element);
+        res.push_str(
+            format!(
+                "\n# [{}]({})\n",
+                titre_ou_url(element).replace(['[', ']'], ""),
+                url(element)
+            )
+            .as_str(),
+        );
+        res.push_str(corps_markdown(element, "##").as_str());
+        res
+    }
+
+    fn proprietes_org(element: &Value) -> String {
+        let mut res = String::from(":PROPERTIES:\n");
+        res.push_str(format!(":ID:       pocket-{}\n", champ_str(element, "item_id")).as_str());
+        res.push_str(format!(":URL:      {}\n", url(element)).as_str());
+        res.push_str(format!(":ADDED:    {}\n", date_org(element, "time_added")).as_str());
+        res.push_str(format!(":STATUS:   {}\n", libelle_statut(element)).as_str());
+        res.push_str(format!(":FAVORITE: {}\n", est_favori(element)).as_str());
+        res.push_str(":END:\n");
+        res
+    }
+
+    /// Les tags org ne peuvent contenir ni espace ni `:`.
+    fn filetags_org(element: &Value) -> String {
+        let tags = tags_tries(element);
+        if tags.is_empty() {
+            "".to_string()
+        } else {
+            let tags: Vec<String> = tags.iter().map(|t| t.replace([' ', ':'], "_")).collect();
+            format!(":{}:", tags.join(":"))
+        }
+    }
+
+    fn corps_org(element: &Value, niveau: &str) -> String {
+        let mut res = String::new();
+        let extrait = champ_str(element, "excerpt");
+        if !extrait.is_empty() {
+            res.push_str(format!("\n#+begin_quote\n{}\n#+end_quote\n", extrait).as_str());
+        }
+        let surlignages = surlignages(element);
+        if !surlignages.is_empty() {
+            res.push_str(format!("\n{} Surlignages\n", niveau).as_str());
+            for quote in surlignages {
+                res.push_str(format!("- {}\n", quote.replace('\n', " ")).as_str());
+            }
+        }
+        res
+    }
+
+    fn note_org(element: &Value) -> String {
+        let mut res = proprietes_org(element);
+        res.push_str(format!("#+title: {}\n", titre_ou_url(element)).as_str());
+        res.push_str(format!("#+filetags: {}\n", filetags_org(element)).as_str());
+        res.push_str(format!("#+date: {}\n", date_org(element, "time_added")).as_str());
+        res.push_str(format!("\n[[{}][{}]]\n", url(element), titre_ou_url(element)).as_str());
+        res.push_str(corps_org(element, "*").as_str());
+        res
+    }
+
+    fn note_mois(mois: &str, elements: &[&Value], format: FormatNote) -> String {
+        let mut res = String::new();
+        match format {
+            FormatNote::Markdown => {
+                res.push_str(
+                    format!("---\nmonth: {}\nitems: {}\n---\n", mois, elements.len()).as_str(),
+                );
+                for element in elements {
+                    let tags: Vec<String> = tags_tries(element)
+                        .iter()
+                        .map(|t| format!("#{}", t.replace(' ', "-")))
+                        .collect();
+                    res.push_str(
+                        format!(
+                            "\n## [{}]({})\n\n",
+                            titre_ou_url(element).replace(['[', ']'], ""),
+                            url(element)
+                        )
+                        .as_str(),
+                    );
+                    res.push_str(
+                        format!(
+                            "- added: {}\n- status: {}\n- favorite: {}\n- tags: {}\n",
+                            date_jour(element, "time_added"),
+                            libelle_statut(element),
+                            est_favori(element),
+                            tags.join(" ")
+                        )
+                        .as_str(),
+                    );
+                    res.push_str(corps_markdown(element, "###").as_str());
+                }
+            }
+            FormatNote::Org => {
+                res.push_str(format!("#+title: {}\n", mois).as_str());
+                for element in elements {
+                    res.push_str(
+                        format!(
+                            "\n* [[{}][{}]] {}\n",
+                            url(element),
+                            titre_ou_url(element),
+                            filetags_org(element)
+                        )
+                        .as_str(),
+                    );
+                    res.push_str(proprietes_org(element).as_str());
+                    res.push_str(corps_org(element, "**").as_str());
+                }
+            }
+        }
+        res
+    }
+
+    /// Ecrit la partie generee, en gardant ce que l'utilisateur a ajoute
+    /// apres le marqueur. Le fichier n'est pas reecrit s'il est identique.
+    fn ecrire_note(
+        fichier: &Path,
+        ancien: Option<&PathBuf>,
+        genere: &str,
+        format: FormatNote,
+        bilan: &mut BilanNotes,
+    ) -> io::Result<()> {
+        let source = ancien.map(|p| p.as_path()).unwrap_or(fichier);
+        let existant = if source.exists() {
+            Some(fs::read_to_string(source)?)
+        } else {
+            None
+        };
+        let personnel = existant
+            .as_deref()
+            .and_then(|c| c.split_once(format.marqueur()))
+            .map(|(_, fin)| fin.to_string())
+            .unwrap_or("\n".to_string());
+        let contenu = format!("{}\n{}{}", genere, format.marqueur(), personnel);
+        if source != fichier {
+            fs::rename(source, fichier)?;
+            bilan.nb_renommes += 1;
+        }
+        match existant {
+            Some(c) if c == contenu => bilan.nb_inchanges += 1,
+            Some(_) => {
+                fs::write(fichier, contenu)?;
+                bilan.nb_mis_a_jour += 1;
+            }
+            None => {
+                fs::write(fichier, contenu)?;
+                bilan.nb_crees += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Nom des notes mensuelles des elements sans date d'ajout.
+    const MOIS_SANS_DATE: &str = "sans-date";
+
+    /// `aaaa-mm` ou `sans-date` : note mensuelle, qui n'appartient a aucun
+    /// element.
+    fn est_note_mois(nom: &str) -> bool {
+        let chiffres = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        nom == MOIS_SANS_DATE
+            || matches!(nom.split_once('-'), Some((a, m)) if a.len() == 4 && m.len() == 2
+                && chiffres(a) && chiffres(m))
+    }
+
+    /// Identifiant d'une note par element, nommee `<item_id>-<slug>` ou
+    /// `<item_id>` quand le titre ne donne pas de slug.
+    fn id_note(nom: &str) -> Option<&str> {
+        if est_note_mois(nom) {
+            return None;
+        }
+        let id = nom.split_once('-').map_or(nom, |(id, _)| id);
+        if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// Notes par element existantes, par item_id.
+    fn notes_existantes(dossier: &Path, extension: &str) -> io::Result<HashMap<String, PathBuf>> {
+        let mut res = HashMap::new();
+        for entree in fs::read_dir(dossier)? {
+            let chemin = entree?.path();
+            if chemin.extension().and_then(|e| e.to_str()) != Some(extension) {
+                continue;
+            }
+            if let Some(id) = chemin
+                .file_stem()
+                .and_then(|n| n.to_str())
+                .and_then(id_note)
+            {
+                res.insert(id.to_string(), chemin.clone());
+            }
+        }
+        Ok(res)
+    }
+
+    /// Une note par element (`<item_id>-<titre>.md`, l'identifiant garantit
+    /// l'unicite et permet de retrouver la note si le titre change) ou une
+    /// note par mois d'ajout (`<aaaa-mm>.md`).
+    pub fn export_notes(
+        data: &Value,
+        dossier: &str,
+        format: FormatNote,
+        regroupement: Regroupement,
+    ) -> io::Result<BilanNotes> {
+        let dossier = Path::new(dossier);
+        fs::create_dir_all(dossier)?;
+        let mut bilan = BilanNotes::default();
+        let elements = elements_tries(data);
+        match regroupement {
+            Regroupement::Element => {
+                let existantes = notes_existantes(dossier, format.extension())?;
+                for (id, element) in elements {
+                    let nom = match slug(titre_ou_url(element)).as_str() {
+                        "" => format!("{}.{}", id, format.extension()),
+                        s => format!("{}-{}.{}", id, s, format.extension()),
+                    };
+                    let genere = match format {
+                        FormatNote::Markdown => note_markdown(element),
+                        FormatNote::Org => note_org(element),
+                    };
+                    ecrire_note(
+                        &dossier.join(nom),
+                        existantes.get(id),
+                        genere.as_str(),
+                        format,
+                        &mut bilan,
+                    )?;
+                }
+            }
+            Regroupement::Mois => {
+                let mut par_mois: BTreeMap<String, Vec<&Value>> = BTreeMap::new();
+                for (_, element) in elements {
+                    let mois = date(element, "time_added")
+                        .map(|d| d.format("%Y-%m").to_string())
+                        .unwrap_or(MOIS_SANS_DATE.to_string());
+                    par_mois.entry(mois).or_default().push(element);
+                }
+                for (mois, elements) in par_mois {
+                    let nom = format!("{}.{}", mois, format.extension());
+                    let genere = note_mois(mois.as_str(), &elements, format);
+                    ecrire_note(
+                        &dossier.join(nom),
+                        None,
+                        genere.as_str(),
+                        format,
+                        &mut bilan,
+                    )?;
+                }
+            }
+        }
+        log::info!(
+            "notes : {} creees, {} mises a jour, {} inchangees, {} renommees",
+            bilan.nb_crees,
+            bilan.nb_mis_a_jour,
+            bilan.nb_inchanges,
+            bilan.nb_renommes
+        );
+        Ok(bilan)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        fn dossier(nom: &str) -> PathBuf {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            dossier
+        }
+
+        fn donnees(titre: &str) -> Value {
+            json!({"liste": {"2024": {
+                "item_id": "2024", "given_url": "https://a.example/",
+                "resolved_title": titre, "time_added": "1709380800", "status": "0"
+            }}})
+        }
+
+        #[test]
+        fn notes_mensuelles_ignorees() {
+            assert_eq!(id_note("2024-03"), None);
+            assert_eq!(id_note("sans-date"), None);
+            assert_eq!(id_note("2024-03-titre"), Some("2024"));
+            assert_eq!(id_note("2024"), Some("2024"));
+            assert_eq!(id_note("a_b-titre"), None);
+        }
+
+        #[test]
+        fn notes_personnelles_conservees_et_note_renommee() {
+            let dossier = dossier("notes_element");
+            let chemin = dossier.to_str().unwrap();
+            // note mensuelle d'un export precedent, a ne pas confondre avec l'element 2024
+            let mensuelle = dossier.join("2024-03.md");
+            fs::write(&mensuelle, "mois\n").unwrap();
+
+            let bilan = export_notes(
+                &donnees("Premier titre"),
+                chemin,
+                FormatNote::Markdown,
+                Regroupement::Element,
+            )
+            .unwrap();
+            assert_eq!((bilan.nb_crees, bilan.nb_renommes), (1, 0));
+            let premiere = dossier.join("2024-premier-titre.md");
+            let contenu = fs::read_to_string(&premiere).unwrap();
+            assert!(contenu.ends_with(&format!("{}\n", MARQUEUR_MARKDOWN)));
+            fs::write(&premiere, format!("{}\nmes notes\n", contenu)).unwrap();
+
+            let bilan = export_notes(
+                &donnees("Autre titre"),
+                chemin,
+                FormatNote::Markdown,
+                Regroupement::Element,
+            )
+            .unwrap();
+            assert_eq!((bilan.nb_renommes, bilan.nb_mis_a_jour), (1, 1));
+            assert!(!premiere.exists());
+            let contenu = fs::read_to_string(dossier.join("2024-autre-titre.md")).unwrap();
+            assert!(contenu.contains("# [Autre titre](https://a.example/)"));
+            assert!(contenu.ends_with(&format!("{}\n\nmes notes\n", MARQUEUR_MARKDOWN)));
+            assert_eq!(fs::read_to_string(&mensuelle).unwrap(), "mois\n");
+
+            let bilan = export_notes(
+                &donnees("Autre titre"),
+                chemin,
+                FormatNote::Markdown,
+                Regroupement::Element,
+            )
+            .unwrap();
+            assert_eq!(bilan.nb_inchanges, 1);
+            let _ = fs::remove_dir_all(&dossier);
+        }
+
+        #[test]
+        fn notes_mensuelles_org() {
+            let dossier = dossier("notes_mois");
+            let chemin = dossier.to_str().unwrap();
+            export_notes(
+                &donnees("Titre"),
+                chemin,
+                FormatNote::Org,
+                Regroupement::Mois,
+            )
+            .unwrap();
+            let fichier = dossier.join("2024-03.org");
+            let contenu = fs::read_to_string(&fichier).unwrap();
+            fs::write(&fichier, format!("{}perso\n", contenu)).unwrap();
+            export_notes(
+                &donnees("Titre"),
+                chemin,
+                FormatNote::Org,
+                Regroupement::Mois,
+            )
+            .unwrap();
+            let contenu = fs::read_to_string(&fichier).unwrap();
+            assert!(contenu.ends_with(&format!("{}\nperso\n", MARQUEUR_ORG)));
+            let _ = fs::remove_dir_all(&dossier);
+        }
+    }
+}
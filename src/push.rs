@@ -0,0 +1,505 @@
+pub mod mod_push {
+    use crate::api::mod_api::ErreurApi;
+    use crate::config::mod_config::{ConfigPush, ConfigSource};
+    use crate::donnees::mod_donnees::{champ_nombre, statut, url, STATUT_SUPPRIME};
+    use crate::export::mod_export::elements_tries;
+    use crate::linkding::mod_linkding::SourceLinkding;
+    use crate::source::mod_source::{SourceProvider, SOURCE_LINKDING, SOURCE_WALLABAG};
+    use crate::wallabag::mod_wallabag::SourceWallabag;
+    use chrono::Local;
+    use reqwest::{Client, StatusCode};
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::fs;
+    use std::time::Duration;
+
+    /// Instance qui recoit les elements.
+    pub enum Cible {
+        Wallabag(SourceWallabag),
+        Linkding(SourceLinkding),
+    }
+
+    impl Cible {
+        pub fn creer(config: &ConfigSource) -> Option<Cible> {
+            match config.type_source.as_str() {
+                SOURCE_WALLABAG => Some(Cible::Wallabag(SourceWallabag::new(config, 30))),
+                SOURCE_LINKDING => Some(Cible::Linkding(SourceLinkding::new(config, 30))),
+                autre => {
+                    log::error!("cible de push inconnue : '{}'", autre);
+                    None
+                }
+            }
+        }
+
+        pub fn nom(&self) -> &'static str {
+            match self {
+                Cible::Wallabag(c) => c.nom(),
+                Cible::Linkding(c) => c.nom(),
+            }
+        }
+
+        async fn authentifier(&mut self, client: &Client) -> Result<(), ErreurApi> {
+            match self {
+                Cible::Wallabag(c) => c.authentifier(client).await,
+                Cible::Linkding(c) => c.authentifier(client).await,
+            }
+        }
+
+        async fn rechercher(
+            &mut self,
+            client: &Client,
+            url: &str,
+        ) -> Result<Option<String>, ErreurApi> {
+            match self {
+                Cible::Wallabag(c) => c.rechercher(client, url).await,
+                Cible::Linkding(c) => c.rechercher(client, url).await,
+            }
+        }
+
+        async fn ajouter(&mut self, client: &Client, element: &Value) -> Result<String, ErreurApi> {
+            match self {
+                Cible::Wallabag(c) => c.ajouter(client, element).await,
+                Cible::Linkding(c) => c.ajouter(client, element).await,
+            }
+        }
+
+        async fn modifier(
+            &mut self,
+            client: &Client,
+            id: &str,
+            element: &Value,
+        ) -> Result<(), ErreurApi> {
+            match self {
+                Cible::Wallabag(c) => c.modifier(client, id, element).await,
+                Cible::Linkding(c) => c.modifier(client, id, element).await,
+            }
+        }
+
+        /// Cree l'element, ou le met a jour s'il est deja connu (envoi
+        /// precedent ou URL deja presente sur l'instance). Un element
+        /// envoye puis supprime de l'instance (404) est recherche par son
+        /// URL ou recree.
+        async fn envoyer(
+            &mut self,
+            client: &Client,
+            id: Option<&String>,
+            element: &Value,
+        ) -> Result<(String, bool), ErreurApi> {
+            if let Some(id) = id {
+                match self.modifier(client, id, element).await {
+                    Err(ErreurApi::Statut(StatusCode::NOT_FOUND)) => {
+                        log::warn!("{} absent de la cible, nouvelle recherche", id)
+                    }
+                    res => return res.map(|_| (id.clone(), false)),
+                }
+            }
+            match self.rechercher(client, url(element)).await? {
+                Some(id) => {
+                    self.modifier(client, &id, element).await?;
+                    Ok((id, false))
+                }
+                None => Ok((self.ajouter(client, element).await?, true)),
+            }
+        }
+    }
+
+    /// Element deja envoye : identifiant sur la cible et `time_updated`
+    /// local au moment de l'envoi.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ElementPousse {
+        pub id: String,
+        pub time_updated: i64,
+        pub date: i64,
+    }
+
+    pub fn fichier_correspondances(repertoire: &str, cible: &str) -> String {
+        format!("{}/push_{}.json", repertoire, cible)
+    }
+
+    pub fn charger_correspondances(fichier: &str) -> BTreeMap<String, ElementPousse> {
+        match fs::read_to_string(fichier) {
+            Ok(contenu) => serde_json::from_str(&contenu).unwrap_or_else(|err| {
+                log::error!("fichier {} invalide : {}", fichier, err);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        }
+    }
+
+    fn sauver_correspondances(fichier: &str, correspondances: &BTreeMap<String, ElementPousse>) {
+        let tmp = format!("{}.tmp", fichier);
+        let contenu = serde_json::to_string(correspondances).expect("Erreur de sérialisation");
+        if let Err(err) = fs::write(&tmp, contenu).and_then(|_| fs::rename(&tmp, fichier)) {
+            log::error!("Erreur d'ecriture de {} : {}", fichier, err);
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ErreurPush {
+        pub item_id: String,
+        pub url: String,
+        pub erreur: String,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanPush {
+        pub cible: String,
+        pub dry_run: bool,
+        pub nb_ajoutes: u64,
+        pub nb_mis_a_jour: u64,
+        /// deja envoyes et non modifies depuis
+        pub nb_inchanges: u64,
+        pub nb_supprimes_ignores: u64,
+        pub erreurs: Vec<ErreurPush>,
+    }
+
+    impl fmt::Display for BilanPush {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let suffixe = if self.dry_run { " (dry-run)" } else { "" };
+            writeln!(f, "cible : {}{}", self.cible, suffixe)?;
+            writeln!(f, "ajoutes : {}", self.nb_ajoutes)?;
+            writeln!(f, "mis a jour : {}", self.nb_mis_a_jour)?;
+            writeln!(f, "inchanges : {}", self.nb_inchanges)?;
+            writeln!(f, "supprimes ignores : {}", self.nb_supprimes_ignores)?;
+            writeln!(f, "erreurs : {}", self.erreurs.len())?;
+            for erreur in &self.erreurs {
+                writeln!(f, "  {} {} : {}", erreur.item_id, erreur.url, erreur.erreur)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Attente avant un nouvel essai apres un 429 : le `Retry-After` de la
+    /// reponse, sinon `attente_429_ms` doublee a chaque essai.
+    fn attente_429(config: &ConfigPush, retry_after: Option<u64>, tentative: u32) -> u64 {
+        match retry_after {
+            Some(secondes) => secondes.saturating_mul(1000),
+            None => config
+                .attente_429_ms
+                .saturating_mul(1u64 << tentative.min(16)),
+        }
+    }
+
+    /// Envoie les elements (les plus anciens d'abord) par lots de
+    /// `taille_lot`, avec `delai_ms` entre deux requetes. Les elements deja
+    /// envoyes et non modifies sont ignores ; la correspondance est sauvee
+    /// apres chaque lot, un nouveau lancement reprend la ou il s'est
+    /// arrete. `limite` borne le nombre d'envois.
+    pub async fn pousser(
+        config: &ConfigPush,
+        repertoire: &str,
+        data: &Value,
+        dry_run: bool,
+        limite: Option<usize>,
+    ) -> Option<BilanPush> {
+        let mut cible = Cible::creer(&config.cible)?;
+        let fichier = fichier_correspondances(repertoire, cible.nom());
+        let mut correspondances = charger_correspondances(fichier.as_str());
+        let mut bilan = BilanPush {
+            cible: cible.nom().to_string(),
+            dry_run,
+            ..Default::default()
+        };
+        let mut a_envoyer: Vec<(&String, &Value)> = Vec::new();
+        for (id, element) in elements_tries(data) {
+            if statut(element) == STATUT_SUPPRIME {
+                bilan.nb_supprimes_ignores += 1;
+                continue;
+            }
+            match correspondances.get(id) {
+                Some(pousse) if pousse.time_updated >= champ_nombre(element, "time_updated") => {
+                    bilan.nb_inchanges += 1
+                }
+                _ => a_envoyer.push((id, element)),
+            }
+        }
+        if let Some(limite) = limite {
+            a_envoyer.truncate(limite);
+        }
+        log::info!(
+            "push {} : {} elements a envoyer",
+            bilan.cible,
+            a_envoyer.len()
+        );
+        if dry_run {
+            for (id, _) in &a_envoyer {
+                if correspondances.contains_key(*id) {
+                    bilan.nb_mis_a_jour += 1;
+                } else {
+                    bilan.nb_ajoutes += 1;
+                }
+            }
+            return Some(bilan);
+        }
+        let client = Client::new();
+        if let Err(err) = cible.authentifier(&client).await {
+            log::error!("{}", err);
+            bilan.erreurs.push(ErreurPush {
+                item_id: "".to_string(),
+                url: config.cible.url.clone(),
+                erreur: err.to_string(),
+            });
+            return Some(bilan);
+        }
+        for lot in a_envoyer.chunks(config.taille_lot.max(1)) {
+            for (id, element) in lot {
+                let mut tentative = 0;
+                let res = loop {
+                    let res = cible
+                        .envoyer(&client, correspondances.get(*id).map(|p| &p.id), element)
+                        .await;
+                    match res {
+                        Err(ErreurApi::Limite(retry_after)) if tentative < config.tentatives => {
+                            let attente = attente_429(config, retry_after, tentative);
+                            tentative += 1;
+                            log::warn!("limite atteinte, nouvel essai dans {} ms", attente);
+                            tokio::time::sleep(Duration::from_millis(attente)).await;
+                        }
+                        res => break res,
+                    }
+                };
+                match res {
+                    Ok((id_cible, ajout)) => {
+                        if ajout {
+                            bilan.nb_ajoutes += 1;
+                        } else {
+                            bilan.nb_mis_a_jour += 1;
+                        }
+                        correspondances.insert(
+                            id.to_string(),
+                            ElementPousse {
+                                id: id_cible,
+                                time_updated: champ_nombre(element, "time_updated"),
+                                date: Local::now().timestamp(),
+                            },
+                        );
+                    }
+                    Err(err) => {
+                        log::error!("push de {} : {}", id, err);
+                        bilan.erreurs.push(ErreurPush {
+                            item_id: id.to_string(),
+                            url: url(element).to_string(),
+                            erreur: err.to_string(),
+                        });
+                    }
+                }
+                if config.delai_ms > 0 {
+                    tokio::time::sleep(Duration::from_millis(config.delai_ms)).await;
+                }
+            }
+            sauver_correspondances(fichier.as_str(), &correspondances);
+            log::info!(
+                "push {} : {} ajoutes, {} mis a jour, {} erreurs",
+                bilan.cible,
+                bilan.nb_ajoutes,
+                bilan.nb_mis_a_jour,
+                bilan.erreurs.len()
+            );
+        }
+        Some(bilan)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::bouchon::mod_bouchon::Bouchon;
+        use serde_json::json;
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+
+        fn repertoire(nom: &str) -> String {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            dossier.to_string_lossy().to_string()
+        }
+
+        fn config_linkding(url: &str) -> ConfigPush {
+            ConfigPush {
+                cible: ConfigSource {
+                    type_source: SOURCE_LINKDING.to_string(),
+                    url: url.to_string(),
+                    jeton: "jeton".to_string(),
+                    ..Default::default()
+                },
+                delai_ms: 0,
+                ..Default::default()
+            }
+        }
+
+        fn donnees(time_updated: i64) -> Value {
+            json!({"liste": {
+                "1": {"item_id": "1", "given_url": "https://a.example/", "given_title": "A",
+                      "time_added": "100", "time_updated": time_updated.to_string(),
+                      "status": "0"},
+                "2": {"item_id": "2", "given_url": "https://b.example/", "time_added": "200",
+                      "status": "2"}
+            }})
+        }
+
+        /// Linkding sans favori existant : creation avec l'id 10.
+        fn linkding_vide() -> Bouchon {
+            Bouchon::demarrer(|requete| {
+                match (requete.methode.as_str(), requete.chemin().as_str()) {
+                    ("GET", "/api/bookmarks/check/") => {
+                        (200, json!({"bookmark": null}).to_string())
+                    }
+                    ("POST", "/api/bookmarks/") => (201, json!({"id": 10}).to_string()),
+                    ("PATCH", "/api/bookmarks/10/") => (200, json!({"id": 10}).to_string()),
+                    _ => (404, "{}".to_string()),
+                }
+            })
+        }
+
+        #[tokio::test]
+        async fn creation_puis_mise_a_jour() {
+            let bouchon = linkding_vide();
+            let repertoire = repertoire("push_creation");
+            let config = config_linkding(&bouchon.adresse);
+
+            let bilan = pousser(&config, &repertoire, &donnees(150), false, None)
+                .await
+                .unwrap();
+            assert_eq!((bilan.nb_ajoutes, bilan.nb_supprimes_ignores), (1, 1));
+            assert!(bilan.erreurs.is_empty());
+            let fichier = fichier_correspondances(&repertoire, SOURCE_LINKDING);
+            let correspondances = charger_correspondances(&fichier);
+            assert_eq!(correspondances["1"].id, "10");
+            assert_eq!(correspondances["1"].time_updated, 150);
+            let requetes = bouchon.requetes();
+            assert_eq!(requetes.len(), 2);
+            assert_eq!(
+                requetes[0].parametre("url").as_deref(),
+                Some("https://a.example/")
+            );
+            assert_eq!(requetes[1].autorisation, "Token jeton");
+            let corps: Value = serde_json::from_str(&requetes[1].corps).unwrap();
+            assert_eq!(corps["url"], "https://a.example/");
+            assert_eq!(corps["title"], "A");
+
+            // rien de modifie : aucun appel
+            let bilan = pousser(&config, &repertoire, &donnees(150), false, None)
+                .await
+                .unwrap();
+            assert_eq!((bilan.nb_inchanges, bilan.nb_ajoutes), (1, 0));
+            assert_eq!(bouchon.requetes().len(), 2);
+
+            let bilan = pousser(&config, &repertoire, &donnees(300), false, None)
+                .await
+                .unwrap();
+            assert_eq!(bilan.nb_mis_a_jour, 1);
+            let requetes = bouchon.requetes();
+            assert_eq!(requetes.len(), 3);
+            assert_eq!(requetes[2].methode, "PATCH");
+            assert_eq!(charger_correspondances(&fichier)["1"].time_updated, 300);
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+
+        #[tokio::test]
+        async fn element_supprime_de_la_cible_recree() {
+            let bouchon = linkding_vide();
+            let repertoire = repertoire("push_supprime");
+            let fichier = fichier_correspondances(&repertoire, SOURCE_LINKDING);
+            let mut correspondances = BTreeMap::new();
+            correspondances.insert(
+                "1".to_string(),
+                ElementPousse {
+                    id: "99".to_string(),
+                    time_updated: 100,
+                    date: 0,
+                },
+            );
+            sauver_correspondances(&fichier, &correspondances);
+
+            let config = config_linkding(&bouchon.adresse);
+            let bilan = pousser(&config, &repertoire, &donnees(150), false, None)
+                .await
+                .unwrap();
+            assert!(bilan.erreurs.is_empty());
+            assert_eq!(bilan.nb_ajoutes, 1);
+            assert_eq!(charger_correspondances(&fichier)["1"].id, "10");
+            let chemins: Vec<String> = bouchon
+                .requetes()
+                .iter()
+                .map(|r| format!("{} {}", r.methode, r.chemin()))
+                .collect();
+            assert_eq!(
+                chemins,
+                [
+                    "PATCH /api/bookmarks/99/",
+                    "GET /api/bookmarks/check/",
+                    "POST /api/bookmarks/"
+                ]
+            );
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+
+        #[tokio::test]
+        async fn dry_run_sans_appel() {
+            let bouchon = linkding_vide();
+            let repertoire = repertoire("push_dry_run");
+            let config = config_linkding(&bouchon.adresse);
+            let bilan = pousser(&config, &repertoire, &donnees(150), true, None)
+                .await
+                .unwrap();
+            assert!(bilan.dry_run);
+            assert_eq!((bilan.nb_ajoutes, bilan.nb_supprimes_ignores), (1, 1));
+            assert!(bouchon.requetes().is_empty());
+            let fichier = fichier_correspondances(&repertoire, SOURCE_LINKDING);
+            assert!(charger_correspondances(&fichier).is_empty());
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+
+        #[tokio::test]
+        async fn limite_429_attend_retry_after() {
+            let nb_posts = Arc::new(AtomicU32::new(0));
+            let compteur = nb_posts.clone();
+            let bouchon = Bouchon::demarrer_avec_entetes(move |requete| {
+                match (requete.methode.as_str(), requete.chemin().as_str()) {
+                    ("GET", "/api/bookmarks/check/") => {
+                        (200, json!({"bookmark": null}).to_string(), Vec::new())
+                    }
+                    ("POST", "/api/bookmarks/") => {
+                        if compteur.fetch_add(1, Ordering::SeqCst) == 0 {
+                            (
+                                429,
+                                "{}".to_string(),
+                                vec![("Retry-After", "0".to_string())],
+                            )
+                        } else {
+                            (201, json!({"id": 10}).to_string(), Vec::new())
+                        }
+                    }
+                    _ => (404, "{}".to_string(), Vec::new()),
+                }
+            });
+            let repertoire = repertoire("push_429");
+            // sans Retry-After, le test attendrait une minute
+            let config = config_linkding(&bouchon.adresse);
+            let bilan = pousser(&config, &repertoire, &donnees(150), false, None)
+                .await
+                .unwrap();
+            assert!(bilan.erreurs.is_empty());
+            assert_eq!(bilan.nb_ajoutes, 1);
+            assert_eq!(nb_posts.load(Ordering::SeqCst), 2);
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+
+        #[test]
+        fn attente_exponentielle_sans_retry_after() {
+            let config = ConfigPush {
+                attente_429_ms: 1000,
+                ..Default::default()
+            };
+            assert_eq!(attente_429(&config, None, 0), 1000);
+            assert_eq!(attente_429(&config, None, 2), 4000);
+            assert_eq!(attente_429(&config, Some(7), 2), 7000);
+        }
+    }
+}
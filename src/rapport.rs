@@ -0,0 +1,151 @@
+pub mod mod_rapport {
+    use chrono::{DateTime, FixedOffset};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use std::fmt;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum TypeAnomalie {
+        /// date plus ancienne que la precedente (dans une page ou entre deux pages)
+        Ordre,
+        /// element deja recu a une autre position pendant l'execution
+        Doublon,
+        /// le total annonce par le serveur diminue entre deux pages
+        TotalEnBaisse,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Anomalie {
+        pub type_anomalie: TypeAnomalie,
+        pub offset: u64,
+        pub since: Option<u64>,
+        pub detail: String,
+        pub rechargement: bool,
+    }
+
+    /// Resultat d'un appel a `traitement`.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct RapportTraitement {
+        pub since: Option<u64>,
+        pub nb_pages: u64,
+        pub nb_ajout: u64,
+        pub nb_modifie: u64,
+        pub nb_rechargements: u64,
+        /// appels recommences apres une erreur temporaire
+        #[serde(default)]
+        pub nb_reessais: u64,
+        pub anomalies: Vec<Anomalie>,
+        /// nombre d'erreurs par code HTTP (ou type d'erreur)
+        pub erreurs: BTreeMap<String, u64>,
+        pub limite_utilisateur: Option<i64>,
+        pub limite_cle: Option<i64>,
+        /// taille de la liste a la fin du traitement
+        pub nb_total: u64,
+        pub duree_ms: i64,
+    }
+
+    impl RapportTraitement {
+        pub fn est_en_erreur(&self) -> bool {
+            !self.erreurs.is_empty()
+        }
+    }
+
+    /// Une execution du programme (enregistree dans `runs.jsonl`).
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct RapportExecution {
+        pub debut: DateTime<FixedOffset>,
+        pub fin: DateTime<FixedOffset>,
+        pub duree_ms: i64,
+        /// miseAJour, datesContinues, listeDates, fileRechargement
+        pub mode: String,
+        pub succes: bool,
+        pub nb_ajout: u64,
+        pub nb_modifie: u64,
+        pub nb_total: u64,
+        pub nb_anomalies: u64,
+        pub erreurs: BTreeMap<String, u64>,
+        pub since_avant: u64,
+        pub since_apres: u64,
+        pub traitements: Vec<RapportTraitement>,
+    }
+
+    impl RapportExecution {
+        pub fn creer(
+            mode: &str,
+            debut: DateTime<FixedOffset>,
+            fin: DateTime<FixedOffset>,
+            since_avant: u64,
+            since_apres: u64,
+            traitements: Vec<RapportTraitement>,
+        ) -> RapportExecution {
+            let mut erreurs: BTreeMap<String, u64> = BTreeMap::new();
+            for rapport in &traitements {
+                for (code, nb) in &rapport.erreurs {
+                    *erreurs.entry(code.clone()).or_insert(0) += nb;
+                }
+            }
+            RapportExecution {
+                debut,
+                fin,
+                duree_ms: (fin - debut).num_milliseconds(),
+                mode: mode.to_string(),
+                succes: erreurs.is_empty(),
+                nb_ajout: traitements.iter().map(|r| r.nb_ajout).sum(),
+                nb_modifie: traitements.iter().map(|r| r.nb_modifie).sum(),
+                nb_total: traitements.last().map_or(0, |r| r.nb_total),
+                nb_anomalies: traitements.iter().map(|r| r.anomalies.len() as u64).sum(),
+                erreurs,
+                since_avant,
+                since_apres,
+                traitements,
+            }
+        }
+    }
+
+    impl fmt::Display for RapportExecution {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "{} | {:<16} | {:<5} | ajout: {:>5} | modifie: {:>5} | anomalies: {:>3} | erreurs: {:?} | duree: {} s",
+                self.debut.format("%Y-%m-%d %H:%M:%S"),
+                self.mode,
+                if self.succes { "ok" } else { "echec" },
+                self.nb_ajout,
+                self.nb_modifie,
+                self.nb_anomalies,
+                self.erreurs,
+                self.duree_ms / 1000
+            )
+        }
+    }
+
+    impl fmt::Display for Anomalie {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "{:?} offset: {}, since: {:?}, rechargement: {}, {}",
+                self.type_anomalie, self.offset, self.since, self.rechargement, self.detail
+            )
+        }
+    }
+
+    impl fmt::Display for RapportTraitement {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "since: {:?}, pages: {}, ajout: {}, modifie: {}, rechargements: {}, anomalies: {}, erreurs: {:?}",
+                self.since,
+                self.nb_pages,
+                self.nb_ajout,
+                self.nb_modifie,
+                self.nb_rechargements,
+                self.anomalies.len(),
+                self.erreurs
+            )
+        }
+    }
+}
@@ -0,0 +1,1150 @@
+pub mod mod_recherche {
+    use crate::archive::mod_archive::{charger_etat, repertoire_archive, ARCHIVE_OK};
+    use crate::config::mod_config::ConfigRecherche;
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, domaine, est_favori, fichier_data, liste, statut, tags, titre, url,
+    };
+    use crate::extraction::mod_extraction::repertoire_articles;
+    use crate::filtre::mod_filtre::{code_statut, oui_non, periode};
+    use chrono::DateTime;
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use sha2::{Digest, Sha256};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use std::fmt;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::time::UNIX_EPOCH;
+
+    pub const REPERTOIRE_RECHERCHE: &str = "recherche";
+    const VERSION_INDEX: u32 = 1;
+
+    pub const CHAMP_TITRE: &str = "titre";
+    pub const CHAMP_EXTRAIT: &str = "extrait";
+    pub const CHAMP_URL: &str = "url";
+    pub const CHAMP_TAGS: &str = "tags";
+    pub const CHAMP_TEXTE: &str = "texte";
+
+    /// Champs indexes et leur poids dans le score.
+    const CHAMPS: [(&str, f64); 5] = [
+        (CHAMP_TITRE, 3.0),
+        (CHAMP_TAGS, 2.5),
+        (CHAMP_EXTRAIT, 1.5),
+        (CHAMP_URL, 1.0),
+        (CHAMP_TEXTE, 1.0),
+    ];
+
+    /// Parametres BM25.
+    const K1: f64 = 1.2;
+    const B: f64 = 0.75;
+
+    /// Mots plus longs ignores (identifiants, donnees encodees).
+    const LONGUEUR_MOT_MAX: usize = 64;
+    /// Nombre maximum de termes pour un prefixe (`rust*`).
+    const NB_TERMES_PREFIXE_MAX: usize = 100;
+
+    /// Element tel qu'il est indexe ; suffisant pour afficher un resultat
+    /// sans relire `data.json`.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Document {
+        pub titre: String,
+        pub url: String,
+        pub domaine: String,
+        pub tags: Vec<String>,
+        pub statut: String,
+        pub favori: bool,
+        pub time_added: i64,
+        pub time_updated: i64,
+        /// nombre de mots par champ
+        pub longueurs: BTreeMap<String, u32>,
+        /// `time_updated` et hash de l'article indexe : le document est
+        /// reindexe quand elle change
+        pub empreinte: String,
+    }
+
+    /// Index inverse : terme -> item_id -> champ -> positions.
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Index {
+        pub version: u32,
+        /// date de modification (ms) de `data.json` et de l'etat de
+        /// l'archive a la derniere mise a jour
+        pub date_sources: i64,
+        pub documents: BTreeMap<String, Document>,
+        pub termes: BTreeMap<String, BTreeMap<String, BTreeMap<String, Vec<u32>>>>,
+    }
+
+    pub fn repertoire_recherche(repertoire: &str) -> PathBuf {
+        PathBuf::from(repertoire).join(REPERTOIRE_RECHERCHE)
+    }
+
+    fn fichier_index(repertoire: &str) -> PathBuf {
+        repertoire_recherche(repertoire).join("index.json")
+    }
+
+    fn date_modification(fichier: &Path) -> i64 {
+        fs::metadata(fichier)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|d| d.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    }
+
+    /// Derniere modification de `data.json` ou de l'etat de l'archive.
+    pub fn date_sources(repertoire: &str) -> i64 {
+        date_modification(Path::new(fichier_data(repertoire).as_str())).max(date_modification(
+            &repertoire_archive(repertoire).join("etat.json"),
+        ))
+    }
+
+    /// Index vide si le fichier est absent, invalide ou d'une autre version.
+    pub fn charger_index(repertoire: &str) -> Index {
+        let fichier = fichier_index(repertoire);
+        let index = match fs::read_to_string(&fichier) {
+            Ok(contenu) => serde_json::from_str(&contenu).unwrap_or_else(|err| {
+                log::error!("fichier {} invalide : {}", fichier.display(), err);
+                Index::default()
+            }),
+            Err(_) => Index::default(),
+        };
+        if index.version != VERSION_INDEX {
+            return Index {
+                version: VERSION_INDEX,
+                ..Default::default()
+            };
+        }
+        index
+    }
+
+    fn sauver_index(repertoire: &str, index: &Index) -> io::Result<()> {
+        fs::create_dir_all(repertoire_recherche(repertoire))?;
+        let fichier = fichier_index(repertoire);
+        let tmp = fichier.with_extension("tmp");
+        fs::write(
+            &tmp,
+            serde_json::to_string(index).expect("Erreur de sérialisation"),
+        )?;
+        fs::rename(&tmp, &fichier)
+    }
+
+    fn sans_accents(mot: &str, res: &mut String) {
+        for c in mot.chars() {
+            match c {
+                'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => res.push('a'),
+                'ç' => res.push('c'),
+                'è' | 'é' | 'ê' | 'ë' => res.push('e'),
+                'ì' | 'í' | 'î' | 'ï' => res.push('i'),
+                'ñ' => res.push('n'),
+                'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => res.push('o'),
+                'ù' | 'ú' | 'û' | 'ü' => res.push('u'),
+                'ý' | 'ÿ' => res.push('y'),
+                'æ' => res.push_str("ae"),
+                'œ' => res.push_str("oe"),
+                'ß' => res.push_str("ss"),
+                _ => res.push(c),
+            }
+        }
+    }
+
+    /// Mots en minuscules et sans accents, dans l'ordre du texte.
+    pub fn mots(texte: &str) -> Vec<String> {
+        texte
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|m| !m.is_empty() && m.chars().count() <= LONGUEUR_MOT_MAX)
+            .map(|m| {
+                let mut mot = String::with_capacity(m.len());
+                sans_accents(m.to_lowercase().as_str(), &mut mot);
+                mot
+            })
+            .collect()
+    }
+
+    /// Texte de l'article extrait, tronque a `taille_max` octets.
+    fn texte_article(repertoire: &str, item_id: &str, taille_max: usize) -> String {
+        let fichier =
+            repertoire_articles(&repertoire_archive(repertoire)).join(format!("{}.txt", item_id));
+        let mut texte = fs::read_to_string(fichier).unwrap_or_default();
+        if texte.len() > taille_max {
+            let mut fin = taille_max;
+            while !texte.is_char_boundary(fin) {
+                fin -= 1;
+            }
+            texte.truncate(fin);
+        }
+        texte
+    }
+
+    impl Index {
+        fn indexer(&mut self, item_id: &str, mut document: Document, champs: &[(&str, &str)]) {
+            for (champ, texte) in champs {
+                let mots = mots(texte);
+                if mots.is_empty() {
+                    continue;
+                }
+                document
+                    .longueurs
+                    .insert(champ.to_string(), mots.len() as u32);
+                for (position, mot) in mots.into_iter().enumerate() {
+                    self.termes
+                        .entry(mot)
+                        .or_default()
+                        .entry(item_id.to_string())
+                        .or_default()
+                        .entry(champ.to_string())
+                        .or_default()
+                        .push(position as u32);
+                }
+            }
+            self.documents.insert(item_id.to_string(), document);
+        }
+
+        /// Un seul parcourt des termes pour tous les documents retires.
+        fn retirer(&mut self, item_ids: &BTreeSet<String>) {
+            if item_ids.is_empty() {
+                return;
+            }
+            self.termes.retain(|_, documents| {
+                documents.retain(|id, _| !item_ids.contains(id));
+                !documents.is_empty()
+            });
+            for id in item_ids {
+                self.documents.remove(id);
+            }
+        }
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanIndex {
+        pub nb_documents: usize,
+        pub nb_indexes: u64,
+        pub nb_retires: u64,
+        pub nb_termes: usize,
+    }
+
+    impl fmt::Display for BilanIndex {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "{} documents, {} (re)indexes, {} retires, {} termes",
+                self.nb_documents, self.nb_indexes, self.nb_retires, self.nb_termes
+            )
+        }
+    }
+
+    /// Hash du contenu indexe et des champs gardes dans le document : un
+    /// tag ou un extrait modifie sans changer time_updated (import, outil
+    /// externe) est reindexe.
+    fn empreinte(element: &Value, hash_article: &str) -> String {
+        let mut hash = Sha256::new();
+        let tags = tags(element).join(" ");
+        let nombres = [
+            champ_nombre(element, "time_added").to_string(),
+            champ_nombre(element, "time_updated").to_string(),
+            est_favori(element).to_string(),
+        ];
+        for champ in [
+            titre(element),
+            champ_str(element, "excerpt"),
+            url(element),
+            tags.as_str(),
+            statut(element),
+            hash_article,
+        ]
+        .into_iter()
+        .chain(nombres.iter().map(|n| n.as_str()))
+        {
+            hash.update(champ.as_bytes());
+            hash.update([0]);
+        }
+        format!("{:x}", hash.finalize())
+    }
+
+    /// Reindexe les elements dont l'empreinte a change depuis la derniere
+    /// mise a jour et retire ceux qui ne sont plus dans `data`.
+    pub fn mettre_a_jour(
+        config: &ConfigRecherche,
+        repertoire: &str,
+        index: &mut Index,
+        data: &Value,
+    ) -> BilanIndex {
+        let mut bilan = BilanIndex::default();
+        let etat = if config.texte_article {
+            charger_etat(repertoire)
+        } else {
+            BTreeMap::new()
+        };
+        let vide = serde_json::Map::new();
+        let elements = liste(data).unwrap_or(&vide);
+        let mut a_indexer: Vec<(&String, &Value, String, bool)> = Vec::new();
+        for (id, element) in elements {
+            let hash_article = etat
+                .get(id)
+                .filter(|a| a.statut == ARCHIVE_OK && a.extraction.is_some())
+                .and_then(|a| a.hash.clone())
+                .unwrap_or_default();
+            let empreinte = empreinte(element, hash_article.as_str());
+            if index.documents.get(id).map(|d| &d.empreinte) != Some(&empreinte) {
+                a_indexer.push((id, element, empreinte, !hash_article.is_empty()));
+            }
+        }
+        let mut retires: BTreeSet<String> = index
+            .documents
+            .keys()
+            .filter(|id| !elements.contains_key(*id))
+            .cloned()
+            .collect();
+        bilan.nb_retires = retires.len() as u64;
+        retires.extend(
+            a_indexer
+                .iter()
+                .filter(|(id, _, _, _)| index.documents.contains_key(*id))
+                .map(|(id, _, _, _)| id.to_string()),
+        );
+        index.retirer(&retires);
+        for (id, element, empreinte, article) in a_indexer {
+            let document = Document {
+                titre: titre(element).to_string(),
+                url: url(element).to_string(),
+                domaine: domaine(element),
+                tags: tags(element),
+                statut: statut(element).to_string(),
+                favori: est_favori(element),
+                time_added: champ_nombre(element, "time_added"),
+                time_updated: champ_nombre(element, "time_updated"),
+                longueurs: BTreeMap::new(),
+                empreinte,
+            };
+            let texte = if article {
+                texte_article(repertoire, id, config.taille_texte_max_ko * 1024)
+            } else {
+                String::new()
+            };
+            let titre = document.titre.clone();
+            let tags = document.tags.join(" ");
+            index.indexer(
+                id,
+                document,
+                &[
+                    (CHAMP_TITRE, titre.as_str()),
+                    (CHAMP_EXTRAIT, champ_str(element, "excerpt")),
+                    (CHAMP_URL, url(element)),
+                    (CHAMP_TAGS, tags.as_str()),
+                    (CHAMP_TEXTE, texte.as_str()),
+                ],
+            );
+            bilan.nb_indexes += 1;
+        }
+        bilan.nb_documents = index.documents.len();
+        bilan.nb_termes = index.termes.len();
+        bilan
+    }
+
+    /// Mise a jour de l'index sur disque apres un traitement.
+    pub fn mettre_a_jour_index(
+        config: &ConfigRecherche,
+        repertoire: &str,
+        data: &Value,
+    ) -> io::Result<BilanIndex> {
+        let mut index = charger_index(repertoire);
+        let bilan = mettre_a_jour(config, repertoire, &mut index, data);
+        index.date_sources = date_sources(repertoire);
+        sauver_index(repertoire, &index)?;
+        Ok(bilan)
+    }
+
+    /// Index a jour des donnees : il est reconstruit (ou complete) avant
+    /// une recherche si `data.json` ou l'archive ont change depuis.
+    pub fn index_a_jour(
+        config: &ConfigRecherche,
+        repertoire: &str,
+        reconstruire: bool,
+    ) -> io::Result<Index> {
+        let mut index = if reconstruire {
+            Index {
+                version: VERSION_INDEX,
+                ..Default::default()
+            }
+        } else {
+            charger_index(repertoire)
+        };
+        let date = date_sources(repertoire);
+        if reconstruire || index.date_sources < date {
+            log::info!("mise a jour de l'index de recherche");
+            // sans data.json lisible, l'index vide retirerait tous les
+            // documents
+            let fichier = fichier_data(repertoire);
+            let contenu = fs::read_to_string(&fichier)
+                .map_err(|err| io::Error::new(err.kind(), format!("{} : {}", fichier, err)))?;
+            let data = serde_json::from_str::<Value>(&contenu).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} invalide : {}", fichier, err),
+                )
+            })?;
+            let bilan = mettre_a_jour(config, repertoire, &mut index, &data);
+            log::info!("index de recherche : {}", bilan);
+            index.date_sources = date;
+            sauver_index(repertoire, &index)?;
+        }
+        Ok(index)
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Jeton {
+        Ouvrante,
+        Fermante,
+        Et,
+        Ou,
+        Non,
+        Terme {
+            champ: Option<String>,
+            valeur: String,
+            phrase: bool,
+        },
+    }
+
+    /// Decoupe la requete : mots, `"phrases"`, `champ:valeur`,
+    /// `champ:"phrase"`, parentheses et operateurs.
+    fn jetons(requete: &str) -> Vec<Jeton> {
+        let caracteres: Vec<char> = requete.chars().collect();
+        let mut res = Vec::new();
+        let mut i = 0;
+        while i < caracteres.len() {
+            let c = caracteres[i];
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            match c {
+                '(' => {
+                    res.push(Jeton::Ouvrante);
+                    i += 1;
+                    continue;
+                }
+                ')' => {
+                    res.push(Jeton::Fermante);
+                    i += 1;
+                    continue;
+                }
+                '-' if caracteres.get(i + 1).is_some_and(|s| !s.is_whitespace()) => {
+                    res.push(Jeton::Non);
+                    i += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let debut = i;
+            while i < caracteres.len()
+                && !caracteres[i].is_whitespace()
+                && !matches!(caracteres[i], '(' | ')' | '"')
+            {
+                i += 1;
+            }
+            let mot: String = caracteres[debut..i].iter().collect();
+            let (champ, valeur) = match mot.split_once(':') {
+                Some((champ, valeur)) if est_champ(champ) => {
+                    (Some(champ.to_lowercase()), valeur.to_string())
+                }
+                _ => (None, mot),
+            };
+            if valeur.is_empty() && caracteres.get(i) == Some(&'"') {
+                let debut = i + 1;
+                i = debut;
+                while i < caracteres.len() && caracteres[i] != '"' {
+                    i += 1;
+                }
+                res.push(Jeton::Terme {
+                    champ,
+                    valeur: caracteres[debut..i].iter().collect(),
+                    phrase: true,
+                });
+                i += 1;
+                continue;
+            }
+            if champ.is_none() {
+                match valeur.as_str() {
+                    "AND" | "ET" => {
+                        res.push(Jeton::Et);
+                        continue;
+                    }
+                    "OR" | "OU" | "|" => {
+                        res.push(Jeton::Ou);
+                        continue;
+                    }
+                    "NOT" | "SAUF" => {
+                        res.push(Jeton::Non);
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            if !valeur.is_empty() {
+                res.push(Jeton::Terme {
+                    champ,
+                    valeur,
+                    phrase: false,
+                });
+            }
+        }
+        res
+    }
+
+    fn champ_texte(champ: &str) -> Option<&'static str> {
+        match champ {
+            "titre" | "title" => Some(CHAMP_TITRE),
+            "extrait" | "excerpt" => Some(CHAMP_EXTRAIT),
+            "url" => Some(CHAMP_URL),
+            "tags" => Some(CHAMP_TAGS),
+            "texte" | "text" | "contenu" => Some(CHAMP_TEXTE),
+            _ => None,
+        }
+    }
+
+    fn est_champ(champ: &str) -> bool {
+        let champ = champ.to_lowercase();
+        champ_texte(champ.as_str()).is_some()
+            || matches!(
+                champ.as_str(),
+                "tag"
+                    | "domaine"
+                    | "domain"
+                    | "site"
+                    | "statut"
+                    | "status"
+                    | "favori"
+                    | "favorite"
+                    | "ajoute"
+                    | "added"
+                    | "maj"
+                    | "updated"
+            )
+    }
+
+    #[derive(Debug, Clone)]
+    enum Filtre {
+        Tag(String),
+        Domaine(String),
+        Statut(&'static str),
+        Favori(bool),
+        /// champ de date, debut inclus et fin exclue
+        Periode(&'static str, Option<i64>, Option<i64>),
+    }
+
+    #[derive(Debug, Clone)]
+    enum Noeud {
+        /// mot seul, ou suite de mots consecutifs pour une phrase ; `*`
+        /// final pour un prefixe
+        Mots {
+            champ: Option<&'static str>,
+            mots: Vec<String>,
+            prefixe: bool,
+        },
+        Filtre(Filtre),
+        Et(Vec<Noeud>),
+        Ou(Vec<Noeud>),
+        Non(Box<Noeud>),
+    }
+
+    fn filtre(champ: &str, valeur: &str) -> Result<Filtre, String> {
+        let valeur_min = valeur.to_lowercase();
+        match champ {
+            "tag" => Ok(Filtre::Tag(valeur_min)),
+            "domaine" | "domain" | "site" => Ok(Filtre::Domaine(
+                valeur_min
+                    .strip_prefix("www.")
+                    .unwrap_or(valeur_min.as_str())
+                    .to_string(),
+            )),
+            "statut" | "status" => code_statut(valeur).map(Filtre::Statut),
+            "favori" | "favorite" => oui_non(valeur).map(Filtre::Favori),
+            "ajoute" | "added" => {
+                let (debut, fin) = periode(valeur)?;
+                Ok(Filtre::Periode("time_added", debut, fin))
+            }
+            "maj" | "updated" => {
+                let (debut, fin) = periode(valeur)?;
+                Ok(Filtre::Periode("time_updated", debut, fin))
+            }
+            _ => Err(format!("champ inconnu : '{}'", champ)),
+        }
+    }
+
+    /// Analyse descendante : `ou := et (OU et)*`, `et := non (ET? non)*`,
+    /// `non := (NON|-) non | '(' ou ')' | terme`.
+    struct Analyseur {
+        jetons: Vec<Jeton>,
+        position: usize,
+    }
+
+    impl Analyseur {
+        fn suivant(&self) -> Option<&Jeton> {
+            self.jetons.get(self.position)
+        }
+
+        fn ou(&mut self) -> Result<Option<Noeud>, String> {
+            let mut branches = Vec::new();
+            loop {
+                if let Some(noeud) = self.et()? {
+                    branches.push(noeud);
+                }
+                if self.suivant() == Some(&Jeton::Ou) {
+                    self.position += 1;
+                } else {
+                    break;
+                }
+            }
+            Ok(match branches.len() {
+                0 => None,
+                1 => branches.pop(),
+                _ => Some(Noeud::Ou(branches)),
+            })
+        }
+
+        fn et(&mut self) -> Result<Option<Noeud>, String> {
+            let mut termes = Vec::new();
+            loop {
+                match self.suivant() {
+                    None | Some(Jeton::Ou) | Some(Jeton::Fermante) => break,
+                    Some(Jeton::Et) => self.position += 1,
+                    _ => {
+                        if let Some(noeud) = self.non()? {
+                            termes.push(noeud);
+                        }
+                    }
+                }
+            }
+            Ok(match termes.len() {
+                0 => None,
+                1 => termes.pop(),
+                _ => Some(Noeud::Et(termes)),
+            })
+        }
+
+        fn non(&mut self) -> Result<Option<Noeud>, String> {
+            let Some(jeton) = self.suivant().cloned() else {
+                return Err("expression attendue en fin de requete".to_string());
+            };
+            self.position += 1;
+            match jeton {
+                Jeton::Non => Ok(self.non()?.map(|n| Noeud::Non(Box::new(n)))),
+                Jeton::Ouvrante => {
+                    let noeud = self.ou()?;
+                    if self.suivant() != Some(&Jeton::Fermante) {
+                        return Err("parenthese fermante manquante".to_string());
+                    }
+                    self.position += 1;
+                    Ok(noeud)
+                }
+                Jeton::Terme {
+                    champ,
+                    valeur,
+                    phrase,
+                } => {
+                    let champ_mots = match champ.as_deref() {
+                        None => None,
+                        Some(champ) => match champ_texte(champ) {
+                            Some(champ) => Some(champ),
+                            None => {
+                                return filtre(champ, valeur.as_str())
+                                    .map(|f| Some(Noeud::Filtre(f)))
+                            }
+                        },
+                    };
+                    let prefixe = !phrase && valeur.ends_with('*');
+                    let mots = mots(valeur.as_str());
+                    if mots.is_empty() {
+                        return Ok(None);
+                    }
+                    Ok(Some(Noeud::Mots {
+                        champ: champ_mots,
+                        prefixe: prefixe && mots.len() == 1,
+                        mots,
+                    }))
+                }
+                Jeton::Et | Jeton::Ou | Jeton::Fermante => {
+                    Err("operateur ou parenthese inattendu".to_string())
+                }
+            }
+        }
+    }
+
+    fn analyser(requete: &str) -> Result<Noeud, String> {
+        let mut analyseur = Analyseur {
+            jetons: jetons(requete),
+            position: 0,
+        };
+        let noeud = analyseur.ou()?;
+        if analyseur.position < analyseur.jetons.len() {
+            return Err("parenthese fermante en trop".to_string());
+        }
+        noeud.ok_or_else(|| "requete vide".to_string())
+    }
+
+    type Postings = BTreeMap<String, BTreeMap<String, Vec<u32>>>;
+
+    impl Index {
+        /// Termes designes par un mot, ou commencant par lui.
+        fn termes_de(&self, mot: &str, prefixe: bool) -> Vec<(&String, &Postings)> {
+            if prefixe {
+                self.termes
+                    .range(mot.to_string()..)
+                    .take_while(|(terme, _)| terme.starts_with(mot))
+                    .take(NB_TERMES_PREFIXE_MAX)
+                    .collect()
+            } else {
+                self.termes.get_key_value(mot).into_iter().collect()
+            }
+        }
+
+        fn documents_mots(
+            &self,
+            champ: Option<&str>,
+            mots: &[String],
+            prefixe: bool,
+        ) -> BTreeSet<&str> {
+            let dans_champ = |champs: &BTreeMap<String, Vec<u32>>| match champ {
+                Some(champ) => champs.contains_key(champ),
+                None => true,
+            };
+            if mots.len() == 1 {
+                return self
+                    .termes_de(mots[0].as_str(), prefixe)
+                    .into_iter()
+                    .flat_map(|(_, documents)| {
+                        documents
+                            .iter()
+                            .filter(|(_, champs)| dans_champ(champs))
+                            .map(|(id, _)| id.as_str())
+                    })
+                    .collect();
+            }
+            let mut postings = Vec::new();
+            for mot in mots {
+                match self.termes.get(mot) {
+                    Some(p) => postings.push(p),
+                    None => return BTreeSet::new(),
+                }
+            }
+            let mut res = BTreeSet::new();
+            'documents: for (id, champs) in postings[0] {
+                for (nom, positions) in champs {
+                    if champ.is_some_and(|c| c != nom) {
+                        continue;
+                    }
+                    let suite = |position: u32| {
+                        postings[1..].iter().enumerate().all(|(k, p)| {
+                            p.get(id).and_then(|c| c.get(nom)).is_some_and(|pos| {
+                                pos.binary_search(&(position + k as u32 + 1)).is_ok()
+                            })
+                        })
+                    };
+                    if positions.iter().any(|p| suite(*p)) {
+                        res.insert(id.as_str());
+                        continue 'documents;
+                    }
+                }
+            }
+            res
+        }
+
+        fn documents_filtre(&self, filtre: &Filtre) -> BTreeSet<&str> {
+            self.documents
+                .iter()
+                .filter(|(_, d)| match filtre {
+                    Filtre::Tag(tag) => d.tags.iter().any(|t| t.to_lowercase() == *tag),
+                    Filtre::Domaine(domaine) => {
+                        d.domaine == *domaine
+                            || d.domaine.ends_with(format!(".{}", domaine).as_str())
+                    }
+                    Filtre::Statut(statut) => d.statut == *statut,
+                    Filtre::Favori(favori) => d.favori == *favori,
+                    Filtre::Periode(champ, debut, fin) => {
+                        let date = if *champ == "time_added" {
+                            d.time_added
+                        } else {
+                            d.time_updated
+                        };
+                        date > 0
+                            && debut.is_none_or(|debut| date >= debut)
+                            && fin.is_none_or(|fin| date < fin)
+                    }
+                })
+                .map(|(id, _)| id.as_str())
+                .collect()
+        }
+
+        fn evaluer(&self, noeud: &Noeud) -> BTreeSet<&str> {
+            match noeud {
+                Noeud::Mots {
+                    champ,
+                    mots,
+                    prefixe,
+                } => self.documents_mots(*champ, mots, *prefixe),
+                Noeud::Filtre(filtre) => self.documents_filtre(filtre),
+                Noeud::Et(noeuds) => {
+                    let mut positifs: Vec<BTreeSet<&str>> = Vec::new();
+                    let mut negatifs: Vec<BTreeSet<&str>> = Vec::new();
+                    for n in noeuds {
+                        match n {
+                            Noeud::Non(n) => negatifs.push(self.evaluer(n)),
+                            n => positifs.push(self.evaluer(n)),
+                        }
+                    }
+                    let mut res: BTreeSet<&str> = if positifs.is_empty() {
+                        self.documents.keys().map(|id| id.as_str()).collect()
+                    } else {
+                        positifs.sort_by_key(|p| p.len());
+                        let premier = positifs.remove(0);
+                        premier
+                            .into_iter()
+                            .filter(|id| positifs.iter().all(|p| p.contains(id)))
+                            .collect()
+                    };
+                    for negatif in negatifs {
+                        res.retain(|id| !negatif.contains(id));
+                    }
+                    res
+                }
+                Noeud::Ou(noeuds) => noeuds.iter().flat_map(|n| self.evaluer(n)).collect(),
+                Noeud::Non(n) => {
+                    let exclus = self.evaluer(n);
+                    self.documents
+                        .keys()
+                        .map(|id| id.as_str())
+                        .filter(|id| !exclus.contains(id))
+                        .collect()
+                }
+            }
+        }
+
+        /// Score BM25 par champ, pondere par le poids du champ, pour les
+        /// mots qui ne sont pas sous une negation.
+        fn scores(&self, noeud: &Noeud, documents: &BTreeSet<&str>) -> HashMap<String, f64> {
+            let mut mots: Vec<(Option<&str>, &String, bool)> = Vec::new();
+            positifs(noeud, &mut mots);
+            let nb_documents = self.documents.len().max(1) as f64;
+            let mut moyennes: HashMap<&str, f64> = HashMap::new();
+            for (champ, _) in CHAMPS {
+                let total: u64 = self
+                    .documents
+                    .values()
+                    .map(|d| d.longueurs.get(champ).copied().unwrap_or(0) as u64)
+                    .sum();
+                moyennes.insert(champ, (total as f64 / nb_documents).max(1.0));
+            }
+            let mut res: HashMap<String, f64> = HashMap::new();
+            for (restriction, mot, prefixe) in mots {
+                for (_, postings) in self.termes_de(mot.as_str(), prefixe) {
+                    let df = postings.len() as f64;
+                    let idf = (1.0 + (nb_documents - df + 0.5) / (df + 0.5)).ln();
+                    for (id, champs) in postings {
+                        if !documents.contains(id.as_str()) {
+                            continue;
+                        }
+                        let Some(document) = self.documents.get(id) else {
+                            continue;
+                        };
+                        let mut score = 0.0;
+                        for (champ, poids) in CHAMPS {
+                            if restriction.is_some_and(|r| r != champ) {
+                                continue;
+                            }
+                            let Some(positions) = champs.get(champ) else {
+                                continue;
+                            };
+                            let tf = positions.len() as f64;
+                            let longueur =
+                                document.longueurs.get(champ).copied().unwrap_or(0) as f64;
+                            let norme = 1.0 - B + B * longueur / moyennes[champ];
+                            score += poids * idf * tf * (K1 + 1.0) / (tf + K1 * norme);
+                        }
+                        *res.entry(id.clone()).or_insert(0.0) += score;
+                    }
+                }
+            }
+            res
+        }
+    }
+
+    /// Mots a prendre en compte dans le score.
+    fn positifs<'a>(noeud: &'a Noeud, res: &mut Vec<(Option<&'a str>, &'a String, bool)>) {
+        match noeud {
+            Noeud::Mots {
+                champ,
+                mots,
+                prefixe,
+            } => {
+                for mot in mots {
+                    res.push((*champ, mot, *prefixe));
+                }
+            }
+            Noeud::Et(noeuds) | Noeud::Ou(noeuds) => {
+                for n in noeuds {
+                    positifs(n, res);
+                }
+            }
+            Noeud::Filtre(_) | Noeud::Non(_) => {}
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Classement {
+        Pertinence,
+        Date,
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Resultat {
+        pub item_id: String,
+        pub score: f64,
+        pub titre: String,
+        pub url: String,
+        pub tags: Vec<String>,
+        pub statut: String,
+        pub favori: bool,
+        pub time_added: i64,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanRecherche {
+        pub requete: String,
+        pub nb_resultats: usize,
+        pub resultats: Vec<Resultat>,
+    }
+
+    impl fmt::Display for BilanRecherche {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(f, "{} resultats pour : {}", self.nb_resultats, self.requete)?;
+            for resultat in &self.resultats {
+                let date = DateTime::from_timestamp(resultat.time_added, 0)
+                    .map(|d| d.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default();
+                let favori = if resultat.favori { " *" } else { "" };
+                writeln!(
+                    f,
+                    "{:>7.2}  {}  {}  {}{}",
+                    resultat.score, date, resultat.item_id, resultat.titre, favori
+                )?;
+                writeln!(f, "{:>9}{}", "", resultat.url)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Mots, `"phrases"`, `ET`/`OU`/`SAUF` (ou `AND`/`OR`/`NOT`, `-mot`),
+    /// parentheses, champs (`titre:`, `extrait:`, `url:`, `tags:`,
+    /// `texte:`), prefixes (`rust*`) et filtres (`tag:`, `domaine:`,
+    /// `statut:`, `favori:`, `ajoute:`, `maj:`). Les resultats sont
+    /// classes par score, puis du plus recent au plus ancien.
+    pub fn rechercher(
+        index: &Index,
+        requete: &str,
+        classement: Classement,
+        limite: usize,
+    ) -> Result<BilanRecherche, String> {
+        let noeud = analyser(requete)?;
+        let documents = index.evaluer(&noeud);
+        let scores = index.scores(&noeud, &documents);
+        let mut resultats: Vec<Resultat> = documents
+            .iter()
+            .filter_map(|id| {
+                let document = index.documents.get(*id)?;
+                Some(Resultat {
+                    item_id: id.to_string(),
+                    score: scores.get(*id).copied().unwrap_or(0.0),
+                    titre: document.titre.clone(),
+                    url: document.url.clone(),
+                    tags: document.tags.clone(),
+                    statut: document.statut.clone(),
+                    favori: document.favori,
+                    time_added: document.time_added,
+                })
+            })
+            .collect();
+        match classement {
+            Classement::Pertinence => resultats.sort_by(|a, b| {
+                b.score
+                    .total_cmp(&a.score)
+                    .then(b.time_added.cmp(&a.time_added))
+            }),
+            Classement::Date => resultats.sort_by_key(|r| std::cmp::Reverse(r.time_added)),
+        }
+        let nb_resultats = resultats.len();
+        resultats.truncate(limite);
+        Ok(BilanRecherche {
+            requete: requete.to_string(),
+            nb_resultats,
+            resultats,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::donnees::mod_donnees::objet_tags;
+        use serde_json::json;
+
+        fn element(id: &str, titre: &str, url: &str, tags: &[&str], ajoute: i64) -> Value {
+            let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+            json!({
+                "item_id": id,
+                "given_url": url,
+                "resolved_title": titre,
+                "tags": objet_tags(id, &tags),
+                "status": "0",
+                "time_added": ajoute.to_string(),
+                "time_updated": ajoute.to_string(),
+            })
+        }
+
+        fn index_de_test() -> Index {
+            let mut data = json!({"liste": {
+                // 2024-03-10
+                "1": element("1", "Apprendre Rust en une semaine",
+                    "https://rust-lang.org/apprendre", &["rust", "langage"], 1710028800),
+                // 2023-05-01
+                "2": element("2", "Cuisine rustique du sud",
+                    "https://cuisine.fr/recette", &["cuisine"], 1682899200),
+                // 2024-06-01
+                "3": element("3", "Python et Rust ensemble",
+                    "https://exemple.org/interop", &["python"], 1717200000),
+                // 2022-01-01
+                "4": element("4", "Notes sur Python",
+                    "https://exemple.org/notes", &[], 1641024000),
+            }});
+            data["liste"]["4"]["excerpt"] = json!("rust est cite une fois");
+            let config = ConfigRecherche {
+                texte_article: false,
+                ..Default::default()
+            };
+            let mut index = Index::default();
+            mettre_a_jour(&config, "", &mut index, &data);
+            index
+        }
+
+        fn trouves(index: &Index, requete: &str) -> Vec<String> {
+            let mut ids: Vec<String> = rechercher(index, requete, Classement::Pertinence, 100)
+                .unwrap()
+                .resultats
+                .into_iter()
+                .map(|r| r.item_id)
+                .collect();
+            ids.sort();
+            ids
+        }
+
+        #[test]
+        fn operateurs_booleens() {
+            let index = index_de_test();
+            assert_eq!(trouves(&index, "rust python"), ["3", "4"]);
+            assert_eq!(trouves(&index, "rust AND python"), ["3", "4"]);
+            assert_eq!(trouves(&index, "rust ET titre:python"), ["3", "4"]);
+            assert_eq!(trouves(&index, "cuisine OR langage"), ["1", "2"]);
+            assert_eq!(trouves(&index, "cuisine OU langage"), ["1", "2"]);
+            assert_eq!(trouves(&index, "rust NOT python"), ["1"]);
+            assert_eq!(trouves(&index, "rust SAUF python"), ["1"]);
+            assert_eq!(trouves(&index, "rust -python"), ["1"]);
+            assert_eq!(trouves(&index, "(cuisine OR python) -notes"), ["2", "3"]);
+        }
+
+        #[test]
+        fn phrases_champs_et_prefixes() {
+            let index = index_de_test();
+            assert_eq!(trouves(&index, "\"python et rust\""), ["3"]);
+            assert!(trouves(&index, "\"rust et python\"").is_empty());
+            assert_eq!(trouves(&index, "titre:rust"), ["1", "3"]);
+            assert_eq!(trouves(&index, "titre:\"une semaine\""), ["1"]);
+            assert_eq!(trouves(&index, "rust*"), ["1", "2", "3", "4"]);
+            assert_eq!(trouves(&index, "titre:rust*"), ["1", "2", "3"]);
+        }
+
+        #[test]
+        fn filtres() {
+            let index = index_de_test();
+            assert_eq!(trouves(&index, "tag:rust"), ["1"]);
+            assert_eq!(trouves(&index, "python tag:python"), ["3"]);
+            assert_eq!(trouves(&index, "domaine:exemple.org"), ["3", "4"]);
+            assert_eq!(trouves(&index, "ajoute:2024"), ["1", "3"]);
+            assert_eq!(trouves(&index, "rust ajoute:<2023"), ["4"]);
+            assert_eq!(trouves(&index, "ajoute:2023-05..2024-03"), ["1", "2"]);
+            assert!(rechercher(&index, "ajoute:hier", Classement::Pertinence, 10).is_err());
+            assert!(rechercher(&index, "statut:inconnu", Classement::Pertinence, 10).is_err());
+        }
+
+        #[test]
+        fn requetes_invalides() {
+            let index = index_de_test();
+            for requete in ["(rust", "rust)", "(rust OR python", "", "OR", "rust NOT"] {
+                assert!(
+                    rechercher(&index, requete, Classement::Pertinence, 10).is_err(),
+                    "{}",
+                    requete
+                );
+            }
+        }
+
+        #[test]
+        fn classement_par_pertinence_puis_date() {
+            let index = index_de_test();
+            let ordre = |classement| -> Vec<String> {
+                rechercher(&index, "rust", classement, 100)
+                    .unwrap()
+                    .resultats
+                    .into_iter()
+                    .map(|r| r.item_id)
+                    .collect()
+            };
+            // titre, url et tag avant titre seul, avant l'extrait seul
+            assert_eq!(ordre(Classement::Pertinence), ["1", "3", "4"]);
+            assert_eq!(ordre(Classement::Date), ["3", "1", "4"]);
+            let bilan = rechercher(&index, "rust", Classement::Pertinence, 1).unwrap();
+            assert_eq!(bilan.nb_resultats, 3);
+            assert_eq!(bilan.resultats.len(), 1);
+        }
+
+        #[test]
+        fn index_a_jour_sans_data_json() {
+            let repertoire =
+                std::env::temp_dir().join(format!("pbackup_recherche_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&repertoire);
+            fs::create_dir_all(&repertoire).unwrap();
+            let repertoire = repertoire.to_str().unwrap();
+            let config = ConfigRecherche::default();
+            assert!(index_a_jour(&config, repertoire, true).is_err());
+            fs::write(fichier_data(repertoire), "{\"liste\": ").unwrap();
+            assert!(index_a_jour(&config, repertoire, true).is_err());
+            assert!(!fichier_index(repertoire).exists());
+            let _ = fs::remove_dir_all(repertoire);
+        }
+
+        #[test]
+        fn tags_modifies_sans_time_updated_reindexes() {
+            let config = ConfigRecherche {
+                texte_article: false,
+                ..Default::default()
+            };
+            let mut data = json!({"liste": {"1": {
+                "item_id": "1",
+                "given_url": "https://exemple.org/1",
+                "resolved_title": "Titre",
+                "time_updated": "1700000000",
+            }}});
+            let mut index = Index::default();
+            assert_eq!(mettre_a_jour(&config, "", &mut index, &data).nb_indexes, 1);
+            assert_eq!(mettre_a_jour(&config, "", &mut index, &data).nb_indexes, 0);
+
+            data["liste"]["1"]["tags"] = objet_tags("1", &["rust".to_string()]);
+            assert_eq!(mettre_a_jour(&config, "", &mut index, &data).nb_indexes, 1);
+            assert!(index.termes.contains_key("rust"));
+
+            data["liste"]["1"]["excerpt"] = json!("un resume");
+            assert_eq!(mettre_a_jour(&config, "", &mut index, &data).nb_indexes, 1);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+pub mod mod_reconciliation {
+    use crate::api::mod_api::ErreurApi;
+    use crate::config::mod_config::Config2;
+    use crate::donnees::mod_donnees::{champ_nombre, liste, statut, DATA_LISTE, STATUT_SUPPRIME};
+    use crate::source::mod_source::{creer_source, SourceProvider};
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::time::Duration;
+
+    pub struct ElementDistant {
+        pub time_updated: i64,
+        /// element complet, conserve seulement pour la recuperation
+        pub element: Option<Value>,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    pub struct Reconciliation {
+        pub nb_distant: usize,
+        pub nb_local: usize,
+        /// presents sur le compte, absents de la sauvegarde
+        pub manquants_local: Vec<String>,
+        /// presents dans la sauvegarde (non supprimes), absents du compte
+        pub manquants_distant: Vec<String>,
+        /// modifies sur le compte depuis la sauvegarde
+        pub obsoletes: Vec<String>,
+        pub nb_recuperes: u64,
+    }
+
+    impl fmt::Display for Reconciliation {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(f, "distant : {}", self.nb_distant)?;
+            writeln!(f, "local : {}", self.nb_local)?;
+            writeln!(
+                f,
+                "manquants en local : {} {:?}",
+                self.manquants_local.len(),
+                self.manquants_local
+            )?;
+            writeln!(
+                f,
+                "manquants sur le compte : {} {:?}",
+                self.manquants_distant.len(),
+                self.manquants_distant
+            )?;
+            writeln!(
+                f,
+                "obsoletes : {} {:?}",
+                self.obsoletes.len(),
+                self.obsoletes
+            )?;
+            writeln!(f, "recuperes : {}", self.nb_recuperes)
+        }
+    }
+
+    /// Parcourt tout le compte (tous les etats, sans since).
+    pub async fn lister_distant(
+        config: &Config2,
+        garder_elements: bool,
+    ) -> Result<BTreeMap<String, ElementDistant>, ErreurApi> {
+        let Some(mut source) = creer_source(config, true) else {
+            return Err(ErreurApi::Requete(format!(
+                "source inconnue : {}",
+                config.source.type_source
+            )));
+        };
+        let client = reqwest::Client::new();
+        source.authentifier(&client).await?;
+        let mut offset = 0;
+        let mut res = BTreeMap::new();
+        loop {
+            log::info!("reconciliation offset: {}", offset);
+            let page = source.page(&client, offset, None).await?;
+            if page.elements.is_empty() {
+                break;
+            }
+            offset += page.elements.len() as u64;
+            for (id, element) in page.elements {
+                res.insert(
+                    id,
+                    ElementDistant {
+                        time_updated: champ_nombre(&element, "time_updated"),
+                        element: if garder_elements { Some(element) } else { None },
+                    },
+                );
+            }
+            log::info!("reconciliation nb: {}", res.len());
+            if config.temporisation > 0 {
+                tokio::time::sleep(Duration::from_millis(config.temporisation)).await;
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn comparer(data: &Value, distant: &BTreeMap<String, ElementDistant>) -> Reconciliation {
+        let mut res = Reconciliation {
+            nb_distant: distant.len(),
+            ..Default::default()
+        };
+        let vide = serde_json::Map::new();
+        let local = liste(data).unwrap_or(&vide);
+        res.nb_local = local.len();
+        for (id, element) in distant {
+            match local.get(id) {
+                None => res.manquants_local.push(id.clone()),
+                Some(l) => {
+                    if element.time_updated > champ_nombre(l, "time_updated") {
+                        res.obsoletes.push(id.clone());
+                    }
+                }
+            }
+        }
+        for (id, element) in local {
+            if !distant.contains_key(id) && statut(element) != STATUT_SUPPRIME {
+                res.manquants_distant.push(id.clone());
+            }
+        }
+        res
+    }
+
+    /// Copie dans la sauvegarde les elements manquants et obsoletes.
+    pub fn recuperer(
+        data: &mut Value,
+        distant: &BTreeMap<String, ElementDistant>,
+        reconciliation: &mut Reconciliation,
+    ) {
+        let liste = &mut data[DATA_LISTE];
+        for id in reconciliation
+            .manquants_local
+            .iter()
+            .chain(reconciliation.obsoletes.iter())
+        {
+            if let Some(element) = distant.get(id).and_then(|e| e.element.clone()) {
+                liste[id] = element;
+                reconciliation.nb_recuperes += 1;
+            }
+        }
+        log::info!("elements recuperes : {}", reconciliation.nb_recuperes);
+    }
+}
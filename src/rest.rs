@@ -0,0 +1,604 @@
+pub mod mod_rest {
+    use crate::config::mod_config::{Config2, ConfigApi};
+    use crate::donnees::mod_donnees::{champ_str, liste};
+    use crate::filtre::mod_filtre::{filtrer, Cle, Filtres, Tri, NOMS_FILTRES};
+    use crate::historique::mod_historique::charger_historique;
+    use crate::import::mod_import::normaliser_url;
+    use crate::stats::mod_stats::calcul_stats;
+    use crate::versions::mod_versions::versions_element;
+    use crate::web::mod_web::{Bibliotheque, Reponse};
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+    use std::cmp::Ordering;
+    use std::collections::BTreeMap;
+    use tiny_http::Request;
+
+    pub const VERSION_API: &str = "v1";
+
+    fn json<T: Serialize + ?Sized>(code: u16, valeur: &T) -> Reponse {
+        let corps = serde_json::to_vec_pretty(valeur).expect("Erreur de sérialisation");
+        Reponse::new(code, "application/json", corps)
+    }
+
+    fn erreur(code: u16, message: &str) -> Reponse {
+        json(code, &json!({ "erreur": message }))
+    }
+
+    fn entete<'a>(requete: &'a Request, nom: &'static str) -> Option<&'a str> {
+        requete
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv(nom))
+            .map(|h| h.value.as_str())
+    }
+
+    /// Comparaison en temps constant pour ne pas laisser deviner le jeton.
+    fn egal(a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    /// Jeton de `[api]` exige, s'il est renseigne, par l'API et l'interface
+    /// web.
+    pub fn autorise(config: &ConfigApi, requete: &Request) -> bool {
+        if config.jeton.is_empty() {
+            return true;
+        }
+        entete(requete, "Authorization")
+            .and_then(|valeur| valeur.trim().strip_prefix("Bearer "))
+            .map(|jeton| egal(jeton.trim().as_bytes(), config.jeton.as_bytes()))
+            .unwrap_or(false)
+    }
+
+    pub fn non_autorise() -> Reponse {
+        let mut reponse = erreur(401, "jeton absent ou invalide");
+        reponse
+            .entetes
+            .push(("WWW-Authenticate", "Bearer realm=\"pbackup\"".to_string()));
+        reponse
+    }
+
+    /// Element avec l'etat de sa copie archivee et le nombre de versions
+    /// precedentes.
+    fn detail(config: &Config2, bibliotheque: &Bibliotheque, id: &str) -> Reponse {
+        match bibliotheque.element(id) {
+            Some(element) => json(
+                200,
+                &json!({
+                    "itemId": id,
+                    "element": element,
+                    "archive": bibliotheque.archives.get(id),
+                    "nbVersions": versions_element(config.repertoire.as_str(), id).len(),
+                }),
+            ),
+            None => erreur(404, "element introuvable"),
+        }
+    }
+
+    /// Recherche par URL donnee ou resolue, comparees apres normalisation.
+    fn par_url(
+        config: &Config2,
+        bibliotheque: &Bibliotheque,
+        parametres: &BTreeMap<String, String>,
+    ) -> Reponse {
+        let Some(cherchee) = parametres.get("url").filter(|u| !u.trim().is_empty()) else {
+            return erreur(400, "parametre 'url' absent");
+        };
+        let cle = normaliser_url(cherchee);
+        let trouve = liste(&bibliotheque.data).and_then(|l| {
+            l.iter().find(|(_, e)| {
+                ["given_url", "resolved_url"].iter().any(|champ| {
+                    let u = champ_str(e, champ);
+                    !u.is_empty() && normaliser_url(u) == cle
+                })
+            })
+        });
+        match trouve {
+            Some((id, _)) => detail(config, bibliotheque, id),
+            None => erreur(404, "aucun element pour cette url"),
+        }
+    }
+
+    /// Dernier element d'une page : tri demande, cle de tri et id. La page
+    /// suivante reprend juste apres cette position, meme si l'element a ete
+    /// supprime entre temps.
+    #[derive(Serialize, Deserialize)]
+    struct Curseur {
+        tri: String,
+        cle: Cle,
+        id: String,
+    }
+
+    impl Curseur {
+        /// JSON en hexadecimal, opaque pour le client.
+        fn encoder(&self) -> String {
+            serde_json::to_vec(self)
+                .expect("Erreur de sérialisation")
+                .iter()
+                .map(|o| format!("{:02x}", o))
+                .collect()
+        }
+
+        fn decoder(texte: &str) -> Option<Curseur> {
+            if !texte.is_ascii() || !texte.len().is_multiple_of(2) {
+                return None;
+            }
+            let octets = (0..texte.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&texte[i..i + 2], 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+            serde_json::from_slice(&octets).ok()
+        }
+    }
+
+    /// Liste filtree (memes filtres que la commande `list`), paginee par
+    /// curseur : `curseur` est le `curseurSuivant` de la page precedente,
+    /// valable pour le meme tri.
+    fn liste_elements(
+        config: &Config2,
+        bibliotheque: &Bibliotheque,
+        parametres: &BTreeMap<String, String>,
+    ) -> Reponse {
+        let mut filtres = Filtres::default();
+        for (nom, valeur) in parametres {
+            if NOMS_FILTRES.contains(&nom.as_str()) {
+                if let Err(err) = filtres.ajouter(nom, valeur) {
+                    return erreur(400, err.as_str());
+                }
+            }
+        }
+        let nom_tri = parametres.get("tri").map(|s| s.as_str()).unwrap_or("");
+        let tri = match nom_tri {
+            "" => Tri::default(),
+            s => match Tri::parse(s) {
+                Ok(tri) => tri,
+                Err(err) => return erreur(400, err.as_str()),
+            },
+        };
+        let limite_max = config.api.limite_max.max(1);
+        let limite = match parametres.get("limite") {
+            Some(s) => match s.parse::<usize>() {
+                Ok(n) if n > 0 => n.min(limite_max),
+                _ => return erreur(400, "limite invalide"),
+            },
+            None => config.api.limite.clamp(1, limite_max),
+        };
+        let elements = filtrer(&bibliotheque.data, &filtres, tri);
+        let total = elements.len();
+        let debut = match parametres.get("curseur") {
+            Some(texte) => {
+                let Some(curseur) = Curseur::decoder(texte).filter(|c| c.tri == nom_tri) else {
+                    return erreur(400, "curseur invalide");
+                };
+                elements
+                    .iter()
+                    .position(|(id, e)| {
+                        tri.comparer_cles((id, &tri.cle(e)), (&curseur.id, &curseur.cle))
+                            == Ordering::Greater
+                    })
+                    .unwrap_or(total)
+            }
+            None => 0,
+        };
+        let page = &elements[debut..total.min(debut + limite)];
+        let curseur_suivant = match page.last() {
+            Some((id, element)) if debut + page.len() < total => Some(
+                Curseur {
+                    tri: nom_tri.to_string(),
+                    cle: tri.cle(element),
+                    id: id.to_string(),
+                }
+                .encoder(),
+            ),
+            _ => None,
+        };
+        let page: Vec<&Value> = page.iter().map(|(_, e)| *e).collect();
+        json(
+            200,
+            &json!({
+                "total": total,
+                "elements": page,
+                "curseurSuivant": curseur_suivant,
+            }),
+        )
+    }
+
+    fn statistiques(bibliotheque: &Bibliotheque, parametres: &BTreeMap<String, String>) -> Reponse {
+        let nb_domaines = match parametres.get("top") {
+            Some(s) => match s.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => return erreur(400, "top invalide"),
+            },
+            None => 20,
+        };
+        json(200, &calcul_stats(&bibliotheque.data, nb_domaines))
+    }
+
+    /// Historique des executions, de la plus recente a la plus ancienne.
+    fn executions(config: &Config2, parametres: &BTreeMap<String, String>) -> Reponse {
+        let limite = match parametres.get("limite") {
+            Some(s) => match s.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => return erreur(400, "limite invalide"),
+            },
+            None => usize::MAX,
+        };
+        let mut historique = charger_historique(config.repertoire.as_str());
+        historique.reverse();
+        historique.truncate(limite);
+        json(200, &historique)
+    }
+
+    fn parametre(nom: &str, description: &str) -> Value {
+        json!({
+            "name": nom,
+            "in": "query",
+            "required": false,
+            "description": description,
+            "schema": { "type": "string" },
+        })
+    }
+
+    fn operation(resume: &str, parametres: Vec<Value>, reponses: &[u16]) -> Value {
+        let mut codes = serde_json::Map::new();
+        for code in reponses {
+            let description = match code {
+                200 => "succes",
+                304 => "inchange depuis l'ETag de If-None-Match",
+                400 => "parametre invalide",
+                401 => "jeton absent ou invalide",
+                _ => "introuvable",
+            };
+            codes.insert(code.to_string(), json!({ "description": description }));
+        }
+        json!({
+            "get": {
+                "summary": resume,
+                "parameters": parametres,
+                "responses": codes,
+            }
+        })
+    }
+
+    /// Description OpenAPI 3 de l'API.
+    pub fn openapi() -> Value {
+        let filtres: Vec<Value> = NOMS_FILTRES
+            .iter()
+            .map(|nom| parametre(nom, "filtre de la commande list"))
+            .chain([
+                parametre(
+                    "tri",
+                    "ajoute, maj, mots, titre, domaine ou id ; '-' pour decroissant",
+                ),
+                parametre("limite", "nombre d'elements par page"),
+                parametre("curseur", "curseurSuivant de la page precedente"),
+            ])
+            .collect();
+        let id = json!({
+            "name": "id",
+            "in": "path",
+            "required": true,
+            "schema": { "type": "string" },
+        });
+        let base = format!("/api/{}", VERSION_API);
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "pbackup",
+                "version": env!("CARGO_PKG_VERSION"),
+                "description": "Consultation en lecture seule de la sauvegarde Pocket",
+            },
+            "components": {
+                "securitySchemes": { "jeton": { "type": "http", "scheme": "bearer" } },
+            },
+            "security": [ { "jeton": [] } ],
+            "paths": {
+                format!("{}/items", base): operation(
+                    "Liste filtree et triee, paginee par curseur",
+                    filtres,
+                    &[200, 304, 400, 401],
+                ),
+                format!("{}/items/{{id}}", base): operation(
+                    "Element par id, avec l'etat de son archive",
+                    vec![id],
+                    &[200, 304, 401, 404],
+                ),
+                format!("{}/lookup", base): operation(
+                    "Element par URL donnee ou resolue",
+                    vec![parametre("url", "URL de l'element")],
+                    &[200, 304, 400, 401, 404],
+                ),
+                format!("{}/stats", base): operation(
+                    "Statistiques de la commande stats",
+                    vec![parametre("top", "nombre de domaines et langues")],
+                    &[200, 304, 400, 401],
+                ),
+                format!("{}/runs", base): operation(
+                    "Historique des executions, la plus recente en premier",
+                    vec![parametre("limite", "nombre d'executions")],
+                    &[200, 304, 400, 401],
+                ),
+                format!("{}/openapi.json", base): operation("Ce document", Vec::new(), &[200]),
+            },
+        })
+    }
+
+    /// ETag fort calcule sur le corps ; `If-None-Match` identique donne un 304
+    /// sans corps.
+    fn etiqueter(requete: &Request, mut reponse: Reponse) -> Reponse {
+        if reponse.code != 200 {
+            return reponse;
+        }
+        let etag = format!("\"{:x}\"", Sha256::digest(&reponse.corps));
+        let inchange = entete(requete, "If-None-Match")
+            .map(|valeur| {
+                valeur
+                    .split(',')
+                    .map(|v| v.trim().trim_start_matches("W/"))
+                    .any(|v| v == etag || v == "*")
+            })
+            .unwrap_or(false);
+        if inchange {
+            reponse.code = 304;
+            reponse.corps = Vec::new();
+        }
+        reponse.entetes.push(("ETag", etag));
+        reponse
+            .entetes
+            .push(("Cache-Control", "no-cache".to_string()));
+        reponse
+    }
+
+    /// Reponse aux chemins `/api/v1/...` ; `segments` suit `api`.
+    pub fn repondre(
+        config: &Config2,
+        bibliotheque: &Bibliotheque,
+        requete: &Request,
+        segments: &[&str],
+        parametres: &BTreeMap<String, String>,
+    ) -> Reponse {
+        let Some((&VERSION_API, chemin)) = segments.split_first() else {
+            return erreur(404, "version d'API inconnue");
+        };
+        if !config.api.actif {
+            return erreur(404, "API desactivee");
+        }
+        if chemin == ["openapi.json"] {
+            return etiqueter(requete, json(200, &openapi()));
+        }
+        if !autorise(&config.api, requete) {
+            return non_autorise();
+        }
+        let reponse = match chemin {
+            ["items"] => liste_elements(config, bibliotheque, parametres),
+            ["items", id] => detail(config, bibliotheque, id),
+            ["lookup"] => par_url(config, bibliotheque, parametres),
+            ["stats"] => statistiques(bibliotheque, parametres),
+            ["runs"] => executions(config, parametres),
+            _ => erreur(404, "chemin inconnu"),
+        };
+        etiqueter(requete, reponse)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::recherche::mod_recherche::Index;
+        use tiny_http::{Header, TestRequest};
+
+        fn element(id: &str, url: &str, ajoute: i64) -> Value {
+            json!({
+                "item_id": id,
+                "given_url": url,
+                "resolved_title": format!("Titre {}", id),
+                "status": "0",
+                "time_added": ajoute.to_string(),
+            })
+        }
+
+        fn bibliotheque() -> Bibliotheque {
+            let mut liste = serde_json::Map::new();
+            for i in 1..=5 {
+                let id = i.to_string();
+                let url = format!("https://exemple.org/{}", i);
+                liste.insert(id.clone(), element(&id, &url, i * 100));
+            }
+            Bibliotheque {
+                data: json!({ "liste": liste }),
+                index: Index::default(),
+                archives: BTreeMap::new(),
+                date: 0,
+            }
+        }
+
+        /// `chemin` sous `/api/v1/`, avec ses parametres.
+        fn appel(
+            config: &Config2,
+            bibliotheque: &Bibliotheque,
+            chemin: &str,
+            entetes: &[(&str, &str)],
+        ) -> Reponse {
+            let mut requete = TestRequest::new().with_path(format!("/api/v1/{}", chemin).as_str());
+            for (nom, valeur) in entetes {
+                requete = requete.with_header(Header::from_bytes(*nom, *valeur).unwrap());
+            }
+            let requete: Request = requete.into();
+            let adresse =
+                reqwest::Url::parse(format!("http://localhost/api/v1/{}", chemin).as_str())
+                    .unwrap();
+            let parametres: BTreeMap<String, String> = adresse.query_pairs().into_owned().collect();
+            let segments: Vec<&str> = adresse.path_segments().unwrap().skip(1).collect();
+            repondre(config, bibliotheque, &requete, &segments, &parametres)
+        }
+
+        fn corps(reponse: &Reponse) -> Value {
+            serde_json::from_slice(&reponse.corps).unwrap()
+        }
+
+        fn ids(reponse: &Reponse) -> Vec<String> {
+            corps(reponse)["elements"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| e["item_id"].as_str().unwrap().to_string())
+                .collect()
+        }
+
+        fn entete<'a>(reponse: &'a Reponse, nom: &str) -> Option<&'a str> {
+            reponse
+                .entetes
+                .iter()
+                .find(|(n, _)| *n == nom)
+                .map(|(_, v)| v.as_str())
+        }
+
+        #[test]
+        fn jeton_exige() {
+            let mut config = Config2::pour_test("");
+            config.api.jeton = "secret".to_string();
+            let bibliotheque = bibliotheque();
+            let reponse = appel(&config, &bibliotheque, "items", &[]);
+            assert_eq!(reponse.code, 401);
+            assert!(entete(&reponse, "WWW-Authenticate").is_some());
+            let faux = [("Authorization", "Bearer secreT")];
+            assert_eq!(appel(&config, &bibliotheque, "items/1", &faux).code, 401);
+            let bon = [("Authorization", "Bearer secret")];
+            assert_eq!(appel(&config, &bibliotheque, "items/1", &bon).code, 200);
+            assert_eq!(appel(&config, &bibliotheque, "openapi.json", &[]).code, 200);
+        }
+
+        #[test]
+        fn etag_et_304() {
+            let config = Config2::pour_test("");
+            let bibliotheque = bibliotheque();
+            let reponse = appel(&config, &bibliotheque, "items/3", &[]);
+            assert_eq!(reponse.code, 200);
+            let etag = entete(&reponse, "ETag").unwrap().to_string();
+            let reponse = appel(
+                &config,
+                &bibliotheque,
+                "items/3",
+                &[("If-None-Match", &etag)],
+            );
+            assert_eq!(reponse.code, 304);
+            assert!(reponse.corps.is_empty());
+            assert_eq!(entete(&reponse, "ETag"), Some(etag.as_str()));
+            let faible = format!("\"autre\", W/{}", etag);
+            let reponse = appel(
+                &config,
+                &bibliotheque,
+                "items/3",
+                &[("If-None-Match", &faible)],
+            );
+            assert_eq!(reponse.code, 304);
+            let reponse = appel(
+                &config,
+                &bibliotheque,
+                "items/4",
+                &[("If-None-Match", &etag)],
+            );
+            assert_eq!(reponse.code, 200);
+            let reponse = appel(&config, &bibliotheque, "items/9", &[("If-None-Match", "*")]);
+            assert_eq!(reponse.code, 404);
+        }
+
+        #[test]
+        fn pages_par_curseur() {
+            let config = Config2::pour_test("");
+            let mut bibliotheque = bibliotheque();
+            let mut vus = Vec::new();
+            let mut chemin = "items?limite=2".to_string();
+            loop {
+                let reponse = appel(&config, &bibliotheque, chemin.as_str(), &[]);
+                assert_eq!(reponse.code, 200);
+                assert_eq!(corps(&reponse)["total"], 5);
+                vus.extend(ids(&reponse));
+                match corps(&reponse)["curseurSuivant"].as_str() {
+                    Some(curseur) => chemin = format!("items?limite=2&curseur={}", curseur),
+                    None => break,
+                }
+            }
+            assert_eq!(vus, ["5", "4", "3", "2", "1"]);
+
+            // le dernier element de la page a ete supprime depuis
+            let reponse = appel(&config, &bibliotheque, "items?limite=2&tri=ajoute", &[]);
+            assert_eq!(ids(&reponse), ["1", "2"]);
+            let curseur = corps(&reponse)["curseurSuivant"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            bibliotheque.data["liste"]
+                .as_object_mut()
+                .unwrap()
+                .remove("2");
+            let chemin = format!("items?limite=2&tri=ajoute&curseur={}", curseur);
+            let reponse = appel(&config, &bibliotheque, chemin.as_str(), &[]);
+            assert_eq!(reponse.code, 200);
+            assert_eq!(ids(&reponse), ["3", "4"]);
+
+            // curseur d'un autre tri, ou illisible
+            let chemin = format!("items?limite=2&tri=-ajoute&curseur={}", curseur);
+            assert_eq!(
+                appel(&config, &bibliotheque, chemin.as_str(), &[]).code,
+                400
+            );
+            for curseur in ["2", "zz", "%C3%A9%C3%A9", "7b7d"] {
+                let chemin = format!("items?curseur={}", curseur);
+                assert_eq!(
+                    appel(&config, &bibliotheque, chemin.as_str(), &[]).code,
+                    400
+                );
+            }
+        }
+
+        #[test]
+        fn limites_nulles() {
+            let mut config = Config2::pour_test("");
+            config.api.limite = 0;
+            config.api.limite_max = 0;
+            let bibliotheque = bibliotheque();
+            let reponse = appel(&config, &bibliotheque, "items", &[]);
+            assert_eq!(ids(&reponse), ["5"]);
+            assert!(corps(&reponse)["curseurSuivant"].is_string());
+            let reponse = appel(&config, &bibliotheque, "items?limite=10", &[]);
+            assert_eq!(ids(&reponse), ["5"]);
+            assert_eq!(
+                appel(&config, &bibliotheque, "items?limite=0", &[]).code,
+                400
+            );
+            let reponse = appel(&config, &bibliotheque, "items?statut=archive", &[]);
+            assert_eq!(corps(&reponse)["total"], 0);
+            assert!(corps(&reponse)["curseurSuivant"].is_null());
+        }
+
+        #[test]
+        fn element_par_url() {
+            let config = Config2::pour_test("");
+            let mut bibliotheque = bibliotheque();
+            bibliotheque.data["liste"]["3"]["resolved_url"] = json!("https://exemple.org/article/");
+            let reponse = appel(
+                &config,
+                &bibliotheque,
+                "lookup?url=http%3A%2F%2Fwww.EXEMPLE.org%2Farticle%3Futm_source%3Dx%23haut",
+                &[],
+            );
+            assert_eq!(reponse.code, 200);
+            assert_eq!(corps(&reponse)["itemId"], "3");
+            let reponse = appel(
+                &config,
+                &bibliotheque,
+                "lookup?url=https://exemple.org/4/",
+                &[],
+            );
+            assert_eq!(corps(&reponse)["itemId"], "4");
+            let reponse = appel(
+                &config,
+                &bibliotheque,
+                "lookup?url=https://exemple.org/9",
+                &[],
+            );
+            assert_eq!(reponse.code, 404);
+            assert_eq!(appel(&config, &bibliotheque, "lookup?url=", &[]).code, 400);
+        }
+    }
+}
@@ -0,0 +1,397 @@
+pub mod mod_retention {
+    use crate::config::mod_config::ConfigRetention;
+    use crate::instantane::mod_instantane::Compression;
+    use chrono::{DateTime, Datelike, Local};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    pub const REPERTOIRE_BACKUP: &str = "/backup";
+
+    pub fn repertoire_backup(repertoire: &str) -> String {
+        repertoire.to_string() + REPERTOIRE_BACKUP
+    }
+
+    /// Copie de `backup/`, nommee `<serie>_<timestamp>.json` (serie `data`
+    /// ou `param`), suivi de `.gz` ou `.zst` si elle est compressee.
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Instantane {
+        pub fichier: String,
+        pub serie: String,
+        pub date: i64,
+    }
+
+    /// `data_1700000000.json.gz` -> (`data`, 1700000000) ; les autres
+    /// fichiers, dont les copies temporaires, ne sont jamais touches.
+    pub fn lire_nom(nom: &str) -> Option<(String, i64)> {
+        let base = nom.split('.').next()?;
+        let (serie, date) = base.rsplit_once('_')?;
+        let compression = Compression::depuis_nom(nom)?;
+        if serie.is_empty() || nom[base.len()..] != format!(".json{}", compression.extension()) {
+            return None;
+        }
+        Some((serie.to_string(), date.parse::<i64>().ok()?))
+    }
+
+    /// Instantanes de `backup/`, absents si le repertoire n'existe pas.
+    pub fn lister_instantanes(repertoire: &str) -> io::Result<Vec<Instantane>> {
+        let dossier = repertoire_backup(repertoire);
+        if !Path::new(&dossier).exists() {
+            return Ok(Vec::new());
+        }
+        let mut res = Vec::new();
+        for entree in fs::read_dir(&dossier)? {
+            let entree = entree?;
+            if !entree.file_type()?.is_file() {
+                continue;
+            }
+            let nom = entree.file_name().to_string_lossy().to_string();
+            if let Some((serie, date)) = lire_nom(&nom) {
+                res.push(Instantane {
+                    fichier: nom,
+                    serie,
+                    date,
+                });
+            }
+        }
+        Ok(res)
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Decision {
+        pub instantane: Instantane,
+        /// regles qui conservent l'instantane, vide s'il est supprime
+        pub raisons: Vec<String>,
+    }
+
+    #[derive(Debug, Default, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BilanRetention {
+        pub dry_run: bool,
+        pub gardes: Vec<Decision>,
+        pub supprimes: Vec<Decision>,
+        pub erreurs: Vec<String>,
+    }
+
+    fn date_locale(timestamp: i64) -> String {
+        DateTime::from_timestamp(timestamp, 0)
+            .map(|d| d.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    }
+
+    impl fmt::Display for BilanRetention {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let suffixe = if self.dry_run { " (dry-run)" } else { "" };
+            writeln!(
+                f,
+                "{} instantanes gardes, {} supprimes{}",
+                self.gardes.len(),
+                self.supprimes.len(),
+                suffixe
+            )?;
+            for decision in &self.gardes {
+                writeln!(
+                    f,
+                    "  garde     {} ({}) : {}",
+                    decision.instantane.fichier,
+                    date_locale(decision.instantane.date),
+                    decision.raisons.join(", ")
+                )?;
+            }
+            for decision in &self.supprimes {
+                writeln!(
+                    f,
+                    "  supprime  {} ({})",
+                    decision.instantane.fichier,
+                    date_locale(decision.instantane.date)
+                )?;
+            }
+            for erreur in &self.erreurs {
+                writeln!(f, "  erreur : {}", erreur)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Une periode de la rotation : garde le plus recent instantane de
+    /// chacune des `limite` dernieres periodes qui en contiennent un.
+    struct Regle {
+        nom: &'static str,
+        limite: u32,
+        cle: fn(&DateTime<Local>) -> String,
+        nb: u32,
+        derniere: Option<String>,
+    }
+
+    impl Regle {
+        fn new(nom: &'static str, limite: u32, cle: fn(&DateTime<Local>) -> String) -> Regle {
+            Regle {
+                nom,
+                limite,
+                cle,
+                nb: 0,
+                derniere: None,
+            }
+        }
+
+        fn garde(&mut self, date: &DateTime<Local>) -> Option<String> {
+            let cle = (self.cle)(date);
+            if self.nb >= self.limite || self.derniere.as_ref() == Some(&cle) {
+                return None;
+            }
+            self.nb += 1;
+            self.derniere = Some(cle.clone());
+            Some(format!("{} {}", self.nom, cle))
+        }
+    }
+
+    /// Rotation grand-pere/pere/fils, serie par serie : les `derniers`
+    /// instantanes, puis un par jour, par semaine et par mois.
+    pub fn selectionner(
+        config: &ConfigRetention,
+        instantanes: Vec<Instantane>,
+    ) -> (Vec<Decision>, Vec<Decision>) {
+        let mut series: BTreeMap<String, Vec<Instantane>> = BTreeMap::new();
+        for instantane in instantanes {
+            series
+                .entry(instantane.serie.clone())
+                .or_default()
+                .push(instantane);
+        }
+        let mut gardes = Vec::new();
+        let mut supprimes = Vec::new();
+        for (_, mut serie) in series {
+            serie.sort_by_key(|i| std::cmp::Reverse(i.date));
+            let mut regles = [
+                Regle::new("jour", config.jours, |d| d.format("%Y-%m-%d").to_string()),
+                Regle::new("semaine", config.semaines, |d| {
+                    let semaine = d.iso_week();
+                    format!("{}-S{:02}", semaine.year(), semaine.week())
+                }),
+                Regle::new("mois", config.mois, |d| d.format("%Y-%m").to_string()),
+            ];
+            for (i, instantane) in serie.into_iter().enumerate() {
+                let mut raisons = Vec::new();
+                if (i as u32) < config.derniers {
+                    raisons.push(format!("dernier {}", i + 1));
+                }
+                if let Some(date) = DateTime::from_timestamp(instantane.date, 0) {
+                    let date = date.with_timezone(&Local);
+                    raisons.extend(regles.iter_mut().filter_map(|r| r.garde(&date)));
+                }
+                let decision = Decision {
+                    instantane,
+                    raisons,
+                };
+                if decision.raisons.is_empty() {
+                    supprimes.push(decision);
+                } else {
+                    gardes.push(decision);
+                }
+            }
+        }
+        (gardes, supprimes)
+    }
+
+    /// Supprime les instantanes hors retention ; `dry_run` ne fait que les
+    /// lister. Sans regle configuree, tout est garde.
+    pub fn elaguer(
+        config: &ConfigRetention,
+        repertoire: &str,
+        dry_run: bool,
+    ) -> io::Result<BilanRetention> {
+        let instantanes = lister_instantanes(repertoire)?;
+        let mut bilan = BilanRetention {
+            dry_run,
+            ..Default::default()
+        };
+        if !config.est_active() {
+            bilan.gardes = instantanes
+                .into_iter()
+                .map(|instantane| Decision {
+                    instantane,
+                    raisons: vec!["pas de retention".to_string()],
+                })
+                .collect();
+            return Ok(bilan);
+        }
+        let (gardes, supprimes) = selectionner(config, instantanes);
+        bilan.gardes = gardes;
+        let dossier = repertoire_backup(repertoire);
+        for decision in supprimes {
+            if !dry_run {
+                let fichier = format!("{}/{}", dossier, decision.instantane.fichier);
+                if let Err(err) = fs::remove_file(&fichier) {
+                    bilan.erreurs.push(format!("{} : {}", fichier, err));
+                    continue;
+                }
+                log::info!("suppression de {}", fichier);
+            }
+            bilan.supprimes.push(decision);
+        }
+        Ok(bilan)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::TimeZone;
+
+        /// Instantane date en heure locale, comme les regles.
+        fn instantane(serie: &str, date: (i32, u32, u32, u32, u32)) -> Instantane {
+            let (a, m, j, h, min) = date;
+            let date = Local
+                .with_ymd_and_hms(a, m, j, h, min, 0)
+                .unwrap()
+                .timestamp();
+            Instantane {
+                fichier: format!("{}_{}.json", serie, date),
+                serie: serie.to_string(),
+                date,
+            }
+        }
+
+        fn regles(derniers: u32, jours: u32, semaines: u32, mois: u32) -> ConfigRetention {
+            ConfigRetention {
+                derniers,
+                jours,
+                semaines,
+                mois,
+                automatique: false,
+            }
+        }
+
+        /// Dates (jour, heure) des instantanes gardes, du plus recent au
+        /// plus ancien.
+        fn gardes(config: &ConfigRetention, instantanes: Vec<Instantane>) -> Vec<String> {
+            let (gardes, _) = selectionner(config, instantanes);
+            gardes
+                .iter()
+                .map(|d| date_locale(d.instantane.date))
+                .collect()
+        }
+
+        #[test]
+        fn noms_des_instantanes() {
+            assert_eq!(
+                lire_nom("data_1700000000.json"),
+                Some(("data".to_string(), 1700000000))
+            );
+            assert_eq!(
+                lire_nom("param_1700000000.json.zst"),
+                Some(("param".to_string(), 1700000000))
+            );
+            assert_eq!(lire_nom("data_1700000000.json.gz.tmp"), None);
+            assert_eq!(lire_nom("data_1700000000.txt"), None);
+            assert_eq!(lire_nom("_1700000000.json"), None);
+        }
+
+        #[test]
+        fn derniers_par_serie() {
+            let instantanes = vec![
+                instantane("data", (2024, 3, 1, 10, 0)),
+                instantane("data", (2024, 3, 2, 10, 0)),
+                instantane("data", (2024, 3, 3, 10, 0)),
+                instantane("param", (2024, 3, 1, 10, 0)),
+            ];
+            let (gardes, supprimes) = selectionner(&regles(2, 0, 0, 0), instantanes);
+            let noms: Vec<(String, String)> = gardes
+                .iter()
+                .map(|d| (d.instantane.serie.clone(), date_locale(d.instantane.date)))
+                .collect();
+            assert_eq!(
+                noms,
+                [
+                    ("data".to_string(), "2024-03-03 10:00".to_string()),
+                    ("data".to_string(), "2024-03-02 10:00".to_string()),
+                    ("param".to_string(), "2024-03-01 10:00".to_string()),
+                ]
+            );
+            assert_eq!(gardes[0].raisons, ["dernier 1"]);
+            assert_eq!(supprimes.len(), 1);
+            assert!(supprimes[0].raisons.is_empty());
+        }
+
+        #[test]
+        fn un_par_jour_au_plus_recent() {
+            let instantanes = vec![
+                instantane("data", (2024, 3, 4, 8, 0)),
+                instantane("data", (2024, 3, 4, 20, 0)),
+                instantane("data", (2024, 3, 3, 23, 59)),
+                instantane("data", (2024, 3, 2, 10, 0)),
+            ];
+            assert_eq!(
+                gardes(&regles(0, 2, 0, 0), instantanes),
+                ["2024-03-04 20:00", "2024-03-03 23:59"]
+            );
+        }
+
+        #[test]
+        fn semaines_iso_du_lundi_au_dimanche() {
+            // le 3 mars 2024 est un dimanche (S09), le 4 un lundi (S10)
+            let instantanes = vec![
+                instantane("data", (2024, 3, 4, 10, 0)),
+                instantane("data", (2024, 3, 3, 22, 0)),
+                instantane("data", (2024, 3, 2, 10, 0)),
+                instantane("data", (2024, 2, 25, 10, 0)),
+            ];
+            let (gardes, supprimes) = selectionner(&regles(0, 0, 2, 0), instantanes);
+            assert_eq!(gardes[0].raisons, ["semaine 2024-S10"]);
+            assert_eq!(gardes[1].raisons, ["semaine 2024-S09"]);
+            assert_eq!(gardes.len(), 2);
+            assert_eq!(supprimes.len(), 2);
+        }
+
+        #[test]
+        fn semaine_a_cheval_sur_deux_annees() {
+            // le 31 decembre 2024 est dans la semaine 2025-S01
+            let instantanes = vec![
+                instantane("data", (2025, 1, 2, 10, 0)),
+                instantane("data", (2024, 12, 31, 10, 0)),
+                instantane("data", (2024, 12, 29, 10, 0)),
+            ];
+            let (gardes, _) = selectionner(&regles(0, 0, 5, 0), instantanes);
+            let raisons: Vec<&str> = gardes.iter().map(|d| d.raisons[0].as_str()).collect();
+            assert_eq!(raisons, ["semaine 2025-S01", "semaine 2024-S52"]);
+        }
+
+        #[test]
+        fn un_par_mois() {
+            let instantanes = vec![
+                instantane("data", (2024, 3, 1, 0, 30)),
+                instantane("data", (2024, 2, 29, 23, 30)),
+                instantane("data", (2024, 2, 10, 10, 0)),
+                instantane("data", (2024, 1, 31, 10, 0)),
+            ];
+            assert_eq!(
+                gardes(&regles(0, 0, 0, 2), instantanes),
+                ["2024-03-01 00:30", "2024-02-29 23:30"]
+            );
+        }
+
+        #[test]
+        fn regles_combinees() {
+            let instantanes = vec![
+                instantane("data", (2024, 3, 4, 20, 0)),
+                instantane("data", (2024, 3, 4, 8, 0)),
+                instantane("data", (2024, 2, 15, 10, 0)),
+                instantane("data", (2024, 1, 15, 10, 0)),
+            ];
+            let (gardes, supprimes) = selectionner(&regles(1, 1, 0, 3), instantanes);
+            assert_eq!(
+                gardes[0].raisons,
+                ["dernier 1", "jour 2024-03-04", "mois 2024-03"]
+            );
+            assert_eq!(gardes.len(), 3);
+            assert_eq!(
+                date_locale(supprimes[0].instantane.date),
+                "2024-03-04 08:00"
+            );
+        }
+    }
+}
@@ -0,0 +1,286 @@
+pub mod mod_source {
+    use crate::anomalie::mod_anomalie::total_reponse;
+    use crate::api::mod_api::{appel_get, ErreurApi, Parameters};
+    use crate::config::mod_config::Config2;
+    use crate::donnees::mod_donnees::champ_nombre;
+    use crate::linkding::mod_linkding::SourceLinkding;
+    use crate::wallabag::mod_wallabag::SourceWallabag;
+    use reqwest::Client;
+    use serde_json::Value;
+
+    pub const SOURCE_POCKET: &str = "pocket";
+    pub const SOURCE_WALLABAG: &str = "wallabag";
+    pub const SOURCE_LINKDING: &str = "linkding";
+
+    /// Une page de resultats, deja convertie au format des elements de
+    /// `/v3/get` (item_id, given_url, time_added, status, tags...).
+    #[derive(Debug, Default)]
+    pub struct Page {
+        /// elements dans l'ordre de la reponse
+        pub elements: Vec<(String, Value)>,
+        /// curseur a utiliser comme since pour la prochaine mise a jour
+        pub since: Option<i64>,
+        /// nombre total d'elements annonce par le serveur
+        pub total: Option<u64>,
+        pub limite_utilisateur: Option<i64>,
+        pub limite_cle: Option<i64>,
+    }
+
+    /// Date sur laquelle le service trie les elements d'une liste.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ChampTri {
+        Ajout,
+        MiseAJour,
+    }
+
+    /// Service dont la bibliotheque est sauvegardee. Les pages sont lues
+    /// par offset ; une page vide termine le parcourt.
+    pub trait SourceProvider {
+        fn nom(&self) -> &'static str;
+
+        /// Obtention d'un jeton si le service en a besoin.
+        async fn authentifier(&mut self, client: &Client) -> Result<(), ErreurApi>;
+
+        /// Elements a partir de `offset`, modifies depuis `since` si donne.
+        async fn page(
+            &mut self,
+            client: &Client,
+            offset: u64,
+            since: Option<u64>,
+        ) -> Result<Page, ErreurApi>;
+
+        /// Date croissante dans les pages demandees avec ou sans since : par
+        /// defaut la mise a jour avec since, l'ajout sinon. `None` si le
+        /// service ne garantit pas d'ordre : la detection d'anomalies ignore
+        /// alors les dates.
+        fn champ_tri(&self, since: Option<u64>) -> Option<ChampTri> {
+            if since.is_some() {
+                Some(ChampTri::MiseAJour)
+            } else {
+                Some(ChampTri::Ajout)
+            }
+        }
+    }
+
+    pub struct SourcePocket {
+        url: String,
+        consumer_key: String,
+        access_token: String,
+        taille_page: u64,
+        /// `all` pour inclure les archives (reconciliation)
+        etat: Option<String>,
+    }
+
+    impl SourceProvider for SourcePocket {
+        fn nom(&self) -> &'static str {
+            SOURCE_POCKET
+        }
+
+        /// Les cles sont envoyees dans chaque requete.
+        async fn authentifier(&mut self, _client: &Client) -> Result<(), ErreurApi> {
+            Ok(())
+        }
+
+        async fn page(
+            &mut self,
+            client: &Client,
+            offset: u64,
+            since: Option<u64>,
+        ) -> Result<Page, ErreurApi> {
+            let param = Parameters {
+                consumer_key: self.consumer_key.clone(),
+                access_token: self.access_token.clone(),
+                //detail_type: "complete".parse().unwrap(),
+                detail_type: "simple".to_string(),
+                count: self.taille_page,
+                offset,
+                total: 1,
+                sort: "oldest".to_string(),
+                since,
+                state: self.etat.clone(),
+            };
+            log::info!("parametre : {}", param);
+            let reponse = appel_get(client, self.url.as_str(), &param).await?;
+            let obj = reponse.json.as_object().unwrap();
+            if obj.contains_key("maxActions") {
+                log::info!("maxActions: {}", obj["maxActions"].as_i64().unwrap_or(-1));
+            }
+            if obj.contains_key("cachetype") {
+                log::info!(
+                    "cachetype: {}",
+                    obj["cachetype"].as_str().unwrap_or("Inconnu")
+                );
+            }
+            if obj.contains_key("since") {
+                log::info!("since: {}", obj["since"].as_i64().unwrap_or(-1));
+            }
+            // la liste est un objet trie par identifiant : sort_id donne
+            // l'ordre de la reponse
+            let mut elements: Vec<(String, Value)> =
+                match obj.get("list").and_then(|l| l.as_object()) {
+                    Some(liste) => liste.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                    None => Vec::new(),
+                };
+            elements.sort_by_key(|e| champ_nombre(&e.1, "sort_id"));
+            Ok(Page {
+                elements,
+                since: obj.get("since").and_then(|s| s.as_i64()),
+                total: total_reponse(obj),
+                limite_utilisateur: reponse.limite_utilisateur,
+                limite_cle: reponse.limite_cle,
+            })
+        }
+    }
+
+    /// Source choisie par `[source] type`.
+    pub enum Source {
+        Pocket(SourcePocket),
+        Wallabag(SourceWallabag),
+        Linkding(SourceLinkding),
+    }
+
+    impl SourceProvider for Source {
+        fn nom(&self) -> &'static str {
+            match self {
+                Source::Pocket(s) => s.nom(),
+                Source::Wallabag(s) => s.nom(),
+                Source::Linkding(s) => s.nom(),
+            }
+        }
+
+        async fn authentifier(&mut self, client: &Client) -> Result<(), ErreurApi> {
+            match self {
+                Source::Pocket(s) => s.authentifier(client).await,
+                Source::Wallabag(s) => s.authentifier(client).await,
+                Source::Linkding(s) => s.authentifier(client).await,
+            }
+        }
+
+        async fn page(
+            &mut self,
+            client: &Client,
+            offset: u64,
+            since: Option<u64>,
+        ) -> Result<Page, ErreurApi> {
+            match self {
+                Source::Pocket(s) => s.page(client, offset, since).await,
+                Source::Wallabag(s) => s.page(client, offset, since).await,
+                Source::Linkding(s) => s.page(client, offset, since).await,
+            }
+        }
+
+        fn champ_tri(&self, since: Option<u64>) -> Option<ChampTri> {
+            match self {
+                Source::Pocket(s) => s.champ_tri(since),
+                Source::Wallabag(s) => s.champ_tri(since),
+                Source::Linkding(s) => s.champ_tri(since),
+            }
+        }
+    }
+
+    /// `complet` : tous les elements, y compris archives (reconciliation).
+    pub fn creer_source(config: &Config2, complet: bool) -> Option<Source> {
+        let taille_page = if config.source.taille_page > 0 {
+            config.source.taille_page
+        } else {
+            30
+        };
+        match config.source.type_source.as_str() {
+            "" | SOURCE_POCKET => Some(Source::Pocket(SourcePocket {
+                url: config.url.clone(),
+                consumer_key: config.consumer_key.clone(),
+                access_token: config.access_token.clone(),
+                taille_page,
+                etat: if complet {
+                    Some("all".to_string())
+                } else {
+                    None
+                },
+            })),
+            SOURCE_WALLABAG => Some(Source::Wallabag(SourceWallabag::new(
+                &config.source,
+                taille_page,
+            ))),
+            SOURCE_LINKDING => Some(Source::Linkding(SourceLinkding::new(
+                &config.source,
+                taille_page,
+            ))),
+            autre => {
+                log::error!("source inconnue : {}", autre);
+                None
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::bouchon::mod_bouchon::Bouchon;
+        use serde_json::json;
+
+        fn source_pocket(url: String) -> SourcePocket {
+            SourcePocket {
+                url,
+                consumer_key: "cle".to_string(),
+                access_token: "jeton".to_string(),
+                taille_page: 2,
+                etat: None,
+            }
+        }
+
+        #[tokio::test]
+        async fn pocket_page_dans_l_ordre_de_sort_id() {
+            let bouchon = Bouchon::demarrer(|_| {
+                let reponse = json!({
+                    "status": 1,
+                    "since": 1700000100,
+                    "total": "5",
+                    "list": {
+                        "10": {"item_id": "10", "sort_id": 1, "resolved_title": "b"},
+                        "9": {"item_id": "9", "sort_id": 0, "resolved_title": "a"}
+                    }
+                });
+                (200, reponse.to_string())
+            });
+            let mut source = source_pocket(format!("{}/v3/get", bouchon.adresse));
+            let page = source
+                .page(&Client::new(), 4, Some(1700000000))
+                .await
+                .unwrap();
+            let ids: Vec<&str> = page.elements.iter().map(|e| e.0.as_str()).collect();
+            assert_eq!(ids, ["9", "10"]);
+            assert_eq!(page.since, Some(1700000100));
+            assert_eq!(page.total, Some(5));
+
+            let requetes = bouchon.requetes();
+            assert_eq!(requetes.len(), 1);
+            assert_eq!(requetes[0].methode, "POST");
+            let corps: Value = serde_json::from_str(&requetes[0].corps).unwrap();
+            assert_eq!(corps["offset"], 4);
+            assert_eq!(corps["count"], 2);
+            assert_eq!(corps["since"], 1700000000);
+            assert_eq!(corps["sort"], "oldest");
+            assert_eq!(corps["consumer_key"], "cle");
+            assert!(corps.get("state").is_none());
+        }
+
+        #[tokio::test]
+        async fn pocket_liste_vide_termine_le_parcourt() {
+            let bouchon =
+                Bouchon::demarrer(|_| (200, json!({"status": 2, "list": []}).to_string()));
+            let mut source = source_pocket(format!("{}/v3/get", bouchon.adresse));
+            let page = source.page(&Client::new(), 0, None).await.unwrap();
+            assert!(page.elements.is_empty());
+            let corps: Value = serde_json::from_str(&bouchon.requetes()[0].corps).unwrap();
+            assert!(corps.get("since").is_none());
+        }
+
+        #[tokio::test]
+        async fn pocket_erreur_http() {
+            let bouchon = Bouchon::demarrer(|_| (503, "{}".to_string()));
+            let mut source = source_pocket(format!("{}/v3/get", bouchon.adresse));
+            let res = source.page(&Client::new(), 0, None).await;
+            assert!(matches!(res, Err(ErreurApi::Statut(s)) if s.as_u16() == 503));
+        }
+    }
+}
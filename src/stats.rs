@@ -0,0 +1,359 @@
+pub mod mod_stats {
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, date, domaine, est_favori, liste, statut, STATUT_ARCHIVE,
+        STATUT_NON_LU, STATUT_SUPPRIME,
+    };
+    use crate::minmax::mod_min_max::{create_min_max_avec_valeurs, MinMax};
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum FormatSortie {
+        Texte,
+        Json,
+        Csv,
+    }
+
+    impl FormatSortie {
+        pub fn parse(s: &str) -> Option<FormatSortie> {
+            match s {
+                "" | "texte" | "text" | "table" => Some(FormatSortie::Texte),
+                "json" => Some(FormatSortie::Json),
+                "csv" => Some(FormatSortie::Csv),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Ligne {
+        pub cle: String,
+        pub nombre: u64,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Section {
+        pub titre: String,
+        pub lignes: Vec<Ligne>,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub struct Rapport {
+        pub nb_total: u64,
+        pub sections: Vec<Section>,
+    }
+
+    const TRANCHES_MOTS: [(i64, &str); 6] = [
+        (0, "0"),
+        (1, "1-499"),
+        (500, "500-999"),
+        (1000, "1000-1999"),
+        (2000, "2000-4999"),
+        (5000, "5000+"),
+    ];
+
+    const TRANCHES_LECTURE: [(i64, &str); 6] = [
+        (0, "0 min"),
+        (1, "1-4 min"),
+        (5, "5-9 min"),
+        (10, "10-19 min"),
+        (20, "20-29 min"),
+        (30, "30+ min"),
+    ];
+
+    pub fn calcul_stats(data: &Value, nb_domaines: usize) -> Rapport {
+        let mut par_annee: BTreeMap<String, u64> = BTreeMap::new();
+        let mut par_mois: BTreeMap<String, u64> = BTreeMap::new();
+        let mut nb_non_lu = 0;
+        let mut nb_archive = 0;
+        let mut nb_supprime = 0;
+        let mut nb_favori = 0;
+        let mut domaines: HashMap<String, u64> = HashMap::new();
+        let mut langues: HashMap<String, u64> = HashMap::new();
+        let mut mots: MinMax<i64> = create_min_max_avec_valeurs();
+        let mut lecture: MinMax<i64> = create_min_max_avec_valeurs();
+        let mut nb_total = 0;
+
+        if let Some(obj) = liste(data) {
+            for element in obj.values() {
+                nb_total += 1;
+                match date(element, "time_added") {
+                    Some(d) => {
+                        *par_annee.entry(d.format("%Y").to_string()).or_insert(0) += 1;
+                        *par_mois.entry(d.format("%Y-%m").to_string()).or_insert(0) += 1;
+                    }
+                    None => {
+                        *par_annee.entry("inconnu".to_string()).or_insert(0) += 1;
+                    }
+                }
+                match statut(element) {
+                    STATUT_NON_LU => nb_non_lu += 1,
+                    STATUT_ARCHIVE => nb_archive += 1,
+                    STATUT_SUPPRIME => nb_supprime += 1,
+                    _ => {}
+                }
+                if est_favori(element) {
+                    nb_favori += 1;
+                }
+                let d = domaine(element);
+                if !d.is_empty() {
+                    *domaines.entry(d).or_insert(0) += 1;
+                }
+                let lang = champ_str(element, "lang");
+                let lang = if lang.is_empty() { "inconnu" } else { lang };
+                *langues.entry(lang.to_string()).or_insert(0) += 1;
+                mots.add(champ_nombre(element, "word_count"));
+                lecture.add(champ_nombre(element, "time_to_read"));
+            }
+        }
+
+        let mut rapport = Rapport {
+            nb_total,
+            sections: Vec::new(),
+        };
+        rapport
+            .sections
+            .push(section("ajouts par annee", par_annee.into_iter().collect()));
+        rapport
+            .sections
+            .push(section("ajouts par mois", par_mois.into_iter().collect()));
+        rapport.sections.push(section(
+            "statut",
+            vec![
+                ("non lu".to_string(), nb_non_lu),
+                ("archive".to_string(), nb_archive),
+                ("supprime".to_string(), nb_supprime),
+                ("favori".to_string(), nb_favori),
+            ],
+        ));
+        let mut domaines: Vec<(String, u64)> = domaines.into_iter().collect();
+        tri_decroissant(&mut domaines);
+        domaines.truncate(nb_domaines);
+        rapport.sections.push(section("domaines", domaines));
+        let mut langues: Vec<(String, u64)> = langues.into_iter().collect();
+        tri_decroissant(&mut langues);
+        rapport.sections.push(section("langues", langues));
+        rapport.sections.push(section(
+            "nombre de mots",
+            distribution(&mots, &TRANCHES_MOTS),
+        ));
+        rapport
+            .sections
+            .push(section("nombre de mots (resume)", resume(&mots)));
+        rapport.sections.push(section(
+            "temps de lecture",
+            distribution(&lecture, &TRANCHES_LECTURE),
+        ));
+        rapport
+            .sections
+            .push(section("temps de lecture (resume)", resume(&lecture)));
+        rapport
+    }
+
+    fn distribution(valeurs: &MinMax<i64>, tranches: &[(i64, &str)]) -> Vec<(String, u64)> {
+        let bornes: Vec<i64> = tranches.iter().map(|t| t.0).collect();
+        tranches
+            .iter()
+            .zip(valeurs.histogramme(&bornes))
+            .map(|(t, nb)| (t.1.to_string(), nb))
+            .collect()
+    }
+
+    fn resume(valeurs: &MinMax<i64>) -> Vec<(String, u64)> {
+        let positif = |v: Option<i64>| v.unwrap_or(0).max(0) as u64;
+        vec![
+            ("min".to_string(), positif(valeurs.min)),
+            ("mediane".to_string(), positif(valeurs.percentile(50.0))),
+            ("p90".to_string(), positif(valeurs.percentile(90.0))),
+            ("max".to_string(), positif(valeurs.max)),
+            (
+                "moyenne".to_string(),
+                valeurs.moyenne().unwrap_or(0.0).round().max(0.0) as u64,
+            ),
+        ]
+    }
+
+    fn tri_decroissant(liste: &mut [(String, u64)]) {
+        liste.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    }
+
+    fn section(titre: &str, lignes: Vec<(String, u64)>) -> Section {
+        Section {
+            titre: titre.to_string(),
+            lignes: lignes
+                .into_iter()
+                .map(|(cle, nombre)| Ligne { cle, nombre })
+                .collect(),
+        }
+    }
+
+    pub fn formater(rapport: &Rapport, format: FormatSortie) -> String {
+        match format {
+            FormatSortie::Texte => rapport.to_string(),
+            FormatSortie::Json => {
+                serde_json::to_string_pretty(rapport).expect("Erreur de sérialisation")
+            }
+            FormatSortie::Csv => {
+                let mut lignes = vec![
+                    vec![
+                        "section".to_string(),
+                        "cle".to_string(),
+                        "nombre".to_string(),
+                    ],
+                    vec![
+                        "total".to_string(),
+                        "".to_string(),
+                        rapport.nb_total.to_string(),
+                    ],
+                ];
+                for section in &rapport.sections {
+                    for ligne in &section.lignes {
+                        lignes.push(vec![
+                            section.titre.clone(),
+                            ligne.cle.clone(),
+                            ligne.nombre.to_string(),
+                        ]);
+                    }
+                }
+                ecrire_csv(&lignes)
+            }
+        }
+    }
+
+    /// Lignes CSV (RFC 4180), la premiere etant l'entete.
+    pub fn ecrire_csv(lignes: &[Vec<String>]) -> String {
+        let mut csv = csv::Writer::from_writer(Vec::new());
+        for ligne in lignes {
+            csv.write_record(ligne).expect("Erreur d'ecriture CSV");
+        }
+        let octets = csv.into_inner().expect("Erreur d'ecriture CSV");
+        String::from_utf8(octets).expect("CSV en UTF-8")
+    }
+
+    impl fmt::Display for Rapport {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(f, "total : {}", self.nb_total)?;
+            for section in &self.sections {
+                writeln!(f)?;
+                writeln!(f, "{}", section.titre)?;
+                let largeur = section
+                    .lignes
+                    .iter()
+                    .map(|l| l.cle.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                for ligne in &section.lignes {
+                    writeln!(f, "  {:<largeur$} | {:>8}", ligne.cle, ligne.nombre)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        fn lignes<'a>(rapport: &'a Rapport, titre: &str) -> Vec<(&'a str, u64)> {
+            rapport
+                .sections
+                .iter()
+                .find(|s| s.titre == titre)
+                .expect("section absente")
+                .lignes
+                .iter()
+                .map(|l| (l.cle.as_str(), l.nombre))
+                .collect()
+        }
+
+        #[test]
+        fn stats_par_date_statut_domaine_langue_et_distributions() {
+            let data = json!({"liste": {
+                "1": {"time_added": "1673784000", "status": "0", "favorite": "1",
+                      "resolved_url": "https://www.rust-lang.org/a", "lang": "en",
+                      "word_count": "300", "time_to_read": 2},
+                "2": {"time_added": "1678449600", "status": "1",
+                      "resolved_url": "https://rust-lang.org/b", "lang": "en",
+                      "word_count": "1500", "time_to_read": 7},
+                "3": {"time_added": "1709380800", "status": "2",
+                      "given_url": "https://example.com/c", "lang": "fr",
+                      "word_count": "0", "time_to_read": 0},
+                "4": {"status": "0", "given_url": "https://other.org/d",
+                      "word_count": "6000", "time_to_read": 35}
+            }});
+            let rapport = calcul_stats(&data, 1);
+            assert_eq!(rapport.nb_total, 4);
+            assert_eq!(
+                lignes(&rapport, "ajouts par annee"),
+                vec![("2023", 2), ("2024", 1), ("inconnu", 1)]
+            );
+            assert_eq!(
+                lignes(&rapport, "ajouts par mois"),
+                vec![("2023-01", 1), ("2023-03", 1), ("2024-03", 1)]
+            );
+            assert_eq!(
+                lignes(&rapport, "statut"),
+                vec![
+                    ("non lu", 2),
+                    ("archive", 1),
+                    ("supprime", 1),
+                    ("favori", 1)
+                ]
+            );
+            assert_eq!(lignes(&rapport, "domaines"), vec![("rust-lang.org", 2)]);
+            assert_eq!(
+                lignes(&rapport, "langues"),
+                vec![("en", 2), ("fr", 1), ("inconnu", 1)]
+            );
+            assert_eq!(
+                lignes(&rapport, "nombre de mots"),
+                vec![
+                    ("0", 1),
+                    ("1-499", 1),
+                    ("500-999", 0),
+                    ("1000-1999", 1),
+                    ("2000-4999", 0),
+                    ("5000+", 1)
+                ]
+            );
+            assert_eq!(
+                lignes(&rapport, "temps de lecture"),
+                vec![
+                    ("0 min", 1),
+                    ("1-4 min", 1),
+                    ("5-9 min", 1),
+                    ("10-19 min", 0),
+                    ("20-29 min", 0),
+                    ("30+ min", 1)
+                ]
+            );
+            let resume = lignes(&rapport, "nombre de mots (resume)");
+            assert_eq!(resume[0], ("min", 0));
+            assert_eq!(resume[3], ("max", 6000));
+            assert_eq!(resume[4], ("moyenne", 1950));
+        }
+
+        #[test]
+        fn stats_bibliotheque_vide() {
+            let rapport = calcul_stats(&json!({}), 10);
+            assert_eq!(rapport.nb_total, 0);
+            assert!(lignes(&rapport, "ajouts par annee").is_empty());
+            assert_eq!(
+                lignes(&rapport, "nombre de mots (resume)")[4],
+                ("moyenne", 0)
+            );
+        }
+
+        #[test]
+        fn csv_echappe_virgules_guillemets_et_retours() {
+            let lignes = vec![
+                vec!["cle".to_string(), "nombre".to_string()],
+                vec!["a,b \"c\"\nd".to_string(), "1".to_string()],
+            ];
+            assert_eq!(ecrire_csv(&lignes), "cle,nombre\n\"a,b \"\"c\"\"\nd\",1\n");
+        }
+    }
+}
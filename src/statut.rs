@@ -0,0 +1,200 @@
+pub mod mod_statut {
+    use crate::config::mod_config::{Config2, ConfigParam};
+    use crate::donnees::mod_donnees::{fichier_data, fichier_param, liste};
+    use crate::historique::mod_historique::{charger_historique, derniere_execution_reussie};
+    use crate::retention::mod_retention::lister_instantanes;
+    use crate::verrou::mod_verrou::{lire_verrou, processus_actif, InfoVerrou};
+    use chrono::{DateTime, FixedOffset, Local};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::fmt;
+    use std::fs;
+    use std::path::Path;
+
+    pub const CODE_OK: i32 = 0;
+    pub const CODE_CRITIQUE: i32 = 2;
+
+    #[derive(Debug, Serialize)]
+    pub struct Statut {
+        pub derniere_execution_reussie: Option<DateTime<FixedOffset>>,
+        pub age_heures: Option<i64>,
+        pub age_max_heures: i64,
+        pub since: u64,
+        pub nb_elements: usize,
+        pub verrou: Option<InfoVerrou>,
+        pub verrou_processus_actif: Option<bool>,
+        pub dernier_backup: Option<String>,
+        pub age_dernier_backup_heures: Option<i64>,
+        /// fichiers illisibles (par exemple pendant leur reecriture)
+        pub erreurs: Vec<String>,
+        pub a_jour: bool,
+    }
+
+    impl fmt::Display for Statut {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            writeln!(
+                f,
+                "{} - derniere execution reussie : {}",
+                if self.a_jour { "OK" } else { "CRITICAL" },
+                match (self.derniere_execution_reussie, self.age_heures) {
+                    (Some(d), Some(age)) => format!(
+                        "{} (il y a {} h, max {} h)",
+                        d.format("%Y-%m-%d %H:%M:%S"),
+                        age,
+                        self.age_max_heures
+                    ),
+                    _ => "aucune".to_string(),
+                }
+            )?;
+            writeln!(
+                f,
+                "since : {} ({:?})",
+                self.since,
+                DateTime::from_timestamp(self.since as i64, 0)
+            )?;
+            writeln!(f, "elements : {}", self.nb_elements)?;
+            match &self.verrou {
+                Some(v) => writeln!(
+                    f,
+                    "verrou : pid {} depuis {} (en cours : {:?})",
+                    v.pid, v.date, self.verrou_processus_actif
+                )?,
+                None => writeln!(f, "verrou : aucun")?,
+            }
+            match (&self.dernier_backup, self.age_dernier_backup_heures) {
+                (Some(nom), Some(age)) => {
+                    writeln!(f, "dernier backup : {} (il y a {} h)", nom, age)?
+                }
+                _ => writeln!(f, "dernier backup : aucun")?,
+            }
+            for erreur in &self.erreurs {
+                writeln!(f, "erreur : {}", erreur)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Instantane le plus recent de `backup/` et son age en heures ; les
+    /// copies temporaires ne comptent pas.
+    fn dernier_backup(repertoire: &str) -> Option<(String, i64)> {
+        let dernier = lister_instantanes(repertoire)
+            .ok()?
+            .into_iter()
+            .max_by_key(|i| i.date)?;
+        let age = (Local::now().timestamp() - dernier.date).max(0) / 3600;
+        Some((dernier.fichier, age))
+    }
+
+    /// Lit un fichier JSON sans paniquer : `Ok(None)` s'il n'existe pas.
+    fn lire_json<T: DeserializeOwned>(fichier: &str) -> Result<Option<T>, String> {
+        if !Path::new(fichier).exists() {
+            return Ok(None);
+        }
+        let contenu = fs::read_to_string(fichier).map_err(|e| format!("{} : {}", fichier, e))?;
+        serde_json::from_str(&contenu)
+            .map(Some)
+            .map_err(|e| format!("{} : {}", fichier, e))
+    }
+
+    pub fn calcul_statut(config: &Config2, age_max_heures: i64) -> Statut {
+        let repertoire = config.repertoire.as_str();
+        let mut erreurs = Vec::new();
+        let param: Option<ConfigParam> = lire_json(fichier_param(repertoire).as_str())
+            .unwrap_or_else(|e| {
+                erreurs.push(e);
+                None
+            });
+        let date_dernier_traiment = param.map_or(0, |p| p.date_dernier_traiment);
+        let historique = charger_historique(repertoire);
+        // sans historique, date_dernier_traiment est la date de la derniere mise a jour
+        let derniere = match derniere_execution_reussie(&historique) {
+            Some(r) => Some(r.fin),
+            None => DateTime::from_timestamp(date_dernier_traiment as i64, 0)
+                .filter(|_| date_dernier_traiment > 0)
+                .map(|d| d.fixed_offset()),
+        };
+        let age_heures = derniere.map(|d| (Local::now().fixed_offset() - d).num_hours());
+        let data: Option<Value> =
+            lire_json(fichier_data(repertoire).as_str()).unwrap_or_else(|e| {
+                erreurs.push(e);
+                None
+            });
+        let nb_elements = data.map_or(0, |data| liste(&data).map_or(0, |l| l.len()));
+        let verrou = lire_verrou(repertoire);
+        let verrou_processus_actif = verrou.as_ref().and_then(|v| processus_actif(v.pid));
+        let backup = dernier_backup(repertoire);
+        Statut {
+            derniere_execution_reussie: derniere,
+            age_heures,
+            age_max_heures,
+            since: date_dernier_traiment,
+            nb_elements,
+            verrou,
+            verrou_processus_actif,
+            dernier_backup: backup.as_ref().map(|b| b.0.clone()),
+            age_dernier_backup_heures: backup.map(|b| b.1),
+            a_jour: erreurs.is_empty() && age_heures.is_some_and(|age| age <= age_max_heures),
+            erreurs,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::retention::mod_retention::repertoire_backup;
+
+        fn repertoire(nom: &str) -> String {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            dossier.to_string_lossy().to_string()
+        }
+
+        #[test]
+        fn fichiers_illisibles_en_critique_sans_panic() {
+            let repertoire = repertoire("statut_illisible");
+            let config = Config2::pour_test(&repertoire);
+            let maintenant = Local::now().timestamp();
+            fs::write(
+                fichier_param(&repertoire),
+                format!(
+                    r#"{{"dateDernierTraiment":{},"offset":0,"etat":""}}"#,
+                    maintenant
+                ),
+            )
+            .unwrap();
+            // data.json tronque pendant sa reecriture
+            fs::write(fichier_data(&repertoire), r#"{"liste":{"1":"#).unwrap();
+            let statut = calcul_statut(&config, 24);
+            assert!(!statut.a_jour);
+            assert_eq!(statut.erreurs.len(), 1);
+            assert!(statut.to_string().starts_with("CRITICAL"));
+
+            fs::write(fichier_data(&repertoire), r#"{"liste":{"1":{}}}"#).unwrap();
+            let statut = calcul_statut(&config, 24);
+            assert!(statut.a_jour);
+            assert_eq!(statut.nb_elements, 1);
+
+            fs::write(fichier_param(&repertoire), "").unwrap();
+            let statut = calcul_statut(&config, 24);
+            assert!(!statut.a_jour);
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+
+        #[test]
+        fn dernier_backup_ignore_les_copies_temporaires() {
+            let repertoire = repertoire("statut_backup");
+            let dossier = repertoire_backup(&repertoire);
+            fs::create_dir_all(&dossier).unwrap();
+            let date = Local::now().timestamp() - 7200;
+            fs::write(format!("{}/data_{}.json.gz", dossier, date), "").unwrap();
+            fs::write(format!("{}/data_{}.json.gz.tmp", dossier, date + 100), "").unwrap();
+            let (nom, age) = dernier_backup(&repertoire).unwrap();
+            assert_eq!(nom, format!("data_{}.json.gz", date));
+            assert_eq!(age, 2);
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+pub mod mod_trous {
+    use crate::config::mod_config::ConfigTrous;
+    use crate::donnees::mod_donnees::{date, liste};
+    use crate::rapport::mod_rapport::RapportExecution;
+    use chrono::{DateTime, Duration, NaiveDate};
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::fs;
+    use std::path::Path;
+
+    pub const FICHIER_FILE_RECHARGEMENT: &str = "/rechargements.json";
+
+    /// Periode sans element ajoute ni modifie, entouree de periodes actives.
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Trou {
+        pub debut: NaiveDate,
+        pub fin: NaiveDate,
+        pub nb_jours: i64,
+        pub densite_avant: f64,
+        pub densite_apres: f64,
+        pub raison: String,
+    }
+
+    impl fmt::Display for Trou {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "{} -> {} ({} jours) densite avant: {:.2}/j, apres: {:.2}/j, {}",
+                self.debut,
+                self.fin,
+                self.nb_jours,
+                self.densite_avant,
+                self.densite_apres,
+                self.raison
+            )
+        }
+    }
+
+    /// Nombre d'elements ajoutes ou modifies par jour.
+    pub fn activite_par_jour(data: &Value) -> BTreeMap<NaiveDate, u64> {
+        let mut res: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+        if let Some(obj) = liste(data) {
+            for element in obj.values() {
+                let ajout = date(element, "time_added").map(|d| d.date_naive());
+                let maj = date(element, "time_updated").map(|d| d.date_naive());
+                if let Some(jour) = ajout {
+                    *res.entry(jour).or_insert(0) += 1;
+                }
+                if let Some(jour) = maj {
+                    if Some(jour) != ajout {
+                        *res.entry(jour).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    fn densite(activite: &BTreeMap<NaiveDate, u64>, debut: NaiveDate, fin: NaiveDate) -> f64 {
+        let nb_jours = (fin - debut).num_days() + 1;
+        if nb_jours <= 0 {
+            return 0.0;
+        }
+        let total: u64 = activite.range(debut..=fin).map(|e| *e.1).sum();
+        total as f64 / nb_jours as f64
+    }
+
+    /// Cherche les periodes vides d'au moins `jours_vides_min` jours dont
+    /// les voisines (avant ou apres) ont une densite suffisante.
+    pub fn detecter_trous(data: &Value, config: &ConfigTrous) -> Vec<Trou> {
+        let activite = activite_par_jour(data);
+        let mut res = Vec::new();
+        let jours: Vec<NaiveDate> = activite.keys().cloned().collect();
+        let voisins = Duration::days(config.jours_voisins.max(1));
+        for paire in jours.windows(2) {
+            let nb_vides = (paire[1] - paire[0]).num_days() - 1;
+            if nb_vides < config.jours_vides_min {
+                continue;
+            }
+            let debut = paire[0] + Duration::days(1);
+            let fin = paire[1] - Duration::days(1);
+            let densite_avant =
+                densite(&activite, paire[0] - voisins + Duration::days(1), paire[0]);
+            let densite_apres =
+                densite(&activite, paire[1], paire[1] + voisins - Duration::days(1));
+            let raison =
+                if densite_avant >= config.densite_min && densite_apres >= config.densite_min {
+                    "entre deux periodes actives"
+                } else if densite_avant >= config.densite_min {
+                    "apres une periode active"
+                } else if densite_apres >= config.densite_min {
+                    "avant une periode active"
+                } else {
+                    continue;
+                };
+            res.push(Trou {
+                debut,
+                fin,
+                nb_jours: nb_vides,
+                densite_avant,
+                densite_apres,
+                raison: raison.to_string(),
+            });
+        }
+        res
+    }
+
+    /// Periodes couvertes par les traitements en erreur de l'historique :
+    /// du since demande jusqu'a la fin de l'execution.
+    pub fn trous_executions_en_echec(historique: &[RapportExecution]) -> Vec<Trou> {
+        let mut res = Vec::new();
+        for execution in historique {
+            for traitement in execution.traitements.iter().filter(|t| t.est_en_erreur()) {
+                let Some(debut) = traitement
+                    .since
+                    .and_then(|s| DateTime::from_timestamp(s as i64, 0))
+                else {
+                    continue;
+                };
+                let debut = debut.date_naive();
+                let fin = execution.fin.date_naive();
+                res.push(Trou {
+                    debut,
+                    fin,
+                    nb_jours: (fin - debut).num_days() + 1,
+                    densite_avant: 0.0,
+                    densite_apres: 0.0,
+                    raison: format!(
+                        "execution en echec du {} ({:?})",
+                        execution.debut.format("%Y-%m-%d %H:%M:%S"),
+                        traitement.erreurs
+                    ),
+                });
+            }
+        }
+        res
+    }
+
+    /// Dates a recharger par `traitement_specifique`, au format des dates
+    /// de `rechargement.dates`.
+    pub fn charger_file(repertoire: &str) -> Vec<String> {
+        let fichier = repertoire.to_string() + FICHIER_FILE_RECHARGEMENT;
+        if !Path::new(&fichier).exists() {
+            return Vec::new();
+        }
+        let contenu = fs::read_to_string(&fichier).expect("file should open read only");
+        serde_json::from_str(&contenu).expect("file should be proper JSON")
+    }
+
+    pub fn sauver_file(repertoire: &str, dates: &[String]) -> std::io::Result<()> {
+        let fichier = repertoire.to_string() + FICHIER_FILE_RECHARGEMENT;
+        if dates.is_empty() {
+            if Path::new(&fichier).exists() {
+                fs::remove_file(&fichier)?;
+            }
+        } else {
+            fs::write(&fichier, serde_json::to_string(dates).unwrap())?;
+        }
+        log::info!("file de rechargement {} : {:?}", fichier, dates);
+        Ok(())
+    }
+
+    /// Ajoute a la file la veille du debut de chaque trou : le parcourt
+    /// avec `since` reprend a partir de cette date.
+    pub fn ajouter_a_la_file(repertoire: &str, trous: &[Trou]) -> std::io::Result<Vec<String>> {
+        let mut dates = charger_file(repertoire);
+        for trou in trous {
+            let jour = (trou.debut - Duration::days(1))
+                .format("%Y-%m-%d")
+                .to_string();
+            if !dates.contains(&jour) {
+                dates.push(jour);
+            }
+        }
+        dates.sort();
+        sauver_file(repertoire, &dates)?;
+        Ok(dates)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::rapport::mod_rapport::RapportTraitement;
+        use serde_json::json;
+
+        fn jour(s: &str) -> NaiveDate {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+        }
+
+        /// Un element ajoute a midi (UTC) chaque jour de chaque periode.
+        fn donnees(periodes: &[(&str, &str)]) -> Value {
+            let mut liste = serde_json::Map::new();
+            for (debut, fin) in periodes {
+                let mut j = jour(debut);
+                while j <= jour(fin) {
+                    let timestamp = j.and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp();
+                    liste.insert(
+                        (liste.len() + 1).to_string(),
+                        json!({"time_added": timestamp.to_string()}),
+                    );
+                    j += Duration::days(1);
+                }
+            }
+            json!({ "liste": liste })
+        }
+
+        fn trou(debut: &str) -> Trou {
+            Trou {
+                debut: jour(debut),
+                fin: jour(debut),
+                nb_jours: 1,
+                densite_avant: 0.0,
+                densite_apres: 0.0,
+                raison: String::new(),
+            }
+        }
+
+        #[test]
+        fn trou_entre_deux_periodes_denses() {
+            let data = donnees(&[("2024-01-01", "2024-01-14"), ("2024-02-01", "2024-02-14")]);
+            let trous = detecter_trous(&data, &ConfigTrous::default());
+            assert_eq!(trous.len(), 1);
+            assert_eq!(trous[0].debut, jour("2024-01-15"));
+            assert_eq!(trous[0].fin, jour("2024-01-31"));
+            assert_eq!(trous[0].nb_jours, 17);
+            assert_eq!(trous[0].densite_avant, 1.0);
+            assert_eq!(trous[0].densite_apres, 1.0);
+            assert_eq!(trous[0].raison, "entre deux periodes actives");
+        }
+
+        #[test]
+        fn trou_apres_une_seule_periode_active() {
+            let data = donnees(&[("2024-01-01", "2024-01-14"), ("2024-03-01", "2024-03-01")]);
+            let trous = detecter_trous(&data, &ConfigTrous::default());
+            assert_eq!(trous.len(), 1);
+            assert_eq!(trous[0].raison, "apres une periode active");
+        }
+
+        #[test]
+        fn periode_vide_trop_courte_ou_voisins_peu_denses() {
+            let config = ConfigTrous::default();
+            let courte = donnees(&[("2024-01-01", "2024-01-14"), ("2024-01-21", "2024-02-14")]);
+            assert!(detecter_trous(&courte, &config).is_empty());
+            let creuse = donnees(&[("2023-01-01", "2023-01-01"), ("2023-06-01", "2023-06-01")]);
+            assert!(detecter_trous(&creuse, &config).is_empty());
+        }
+
+        #[test]
+        fn execution_en_echec_devient_une_fenetre() {
+            let debut = DateTime::parse_from_rfc3339("2024-03-07T08:00:00+00:00").unwrap();
+            let fin = DateTime::parse_from_rfc3339("2024-03-07T08:05:00+00:00").unwrap();
+            let since = jour("2024-03-05")
+                .and_hms_opt(10, 0, 0)
+                .unwrap()
+                .and_utc()
+                .timestamp() as u64;
+            let ok = RapportTraitement {
+                since: Some(since),
+                ..Default::default()
+            };
+            let mut echec = ok.clone();
+            echec.erreurs.insert("503".to_string(), 1);
+            let mut sans_since = echec.clone();
+            sans_since.since = None;
+            let execution = RapportExecution::creer(
+                "miseAJour",
+                debut,
+                fin,
+                since,
+                since,
+                vec![ok, echec, sans_since],
+            );
+            let trous = trous_executions_en_echec(&[execution]);
+            assert_eq!(trous.len(), 1);
+            assert_eq!(trous[0].debut, jour("2024-03-05"));
+            assert_eq!(trous[0].fin, jour("2024-03-07"));
+            assert_eq!(trous[0].nb_jours, 3);
+            assert!(trous[0]
+                .raison
+                .starts_with("execution en echec du 2024-03-07"));
+        }
+
+        #[test]
+        fn file_dedoublonnee_et_triee() {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_trous_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            let repertoire = dossier.to_string_lossy().to_string();
+            sauver_file(&repertoire, &["2024-05-01".to_string()]).unwrap();
+
+            let trous = [trou("2024-05-02"), trou("2024-03-06"), trou("2024-03-06")];
+            let dates = ajouter_a_la_file(&repertoire, &trous).unwrap();
+            assert_eq!(dates, vec!["2024-03-05", "2024-05-01"]);
+            assert_eq!(charger_file(&repertoire), dates);
+
+            sauver_file(&repertoire, &[]).unwrap();
+            assert!(charger_file(&repertoire).is_empty());
+            let _ = fs::remove_dir_all(&dossier);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+pub mod mod_verrou {
+    use chrono::{DateTime, FixedOffset, Local};
+    use serde::{Deserialize, Serialize};
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Write};
+    use std::path::Path;
+    use std::time::Duration;
+
+    pub const FICHIER_VERROU: &str = "/pbackup.lock";
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct InfoVerrou {
+        pub pid: u32,
+        pub date: DateTime<FixedOffset>,
+    }
+
+    /// Verrou pose pendant une execution ; supprime a la fin, y compris
+    /// en cas de panic.
+    pub struct Verrou {
+        fichier: String,
+    }
+
+    impl Verrou {
+        /// Cree le fichier de verrou, qui ne doit pas deja exister. Un
+        /// verrou dont le processus est encore actif fait refuser le
+        /// demarrage ; celui d'une execution interrompue est signale puis
+        /// remplace.
+        pub fn prendre(repertoire: &str) -> Result<Verrou, String> {
+            let fichier = repertoire.to_string() + FICHIER_VERROU;
+            let info = InfoVerrou {
+                pid: std::process::id(),
+                date: Local::now().fixed_offset(),
+            };
+            let contenu = serde_json::to_string(&info).unwrap();
+            for _ in 0..2 {
+                match OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&fichier)
+                {
+                    Ok(mut f) => {
+                        if let Err(err) = f.write_all(contenu.as_bytes()) {
+                            let _ = fs::remove_file(&fichier);
+                            return Err(format!(
+                                "Erreur d'ecriture du verrou {} : {}",
+                                fichier, err
+                            ));
+                        }
+                        return Ok(Verrou { fichier });
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                        match lire(&fichier) {
+                            Some(ancien) if processus_actif(ancien.pid) == Some(true) => {
+                                return Err(format!(
+                                    "execution en cours : pid {} depuis {}",
+                                    ancien.pid, ancien.date
+                                ));
+                            }
+                            Some(ancien) => log::warn!(
+                                "verrou d'une execution interrompue remplace : pid {} depuis {}",
+                                ancien.pid,
+                                ancien.date
+                            ),
+                            // fichier en cours d'ecriture par une autre execution
+                            None if recent(&fichier) => {
+                                return Err(format!("verrou {} en cours de creation", fichier));
+                            }
+                            None => log::warn!("verrou illisible remplace : {}", fichier),
+                        }
+                        if let Err(err) = fs::remove_file(&fichier) {
+                            if err.kind() != io::ErrorKind::NotFound {
+                                return Err(format!(
+                                    "Erreur de suppression du verrou {} : {}",
+                                    fichier, err
+                                ));
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        return Err(format!(
+                            "Erreur de creation du verrou {} : {}",
+                            fichier, err
+                        ))
+                    }
+                }
+            }
+            Err(format!("verrou {} pris par une autre execution", fichier))
+        }
+    }
+
+    fn recent(fichier: &str) -> bool {
+        fs::metadata(fichier)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|date| date.elapsed().ok())
+            .is_some_and(|age| age < Duration::from_secs(10))
+    }
+
+    impl Drop for Verrou {
+        /// Le fichier n'est supprime que s'il porte encore notre pid.
+        fn drop(&mut self) {
+            match lire(&self.fichier) {
+                Some(info) if info.pid == std::process::id() => {
+                    if let Err(err) = fs::remove_file(&self.fichier) {
+                        log::error!("Erreur de suppression du verrou {} : {}", self.fichier, err);
+                    }
+                }
+                _ => log::warn!("verrou {} repris par une autre execution", self.fichier),
+            }
+        }
+    }
+
+    pub fn lire_verrou(repertoire: &str) -> Option<InfoVerrou> {
+        lire(&(repertoire.to_string() + FICHIER_VERROU))
+    }
+
+    fn lire(fichier: &str) -> Option<InfoVerrou> {
+        if !Path::new(fichier).exists() {
+            return None;
+        }
+        let contenu = fs::read_to_string(fichier).ok()?;
+        serde_json::from_str(&contenu).ok()
+    }
+
+    /// `None` quand on ne sait pas le determiner (hors Linux).
+    pub fn processus_actif(pid: u32) -> Option<bool> {
+        if cfg!(target_os = "linux") {
+            Some(Path::new(&format!("/proc/{}", pid)).exists())
+        } else {
+            None
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn repertoire(nom: &str) -> String {
+            let dossier =
+                std::env::temp_dir().join(format!("pbackup_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&dossier);
+            fs::create_dir_all(&dossier).unwrap();
+            dossier.to_string_lossy().to_string()
+        }
+
+        #[test]
+        fn verrou_actif_refuse() {
+            let repertoire = repertoire("verrou_actif");
+            let verrou = Verrou::prendre(&repertoire).unwrap();
+            assert!(Verrou::prendre(&repertoire).is_err());
+            drop(verrou);
+            assert!(lire_verrou(&repertoire).is_none());
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+
+        #[test]
+        fn verrou_interrompu_remplace_et_verrou_repris_conserve() {
+            let repertoire = repertoire("verrou_interrompu");
+            let fichier = repertoire.clone() + FICHIER_VERROU;
+            let ancien = InfoVerrou {
+                pid: u32::MAX,
+                date: Local::now().fixed_offset(),
+            };
+            fs::write(&fichier, serde_json::to_string(&ancien).unwrap()).unwrap();
+            let verrou = Verrou::prendre(&repertoire).unwrap();
+            assert_eq!(lire_verrou(&repertoire).unwrap().pid, std::process::id());
+
+            // un autre processus a repris le verrou : il n'est pas supprime
+            fs::write(&fichier, serde_json::to_string(&ancien).unwrap()).unwrap();
+            drop(verrou);
+            assert_eq!(lire_verrou(&repertoire).unwrap().pid, u32::MAX);
+            let _ = fs::remove_dir_all(&repertoire);
+        }
+    }
+}
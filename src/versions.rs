@@ -0,0 +1,110 @@
+pub mod mod_versions {
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use std::fs::{File, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::path::Path;
+
+    pub const FICHIER_VERSIONS: &str = "/versions.jsonl";
+
+    /// Etat d'un element avant son remplacement par une version differente
+    /// recue du serveur.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Version {
+        pub item_id: String,
+        /// date du remplacement
+        pub date: i64,
+        pub element: Value,
+    }
+
+    /// Champs qui changent d'une reponse a l'autre sans que l'element soit
+    /// modifie (`sort_id` est la position dans la reponse de Pocket).
+    pub const CHAMPS_VOLATILS: [&str; 1] = ["sort_id"];
+
+    fn sans_champs_volatils(element: &Value) -> Value {
+        let mut res = element.clone();
+        if let Some(obj) = res.as_object_mut() {
+            for champ in CHAMPS_VOLATILS {
+                obj.remove(champ);
+            }
+        }
+        res
+    }
+
+    /// Version a enregistrer quand `nouveau` remplace `ancien`, `None` si
+    /// seuls les champs volatils different.
+    pub fn version_remplacee(item_id: &str, ancien: &Value, nouveau: &Value) -> Option<Version> {
+        let ancien = sans_champs_volatils(ancien);
+        if ancien == sans_champs_volatils(nouveau) {
+            return None;
+        }
+        Some(Version {
+            item_id: item_id.to_string(),
+            date: chrono::Local::now().timestamp(),
+            element: ancien,
+        })
+    }
+
+    /// Ajoute les versions remplacees a `versions.jsonl`.
+    pub fn ajouter_versions(repertoire: &str, versions: &[Version]) -> std::io::Result<()> {
+        if versions.is_empty() {
+            return Ok(());
+        }
+        let fichier = repertoire.to_string() + FICHIER_VERSIONS;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&fichier)?;
+        for version in versions {
+            let ligne = serde_json::to_string(version).expect("Erreur de sérialisation");
+            writeln!(file, "{}", ligne)?;
+        }
+        log::info!("{} versions ajoutees a {}", versions.len(), fichier);
+        Ok(())
+    }
+
+    /// Versions precedentes d'un element, de la plus ancienne a la plus
+    /// recente.
+    pub fn versions_element(repertoire: &str, item_id: &str) -> Vec<Version> {
+        let fichier = repertoire.to_string() + FICHIER_VERSIONS;
+        if !Path::new(&fichier).exists() {
+            return Vec::new();
+        }
+        let Ok(file) = File::open(&fichier) else {
+            return Vec::new();
+        };
+        let motif = format!("\"{}\"", item_id);
+        let mut res: Vec<Version> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter(|ligne| ligne.contains(motif.as_str()))
+            .filter_map(|ligne| serde_json::from_str::<Version>(&ligne).ok())
+            .filter(|v| v.item_id == item_id)
+            .collect();
+        res.sort_by_key(|v| v.date);
+        res
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        #[test]
+        fn sort_id_seul_ne_cree_pas_de_version() {
+            let ancien = json!({"item_id": "1", "sort_id": 0, "resolved_title": "a"});
+            let nouveau = json!({"item_id": "1", "sort_id": 7, "resolved_title": "a"});
+            assert!(version_remplacee("1", &ancien, &nouveau).is_none());
+        }
+
+        #[test]
+        fn version_sans_champs_volatils() {
+            let ancien = json!({"item_id": "1", "sort_id": 0, "resolved_title": "a"});
+            let nouveau = json!({"item_id": "1", "sort_id": 3, "resolved_title": "b"});
+            let version = version_remplacee("1", &ancien, &nouveau).unwrap();
+            assert_eq!(version.item_id, "1");
+            assert_eq!(version.element, json!({"item_id": "1", "resolved_title": "a"}));
+        }
+    }
+}
@@ -0,0 +1,424 @@
+pub mod mod_wallabag {
+    use crate::api::mod_api::{appel_json, ErreurApi};
+    use crate::config::mod_config::ConfigSource;
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, est_favori, objet_tags, statut, tags, timestamp_iso, titre, url,
+        STATUT_ARCHIVE, STATUT_NON_LU,
+    };
+    use crate::source::mod_source::{ChampTri, Page, SourceProvider, SOURCE_WALLABAG};
+    use chrono::Local;
+    use reqwest::{Client, RequestBuilder, StatusCode};
+    use serde_json::Value;
+
+    /// API Wallabag 2 : jeton OAuth (`grant_type=password`) puis
+    /// `/api/entries` par pages, triees par date de mise a jour.
+    pub struct SourceWallabag {
+        url: String,
+        client_id: String,
+        client_secret: String,
+        utilisateur: String,
+        mot_de_passe: String,
+        taille_page: u64,
+        jeton: Option<String>,
+    }
+
+    impl SourceWallabag {
+        pub fn new(config: &ConfigSource, taille_page: u64) -> SourceWallabag {
+            SourceWallabag {
+                url: config.url.trim_end_matches('/').to_string(),
+                client_id: config.client_id.clone(),
+                client_secret: config.client_secret.clone(),
+                utilisateur: config.utilisateur.clone(),
+                mot_de_passe: config.mot_de_passe.clone(),
+                taille_page,
+                jeton: None,
+            }
+        }
+
+        async fn appel_entries(
+            &self,
+            client: &Client,
+            numero_page: u64,
+            since: Option<u64>,
+        ) -> Result<Value, ErreurApi> {
+            let mut requete = client
+                .get(format!("{}/api/entries", self.url))
+                .bearer_auth(self.jeton.clone().unwrap_or_default())
+                .query(&[
+                    ("sort", "updated".to_string()),
+                    ("order", "asc".to_string()),
+                    ("detail", "metadata".to_string()),
+                    ("page", numero_page.to_string()),
+                    ("perPage", self.taille_page.to_string()),
+                ]);
+            if let Some(since) = since {
+                requete = requete.query(&[("since", since)]);
+            }
+            appel_json(requete).await
+        }
+
+        /// Appel avec le jeton courant, redemande en cas de 401.
+        async fn appel_authentifie<F: Fn(&str) -> RequestBuilder>(
+            &mut self,
+            client: &Client,
+            requete: F,
+        ) -> Result<Value, ErreurApi> {
+            if self.jeton.is_none() {
+                self.authentifier(client).await?;
+            }
+            match appel_json(requete(self.jeton.as_deref().unwrap_or(""))).await {
+                Err(ErreurApi::Statut(StatusCode::UNAUTHORIZED)) => {
+                    log::info!("jeton wallabag expire");
+                    self.authentifier(client).await?;
+                    appel_json(requete(self.jeton.as_deref().unwrap_or(""))).await
+                }
+                res => res,
+            }
+        }
+
+        /// Identifiant de l'entree deja presente pour cette URL.
+        pub async fn rechercher(
+            &mut self,
+            client: &Client,
+            url: &str,
+        ) -> Result<Option<String>, ErreurApi> {
+            let adresse = format!("{}/api/entries/exists", self.url);
+            let json = self
+                .appel_authentifie(client, |jeton| {
+                    client
+                        .get(adresse.as_str())
+                        .bearer_auth(jeton)
+                        .query(&[("url", url), ("return_id", "1")])
+                })
+                .await?;
+            Ok(json
+                .get("exists")
+                .and_then(|e| e.as_i64())
+                .map(|id| id.to_string()))
+        }
+
+        /// Cree l'entree ; Wallabag ne permet pas de fixer la date d'ajout.
+        pub async fn ajouter(
+            &mut self,
+            client: &Client,
+            element: &Value,
+        ) -> Result<String, ErreurApi> {
+            let adresse = format!("{}/api/entries", self.url);
+            let mut corps = corps_entree(element);
+            corps.push(("url", url(element).to_string()));
+            let json = self
+                .appel_authentifie(client, |jeton| {
+                    client
+                        .post(adresse.as_str())
+                        .bearer_auth(jeton)
+                        .form(&corps)
+                })
+                .await?;
+            match json.get("id").and_then(|id| id.as_i64()) {
+                Some(id) => Ok(id.to_string()),
+                None => Err(ErreurApi::Json("id absent".to_string())),
+            }
+        }
+
+        pub async fn modifier(
+            &mut self,
+            client: &Client,
+            id: &str,
+            element: &Value,
+        ) -> Result<(), ErreurApi> {
+            let adresse = format!("{}/api/entries/{}", self.url, id);
+            let corps = corps_entree(element);
+            self.appel_authentifie(client, |jeton| {
+                client
+                    .patch(adresse.as_str())
+                    .bearer_auth(jeton)
+                    .form(&corps)
+            })
+            .await
+            .map(|_| ())
+        }
+    }
+
+    /// Titre, tags, archive et favori d'un element Pocket.
+    fn corps_entree(element: &Value) -> Vec<(&'static str, String)> {
+        vec![
+            ("title", titre(element).to_string()),
+            ("tags", tags(element).join(",")),
+            (
+                "archive",
+                if statut(element) == STATUT_ARCHIVE {
+                    "1"
+                } else {
+                    "0"
+                }
+                .to_string(),
+            ),
+            (
+                "starred",
+                if est_favori(element) { "1" } else { "0" }.to_string(),
+            ),
+        ]
+    }
+
+    /// Entree Wallabag convertie au format des elements Pocket.
+    pub fn vers_element(entree: &Value) -> (String, Value) {
+        let id = champ_nombre(entree, "id").to_string();
+        let titre = champ_str(entree, "title");
+        let url = champ_str(entree, "url");
+        let archive = entree
+            .get("is_archived")
+            .is_some_and(|a| a == 1 || a == true);
+        let favori = entree
+            .get("is_starred")
+            .is_some_and(|a| a == 1 || a == true);
+        let tags: Vec<String> = entree
+            .get("tags")
+            .and_then(|t| t.as_array())
+            .map(|t| {
+                t.iter()
+                    .map(|t| champ_str(t, "label").to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut element = serde_json::json!({
+            "item_id": id,
+            "given_url": url,
+            "resolved_url": champ_str(entree, "origin_url"),
+            "given_title": titre,
+            "resolved_title": titre,
+            "time_added": timestamp_iso(champ_str(entree, "created_at")).to_string(),
+            "time_updated": timestamp_iso(champ_str(entree, "updated_at")).to_string(),
+            "time_read": timestamp_iso(champ_str(entree, "archived_at")).to_string(),
+            "status": if archive { STATUT_ARCHIVE } else { STATUT_NON_LU },
+            "favorite": if favori { "1" } else { "0" },
+            "lang": champ_str(entree, "language"),
+            "time_to_read": champ_nombre(entree, "reading_time"),
+            "top_image_url": champ_str(entree, "preview_picture"),
+        });
+        if !tags.is_empty() {
+            element["tags"] = objet_tags(id.as_str(), &tags);
+        }
+        if let Some(annotations) = entree.get("annotations").and_then(|a| a.as_array()) {
+            if !annotations.is_empty() {
+                element["annotations"] = Value::Array(
+                    annotations
+                        .iter()
+                        .map(|a| serde_json::json!({"quote": champ_str(a, "quote")}))
+                        .collect(),
+                );
+            }
+        }
+        (id, element)
+    }
+
+    impl SourceProvider for SourceWallabag {
+        fn nom(&self) -> &'static str {
+            SOURCE_WALLABAG
+        }
+
+        async fn authentifier(&mut self, client: &Client) -> Result<(), ErreurApi> {
+            let requete = client.post(format!("{}/oauth/v2/token", self.url)).form(&[
+                ("grant_type", "password"),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("username", self.utilisateur.as_str()),
+                ("password", self.mot_de_passe.as_str()),
+            ]);
+            let reponse = appel_json(requete).await?;
+            match reponse.get("access_token").and_then(|t| t.as_str()) {
+                Some(jeton) => {
+                    self.jeton = Some(jeton.to_string());
+                    Ok(())
+                }
+                None => Err(ErreurApi::Json("access_token absent".to_string())),
+            }
+        }
+
+        /// Wallabag pagine par numero de page : la page contenant `offset`
+        /// est demandee et le debut est ignore si besoin. Le jeton (valable
+        /// une heure) est redemande en cas de 401.
+        async fn page(
+            &mut self,
+            client: &Client,
+            offset: u64,
+            since: Option<u64>,
+        ) -> Result<Page, ErreurApi> {
+            let debut = Local::now().timestamp();
+            let numero_page = offset / self.taille_page + 1;
+            let json = match self.appel_entries(client, numero_page, since).await {
+                Err(ErreurApi::Statut(StatusCode::UNAUTHORIZED)) => {
+                    log::info!("jeton wallabag expire");
+                    self.authentifier(client).await?;
+                    self.appel_entries(client, numero_page, since).await?
+                }
+                // au dela de la derniere page, Wallabag repond 404
+                Err(ErreurApi::Statut(StatusCode::NOT_FOUND)) if numero_page > 1 => {
+                    return Ok(Page::default())
+                }
+                res => res?,
+            };
+            let entrees = json
+                .pointer("/_embedded/items")
+                .and_then(|i| i.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let elements = entrees
+                .iter()
+                .skip((offset % self.taille_page) as usize)
+                .map(vers_element)
+                .collect();
+            Ok(Page {
+                elements,
+                since: Some(debut),
+                total: json.get("total").and_then(|t| t.as_u64()),
+                ..Default::default()
+            })
+        }
+
+        /// Les entrees sont toujours demandees par `updated` croissant.
+        fn champ_tri(&self, _since: Option<u64>) -> Option<ChampTri> {
+            Some(ChampTri::MiseAJour)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::bouchon::mod_bouchon::Bouchon;
+        use serde_json::json;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        fn source_wallabag(url: &str) -> SourceWallabag {
+            let config = ConfigSource {
+                url: format!("{}/", url),
+                client_id: "client".to_string(),
+                client_secret: "secret".to_string(),
+                utilisateur: "moi".to_string(),
+                mot_de_passe: "mdp".to_string(),
+                ..Default::default()
+            };
+            SourceWallabag::new(&config, 2)
+        }
+
+        fn entree(id: i64) -> Value {
+            json!({
+                "id": id,
+                "url": format!("https://exemple.org/{}", id),
+                "origin_url": format!("https://exemple.org/origine/{}", id),
+                "title": format!("Titre {}", id),
+                "created_at": "2024-01-02T03:04:05+0000",
+                "updated_at": "2024-01-03T03:04:05+0000",
+                "archived_at": null,
+                "is_archived": 1,
+                "is_starred": 0,
+                "language": "fr",
+                "reading_time": 4,
+                "tags": [{"label": "rust"}, {"label": ""}],
+                "annotations": [{"quote": "passage"}]
+            })
+        }
+
+        #[tokio::test]
+        async fn wallabag_jeton_puis_page_contenant_l_offset() {
+            let bouchon = Bouchon::demarrer(|requete| match requete.chemin().as_str() {
+                "/oauth/v2/token" => (200, json!({"access_token": "t1"}).to_string()),
+                "/api/entries" => {
+                    let items = json!({"total": 5, "_embedded": {"items": [entree(3), entree(4)]}});
+                    (200, items.to_string())
+                }
+                _ => (404, "{}".to_string()),
+            });
+            let client = Client::new();
+            let mut source = source_wallabag(&bouchon.adresse);
+            source.authentifier(&client).await.unwrap();
+            let page = source.page(&client, 3, Some(1700000000)).await.unwrap();
+
+            // offset 3 avec 2 par page : page 2, dont le premier element est
+            // deja lu
+            assert_eq!(page.elements.len(), 1);
+            assert_eq!(page.total, Some(5));
+            let requetes = bouchon.requetes();
+            let token = &requetes[0];
+            assert_eq!(token.methode, "POST");
+            assert!(token.corps.contains("grant_type=password"));
+            assert!(token.corps.contains("username=moi"));
+            let entries = &requetes[1];
+            assert_eq!(entries.autorisation, "Bearer t1");
+            assert_eq!(entries.parametre("page").as_deref(), Some("2"));
+            assert_eq!(entries.parametre("perPage").as_deref(), Some("2"));
+            assert_eq!(entries.parametre("since").as_deref(), Some("1700000000"));
+        }
+
+        #[test]
+        fn wallabag_conversion_en_element() {
+            let (id, element) = vers_element(&entree(4));
+            assert_eq!(id, "4");
+            assert_eq!(element["item_id"], "4");
+            assert_eq!(element["given_url"], "https://exemple.org/4");
+            assert_eq!(element["resolved_url"], "https://exemple.org/origine/4");
+            assert_eq!(element["resolved_title"], "Titre 4");
+            assert_eq!(element["time_added"], "1704164645");
+            assert_eq!(element["time_updated"], "1704251045");
+            assert_eq!(element["time_read"], "0");
+            assert_eq!(element["status"], STATUT_ARCHIVE);
+            assert_eq!(element["favorite"], "0");
+            assert_eq!(element["lang"], "fr");
+            assert_eq!(element["time_to_read"], 4);
+            assert_eq!(tags(&element), ["rust"]);
+            assert_eq!(element["annotations"], json!([{"quote": "passage"}]));
+        }
+
+        #[test]
+        fn wallabag_trie_par_mise_a_jour_meme_sans_since() {
+            let source = source_wallabag("http://localhost");
+            assert_eq!(source.champ_tri(None), Some(ChampTri::MiseAJour));
+            assert_eq!(source.champ_tri(Some(1)), Some(ChampTri::MiseAJour));
+        }
+
+        #[tokio::test]
+        async fn wallabag_jeton_redemande_apres_401() {
+            let nb_jetons = AtomicU32::new(0);
+            let bouchon = Bouchon::demarrer(move |requete| match requete.chemin().as_str() {
+                "/oauth/v2/token" => {
+                    let n = nb_jetons.fetch_add(1, Ordering::SeqCst) + 1;
+                    (200, json!({"access_token": format!("t{}", n)}).to_string())
+                }
+                "/api/entries" if requete.autorisation == "Bearer t2" => (
+                    200,
+                    json!({"_embedded": {"items": [entree(1)]}}).to_string(),
+                ),
+                _ => (401, "{}".to_string()),
+            });
+            let client = Client::new();
+            let mut source = source_wallabag(&bouchon.adresse);
+            source.authentifier(&client).await.unwrap();
+            let page = source.page(&client, 0, None).await.unwrap();
+            assert_eq!(page.elements.len(), 1);
+            let chemins: Vec<String> = bouchon.requetes().iter().map(|r| r.chemin()).collect();
+            assert_eq!(
+                chemins,
+                [
+                    "/oauth/v2/token",
+                    "/api/entries",
+                    "/oauth/v2/token",
+                    "/api/entries"
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn wallabag_404_apres_la_derniere_page() {
+            let bouchon = Bouchon::demarrer(|requete| match requete.chemin().as_str() {
+                "/oauth/v2/token" => (200, json!({"access_token": "t"}).to_string()),
+                _ => (404, "{}".to_string()),
+            });
+            let client = Client::new();
+            let mut source = source_wallabag(&bouchon.adresse);
+            source.authentifier(&client).await.unwrap();
+            let page = source.page(&client, 4, None).await.unwrap();
+            assert!(page.elements.is_empty());
+            assert!(source.page(&client, 0, None).await.is_err());
+        }
+    }
+}
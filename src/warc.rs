@@ -0,0 +1,341 @@
+pub mod mod_warc {
+    use crate::archive::mod_archive::Telechargement;
+    use chrono::{DateTime, Utc};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use reqwest::{StatusCode, Url};
+    use sha1::Sha1;
+    use sha2::{Digest, Sha256};
+    use std::fs;
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::io::Write;
+    use std::net::IpAddr;
+    use std::path::{Path, PathBuf};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub const FICHIER_CDX: &str = "index.cdx";
+    const ENTETE_CDX: &str = " CDX N b a m s k r M S V g";
+
+    /// Ecrit les telechargements dans des fichiers `.warc.gz` (un membre
+    /// gzip par enregistrement), en passant au fichier suivant au dela de
+    /// `taille_max` octets. Les lignes CDX des reponses sont ajoutees a
+    /// `index.cdx` par `terminer`.
+    pub struct EcrivainWarc {
+        dossier: PathBuf,
+        prefixe: String,
+        taille_max: u64,
+        numero: u32,
+        /// nom, fichier et taille du fichier en cours
+        courant: Option<(String, File, u64)>,
+        lignes_cdx: Vec<String>,
+        nb_enregistrements: u64,
+    }
+
+    impl EcrivainWarc {
+        pub fn new(dossier: &Path, taille_max: u64) -> io::Result<EcrivainWarc> {
+            fs::create_dir_all(dossier)?;
+            Ok(EcrivainWarc {
+                dossier: dossier.to_path_buf(),
+                prefixe: format!("pbackup-{}", Utc::now().format("%Y%m%d%H%M%S")),
+                taille_max,
+                numero: 0,
+                courant: None,
+                lignes_cdx: Vec::new(),
+                nb_enregistrements: 0,
+            })
+        }
+
+        /// Ouvre un nouveau fichier si besoin, et y ecrit l'enregistrement
+        /// `warcinfo`.
+        fn fichier(&mut self) -> io::Result<()> {
+            if let Some((_, _, taille)) = &self.courant {
+                if *taille < self.taille_max {
+                    return Ok(());
+                }
+            }
+            let nom = format!("{}-{:05}.warc.gz", self.prefixe, self.numero);
+            self.numero += 1;
+            let fichier = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dossier.join(&nom))?;
+            let taille = fichier.metadata()?.len();
+            log::info!("fichier WARC {}", nom);
+            self.courant = Some((nom.clone(), fichier, taille));
+            let info = format!(
+                "software: pbackup {}\r\nformat: WARC File Format 1.1\r\n",
+                env!("CARGO_PKG_VERSION")
+            );
+            self.enregistrement(
+                vec![
+                    ("WARC-Type", "warcinfo".to_string()),
+                    ("WARC-Record-ID", identifiant(&nom)),
+                    ("WARC-Date", date_warc(&Utc::now())),
+                    ("WARC-Filename", nom),
+                    ("Content-Type", "application/warc-fields".to_string()),
+                ],
+                info.as_bytes(),
+            )?;
+            Ok(())
+        }
+
+        /// Retourne la position et la taille (compressee) de
+        /// l'enregistrement dans le fichier en cours.
+        fn enregistrement(
+            &mut self,
+            entetes: Vec<(&str, String)>,
+            bloc: &[u8],
+        ) -> io::Result<(u64, u64)> {
+            let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+            gz.write_all(b"WARC/1.1\r\n")?;
+            for (nom, valeur) in entetes {
+                gz.write_all(format!("{}: {}\r\n", nom, valeur).as_bytes())?;
+            }
+            gz.write_all(format!("Content-Length: {}\r\n\r\n", bloc.len()).as_bytes())?;
+            gz.write_all(bloc)?;
+            gz.write_all(b"\r\n\r\n")?;
+            let octets = gz.finish()?;
+            let (_, fichier, taille) = self.courant.as_mut().expect("fichier WARC ouvert");
+            let position = *taille;
+            fichier.write_all(&octets)?;
+            *taille += octets.len() as u64;
+            self.nb_enregistrements += 1;
+            Ok((position, octets.len() as u64))
+        }
+
+        /// Enregistrements `request` et `response` d'un telechargement.
+        pub fn ecrire(&mut self, telechargement: &Telechargement) -> io::Result<()> {
+            self.fichier()?;
+            let cible = telechargement.url_finale.as_str();
+            let date = date_warc(&telechargement.date);
+            let id_requete = identifiant(format!("requete {} {}", cible, date).as_str());
+            self.enregistrement(
+                vec![
+                    ("WARC-Type", "request".to_string()),
+                    ("WARC-Record-ID", id_requete.clone()),
+                    ("WARC-Date", date.clone()),
+                    ("WARC-Target-URI", cible.to_string()),
+                    (
+                        "Content-Type",
+                        "application/http;msgtype=request".to_string(),
+                    ),
+                ],
+                bloc_requete(telechargement).as_bytes(),
+            )?;
+            let mut bloc = entete_reponse(telechargement).into_bytes();
+            bloc.extend_from_slice(&telechargement.corps);
+            let sha1 = base32(&Sha1::digest(&telechargement.corps));
+            let digest = format!("sha1:{}", sha1);
+            let (position, taille) = self.enregistrement(
+                vec![
+                    ("WARC-Type", "response".to_string()),
+                    (
+                        "WARC-Record-ID",
+                        identifiant(format!("reponse {} {}", cible, date).as_str()),
+                    ),
+                    ("WARC-Date", date),
+                    ("WARC-Target-URI", cible.to_string()),
+                    ("WARC-Concurrent-To", id_requete),
+                    ("WARC-Payload-Digest", digest),
+                    (
+                        "Content-Type",
+                        "application/http;msgtype=response".to_string(),
+                    ),
+                ],
+                &bloc,
+            )?;
+            let nom = self
+                .courant
+                .as_ref()
+                .map(|c| c.0.clone())
+                .unwrap_or_default();
+            let type_mime = telechargement
+                .type_contenu()
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_string();
+            self.lignes_cdx.push(format!(
+                "{} {} {} {} {} {} - - {} {} {}",
+                cle_surt(cible),
+                telechargement.date.format("%Y%m%d%H%M%S"),
+                cible,
+                if type_mime.is_empty() {
+                    "-"
+                } else {
+                    type_mime.as_str()
+                },
+                telechargement.code,
+                sha1,
+                taille,
+                position,
+                nom
+            ));
+            Ok(())
+        }
+
+        /// Fusionne les nouvelles lignes dans `index.cdx`, trie.
+        pub fn terminer(self) -> io::Result<u64> {
+            if self.lignes_cdx.is_empty() {
+                return Ok(self.nb_enregistrements);
+            }
+            let fichier = self.dossier.join(FICHIER_CDX);
+            let mut lignes: Vec<String> = match fs::read_to_string(&fichier) {
+                Ok(contenu) => contenu
+                    .lines()
+                    .filter(|l| !l.is_empty() && *l != ENTETE_CDX)
+                    .map(|l| l.to_string())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            lignes.extend(self.lignes_cdx);
+            lignes.sort();
+            let mut contenu = format!("{}\n", ENTETE_CDX);
+            for ligne in lignes {
+                contenu.push_str(ligne.as_str());
+                contenu.push('\n');
+            }
+            let tmp = fichier.with_extension("tmp");
+            fs::write(&tmp, contenu)?;
+            fs::rename(&tmp, &fichier)?;
+            Ok(self.nb_enregistrements)
+        }
+    }
+
+    fn date_warc(date: &DateTime<Utc>) -> String {
+        date.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    /// `<urn:uuid:...>` derive du contenu donne et de l'heure.
+    fn identifiant(graine: &str) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let h = format!("{:x}", Sha256::digest(format!("{} {}", graine, nanos)));
+        format!(
+            "<urn:uuid:{}-{}-4{}-8{}-{}>",
+            &h[0..8],
+            &h[8..12],
+            &h[13..16],
+            &h[17..20],
+            &h[20..32]
+        )
+    }
+
+    /// Base32 (RFC 4648), la forme des empreintes `sha1:` des WARC et de la
+    /// colonne `k` du CDX.
+    fn base32(octets: &[u8]) -> String {
+        const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+        let mut res = String::new();
+        let mut tampon: u32 = 0;
+        let mut nb_bits = 0;
+        for octet in octets {
+            tampon = (tampon << 8) | *octet as u32;
+            nb_bits += 8;
+            while nb_bits >= 5 {
+                nb_bits -= 5;
+                res.push(ALPHABET[((tampon >> nb_bits) & 31) as usize] as char);
+            }
+        }
+        if nb_bits > 0 {
+            res.push(ALPHABET[((tampon << (5 - nb_bits)) & 31) as usize] as char);
+        }
+        while !res.len().is_multiple_of(8) {
+            res.push('=');
+        }
+        res
+    }
+
+    fn bloc_requete(telechargement: &Telechargement) -> String {
+        let url = Url::parse(telechargement.url_finale.as_str()).ok();
+        let chemin = url
+            .as_ref()
+            .map(|u| match u.query() {
+                Some(q) => format!("{}?{}", u.path(), q),
+                None => u.path().to_string(),
+            })
+            .unwrap_or("/".to_string());
+        let hote = url
+            .as_ref()
+            .map(|u| match u.port() {
+                Some(port) => format!("{}:{}", u.host_str().unwrap_or(""), port),
+                None => u.host_str().unwrap_or("").to_string(),
+            })
+            .unwrap_or_default();
+        let mut bloc = format!(
+            "GET {} {}\r\nHost: {}\r\n",
+            chemin, telechargement.version_requete, hote
+        );
+        for (nom, valeur) in &telechargement.entetes_requete {
+            bloc.push_str(format!("{}: {}\r\n", nom, valeur).as_str());
+        }
+        bloc.push_str("\r\n");
+        bloc
+    }
+
+    /// Ligne de statut et entetes de la reponse ; le corps ayant ete
+    /// recu en entier, `Transfer-Encoding` est retire.
+    fn entete_reponse(telechargement: &Telechargement) -> String {
+        let raison = StatusCode::from_u16(telechargement.code)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or("");
+        let mut entete = format!(
+            "{} {} {}\r\n",
+            telechargement.version, telechargement.code, raison
+        );
+        for (nom, valeur) in &telechargement.entetes {
+            if !nom.eq_ignore_ascii_case("transfer-encoding") {
+                entete.push_str(format!("{}: {}\r\n", nom, valeur).as_str());
+            }
+        }
+        entete.push_str("\r\n");
+        entete
+    }
+
+    /// Cle SURT de l'URL : `com,example)/chemin?requete`, hote inverse sans
+    /// `www.` (sauf adresse IP), le tout en minuscules.
+    pub fn cle_surt(url: &str) -> String {
+        let Ok(url) = Url::parse(url) else {
+            return url.to_lowercase();
+        };
+        let hote = url.host_str().unwrap_or("").to_lowercase();
+        let hote = hote.strip_prefix("www.").unwrap_or(hote.as_str());
+        let mut cle = if hote.starts_with('[') || hote.parse::<IpAddr>().is_ok() {
+            hote.to_string()
+        } else {
+            let mut parties: Vec<&str> = hote.split('.').collect();
+            parties.reverse();
+            parties.join(",")
+        };
+        if let Some(port) = url.port() {
+            cle.push_str(format!(":{}", port).as_str());
+        }
+        cle.push(')');
+        cle.push_str(url.path().to_lowercase().as_str());
+        if let Some(requete) = url.query() {
+            cle.push('?');
+            cle.push_str(requete.to_lowercase().as_str());
+        }
+        cle
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn base32_rfc4648() {
+            assert_eq!(base32(b""), "");
+            assert_eq!(base32(b"f"), "MY======");
+            assert_eq!(base32(b"foobar"), "MZXW6YTBOI======");
+            assert_eq!(
+                base32(&Sha1::digest(b"")),
+                "3I42H3S6NNFQ2MSVX7XZKYAYSCX5QBYJ"
+            );
+        }
+    }
+}
@@ -0,0 +1,897 @@
+pub mod mod_web {
+    use crate::archive::mod_archive::{
+        charger_etat, lire_objet, repertoire_archive, EtatArchive, ARCHIVE_OK,
+    };
+    use crate::config::mod_config::Config2;
+    use crate::donnees::mod_donnees::{
+        champ_nombre, champ_str, charger_donnees, domaine, est_favori, fichier_data, liste, statut,
+        tags, titre, url,
+    };
+    use crate::export::mod_export::echapper_html;
+    use crate::extraction::mod_extraction::repertoire_articles;
+    use crate::filtre::mod_filtre::{filtrer, nom_statut, Filtres, Tri};
+    use crate::recherche::mod_recherche::{
+        date_sources, index_a_jour, rechercher, Classement, Index,
+    };
+    use crate::rest::mod_rest;
+    use crate::versions::mod_versions::versions_element;
+    use chrono::{DateTime, Local};
+    use reqwest::Url;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fmt::Write as _;
+    use std::fs;
+    use std::net::IpAddr;
+    use tiny_http::{Header, Method, Request, Response, Server};
+
+    /// Donnees servies, relues quand `data.json` ou l'archive changent.
+    pub struct Bibliotheque {
+        pub data: Value,
+        pub index: Index,
+        pub archives: BTreeMap<String, EtatArchive>,
+        pub date: i64,
+    }
+
+    impl Bibliotheque {
+        fn charger(config: &Config2) -> Bibliotheque {
+            let repertoire = config.repertoire.as_str();
+            let date = date_sources(repertoire);
+            let index = index_a_jour(&config.recherche, repertoire, false).unwrap_or_else(|err| {
+                log::error!("Erreur de mise a jour de l'index de recherche : {}", err);
+                Index::default()
+            });
+            Bibliotheque {
+                data: charger_donnees(fichier_data(repertoire).as_str())
+                    .unwrap_or(serde_json::json!({})),
+                index,
+                archives: charger_etat(repertoire),
+                date,
+            }
+        }
+
+        pub fn element(&self, id: &str) -> Option<&Value> {
+            liste(&self.data).and_then(|l| l.get(id))
+        }
+
+        fn archive(&self, id: &str) -> Option<&EtatArchive> {
+            self.archives
+                .get(id)
+                .filter(|a| a.statut == ARCHIVE_OK && a.hash.is_some())
+        }
+    }
+
+    pub struct Reponse {
+        pub code: u16,
+        pub type_contenu: String,
+        pub corps: Vec<u8>,
+        pub entetes: Vec<(&'static str, String)>,
+    }
+
+    impl Reponse {
+        pub fn new(code: u16, type_contenu: &str, corps: Vec<u8>) -> Reponse {
+            Reponse {
+                code,
+                type_contenu: type_contenu.to_string(),
+                corps,
+                entetes: Vec::new(),
+            }
+        }
+
+        fn html(code: u16, titre: &str, contenu: &str) -> Reponse {
+            Reponse::new(
+                code,
+                "text/html; charset=utf-8",
+                page(titre, contenu).into_bytes(),
+            )
+        }
+
+        fn introuvable() -> Reponse {
+            Reponse::html(404, "Introuvable", "<p>Element introuvable.</p>")
+        }
+    }
+
+    const STYLE: &str = "body{font-family:sans-serif;max-width:70em;margin:1em auto;padding:0 1em;color:#222}\
+        a{color:#0645ad;text-decoration:none}a:hover{text-decoration:underline}\
+        table{border-collapse:collapse;width:100%}td,th{padding:.3em .5em;border-bottom:1px solid #ddd;text-align:left;vertical-align:top}\
+        form{display:flex;flex-wrap:wrap;gap:.5em;margin-bottom:1em}input[name=q]{flex:1;min-width:15em}\
+        .tag{background:#eef;border-radius:3px;padding:0 .3em;margin-right:.2em;font-size:.9em}\
+        .discret{color:#777;font-size:.9em}dt{font-weight:bold;margin-top:.5em}dd{margin-left:1em}\
+        nav{margin:1em 0;display:flex;gap:1em}pre{white-space:pre-wrap}";
+
+    fn page(titre: &str, contenu: &str) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+             <title>{} - pbackup</title><style>{}</style></head>\n\
+             <body><p><a href=\"/\">pbackup</a></p>\n{}\n</body></html>\n",
+            echapper_html(titre),
+            STYLE,
+            contenu
+        )
+    }
+
+    fn date_locale(timestamp: i64, format: &str) -> String {
+        if timestamp <= 0 {
+            return String::new();
+        }
+        DateTime::from_timestamp(timestamp, 0)
+            .map(|d| d.with_timezone(&Local).format(format).to_string())
+            .unwrap_or_default()
+    }
+
+    /// Chaine de requete a partir des parametres non vides.
+    fn requete(parametres: &[(&str, &str)]) -> String {
+        let mut url = Url::parse("http://localhost/").expect("url valide");
+        {
+            let mut paires = url.query_pairs_mut();
+            for (nom, valeur) in parametres {
+                if !valeur.is_empty() {
+                    paires.append_pair(nom, valeur);
+                }
+            }
+        }
+        match url.query() {
+            Some(q) if !q.is_empty() => format!("/?{}", q),
+            _ => "/".to_string(),
+        }
+    }
+
+    const PARAMETRES_LISTE: [&str; 6] = ["q", "statut", "tag", "domaine", "favori", "tri"];
+
+    fn liste_elements(
+        config: &Config2,
+        bibliotheque: &Bibliotheque,
+        parametres: &BTreeMap<String, String>,
+    ) -> Reponse {
+        let parametre = |nom: &str| parametres.get(nom).map(|s| s.trim()).unwrap_or("");
+        let mut erreurs: Vec<String> = Vec::new();
+        let mut filtres = Filtres::default();
+        for nom in ["statut", "tag", "domaine", "favori"] {
+            if !parametre(nom).is_empty() {
+                if let Err(err) = filtres.ajouter(nom, parametre(nom)) {
+                    erreurs.push(err);
+                }
+            }
+        }
+        let tri = match parametre("tri") {
+            "" => None,
+            tri => match Tri::parse(tri) {
+                Ok(tri) => Some(tri),
+                Err(err) => {
+                    erreurs.push(err);
+                    None
+                }
+            },
+        };
+        let q = parametre("q");
+        let elements: Vec<(&String, &Value)> = if q.is_empty() {
+            filtrer(&bibliotheque.data, &filtres, tri.unwrap_or_default())
+        } else {
+            match rechercher(&bibliotheque.index, q, Classement::Pertinence, usize::MAX) {
+                Ok(bilan) => {
+                    let mut elements: Vec<(&String, &Value)> = bilan
+                        .resultats
+                        .iter()
+                        .filter_map(|r| {
+                            liste(&bibliotheque.data).and_then(|l| l.get_key_value(&r.item_id))
+                        })
+                        .filter(|(_, e)| filtres.accepte(e))
+                        .collect();
+                    if let Some(tri) = tri {
+                        elements.sort_by(|a, b| tri.comparer(*a, *b));
+                    }
+                    elements
+                }
+                Err(err) => {
+                    erreurs.push(err);
+                    Vec::new()
+                }
+            }
+        };
+        let taille_page = config.serveur.taille_page.max(1);
+        let nb_pages = elements.len().div_ceil(taille_page).max(1);
+        let numero = parametre("page")
+            .parse::<usize>()
+            .unwrap_or(1)
+            .clamp(1, nb_pages);
+
+        let mut html = String::new();
+        let valeurs: Vec<(&str, &str)> = PARAMETRES_LISTE
+            .iter()
+            .map(|n| (*n, parametre(n)))
+            .collect();
+        let _ = write!(
+            html,
+            "<form method=\"get\" action=\"/\">\
+             <input name=\"q\" value=\"{}\" placeholder=\"recherche : mots, &quot;phrase&quot;, titre:, -mot...\">\
+             <select name=\"statut\">{}</select>\
+             <input name=\"tag\" value=\"{}\" placeholder=\"tag\" size=\"10\">\
+             <input name=\"domaine\" value=\"{}\" placeholder=\"domaine\" size=\"14\">\
+             <select name=\"favori\">{}</select>\
+             <select name=\"tri\">{}</select>\
+             <button>Filtrer</button></form>",
+            echapper_html(q),
+            options(
+                &[
+                    ("", "tous statuts"),
+                    ("non_lu", "non lus"),
+                    ("archive", "archives"),
+                    ("supprime", "supprimes")
+                ],
+                parametre("statut")
+            ),
+            echapper_html(parametre("tag")),
+            echapper_html(parametre("domaine")),
+            options(
+                &[("", "favoris ou non"), ("oui", "favoris"), ("non", "non favoris")],
+                parametre("favori")
+            ),
+            options(
+                &[
+                    ("", "tri par defaut"),
+                    ("-ajoute", "ajout recent"),
+                    ("ajoute", "ajout ancien"),
+                    ("-maj", "modification recente"),
+                    ("titre", "titre"),
+                    ("domaine", "domaine"),
+                    ("-mots", "plus longs"),
+                ],
+                parametre("tri")
+            ),
+        );
+        for erreur in &erreurs {
+            let _ = write!(html, "<p><strong>{}</strong></p>", echapper_html(erreur));
+        }
+        let _ = write!(
+            html,
+            "<p class=\"discret\">{} elements - page {} / {}</p><table>\
+             <tr><th>ajoute</th><th>titre</th><th>domaine</th><th>tags</th><th>statut</th><th></th></tr>",
+            elements.len(),
+            numero,
+            nb_pages
+        );
+        for (id, element) in elements
+            .iter()
+            .skip((numero - 1) * taille_page)
+            .take(taille_page)
+        {
+            let titre = match titre(element) {
+                "" => url(element),
+                t => t,
+            };
+            let domaine = domaine(element);
+            let mut valeurs_domaine = valeurs.clone();
+            valeurs_domaine.retain(|(n, _)| *n != "domaine");
+            valeurs_domaine.push(("domaine", domaine.as_str()));
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td><a href=\"/item/{}\">{}</a>{}</td><td><a href=\"{}\">{}</a></td><td>",
+                date_locale(champ_nombre(element, "time_added"), "%Y-%m-%d"),
+                echapper_html(id),
+                echapper_html(titre),
+                if est_favori(element) { " &#9733;" } else { "" },
+                echapper_html(requete(&valeurs_domaine).as_str()),
+                echapper_html(domaine.as_str())
+            );
+            for tag in tags(element) {
+                let _ = write!(
+                    html,
+                    "<a class=\"tag\" href=\"{}\">{}</a>",
+                    echapper_html(requete(&[("tag", tag.as_str())]).as_str()),
+                    echapper_html(tag.as_str())
+                );
+            }
+            let _ = write!(
+                html,
+                "</td><td>{}</td><td>{}</td></tr>",
+                nom_statut(statut(element)),
+                match bibliotheque.archive(id) {
+                    Some(_) => format!("<a href=\"/archive/{}\">archive</a>", echapper_html(id)),
+                    None => String::new(),
+                }
+            );
+        }
+        html.push_str("</table><nav>");
+        for (libelle, cible) in [
+            ("&laquo; precedente", numero - 1),
+            ("suivante &raquo;", numero + 1),
+        ] {
+            if cible >= 1 && cible <= nb_pages {
+                let numero = cible.to_string();
+                let mut valeurs_page = valeurs.clone();
+                valeurs_page.push(("page", numero.as_str()));
+                let _ = write!(
+                    html,
+                    "<a href=\"{}\">{}</a>",
+                    echapper_html(requete(&valeurs_page).as_str()),
+                    libelle
+                );
+            }
+        }
+        html.push_str("</nav>");
+        Reponse::html(200, "Bibliotheque", html.as_str())
+    }
+
+    fn options(choix: &[(&str, &str)], courant: &str) -> String {
+        choix
+            .iter()
+            .map(|(valeur, libelle)| {
+                format!(
+                    "<option value=\"{}\"{}>{}</option>",
+                    valeur,
+                    if *valeur == courant { " selected" } else { "" },
+                    libelle
+                )
+            })
+            .collect()
+    }
+
+    /// Valeur courte d'un champ pour l'affichage des differences.
+    fn apercu(valeur: Option<&Value>) -> String {
+        let texte = match valeur {
+            None | Some(Value::Null) => return "-".to_string(),
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Object(o)) => o.keys().cloned().collect::<Vec<_>>().join(", "),
+            Some(autre) => autre.to_string(),
+        };
+        if texte.chars().count() > 80 {
+            let mut court: String = texte.chars().take(79).collect();
+            court.push('…');
+            court
+        } else {
+            texte
+        }
+    }
+
+    fn differences(avant: &Value, apres: &Value) -> Vec<(String, String, String)> {
+        let vide = serde_json::Map::new();
+        let avant = avant.as_object().unwrap_or(&vide);
+        let apres = apres.as_object().unwrap_or(&vide);
+        let mut champs: Vec<&String> = avant.keys().chain(apres.keys()).collect();
+        champs.sort();
+        champs.dedup();
+        champs
+            .into_iter()
+            .filter(|c| avant.get(*c) != apres.get(*c))
+            .map(|c| (c.clone(), apercu(avant.get(c)), apercu(apres.get(c))))
+            .collect()
+    }
+
+    /// Lien vers l'url de l'element ; seules les adresses http(s) sont
+    /// cliquables, une url `javascript:` ou `data:` importee reste du texte.
+    fn lien_externe(adresse: &str) -> String {
+        let web = Url::parse(adresse).is_ok_and(|u| matches!(u.scheme(), "http" | "https"));
+        if web {
+            format!(
+                "<a href=\"{0}\" rel=\"noreferrer\" target=\"_blank\">{0}</a>",
+                echapper_html(adresse)
+            )
+        } else {
+            echapper_html(adresse)
+        }
+    }
+
+    fn detail_element(config: &Config2, bibliotheque: &Bibliotheque, id: &str) -> Reponse {
+        let Some(element) = bibliotheque.element(id) else {
+            return Reponse::introuvable();
+        };
+        let titre = match titre(element) {
+            "" => url(element),
+            t => t,
+        };
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<h1>{}</h1><p>{}</p><dl>",
+            echapper_html(titre),
+            lien_externe(url(element))
+        );
+        let mut champ = |nom: &str, valeur: String| {
+            if !valeur.is_empty() {
+                let _ = write!(html, "<dt>{}</dt><dd>{}</dd>", nom, valeur);
+            }
+        };
+        champ("item_id", echapper_html(id));
+        champ(
+            "statut",
+            format!(
+                "{}{}",
+                nom_statut(statut(element)),
+                if est_favori(element) { ", favori" } else { "" }
+            ),
+        );
+        champ(
+            "tags",
+            tags(element)
+                .iter()
+                .map(|t| {
+                    format!(
+                        "<a class=\"tag\" href=\"{}\">{}</a>",
+                        echapper_html(requete(&[("tag", t.as_str())]).as_str()),
+                        echapper_html(t)
+                    )
+                })
+                .collect(),
+        );
+        champ("extrait", echapper_html(champ_str(element, "excerpt")));
+        for (nom, cle) in [
+            ("ajoute", "time_added"),
+            ("modifie", "time_updated"),
+            ("lu", "time_read"),
+            ("favori depuis", "time_favorited"),
+        ] {
+            champ(
+                nom,
+                date_locale(champ_nombre(element, cle), "%Y-%m-%d %H:%M"),
+            );
+        }
+        let mots = champ_nombre(element, "word_count");
+        if mots > 0 {
+            champ("mots", mots.to_string());
+        }
+        champ("langue", echapper_html(champ_str(element, "lang")));
+        html.push_str("</dl>");
+
+        html.push_str("<h2>Archive</h2>");
+        match bibliotheque.archives.get(id) {
+            Some(archive) if archive.statut == ARCHIVE_OK => {
+                let _ = write!(
+                    html,
+                    "<p>Page archivee le {} ({} octets, {} ressources) : \
+                     <a href=\"/archive/{}\">copie archivee</a>",
+                    date_locale(archive.date, "%Y-%m-%d %H:%M"),
+                    archive.taille,
+                    archive.ressources.len(),
+                    echapper_html(id)
+                );
+                if archive.extraction.is_some() {
+                    let _ = write!(
+                        html,
+                        " - <a href=\"/article/{}\">article extrait</a>",
+                        echapper_html(id)
+                    );
+                }
+                html.push_str("</p>");
+                if !archive.ressources.is_empty() {
+                    html.push_str("<details><summary>Ressources</summary><ul>");
+                    for ressource in &archive.ressources {
+                        let _ = write!(
+                            html,
+                            "<li><a href=\"/objet/{}\">{}</a> <span class=\"discret\">{}</span></li>",
+                            echapper_html(ressource.hash.as_str()),
+                            echapper_html(ressource.url.as_str()),
+                            echapper_html(ressource.type_contenu.as_str())
+                        );
+                    }
+                    html.push_str("</ul></details>");
+                }
+            }
+            Some(archive) => {
+                let _ = write!(
+                    html,
+                    "<p>Archivage : {} {}</p>",
+                    echapper_html(archive.statut.as_str()),
+                    echapper_html(archive.erreur.as_deref().unwrap_or(""))
+                );
+            }
+            None => html.push_str("<p class=\"discret\">Page non archivee.</p>"),
+        }
+
+        html.push_str("<h2>Versions</h2>");
+        let versions = versions_element(config.repertoire.as_str(), id);
+        if versions.is_empty() {
+            html.push_str("<p class=\"discret\">Aucune modification enregistree.</p>");
+        } else {
+            html.push_str(
+                "<table><tr><th>remplacee le</th><th>champ</th><th>avant</th><th>apres</th></tr>",
+            );
+            let suivants = versions
+                .iter()
+                .skip(1)
+                .map(|v| &v.element)
+                .chain(std::iter::once(element));
+            // la modification la plus recente en premier
+            let mut groupes: Vec<String> = Vec::new();
+            for (version, suivant) in versions.iter().zip(suivants) {
+                let differences = differences(&version.element, suivant);
+                let date = date_locale(version.date, "%Y-%m-%d %H:%M");
+                let mut groupe = String::new();
+                for (i, (champ, avant, apres)) in differences.iter().enumerate() {
+                    groupe.push_str(&format!(
+                        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        if i == 0 { date.as_str() } else { "" },
+                        echapper_html(champ),
+                        echapper_html(avant),
+                        echapper_html(apres)
+                    ));
+                }
+                groupes.push(groupe);
+            }
+            groupes.reverse();
+            html.push_str(groupes.concat().as_str());
+            html.push_str("</table>");
+        }
+        Reponse::html(200, titre, html.as_str())
+    }
+
+    /// Page telle qu'archivee ; `sandbox` empeche l'execution de ses
+    /// scripts.
+    fn copie_archivee(config: &Config2, bibliotheque: &Bibliotheque, id: &str) -> Reponse {
+        let Some(archive) = bibliotheque.archive(id) else {
+            return Reponse::introuvable();
+        };
+        let Some(hash) = archive.hash.as_deref() else {
+            return Reponse::introuvable();
+        };
+        match lire_objet(config.repertoire.as_str(), hash) {
+            Ok(contenu) => {
+                let mut reponse = Reponse::new(200, archive.type_contenu.as_str(), contenu);
+                reponse
+                    .entetes
+                    .push(("Content-Security-Policy", "sandbox".to_string()));
+                reponse
+            }
+            Err(err) => {
+                log::error!("objet {} de {} : {}", hash, id, err);
+                Reponse::introuvable()
+            }
+        }
+    }
+
+    fn objet(config: &Config2, bibliotheque: &Bibliotheque, hash: &str) -> Reponse {
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Reponse::introuvable();
+        }
+        let type_contenu = bibliotheque
+            .archives
+            .values()
+            .flat_map(|a| a.ressources.iter())
+            .find(|r| r.hash == hash)
+            .map(|r| r.type_contenu.clone())
+            .unwrap_or("application/octet-stream".to_string());
+        match lire_objet(config.repertoire.as_str(), hash) {
+            Ok(contenu) => {
+                let mut reponse = Reponse::new(200, type_contenu.as_str(), contenu);
+                reponse
+                    .entetes
+                    .push(("Content-Security-Policy", "sandbox".to_string()));
+                reponse
+            }
+            Err(_) => Reponse::introuvable(),
+        }
+    }
+
+    fn article(config: &Config2, id: &str) -> Reponse {
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Reponse::introuvable();
+        }
+        let fichier = repertoire_articles(&repertoire_archive(config.repertoire.as_str()))
+            .join(format!("{}.md", id));
+        match fs::read(fichier) {
+            Ok(contenu) => Reponse::new(200, "text/plain; charset=utf-8", contenu),
+            Err(_) => Reponse::introuvable(),
+        }
+    }
+
+    fn repondre(config: &Config2, bibliotheque: &Bibliotheque, requete: &Request) -> Reponse {
+        if !matches!(requete.method(), Method::Get | Method::Head) {
+            let mut reponse = Reponse::new(
+                405,
+                "text/plain; charset=utf-8",
+                b"lecture seule\n".to_vec(),
+            );
+            reponse.entetes.push(("Allow", "GET, HEAD".to_string()));
+            return reponse;
+        }
+        let Ok(adresse) = Url::parse(format!("http://localhost{}", requete.url()).as_str()) else {
+            return Reponse::introuvable();
+        };
+        let parametres: BTreeMap<String, String> = adresse.query_pairs().into_owned().collect();
+        let segments: Vec<&str> = adresse
+            .path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        // le jeton de l'API protege aussi les pages, sauf la description
+        // de l'API
+        let description = segments == ["api", mod_rest::VERSION_API, "openapi.json"];
+        if !description && !mod_rest::autorise(&config.api, requete) {
+            return mod_rest::non_autorise();
+        }
+        match segments.as_slice() {
+            [] => liste_elements(config, bibliotheque, &parametres),
+            ["item", id] => detail_element(config, bibliotheque, id),
+            ["archive", id] => copie_archivee(config, bibliotheque, id),
+            ["article", id] => article(config, id),
+            ["objet", hash] => objet(config, bibliotheque, hash),
+            ["api", reste @ ..] => {
+                mod_rest::repondre(config, bibliotheque, requete, reste, &parametres)
+            }
+            _ => Reponse::introuvable(),
+        }
+    }
+
+    /// Sert la bibliotheque en lecture seule jusqu'a l'arret du programme.
+    pub fn servir(config: &Config2, adresse: &str, port: u16) -> Result<(), String> {
+        match adresse.parse::<IpAddr>() {
+            Ok(ip) if ip.is_loopback() => {}
+            _ if !config.api.jeton.is_empty() => {}
+            _ => log::warn!(
+                "ecoute sur {} : la sauvegarde est accessible depuis le reseau, sans authentification",
+                adresse
+            ),
+        }
+        let serveur = Server::http((adresse, port)).map_err(|err| err.to_string())?;
+        log::info!("interface web sur http://{}:{}/", adresse, port);
+        println!("interface web sur http://{}:{}/", adresse, port);
+        if config.api.actif {
+            println!(
+                "API JSON sur http://{}:{}/api/{}/",
+                adresse,
+                port,
+                mod_rest::VERSION_API
+            );
+        }
+        let mut bibliotheque = Bibliotheque::charger(config);
+        for requete in serveur.incoming_requests() {
+            if date_sources(config.repertoire.as_str()) > bibliotheque.date {
+                log::info!("rechargement des donnees");
+                bibliotheque = Bibliotheque::charger(config);
+            }
+            let reponse = repondre(config, &bibliotheque, &requete);
+            log::info!("{} {} {}", requete.method(), requete.url(), reponse.code);
+            let mut http = Response::from_data(reponse.corps).with_status_code(reponse.code);
+            let entetes = std::iter::once(("Content-Type", reponse.type_contenu))
+                .chain(std::iter::once((
+                    "X-Content-Type-Options",
+                    "nosniff".to_string(),
+                )))
+                .chain(reponse.entetes);
+            for (nom, valeur) in entetes {
+                if let Ok(entete) = Header::from_bytes(nom.as_bytes(), valeur.as_bytes()) {
+                    http.add_header(entete);
+                }
+            }
+            if let Err(err) = requete.respond(http) {
+                log::warn!("Erreur d'envoi de la reponse : {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::config::mod_config::ConfigRecherche;
+        use crate::recherche::mod_recherche::mettre_a_jour;
+        use crate::versions::mod_versions::{ajouter_versions, Version};
+        use serde_json::json;
+
+        fn repertoire_test(nom: &str) -> String {
+            let repertoire =
+                std::env::temp_dir().join(format!("pbackup_web_{}_{}", nom, std::process::id()));
+            let _ = fs::remove_dir_all(&repertoire);
+            fs::create_dir_all(&repertoire).unwrap();
+            repertoire.to_str().unwrap().to_string()
+        }
+
+        fn element(id: &str, url: &str, statut: &str, tags: &[&str], ajoute: i64) -> Value {
+            let tags: serde_json::Map<String, Value> = tags
+                .iter()
+                .map(|t| (t.to_string(), json!({"item_id": id, "tag": t})))
+                .collect();
+            json!({
+                "item_id": id,
+                "given_url": url,
+                "resolved_title": format!("Titre {}", id),
+                "status": statut,
+                "tags": tags,
+                "time_added": ajoute.to_string(),
+            })
+        }
+
+        fn bibliotheque(data: Value) -> Bibliotheque {
+            Bibliotheque {
+                data,
+                index: Index::default(),
+                archives: BTreeMap::new(),
+                date: 0,
+            }
+        }
+
+        fn corps(reponse: &Reponse) -> String {
+            String::from_utf8(reponse.corps.clone()).unwrap()
+        }
+
+        fn parametres(paires: &[(&str, &str)]) -> BTreeMap<String, String> {
+            paires
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect()
+        }
+
+        fn cinq_elements() -> Value {
+            json!({"liste": {
+                "1": element("1", "https://a.org/1", "0", &["rust"], 100),
+                "2": element("2", "https://b.org/2", "1", &["rust", "web"], 200),
+                "3": element("3", "https://a.org/3", "0", &[], 300),
+                "4": element("4", "https://blog.a.org/4", "1", &["web"], 400),
+                "5": element("5", "https://c.org/5", "0", &["rust"], 500),
+            }})
+        }
+
+        #[test]
+        fn liste_paginee() {
+            let mut config = Config2::pour_test("");
+            config.serveur.taille_page = 2;
+            let bibliotheque = bibliotheque(cinq_elements());
+            let html = corps(&liste_elements(
+                &config,
+                &bibliotheque,
+                &parametres(&[("page", "2")]),
+            ));
+            assert!(html.contains("5 elements - page 2 / 3"));
+            // du plus recent au plus ancien
+            assert!(html.contains("/item/3") && html.contains("/item/2"));
+            assert!(!html.contains("/item/5") && !html.contains("/item/1\""));
+            assert!(html.contains("href=\"/?page=1\"") && html.contains("href=\"/?page=3\""));
+
+            let html = corps(&liste_elements(
+                &config,
+                &bibliotheque,
+                &parametres(&[("page", "99")]),
+            ));
+            assert!(html.contains("page 3 / 3"));
+            assert!(html.contains("/item/1\""));
+            assert!(!html.contains("suivante"));
+        }
+
+        #[test]
+        fn liste_filtree() {
+            let config = Config2::pour_test("");
+            let mut bibliotheque = bibliotheque(cinq_elements());
+            let ids = |html: String| -> Vec<String> {
+                ["1", "2", "3", "4", "5"]
+                    .iter()
+                    .filter(|id| html.contains(format!("/item/{}\"", id).as_str()))
+                    .map(|id| id.to_string())
+                    .collect()
+            };
+            let liste = |bibliotheque: &Bibliotheque, paires: &[(&str, &str)]| {
+                corps(&liste_elements(&config, bibliotheque, &parametres(paires)))
+            };
+            assert_eq!(
+                ids(liste(&bibliotheque, &[("statut", "archive")])),
+                ["2", "4"]
+            );
+            assert_eq!(ids(liste(&bibliotheque, &[("tag", "rust,web")])), ["2"]);
+            assert_eq!(
+                ids(liste(&bibliotheque, &[("domaine", "a.org")])),
+                ["1", "3", "4"]
+            );
+            let html = liste(&bibliotheque, &[("tri", "taille")]);
+            assert!(html.contains("<strong>tri inconnu : 'taille'</strong>"));
+
+            let config_recherche = ConfigRecherche {
+                texte_article: false,
+                ..Default::default()
+            };
+            let data = bibliotheque.data.clone();
+            mettre_a_jour(&config_recherche, "", &mut bibliotheque.index, &data);
+            assert_eq!(
+                ids(liste(
+                    &bibliotheque,
+                    &[("q", "tags:rust"), ("statut", "non_lu")]
+                )),
+                ["1", "5"]
+            );
+            let html = liste(&bibliotheque, &[("q", "(rust")]);
+            assert!(html.contains("0 elements") && html.contains("<strong>"));
+        }
+
+        #[test]
+        fn url_non_web_sans_lien() {
+            let config = Config2::pour_test("");
+            let bibliotheque = bibliotheque(json!({"liste": {
+                "1": element("1", "javascript:alert(1)", "0", &[], 100),
+                "2": element("2", "https://exemple.org/?a=1&b=2", "0", &[], 100),
+            }}));
+            let html = corps(&detail_element(&config, &bibliotheque, "1"));
+            assert!(!html.contains("href=\"javascript:"));
+            assert!(html.contains("<p>javascript:alert(1)</p>"));
+            let html = corps(&detail_element(&config, &bibliotheque, "2"));
+            assert!(html.contains("<a href=\"https://exemple.org/?a=1&amp;b=2\""));
+            assert_eq!(detail_element(&config, &bibliotheque, "3").code, 404);
+        }
+
+        #[test]
+        fn versions_les_plus_recentes_en_premier() {
+            let repertoire = repertoire_test("versions");
+            let config = Config2::pour_test(repertoire.as_str());
+            let mut v1 = element("1", "https://a.org/1", "0", &[], 100);
+            v1["resolved_title"] = json!("Premier titre");
+            let mut v2 = v1.clone();
+            v2["resolved_title"] = json!("Second titre");
+            let mut actuel = v2.clone();
+            actuel["status"] = json!("1");
+            actuel["tags"] = json!({"rust": {"item_id": "1", "tag": "rust"}});
+            ajouter_versions(
+                repertoire.as_str(),
+                &[
+                    Version {
+                        item_id: "1".to_string(),
+                        date: 1000,
+                        element: v1,
+                    },
+                    Version {
+                        item_id: "1".to_string(),
+                        date: 2000,
+                        element: v2,
+                    },
+                ],
+            )
+            .unwrap();
+            let bibliotheque = bibliotheque(json!({"liste": {"1": actuel}}));
+            let html = corps(&detail_element(&config, &bibliotheque, "1"));
+            let statut = html.find("<td>status</td><td>0</td><td>1</td>").unwrap();
+            let tags = html.find("<td>tags</td><td></td><td>rust</td>").unwrap();
+            let titre = html
+                .find("<td>resolved_title</td><td>Premier titre</td><td>Second titre</td>")
+                .unwrap();
+            assert!(statut < tags && tags < titre);
+            let _ = fs::remove_dir_all(repertoire);
+        }
+
+        #[test]
+        fn jeton_exige_sur_toutes_les_pages() {
+            let mut config = Config2::pour_test("");
+            config.api.jeton = "secret".to_string();
+            let bibliotheque = bibliotheque(cinq_elements());
+            let appel = |config: &Config2, chemin: &str, jeton: Option<&str>| {
+                let mut requete = tiny_http::TestRequest::new().with_path(chemin);
+                if let Some(jeton) = jeton {
+                    let valeur = format!("Bearer {}", jeton);
+                    requete =
+                        requete.with_header(Header::from_bytes("Authorization", valeur).unwrap());
+                }
+                repondre(config, &bibliotheque, &requete.into())
+            };
+            let hash = "0".repeat(64);
+            let objet = format!("/objet/{}", hash);
+            for chemin in [
+                "/",
+                "/?q=rust",
+                "/item/1",
+                "/archive/1",
+                objet.as_str(),
+                "/article/1",
+            ] {
+                assert_eq!(appel(&config, chemin, None).code, 401, "{}", chemin);
+                assert_eq!(appel(&config, chemin, Some("faux")).code, 401, "{}", chemin);
+            }
+            assert_eq!(appel(&config, "/", Some("secret")).code, 200);
+            assert_eq!(appel(&config, "/item/1", Some("secret")).code, 200);
+            assert_eq!(appel(&config, "/api/v1/openapi.json", None).code, 200);
+
+            config.api.jeton.clear();
+            assert_eq!(appel(&config, "/item/1", None).code, 200);
+        }
+
+        #[test]
+        fn differences_des_champs() {
+            let avant = json!({"a": "1", "b": "x".repeat(100)});
+            let apres = json!({"a": "1", "b": "y", "d": 2});
+            let differences = differences(&avant, &apres);
+            let champs: Vec<&str> = differences.iter().map(|d| d.0.as_str()).collect();
+            assert_eq!(champs, ["b", "d"]);
+            assert_eq!(differences[0].1.chars().count(), 80);
+            assert!(differences[0].1.ends_with('…'));
+            assert_eq!(
+                differences[1],
+                ("d".to_string(), "-".to_string(), "2".to_string())
+            );
+        }
+    }
+}